@@ -16,7 +16,7 @@ fn main() {
     let mut vec =
         unsafe { MemVec::<Record, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
 
-    if vec.len() == 0 {
+    if vec.is_empty() {
         // creating a new file
         println!("creating a new file: {path:?}");
         for i in 0..10 {
@@ -0,0 +1,41 @@
+/* C interface to memvec vector files, see the `capi` module of the memvec crate. */
+
+#ifndef MEMVEC_H
+#define MEMVEC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Create the file if it does not exist. */
+#define MEMVEC_CREATE (1u << 0)
+/* Take an exclusive lock on the file. */
+#define MEMVEC_LOCK (1u << 1)
+
+typedef struct MemvecFile MemvecFile;
+
+/* Open the file at the UTF-8 `path` with records of `record_size` bytes, or return NULL. */
+MemvecFile *memvec_file_open(const char *path, size_t record_size, uint32_t flags);
+/* Append a record of exactly the record size. Returns 0, or -1 on failure. */
+int memvec_file_push_bytes(MemvecFile *file, const uint8_t *data, size_t len);
+/* The record at `index`, valid until the next push or close, or NULL if out of bounds. */
+const uint8_t *memvec_file_get(const MemvecFile *file, size_t index);
+/* The number of records. */
+size_t memvec_file_len(const MemvecFile *file);
+/* The record size the file was opened with. */
+size_t memvec_file_record_size(const MemvecFile *file);
+/* Write the records and the length durably. Returns 0, or -1 on failure. */
+int memvec_file_flush(MemvecFile *file);
+/* Close the file. NULL is ignored. */
+void memvec_file_close(MemvecFile *file);
+/* The message of the last failure on the calling thread, or NULL. */
+const char *memvec_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MEMVEC_H */
@@ -0,0 +1,276 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::{
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A fixed-capacity log appended to by any number of producers, which may be different processes
+/// sharing the memory, and read by any number of consumers.
+///
+/// A producer reserves a slot by incrementing the shared slot counter, writes its record and then
+/// sets the commit word of the slot with release ordering. Slots are committed out of order, so a
+/// consumer sees a gap where a producer is still writing. A producer which died after reserving
+/// leaves a gap forever; a consumer which gave up waiting can [`AppendQueue::abandon`] the slot,
+/// and a producer finding its slot abandoned appends to another one.
+pub struct AppendQueue<T: Plain, A: Memory> {
+    mem: A,
+    header: NonNull<QueueHeader>,
+    slots: NonNull<u8>,
+    capacity: u64,
+    _marker: PhantomData<T>,
+}
+
+// records are only written to reserved slots, and only read after they are committed
+unsafe impl<T: Plain + Send, A: Memory + Send> Send for AppendQueue<T, A> {}
+unsafe impl<T: Plain + Send + Sync, A: Memory + Sync> Sync for AppendQueue<T, A> {}
+
+#[repr(C)]
+struct QueueHeader {
+    magic: [u8; 8],
+    capacity: u64,
+    record_size: u64,
+    _reserved: [u8; 40],
+    /// The number of reserved slots.
+    next: AtomicU64,
+    _pad_next: [u8; 56],
+}
+
+const _: () = assert!(core::mem::size_of::<QueueHeader>() == QueueHeader::LEN);
+
+impl QueueHeader {
+    const LEN: usize = 128;
+    const MAGIC: [u8; 8] = *b"MEMVECAQ";
+}
+
+/// The state of a slot of an [`AppendQueue`], stored in the commit word before its record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotState {
+    /// Not reserved, or reserved by a producer which has not committed it yet.
+    Empty,
+    Committed,
+    /// Given up by a consumer; the record is never committed.
+    Abandoned,
+}
+
+impl SlotState {
+    fn from_word(word: u64) -> Self {
+        match u64::from_le(word) {
+            0 => Self::Empty,
+            1 => Self::Committed,
+            _ => Self::Abandoned,
+        }
+    }
+
+    fn to_word(self) -> u64 {
+        let word: u64 = match self {
+            Self::Empty => 0,
+            Self::Committed => 1,
+            Self::Abandoned => 2,
+        };
+        word.to_le()
+    }
+}
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> AppendQueue<T, A> {
+    /// Create a queue of `capacity` slots in empty memory, or open the one the memory holds.
+    ///
+    /// The memory is reserved once and never resized afterwards. Opening fails with
+    /// [`std::io::ErrorKind::InvalidData`] if the stored queue differs in capacity or record size.
+    ///
+    /// # Safety
+    /// Committed slots must hold valid bytes representations of T.
+    pub unsafe fn new(mut mem: A, capacity: usize) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let bytes = capacity
+            .checked_mul(Self::stride())
+            .and_then(|bytes| bytes.checked_add(QueueHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let fresh = mem.len() == 0;
+        if !fresh && mem.len() != bytes {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        mem.reserve(bytes)?;
+        let base = mem.as_mut_ptr();
+        let slots = base.wrapping_add(QueueHeader::LEN);
+        check_align::<QueueHeader>(base)
+            .and_then(|()| check_align::<T>(slots))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let header = NonNull::new(base.cast::<QueueHeader>()).expect("mapped memory is not null");
+        let record_size = core::mem::size_of::<T>() as u64;
+        {
+            let header = header.as_ptr();
+            if fresh {
+                // the commit words are zero, i.e. empty, in freshly reserved memory
+                header.write(QueueHeader {
+                    magic: QueueHeader::MAGIC,
+                    capacity: (capacity as u64).to_le(),
+                    record_size: record_size.to_le(),
+                    _reserved: [0; 40],
+                    next: AtomicU64::new(0),
+                    _pad_next: [0; 56],
+                });
+                mem.set_len(bytes);
+            } else if (*header).magic != QueueHeader::MAGIC {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "not a memvec append queue",
+                ));
+            } else if u64::from_le((*header).capacity) != capacity as u64
+                || u64::from_le((*header).record_size) != record_size
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "memvec append queue has a different capacity or record size",
+                ));
+            }
+        }
+        Ok(Self {
+            mem,
+            header,
+            slots: NonNull::new(slots).expect("mapped memory is not null"),
+            capacity: capacity as u64,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Plain, A: Memory> AppendQueue<T, A> {
+    /// The commit word is followed by the record at the alignment of `T`.
+    fn record_offset() -> usize {
+        core::mem::align_of::<T>().max(core::mem::size_of::<u64>())
+    }
+
+    fn stride() -> usize {
+        (Self::record_offset() + core::mem::size_of::<T>()).next_multiple_of(Self::record_offset())
+    }
+
+    fn header(&self) -> &QueueHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    fn commit_word(&self, index: usize) -> &AtomicU64 {
+        debug_assert!((index as u64) < self.capacity);
+        unsafe {
+            &*self
+                .slots
+                .as_ptr()
+                .add(index * Self::stride())
+                .cast::<AtomicU64>()
+        }
+    }
+
+    fn record(&self, index: usize) -> *mut T {
+        let offset = index * Self::stride() + Self::record_offset();
+        unsafe { self.slots.as_ptr().add(offset).cast::<T>() }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// The number of reserved slots, including those not committed yet.
+    pub fn len(&self) -> usize {
+        let next = u64::from_le(self.header().next.load(Ordering::Acquire));
+        next.min(self.capacity) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append `value` and return the index of its slot, or return `value` back if the queue is
+    /// full.
+    pub fn append(&self, mut value: T) -> Result<usize, T> {
+        loop {
+            let next = &self.header().next;
+            let index = u64::from_le(next.load(Ordering::Relaxed));
+            if index >= self.capacity {
+                return Err(value);
+            }
+            if next
+                .compare_exchange_weak(
+                    index.to_le(),
+                    (index + 1).to_le(),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            let index = index as usize;
+            // SAFETY: the slot is reserved by this call only, and not read before it is committed
+            unsafe { self.record(index).write(value) };
+            let committed = self.commit_word(index).compare_exchange(
+                SlotState::Empty.to_word(),
+                SlotState::Committed.to_word(),
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+            if committed.is_ok() {
+                return Ok(index);
+            }
+            // SAFETY: an abandoned slot is never read by consumers, so the record is still ours
+            value = unsafe { self.record(index).read() };
+        }
+    }
+
+    pub fn slot_state(&self, index: usize) -> Option<SlotState> {
+        (index < self.len())
+            .then(|| SlotState::from_word(self.commit_word(index).load(Ordering::Acquire)))
+    }
+
+    /// The record at `index` if it is committed.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        // SAFETY: committed records are never written again
+        (self.slot_state(index)? == SlotState::Committed).then(|| unsafe { &*self.record(index) })
+    }
+
+    /// Give up the reserved slot at `index` unless it was committed in the meantime, and return
+    /// whether it is abandoned.
+    pub fn abandon(&self, index: usize) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        let state = self.commit_word(index).compare_exchange(
+            SlotState::Empty.to_word(),
+            SlotState::Abandoned.to_word(),
+            Ordering::Relaxed,
+            Ordering::Acquire,
+        );
+        match state {
+            Ok(_) => true,
+            Err(word) => SlotState::from_word(word) == SlotState::Abandoned,
+        }
+    }
+
+    /// Iterate over the committed records with their indices, up to the first slot not committed
+    /// yet; abandoned slots are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        (0..self.len())
+            .map(|index| (index, self.slot_state(index)))
+            .take_while(|(_, state)| *state != Some(SlotState::Empty))
+            .filter_map(|(index, _)| Some((index, self.get(index)?)))
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain, A: Memory> core::fmt::Debug for AppendQueue<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AppendQueue")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{log::MemLog, mem_vec::MemVec, memory::Memory};
+use ::rkyv::{
+    api::high::{HighSerializer, HighValidator},
+    bytecheck::CheckBytes,
+    rancor::{self, Source},
+    ser::{
+        allocator::ArenaHandle,
+        writer::{Positional, Writer},
+    },
+    util::AlignedVec,
+    Archive, Serialize,
+};
+use std::io::{Error, ErrorKind};
+
+/// Validate the archive whose root ends `bytes` and access it in place.
+fn access<T: Archive>(bytes: &[u8]) -> std::io::Result<&T::Archived>
+where
+    T::Archived: for<'v> CheckBytes<HighValidator<'v, rancor::Error>>,
+{
+    ::rkyv::access::<T::Archived, rancor::Error>(bytes)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Serialization of rkyv archives straight into the vector, e.g. into the mapping of a
+/// [`crate::VecFile`] without an intermediate buffer.
+///
+/// The position is the length of the vector, so the archive starts after the existing bytes and
+/// its padding is relative to the start of the memory. The vector grows like
+/// [`MemVec::extend_from_slice`].
+impl<'a, A: Memory> Positional for MemVec<'a, u8, A> {
+    fn pos(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'a, A: Memory, E: Source> Writer<E> for MemVec<'a, u8, A>
+where
+    A::Error: Into<std::io::Error>,
+{
+    fn write(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.try_reserve(bytes.len())
+            .map_err(|e| E::new(e.into()))?;
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<'a, A: Memory> MemVec<'a, u8, A>
+where
+    A::Error: Into<std::io::Error>,
+{
+    /// Serialize `value` with rkyv after the bytes of the vector, making it the root that
+    /// [`MemVec::access_archived`] reads.
+    pub fn push_archived<T>(&mut self, value: &T) -> std::io::Result<()>
+    where
+        T: for<'w, 'r> Serialize<HighSerializer<&'w mut Self, ArenaHandle<'r>, rancor::Error>>,
+    {
+        ::rkyv::api::high::to_bytes_in::<_, rancor::Error>(value, self)
+            .map(|_| ())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    }
+}
+
+impl<'a, A: Memory> MemVec<'a, u8, A> {
+    /// Access the rkyv archive whose root ends the vector in place, e.g. an object graph in a
+    /// mapped [`crate::MmapFile`], after validating it.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] if the bytes are not a valid archive of
+    /// `T`, including when the memory is not aligned for it.
+    pub fn access_archived<T: Archive>(&self) -> std::io::Result<&T::Archived>
+    where
+        T::Archived: for<'v> CheckBytes<HighValidator<'v, rancor::Error>>,
+    {
+        access::<T>(self.as_slice())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<M: crate::memory::ReadOnlyMemory> crate::read_only::ReadOnlyMemVec<u8, M> {
+    /// Access the rkyv archive whose root ends the vector in place. See
+    /// [`MemVec::access_archived`].
+    pub fn access_archived<T: Archive>(&self) -> std::io::Result<&T::Archived>
+    where
+        T::Archived: for<'v> CheckBytes<HighValidator<'v, rancor::Error>>,
+    {
+        access::<T>(self.as_slice())
+    }
+}
+
+impl<A: Memory<Error = std::io::Error>> MemLog<A> {
+    /// Serialize `value` with rkyv and append it as a record. See [`MemLog::append`].
+    ///
+    /// Payloads are 8-byte aligned, so archives of types aligned to at most 8 bytes are read in
+    /// place by [`MemLog::get_archived`].
+    pub fn append_archived<T>(&mut self, value: &T) -> std::io::Result<usize>
+    where
+        T: for<'r> Serialize<HighSerializer<AlignedVec, ArenaHandle<'r>, rancor::Error>>,
+    {
+        let bytes = ::rkyv::to_bytes::<rancor::Error>(value)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        self.append(&bytes)
+    }
+}
+
+impl<A: Memory> MemLog<A> {
+    /// The record at `offset` accessed in place as an rkyv archive of `T`, or `None` if no record
+    /// starts there. See [`MemLog::get`].
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] if the record is not a valid archive of `T`.
+    pub fn get_archived<T: Archive>(&self, offset: usize) -> Option<std::io::Result<&T::Archived>>
+    where
+        T::Archived: for<'v> CheckBytes<HighValidator<'v, rancor::Error>>,
+    {
+        self.get(offset).map(access::<T>)
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::marker::PhantomData;
+
+/// A bump allocator over [`Memory`], handing out typed allocations so that several structures of
+/// different types share one region, e.g. one [`crate::VecFile`].
+///
+/// The memory starts with an arena header holding the high-water mark of the allocations, which
+/// follow it back to back. Allocations are never freed one by one; [`MemArena::reset`] releases
+/// them all at once. Since the memory may move when it grows, allocations are referred to by
+/// [`ArenaRef`] and [`ArenaSlice`] handles holding their offset rather than by references. The
+/// handles are plain values themselves, so they can be stored in the arena or elsewhere in the
+/// file to find the structures again after reopening.
+pub struct MemArena<A: Memory> {
+    mem: A,
+}
+
+#[repr(C)]
+struct ArenaHeader {
+    magic: [u8; 8],
+    /// The end of the allocations, as an offset from the start of the memory.
+    used: u64,
+    /// Incremented by every reset, to tell handles of released allocations.
+    generation: u64,
+    _reserved: [u8; 40],
+}
+
+const _: () = assert!(core::mem::size_of::<ArenaHeader>() == ArenaHeader::LEN);
+
+impl ArenaHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECAR";
+}
+
+/// A handle of a value allocated by [`MemArena::alloc`].
+#[repr(C)]
+pub struct ArenaRef<T> {
+    offset: u64,
+    generation: u64,
+    _marker: PhantomData<T>,
+}
+
+/// A handle of values allocated by [`MemArena::alloc_slice`].
+#[repr(C)]
+pub struct ArenaSlice<T> {
+    offset: u64,
+    len: u64,
+    generation: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ArenaRef<T> {
+    /// The offset of the value from the start of the memory.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<T> ArenaSlice<T> {
+    /// The offset of the values from the start of the memory.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+// derived impls would require T to implement them, too
+impl<T> Clone for ArenaRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaRef<T> {}
+
+impl<T> PartialEq for ArenaRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.offset, self.generation) == (other.offset, other.generation)
+    }
+}
+
+impl<T> Eq for ArenaRef<T> {}
+
+impl<T> core::fmt::Debug for ArenaRef<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArenaRef")
+            .field("offset", &self.offset)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<T> Clone for ArenaSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaSlice<T> {}
+
+impl<T> PartialEq for ArenaSlice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.offset, self.len, self.generation) == (other.offset, other.len, other.generation)
+    }
+}
+
+impl<T> Eq for ArenaSlice<T> {}
+
+impl<T> core::fmt::Debug for ArenaSlice<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArenaSlice")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<A: Memory<Error = std::io::Error>> MemArena<A> {
+    /// Create an empty arena in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no arena.
+    ///
+    /// # Safety
+    /// The allocations must hold valid bytes representations of the types of their handles, and
+    /// only handles allocated by this arena may be used with it; the handles of another arena are
+    /// not told apart.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(ArenaHeader::LEN)?;
+        } else if mem.len() < ArenaHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec arena"));
+        }
+        check_align::<ArenaHeader>(mem.as_ptr())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            mem.as_mut_ptr().cast::<ArenaHeader>().write(ArenaHeader {
+                magic: ArenaHeader::MAGIC,
+                used: (ArenaHeader::LEN as u64).to_le(),
+                generation: 0,
+                _reserved: [0; 40],
+            });
+            mem.set_len(ArenaHeader::LEN);
+        }
+        let this = Self { mem };
+        if this.header().magic != ArenaHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec arena"));
+        }
+        if u64::from_le(this.header().used) != this.mem.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Bump the high-water mark past `len` values of T, returning their offset.
+    fn bump<T>(&mut self, len: usize) -> std::io::Result<u64> {
+        use std::io::{Error, ErrorKind};
+        check_align::<T>(self.mem.as_ptr()).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let offset = self.used().next_multiple_of(core::mem::align_of::<T>());
+        let end = len
+            .checked_mul(core::mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(offset))
+            .filter(|&end| end <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if end > capacity {
+            self.mem
+                .reserve(end.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        self.mem.set_len(end);
+        self.header_mut().used = (end as u64).to_le();
+        Ok(offset as u64)
+    }
+
+    /// Allocate `value`.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if the memory is not aligned for T.
+    pub fn alloc<T: Plain>(&mut self, value: T) -> std::io::Result<ArenaRef<T>> {
+        let offset = self.bump::<T>(1)?;
+        unsafe {
+            let ptr = self.mem.as_mut_ptr().add(offset as usize).cast::<T>();
+            ptr.write(value);
+        }
+        Ok(ArenaRef {
+            offset,
+            generation: self.generation(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Allocate a copy of `values`. See [`MemArena::alloc`].
+    pub fn alloc_slice<T: Plain>(&mut self, values: &[T]) -> std::io::Result<ArenaSlice<T>> {
+        let offset = self.bump::<T>(values.len())?;
+        unsafe {
+            let ptr = self.mem.as_mut_ptr().add(offset as usize).cast::<T>();
+            core::ptr::copy_nonoverlapping(values.as_ptr(), ptr, values.len());
+        }
+        Ok(ArenaSlice {
+            offset,
+            len: values.len() as u64,
+            generation: self.generation(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<A: Memory> MemArena<A> {
+    fn header(&self) -> &ArenaHeader {
+        unsafe { &*self.mem.as_ptr().cast::<ArenaHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut ArenaHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<ArenaHeader>() }
+    }
+
+    fn generation(&self) -> u64 {
+        u64::from_le(self.header().generation)
+    }
+
+    /// Check that `len` values of T at `offset` were allocated since the last reset.
+    fn check<T>(&self, offset: u64, len: u64, generation: u64) -> Option<usize> {
+        let end = len
+            .checked_mul(core::mem::size_of::<T>() as u64)?
+            .checked_add(offset)?;
+        let valid = generation == self.generation()
+            && offset >= ArenaHeader::LEN as u64
+            && end <= self.used() as u64
+            && offset.is_multiple_of(core::mem::align_of::<T>() as u64);
+        valid.then_some(offset as usize)
+    }
+
+    /// The high-water mark, i.e. the end of the allocations in bytes from the start of the
+    /// memory.
+    pub fn used(&self) -> usize {
+        u64::from_le(self.header().used) as usize
+    }
+
+    /// The value of `handle`, or `None` if it was released by a reset.
+    pub fn get<T: Plain>(&self, handle: ArenaRef<T>) -> Option<&T> {
+        let offset = self.check::<T>(handle.offset, 1, handle.generation)?;
+        Some(unsafe { &*self.mem.as_ptr().add(offset).cast::<T>() })
+    }
+
+    pub fn get_mut<T: Plain>(&mut self, handle: ArenaRef<T>) -> Option<&mut T> {
+        let offset = self.check::<T>(handle.offset, 1, handle.generation)?;
+        Some(unsafe { &mut *self.mem.as_mut_ptr().add(offset).cast::<T>() })
+    }
+
+    /// The values of `handle`, or `None` if they were released by a reset.
+    pub fn slice<T: Plain>(&self, handle: ArenaSlice<T>) -> Option<&[T]> {
+        let offset = self.check::<T>(handle.offset, handle.len, handle.generation)?;
+        Some(unsafe {
+            core::slice::from_raw_parts(
+                self.mem.as_ptr().add(offset).cast::<T>(),
+                handle.len as usize,
+            )
+        })
+    }
+
+    pub fn slice_mut<T: Plain>(&mut self, handle: ArenaSlice<T>) -> Option<&mut [T]> {
+        let offset = self.check::<T>(handle.offset, handle.len, handle.generation)?;
+        Some(unsafe {
+            core::slice::from_raw_parts_mut(
+                self.mem.as_mut_ptr().add(offset).cast::<T>(),
+                handle.len as usize,
+            )
+        })
+    }
+
+    /// Release all allocations, keeping the reserved memory. Handles of them are rejected from
+    /// now on.
+    pub fn reset(&mut self) {
+        let generation = self.generation().wrapping_add(1);
+        let header = self.header_mut();
+        header.used = (ArenaHeader::LEN as u64).to_le();
+        header.generation = generation.to_le();
+        self.mem.set_len(ArenaHeader::LEN);
+    }
+
+    /// Write the allocations and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<A: Memory> core::fmt::Debug for MemArena<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemArena")
+            .field("used", &self.used())
+            .field("generation", &self.generation())
+            .finish()
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+use ::arrow_buffer::{ArrowNativeType, Buffer, ScalarBuffer};
+use core::{panic::AssertUnwindSafe, ptr::NonNull};
+use std::sync::Arc;
+
+/// Zero-copy export to Arrow, e.g. of a mapped numeric column into an Arrow or Parquet pipeline.
+///
+/// The buffer takes the vector over: the memory, e.g. the mapping of a [`crate::VecFile`], stays
+/// in place and unchanged until the last clone of the buffer drops, and the vector drops then.
+impl<T: 'static + Plain, A: 'static + Memory> MemVec<'static, T, A>
+where
+    Self: Send + Sync,
+{
+    /// Move the vector into a [`Buffer`] of the bytes of its elements.
+    pub fn into_arrow_buffer(self) -> Buffer {
+        let len = core::mem::size_of_val(self.as_slice());
+        // empty memory may have no address; a dangling one keeps the buffer aligned for T
+        let ptr = NonNull::new(self.as_ptr().cast_mut())
+            .unwrap_or(NonNull::dangling())
+            .cast::<u8>();
+        // the buffer only holds the vector to drop it, so no broken state can be observed
+        let owner = Arc::new(AssertUnwindSafe(self));
+        unsafe { Buffer::from_custom_allocation(ptr, len, owner) }
+    }
+
+    /// Move the vector into a [`ScalarBuffer`] of its elements.
+    pub fn into_scalar_buffer(self) -> ScalarBuffer<T>
+    where
+        T: ArrowNativeType,
+    {
+        let len = self.len();
+        ScalarBuffer::new(self.into_arrow_buffer(), 0, len)
+    }
+}
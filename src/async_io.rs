@@ -0,0 +1,191 @@
+use crate::{
+    builder::VecFileBuilder, follower::Follower, mem_vec::MemVec, memory::Memory, plain::Plain,
+    vec_file::VecFile,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::path::Path;
+
+/// Run `f` on the blocking thread pool of the Tokio runtime.
+async fn spawn_blocking<R: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<R> + Send + 'static,
+) -> std::io::Result<R> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Run `f` with a clone of `file` on the blocking thread pool of the Tokio runtime.
+async fn spawn_blocking_with(
+    file: &std::fs::File,
+    f: impl FnOnce(std::fs::File) -> std::io::Result<()> + Send + 'static,
+) -> std::io::Result<()> {
+    let file = file.try_clone()?;
+    spawn_blocking(move || f(file)).await
+}
+
+impl VecFileBuilder {
+    /// [`VecFileBuilder::open`] on the blocking thread pool, so that opening, validating and
+    /// mapping the file never block the executor. Must be called within a Tokio runtime.
+    pub async fn open_async(&self, path: impl AsRef<Path>) -> std::io::Result<VecFile> {
+        let (builder, path) = (self.clone(), path.as_ref().to_owned());
+        spawn_blocking(move || builder.open(path)).await
+    }
+}
+
+impl VecFile {
+    /// [`VecFile::open`] on the blocking thread pool. See [`VecFileBuilder::open_async`].
+    pub async fn open_async(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::builder().open_async(path).await
+    }
+
+    /// [`VecFile::open_or_create`] on the blocking thread pool, running `init` there too. See
+    /// [`VecFileBuilder::open_async`].
+    pub async fn open_or_create_async(
+        path: impl AsRef<Path>,
+        init: impl FnOnce(&mut VecFile) -> std::io::Result<()> + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        spawn_blocking(move || Self::open_or_create(path, init)).await
+    }
+
+    /// Write the data region and the header durably, like [`Memory::sync`] of everything, without
+    /// blocking the executor.
+    ///
+    /// Writeback of the dirty pages is started right away, and the file is synced on the blocking
+    /// thread pool. Must be called within a Tokio runtime. Dropping the future does not cancel the
+    /// sync.
+    pub async fn flush_async(&self) -> std::io::Result<()> {
+        self.flush_start()?;
+        spawn_blocking_with(self.file(), |file| file.sync_data()).await
+    }
+
+    /// Reserve a data region of `capacity` bytes like [`Memory::reserve`], extending the file on
+    /// the blocking thread pool, so that only remapping it runs on the executor.
+    ///
+    /// Must be called within a Tokio runtime. If the future is dropped, the file may be extended
+    /// without the mapping, which the next reserve picks up.
+    pub async fn reserve_async(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity <= self.capacity() {
+            return Ok(());
+        }
+        let file_len = self.data_file_offset() + capacity as u64;
+        if self.file().metadata()?.len() < file_len {
+            spawn_blocking_with(self.file(), move |file| file.set_len(file_len)).await?;
+            self.refresh_len()?;
+        }
+        Memory::reserve(self, capacity)
+    }
+}
+
+impl<'a, T: Plain> MemVec<'a, T, VecFile> {
+    /// [`MemVec::flush`] without blocking the executor. See [`VecFile::flush_async`].
+    pub async fn flush_async(&self) -> std::io::Result<()> {
+        self.as_mem().flush_async().await
+    }
+
+    /// Reserve capacity for exactly `additional` more elements without blocking the executor on
+    /// extending the file. See [`VecFile::reserve_async`].
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    pub async fn reserve_async(&mut self, additional: usize) -> std::io::Result<()> {
+        let capacity = self
+            .len()
+            .checked_add(additional)
+            .and_then(|capacity| capacity.checked_mul(core::mem::size_of::<T>()))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .expect("capacity overflow");
+        self.as_mem_mut().reserve_async(capacity).await
+    }
+}
+
+/// A [`Stream`](futures_core::Stream) of the records of a [`Follower`], yielding them as the
+/// writer appends them. See [`Follower::into_stream`].
+///
+/// While no new records are visible, the follower is refreshed on a timer whose interval doubles
+/// up to [`FollowerStream::set_max_interval`], like [`crate::Watch`]. The stream never ends; a
+/// failing refresh is yielded as an error.
+pub struct FollowerStream<T: Plain> {
+    follower: Follower<T>,
+    index: usize,
+    interval: Duration,
+    max_interval: Duration,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<T: Plain> Follower<T> {
+    /// Stream the records from the first one on. Must be polled within a Tokio runtime with the
+    /// timer enabled.
+    pub fn into_stream(self) -> FollowerStream<T> {
+        FollowerStream {
+            follower: self,
+            index: 0,
+            interval: FollowerStream::<T>::MIN_INTERVAL,
+            max_interval: FollowerStream::<T>::DEFAULT_MAX_INTERVAL,
+            sleep: None,
+        }
+    }
+}
+
+impl<T: Plain> FollowerStream<T> {
+    const MIN_INTERVAL: Duration = Duration::from_micros(100);
+    const DEFAULT_MAX_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// The longest wait between two refreshes, which bounds the delay of yielding a new record.
+    pub fn set_max_interval(&mut self, max_interval: Duration) {
+        self.max_interval = max_interval;
+    }
+
+    /// The follower and the index of the next record to yield.
+    pub fn into_inner(self) -> (Follower<T>, usize) {
+        (self.follower, self.index)
+    }
+}
+
+// nothing is pinned structurally; the timer is boxed
+impl<T: Plain> Unpin for FollowerStream<T> {}
+
+impl<T: Plain> futures_core::Stream for FollowerStream<T> {
+    type Item = std::io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(record) = this.follower.get(this.index) {
+                // SAFETY: Plain values are copied bitwise
+                let record = unsafe { core::ptr::read(record) };
+                this.index += 1;
+                this.interval = Self::MIN_INTERVAL;
+                return Poll::Ready(Some(Ok(record)));
+            }
+            if let Some(sleep) = &mut this.sleep {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.sleep = None;
+            }
+            match this.follower.refresh() {
+                Err(e) => return Poll::Ready(Some(Err(e))),
+                Ok(0) => {
+                    this.sleep = Some(Box::pin(tokio::time::sleep(this.interval)));
+                    this.interval = (this.interval * 2).min(this.max_interval);
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+impl<T: Plain> core::fmt::Debug for FollowerStream<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FollowerStream")
+            .field("index", &self.index)
+            .field("len", &self.follower.len())
+            .finish()
+    }
+}
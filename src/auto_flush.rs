@@ -0,0 +1,155 @@
+use std::{
+    fs::File,
+    sync::{Arc, Condvar, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Policy of the background flusher of a [`crate::VecFile`].
+///
+/// See [`crate::VecFile::enable_auto_flush`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoFlush {
+    /// The file is flushed every `interval`.
+    pub interval: Duration,
+    /// Flush as soon as this many bytes of records were appended since the last flush.
+    pub dirty_bytes: Option<usize>,
+    /// Size of a record in bytes, used to count appended bytes from the length.
+    pub record_size: usize,
+}
+
+impl AutoFlush {
+    /// Flush every `interval` without a dirty-byte threshold.
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            dirty_bytes: None,
+            record_size: 0,
+        }
+    }
+}
+
+/// Background thread flushing a file as configured by [`AutoFlush`].
+#[derive(Debug)]
+pub(crate) struct Flusher {
+    policy: AutoFlush,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    /// The file flushed, replaced when the vector file is, see [`Flusher::retarget`].
+    file: Mutex<File>,
+    state: Mutex<FlushState>,
+    wakeup: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct FlushState {
+    dirty_bytes: usize,
+    stop: bool,
+    error: Option<std::io::Error>,
+}
+
+impl Flusher {
+    pub fn start(file: File, policy: AutoFlush) -> std::io::Result<Self> {
+        let shared = Arc::new(Shared {
+            file: Mutex::new(file),
+            state: Mutex::default(),
+            wakeup: Condvar::new(),
+        });
+        let thread = std::thread::Builder::new()
+            .name("memvec-flush".to_owned())
+            .spawn({
+                let shared = shared.clone();
+                move || shared.run(policy.interval)
+            })?;
+        Ok(Self {
+            policy,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Flush `file` from now on, after the vector file was replaced by another one, e.g. by
+    /// [`crate::VecFile::compact`].
+    pub fn retarget(&self, file: File) {
+        *self.shared.file.lock().unwrap() = file;
+    }
+
+    /// Count records appended by the change of the length from `old_len` to `len`.
+    pub fn record_len_change(&self, old_len: usize, len: usize) {
+        let Some(threshold) = self.policy.dirty_bytes else {
+            return;
+        };
+        let appended = len.saturating_sub(old_len) * self.policy.record_size;
+        if appended == 0 {
+            return;
+        }
+        let mut state = self.shared.state.lock().unwrap();
+        state.dirty_bytes += appended;
+        if state.dirty_bytes >= threshold {
+            self.shared.wakeup.notify_one();
+        }
+    }
+
+    /// Flush once more, stop the thread and return the last flush error, if any.
+    pub fn stop(mut self) -> std::io::Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> std::io::Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.wakeup.notify_one();
+        let _ = thread.join();
+        match self.shared.state.lock().unwrap().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Flusher {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+impl Shared {
+    fn run(&self, interval: Duration) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let stop = state.stop;
+            state.dirty_bytes = 0;
+            drop(state);
+            if let Err(e) = Self::flush(&self.file.lock().unwrap()) {
+                self.state.lock().unwrap().error = Some(e);
+            }
+            if stop {
+                return;
+            }
+            state = self.state.lock().unwrap();
+            if !state.stop {
+                state = self.wakeup.wait_timeout(state, interval).unwrap().0;
+            }
+        }
+    }
+
+    /// Write back the dirty pages of the mappings of `file`.
+    #[cfg(not(windows))]
+    fn flush(file: &File) -> std::io::Result<()> {
+        // the page cache holds the pages of the mappings, so syncing the file covers them
+        file.sync_data()
+    }
+
+    /// Write back the dirty pages of the mappings of `file`.
+    #[cfg(windows)]
+    fn flush(file: &File) -> std::io::Result<()> {
+        // FlushFileBuffers skips pages dirtied through views, which only FlushViewOfFile writes
+        memmap2::MmapRaw::map_raw(file).and_then(|mmap| mmap.flush())
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{mem_vec::MemVec, plain::Plain, vec_file::VecFile};
+use core::ops::Range;
+
+/// Sort `ranges` of the data region and merge those which overlap or touch, dropping empty ones.
+fn merge_ranges(ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut sorted: Vec<_> = ranges.iter().filter(|r| !r.is_empty()).cloned().collect();
+    sorted.sort_unstable_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(sorted.len());
+    for range in sorted {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+impl VecFile {
+    /// Write the bytes of several `ranges` of the data region and the header durably.
+    ///
+    /// The ranges are merged first. With the `io-uring` feature on Linux, the syncs of all of them
+    /// are submitted to an io_uring at once and waited for together; otherwise, or if the kernel
+    /// refuses to set up a ring, they are synced one after another.
+    ///
+    /// # Panics
+    /// Panics if a range is out of bounds of the data region.
+    pub fn flush_ranges(&self, ranges: &[Range<usize>]) -> std::io::Result<()> {
+        let ranges = merge_ranges(ranges);
+        if let Some(last) = ranges.last() {
+            assert!(last.end <= self.capacity(), "range out of bounds");
+        }
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        let synced = match uring::flush_ranges(self, &ranges) {
+            Some(result) => {
+                result?;
+                true
+            }
+            None => false,
+        };
+        #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+        let synced = false;
+        if !synced {
+            for range in ranges {
+                self.sync_data_range(range)?;
+            }
+        }
+        // the length last, so it never covers records which are not durable yet
+        self.sync_header()
+    }
+
+    /// Advise the kernel that `ranges` of the data region will be read soon, so it reads them
+    /// ahead in the background.
+    ///
+    /// Like [`VecFile::flush_ranges`], the advice is submitted at once with the `io-uring`
+    /// feature. It does nothing on platforms without such advice.
+    pub fn readahead(&self, ranges: &[Range<usize>]) -> std::io::Result<()> {
+        let ranges = merge_ranges(ranges);
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if let Some(result) = uring::readahead(self, &ranges) {
+            return result;
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        for range in ranges {
+            use std::os::unix::io::AsRawFd;
+            let offset = self.data_file_offset() + range.start as u64;
+            let ret = unsafe {
+                libc::posix_fadvise(
+                    self.file().as_raw_fd(),
+                    offset as libc::off_t,
+                    range.len() as libc::off_t,
+                    libc::POSIX_FADV_WILLNEED,
+                )
+            };
+            if ret != 0 {
+                return Err(std::io::Error::from_raw_os_error(ret));
+            }
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let _ = ranges;
+        Ok(())
+    }
+}
+
+impl<'a, T: Plain> MemVec<'a, T, VecFile> {
+    /// Write the elements in several `ranges` and the length durably. See
+    /// [`VecFile::flush_ranges`].
+    ///
+    /// # Panics
+    /// Panics if a range is out of bounds of the vector.
+    pub fn flush_ranges(&self, ranges: &[Range<usize>]) -> std::io::Result<()> {
+        let size = core::mem::size_of::<T>();
+        let bytes: Vec<_> = ranges
+            .iter()
+            .map(|r| {
+                assert!(
+                    r.start <= r.end && r.end <= self.len(),
+                    "range out of bounds"
+                );
+                r.start * size..r.end * size
+            })
+            .collect();
+        self.as_mem().flush_ranges(&bytes)
+    }
+
+    /// Read the elements in `ranges` ahead. See [`VecFile::readahead`].
+    pub fn readahead(&self, ranges: &[Range<usize>]) -> std::io::Result<()> {
+        let size = core::mem::size_of::<T>();
+        let len = self.len();
+        let bytes: Vec<_> = ranges
+            .iter()
+            .map(|r| r.start.min(len) * size..r.end.min(len) * size)
+            .collect();
+        self.as_mem().readahead(&bytes)
+    }
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring {
+    use super::*;
+    use io_uring::{opcode, squeue, types, IoUring};
+    use std::os::unix::io::AsRawFd;
+
+    /// The ring is not worth setting up for fewer operations.
+    const MIN_BATCH: usize = 2;
+    const MAX_ENTRIES: usize = 256;
+
+    /// Submit the entries in batches of the ring size and wait for all of them. `None` if no ring
+    /// can be set up, so the caller falls back to plain system calls.
+    fn submit_all(entries: &[squeue::Entry]) -> Option<std::io::Result<()>> {
+        if entries.len() < MIN_BATCH {
+            return None;
+        }
+        let size = entries.len().min(MAX_ENTRIES).next_power_of_two();
+        let mut ring = IoUring::new(size as u32).ok()?;
+        Some((|| {
+            for batch in entries.chunks(size) {
+                for entry in batch {
+                    // SAFETY: the entries refer to no buffers, only to the file and the mapping,
+                    // which outlive the wait below
+                    unsafe { ring.submission().push(entry) }.expect("ring has room for a batch");
+                }
+                ring.submit_and_wait(batch.len())?;
+                let mut first_error = None;
+                for cqe in ring.completion() {
+                    if cqe.result() < 0 && first_error.is_none() {
+                        first_error = Some(std::io::Error::from_raw_os_error(-cqe.result()));
+                    }
+                }
+                if let Some(e) = first_error {
+                    return Err(e);
+                }
+            }
+            Ok(())
+        })())
+    }
+
+    /// Split `range` of the data region into file ranges of at most `u32::MAX` bytes.
+    fn file_ranges(vec_file: &VecFile, range: &Range<usize>) -> impl Iterator<Item = (u64, u32)> {
+        let start = vec_file.data_file_offset() + range.start as u64;
+        let end = vec_file.data_file_offset() + range.end as u64;
+        (start..end)
+            .step_by(u32::MAX as usize)
+            .map(move |offset| (offset, (end - offset).min(u32::MAX as u64) as u32))
+    }
+
+    pub(super) fn flush_ranges(
+        vec_file: &VecFile,
+        ranges: &[Range<usize>],
+    ) -> Option<std::io::Result<()>> {
+        let fd = types::Fd(vec_file.file().as_raw_fd());
+        // a mapping of a file is synced like the file itself
+        let entries: Vec<_> = ranges
+            .iter()
+            .flat_map(|range| file_ranges(vec_file, range))
+            .map(|(offset, len)| {
+                opcode::Fsync::new(fd)
+                    .offset(offset)
+                    .len(len)
+                    .flags(types::FsyncFlags::DATASYNC)
+                    .build()
+            })
+            .collect();
+        submit_all(&entries)
+    }
+
+    pub(super) fn readahead(
+        vec_file: &VecFile,
+        ranges: &[Range<usize>],
+    ) -> Option<std::io::Result<()>> {
+        let fd = types::Fd(vec_file.file().as_raw_fd());
+        let entries: Vec<_> = ranges
+            .iter()
+            .flat_map(|range| file_ranges(vec_file, range))
+            .map(|(offset, len)| {
+                opcode::Fadvise::new(fd, len as libc::off_t, libc::POSIX_FADV_WILLNEED)
+                    .offset(offset)
+                    .build()
+            })
+            .collect();
+        submit_all(&entries)
+    }
+}
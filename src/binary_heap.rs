@@ -0,0 +1,120 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+
+/// A priority queue over a [`MemVec`], popping the greatest element first like
+/// [`std::collections::BinaryHeap`].
+///
+/// The elements are kept in the implicit binary tree layout of the vector, so pushing and popping
+/// sift the elements in place in the memory. The layout is established when the wrapper is
+/// created, which only compares the elements of a vector that already holds a heap.
+pub struct MemBinaryHeap<'a, T: Plain + Ord, A: 'a + Memory> {
+    vec: MemVec<'a, T, A>,
+}
+
+impl<'a, T: Plain + Ord, A: 'a + Memory> MemBinaryHeap<'a, T, A> {
+    /// Wrap `vec`, rearranging its elements into a heap unless they already are one.
+    pub fn from_vec(vec: MemVec<'a, T, A>) -> Self {
+        let mut this = Self { vec };
+        let len = this.vec.len();
+        for index in (0..len / 2).rev() {
+            this.sift_down(index, len);
+        }
+        this
+    }
+
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if self.vec[index] <= self.vec[parent] {
+                break;
+            }
+            self.vec.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    /// Sift the element at `index` down within the first `len` elements.
+    fn sift_down(&mut self, mut index: usize, len: usize) {
+        loop {
+            let left = 2 * index + 1;
+            if left >= len {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < len && self.vec[right] > self.vec[left] {
+                right
+            } else {
+                left
+            };
+            if self.vec[index] >= self.vec[child] {
+                break;
+            }
+            self.vec.swap(index, child);
+            index = child;
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.vec.push(value);
+        self.sift_up(self.vec.len() - 1);
+    }
+
+    /// Remove the greatest element.
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.vec.len();
+        if len == 0 {
+            return None;
+        }
+        self.vec.swap(0, len - 1);
+        let value = self.vec.pop();
+        self.sift_down(0, len - 1);
+        value
+    }
+
+    /// The greatest element.
+    pub fn peek(&self) -> Option<&T> {
+        self.vec.first()
+    }
+
+    /// Consume the heap into its vector sorted in ascending order, in place.
+    pub fn into_sorted_vec(mut self) -> MemVec<'a, T, A> {
+        for end in (1..self.vec.len()).rev() {
+            self.vec.swap(0, end);
+            self.sift_down(0, end);
+        }
+        self.vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// The elements in the heap order, which is arbitrary apart from the greatest one first.
+    pub fn as_slice(&self) -> &[T] {
+        &self.vec
+    }
+
+    pub fn into_vec(self) -> MemVec<'a, T, A> {
+        self.vec
+    }
+
+    /// Write the elements and the length durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.vec.flush()
+    }
+}
+
+impl<'a, T: core::fmt::Debug + Plain + Ord, A: 'a + Memory> core::fmt::Debug
+    for MemBinaryHeap<'a, T, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&*self.vec, f)
+    }
+}
@@ -0,0 +1,286 @@
+use crate::memory::{check_align, Memory, MemoryConversionError};
+
+/// A vector of bits over [`Memory`], e.g. per-record flags of a [`crate::VecFile`] at one bit per
+/// record.
+///
+/// The memory starts with a bit vector header holding the length in bits, followed by the bits in
+/// little-endian 64-bit words. Bits past the length are kept zero, so counting only sums the
+/// population counts of the words.
+pub struct MemBitVec<A: Memory> {
+    mem: A,
+}
+
+#[repr(C)]
+struct BitVecHeader {
+    magic: [u8; 8],
+    /// The length in bits.
+    len: u64,
+    _reserved: [u8; 48],
+}
+
+const _: () = assert!(core::mem::size_of::<BitVecHeader>() == BitVecHeader::LEN);
+
+impl BitVecHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECBV";
+}
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+fn words_for(bits: usize) -> usize {
+    bits.div_ceil(WORD_BITS)
+}
+
+impl<A: Memory<Error = std::io::Error>> MemBitVec<A> {
+    /// Create an empty bit vector in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no bit vector.
+    pub fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(BitVecHeader::LEN)?;
+        } else if mem.len() < BitVecHeader::LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a memvec bit vector",
+            ));
+        }
+        check_align::<BitVecHeader>(mem.as_ptr())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            unsafe {
+                mem.as_mut_ptr().cast::<BitVecHeader>().write(BitVecHeader {
+                    magic: BitVecHeader::MAGIC,
+                    len: 0,
+                    _reserved: [0; 48],
+                })
+            };
+            mem.set_len(BitVecHeader::LEN);
+        }
+        let this = Self { mem };
+        if this.header().magic != BitVecHeader::MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a memvec bit vector",
+            ));
+        }
+        let bytes = usize::try_from(u64::from_le(this.header().len))
+            .ok()
+            .map(|len| BitVecHeader::LEN + words_for(len) * 8);
+        if bytes != Some(this.mem.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Resize to `new_len` bits, setting the added ones to `value`.
+    pub fn resize(&mut self, new_len: usize, value: bool) -> std::io::Result<()> {
+        let len = self.len();
+        if new_len <= len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+        let bytes = words_for(new_len)
+            .checked_mul(8)
+            .and_then(|bytes| bytes.checked_add(BitVecHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+            })?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if bytes > capacity {
+            self.mem
+                .reserve(bytes.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        let old_words = words_for(len);
+        self.mem.set_len(bytes);
+        // the grown words may hold stale bits of a truncated vector
+        self.mem[BitVecHeader::LEN + old_words * 8..bytes].fill(0);
+        self.header_mut().len = (new_len as u64).to_le();
+        if value {
+            self.fill_range(len, new_len);
+        }
+        Ok(())
+    }
+
+    /// Append a bit.
+    pub fn push(&mut self, value: bool) -> std::io::Result<()> {
+        self.resize(self.len() + 1, value)
+    }
+}
+
+impl<A: Memory> MemBitVec<A> {
+    fn header(&self) -> &BitVecHeader {
+        unsafe { &*self.mem.as_ptr().cast::<BitVecHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut BitVecHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<BitVecHeader>() }
+    }
+
+    fn words(&self) -> &[u64] {
+        let words = words_for(self.len());
+        unsafe {
+            let ptr = self.mem.as_ptr().add(BitVecHeader::LEN).cast();
+            core::slice::from_raw_parts(ptr, words)
+        }
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        let words = words_for(self.len());
+        unsafe {
+            let ptr = self.mem.as_mut_ptr().add(BitVecHeader::LEN).cast();
+            core::slice::from_raw_parts_mut(ptr, words)
+        }
+    }
+
+    /// Set the bits of `start..end`, which must be in bounds.
+    fn fill_range(&mut self, start: usize, end: usize) {
+        let words = self.words_mut();
+        for bit in start..end {
+            let word = &mut words[bit / WORD_BITS];
+            *word = (u64::from_le(*word) | 1 << (bit % WORD_BITS)).to_le();
+        }
+    }
+
+    /// The length in bits.
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len() {
+            return None;
+        }
+        let word = u64::from_le(self.words()[index / WORD_BITS]);
+        Some(word >> (index % WORD_BITS) & 1 == 1)
+    }
+
+    /// Set the bit at `index` to `value`, returning its previous value.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: bool) -> bool {
+        let len = self.len();
+        assert!(index < len, "index {index} out of bounds of {len} bits");
+        let word = &mut self.words_mut()[index / WORD_BITS];
+        let mask = 1 << (index % WORD_BITS);
+        let old = u64::from_le(*word);
+        let new = if value { old | mask } else { old & !mask };
+        *word = new.to_le();
+        old & mask != 0
+    }
+
+    /// Shorten to `new_len` bits, doing nothing if it is not shorter.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.len() {
+            return;
+        }
+        let words = words_for(new_len);
+        if !new_len.is_multiple_of(WORD_BITS) {
+            let word = &mut self.words_mut()[words - 1];
+            let mask = (1 << (new_len % WORD_BITS)) - 1;
+            *word = (u64::from_le(*word) & mask).to_le();
+        }
+        self.header_mut().len = (new_len as u64).to_le();
+        self.mem.set_len(BitVecHeader::LEN + words * 8);
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn count_zeros(&self) -> usize {
+        self.len() - self.count_ones()
+    }
+
+    /// The number of set bits before `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the length.
+    pub fn rank(&self, index: usize) -> usize {
+        let len = self.len();
+        assert!(index <= len, "index {index} out of bounds of {len} bits");
+        let words = self.words();
+        let full = index / WORD_BITS;
+        let ones: usize = words[..full]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum();
+        match index % WORD_BITS {
+            0 => ones,
+            rest => ones + (u64::from_le(words[full]) & ((1 << rest) - 1)).count_ones() as usize,
+        }
+    }
+
+    /// The index of the set bit of rank `rank`, i.e. with `rank` set bits before it.
+    pub fn select(&self, mut rank: usize) -> Option<usize> {
+        for (i, &word) in self.words().iter().enumerate() {
+            let mut word = u64::from_le(word);
+            let ones = word.count_ones() as usize;
+            if rank >= ones {
+                rank -= ones;
+                continue;
+            }
+            for _ in 0..rank {
+                // clear the lowest set bit
+                word &= word - 1;
+            }
+            return Some(i * WORD_BITS + word.trailing_zeros() as usize);
+        }
+        None
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len()).map(|index| self.get(index).expect("in bounds"))
+    }
+
+    /// The indices of the set bits in ascending order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words().iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = u64::from_le(word);
+            core::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+
+    /// Write the bits and the length durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<A: Memory> core::fmt::Debug for MemBitVec<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(feature = "mmap")]
+use crate::segment_file::{Segment, SegmentFile};
+use crate::{
+    mem_vec::MemVec,
+    memory::{Memory, MemoryConversionError},
+};
+
+/// A vector of variable-length byte strings over two [`Memory`] regions, e.g. a string table or
+/// a document store in two segments of one [`crate::SegmentFile`].
+///
+/// The heap holds the bytes of the blobs back to back, and the index holds the offset and the
+/// length of every blob in the heap, so that the blobs are found by their index like the records
+/// of a [`MemVec`]. The bytes of a blob are written to the heap before its entry is pushed to the
+/// index; heap bytes not covered by an entry, e.g. of a push torn by a crash, are dropped when
+/// the store is opened.
+pub struct BlobStore<'a, A: 'a + Memory> {
+    index: MemVec<'a, BlobEntry, A>,
+    heap: MemVec<'a, u8, A>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BlobEntry {
+    offset: u64,
+    len: u64,
+}
+
+impl BlobEntry {
+    fn range(&self) -> core::ops::Range<usize> {
+        let offset = u64::from_le(self.offset) as usize;
+        offset..offset + u64::from_le(self.len) as usize
+    }
+}
+
+impl<'a, A: 'a + Memory<Error = std::io::Error>> BlobStore<'a, A> {
+    /// Create an empty store in empty memories, or open the one they hold.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if an entry of the index lies
+    /// outside of the heap.
+    pub fn new(index: A, heap: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        // any bytes are valid offsets and lengths; the alignment and the length are checked
+        let index = unsafe { MemVec::<BlobEntry, A>::try_from_memory(index) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        let mut heap = unsafe { MemVec::<u8, A>::try_from_memory(heap) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        let mut end = 0;
+        for entry in index.iter() {
+            let (offset, len) = (u64::from_le(entry.offset), u64::from_le(entry.len));
+            match offset.checked_add(len) {
+                Some(entry_end) if entry_end <= heap.len() as u64 => {
+                    end = end.max(entry_end as usize)
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        MemoryConversionError::SizeMismatch,
+                    ))
+                }
+            }
+        }
+        heap.truncate(end);
+        Ok(Self { index, heap })
+    }
+
+    /// Append a blob and return its index.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.heap.try_reserve(bytes.len())?;
+        self.index.try_reserve(1)?;
+        let offset = self.heap.len();
+        unsafe {
+            let ptr = self.heap.as_mut_ptr().add(offset);
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            self.heap.set_len(offset + bytes.len());
+        }
+        self.index.push(BlobEntry {
+            offset: (offset as u64).to_le(),
+            len: (bytes.len() as u64).to_le(),
+        });
+        Ok(self.index.len() - 1)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl BlobStore<'static, Segment> {
+    /// Create an empty store in the segments `"blob_index"` and `"blob_heap"` of `file`, or open
+    /// the one they hold. See [`BlobStore::new`].
+    pub fn from_segment_file(file: &SegmentFile) -> std::io::Result<Self> {
+        Self::new(file.segment("blob_index")?, file.segment("blob_heap")?)
+    }
+}
+
+impl<'a, A: 'a + Memory> BlobStore<'a, A> {
+    /// The number of blobs.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// The total length of the blobs in bytes.
+    pub fn heap_len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let entry = self.index.get(index)?;
+        Some(&self.heap[entry.range()])
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+        self.index.iter().map(|entry| &self.heap[entry.range()])
+    }
+
+    /// Shorten to `len` blobs, doing nothing if it is not shorter.
+    pub fn truncate(&mut self, len: usize) {
+        if let Some(entry) = self.index.get(len) {
+            let offset = entry.range().start;
+            self.index.truncate(len);
+            self.heap.truncate(offset);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Write the heap, then the index durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.heap.flush()?;
+        self.index.flush()
+    }
+
+    /// The memories of the index and the heap.
+    pub fn into_memories(self) -> (A, A) {
+        (self.index.into_mem(), self.heap.into_mem())
+    }
+}
+
+impl<'a, A: 'a + Memory> core::fmt::Debug for BlobStore<'a, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BlobStore")
+            .field("len", &self.len())
+            .field("heap_len", &self.heap_len())
+            .finish()
+    }
+}
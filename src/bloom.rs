@@ -0,0 +1,171 @@
+use crate::{hash_map::hash, mem_vec::MemVec, memory::MemoryConversionError, vec_file::VecFile};
+use core::hash::Hash;
+use std::path::Path;
+
+/// A Bloom filter in a [`VecFile`], answering whether an item may have been inserted without
+/// scanning the records it stands for.
+///
+/// The parameters of the filter are stored in the metadata region of the file and the bits in
+/// its data region as little-endian 64-bit words, so the file has the header, the version check
+/// and the optional checksum of every [`VecFile`]. Items are hashed with the same fixed hash
+/// function as [`crate::MemHashMap`], with the same requirements on their [`Hash`]
+/// implementation; the bits of an item are derived from its hash by double hashing.
+pub struct MemBloomFilter {
+    words: MemVec<'static, u64, VecFile>,
+}
+
+/// The metadata of a filter: a magic, the number of bits and the number of hashes.
+const METADATA_LEN: usize = 32;
+const MAGIC: [u8; 8] = *b"MEMVECBF";
+
+impl MemBloomFilter {
+    /// Create a filter of `bits` bits setting `hashes` bits per item in a new vector file at
+    /// `path`.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if either is zero. See
+    /// [`MemBloomFilter::optimal_parameters`] for choosing them.
+    pub fn create(path: impl AsRef<Path>, bits: u64, hashes: u32) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if bits == 0 || hashes == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Bloom filter without bits or hashes",
+            ));
+        }
+        let words = usize::try_from(bits.div_ceil(64))
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let mut vec_file = VecFile::create_with_metadata(path, METADATA_LEN)?;
+        let metadata = vec_file.metadata_mut();
+        metadata[..8].copy_from_slice(&MAGIC);
+        metadata[8..16].copy_from_slice(&bits.to_le_bytes());
+        metadata[16..20].copy_from_slice(&hashes.to_le_bytes());
+        let mut vec = unsafe { MemVec::try_from_memory(vec_file) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        vec.try_reserve_exact(words)?;
+        vec.resize_with(words, || 0);
+        Ok(Self { words: vec })
+    }
+
+    /// Open the filter in the vector file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::from_vec_file(VecFile::open(path)?)
+    }
+
+    /// Open the filter in `vec_file`, failing with [`std::io::ErrorKind::InvalidData`] if it holds
+    /// none.
+    pub fn from_vec_file(vec_file: VecFile) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let metadata = vec_file.metadata();
+        if metadata.len() != METADATA_LEN || metadata[..8] != MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a memvec Bloom filter",
+            ));
+        }
+        // any bytes are valid words; the alignment and the length are checked
+        let words = unsafe { MemVec::try_from_memory(vec_file) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        let this = Self { words };
+        if this.bits() == 0
+            || this.hashes() == 0
+            || this.bits().div_ceil(64) != this.words.len() as u64
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// The number of bits and of hashes for `items` items at a false positive rate of
+    /// `false_positive_rate`, which must be between 0 and 1.
+    pub fn optimal_parameters(items: u64, false_positive_rate: f64) -> (u64, u32) {
+        let ln2 = core::f64::consts::LN_2;
+        let items = items.max(1) as f64;
+        let bits = (-items * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(1.0);
+        let hashes = (bits / items * ln2).round().max(1.0);
+        (bits as u64, hashes as u32)
+    }
+
+    pub fn bits(&self) -> u64 {
+        u64::from_le_bytes(self.words.as_mem().metadata()[8..16].try_into().unwrap())
+    }
+
+    pub fn hashes(&self) -> u32 {
+        u32::from_le_bytes(self.words.as_mem().metadata()[16..20].try_into().unwrap())
+    }
+
+    /// The bits of `item`.
+    fn indices<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = u64> {
+        let bits = self.bits();
+        let h1 = hash(item);
+        let h2 = hash(&h1) | 1;
+        (0..self.hashes() as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bits)
+    }
+
+    /// Insert `item`, returning whether it set any bit, i.e. it was surely not in the filter.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let mut added = false;
+        for index in self.indices(item) {
+            let word = &mut self.words[(index / 64) as usize];
+            let mask = 1 << (index % 64);
+            added |= u64::from_le(*word) & mask == 0;
+            *word = (u64::from_le(*word) | mask).to_le();
+        }
+        added
+    }
+
+    /// Whether `item` may have been inserted. It was not if this is false.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.indices(item)
+            .all(|index| u64::from_le(self.words[(index / 64) as usize]) & 1 << (index % 64) != 0)
+    }
+
+    /// Add the items of `other`, which must have the same parameters.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if they differ.
+    pub fn merge(&mut self, other: &MemBloomFilter) -> std::io::Result<()> {
+        if (self.bits(), self.hashes()) != (other.bits(), other.hashes()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Bloom filters of different parameters",
+            ));
+        }
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= *other;
+        }
+        Ok(())
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> u64 {
+        self.words.iter().map(|word| word.count_ones() as u64).sum()
+    }
+
+    /// Remove all items.
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Write the bits durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.words.flush()
+    }
+
+    pub fn into_vec_file(self) -> VecFile {
+        self.words.into_mem()
+    }
+}
+
+impl core::fmt::Debug for MemBloomFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemBloomFilter")
+            .field("bits", &self.bits())
+            .field("hashes", &self.hashes())
+            .field("ones", &self.count_ones())
+            .finish()
+    }
+}
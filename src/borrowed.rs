@@ -0,0 +1,94 @@
+use crate::{mem_vec::MemVec, memory::Memory, policy::GrowthPolicy};
+use core::ops::{Deref, DerefMut};
+
+/// A [`MemVec`] over borrowed memory, see [`Memory::try_as_memvec`].
+///
+/// The memory stays with its owner, e.g. a field of an application struct, and is used as a
+/// vector for the scope of the borrow instead of being moved into a [`MemVec`] and taken back by
+/// [`MemVec::into_mem`] on every access. The policies set on the vector last as long as it does.
+pub type MemVecRef<'m, T, A> = MemVec<'m, T, BorrowedMemory<'m, A>>;
+
+/// Memory borrowed from its owner, forwarding everything to it.
+#[derive(Debug)]
+pub struct BorrowedMemory<'m, A: Memory> {
+    mem: &'m mut A,
+}
+
+impl<'m, A: Memory> BorrowedMemory<'m, A> {
+    pub fn new(mem: &'m mut A) -> Self {
+        Self { mem }
+    }
+
+    pub fn get(&self) -> &A {
+        self.mem
+    }
+
+    pub fn get_mut(&mut self) -> &mut A {
+        self.mem
+    }
+
+    pub fn into_inner(self) -> &'m mut A {
+        self.mem
+    }
+}
+
+impl<'m, A: Memory> Deref for BorrowedMemory<'m, A> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.mem
+    }
+}
+
+impl<'m, A: Memory> DerefMut for BorrowedMemory<'m, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.mem
+    }
+}
+
+impl<'m, A: Memory> Memory for BorrowedMemory<'m, A> {
+    type Error = A::Error;
+
+    fn as_ptr(&self) -> *const u8 {
+        self.mem.as_ptr()
+    }
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.mem.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.mem.len()
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.mem.set_len(len)
+    }
+
+    fn reserve(&mut self, capacity: usize) -> Result<(), Self::Error> {
+        self.mem.reserve(capacity)
+    }
+
+    fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
+        self.mem.shrink(capacity)
+    }
+
+    fn max_len(&self) -> usize {
+        self.mem.max_len()
+    }
+
+    fn capacity_overflow(&self) -> Option<Self::Error> {
+        self.mem.capacity_overflow()
+    }
+
+    fn growth_policy(&self) -> GrowthPolicy {
+        self.mem.growth_policy()
+    }
+
+    fn sync(&self, range: core::ops::Range<usize>) -> Result<(), Self::Error> {
+        self.mem.sync(range)
+    }
+
+    fn set_poisoned(&mut self, poisoned: bool) -> bool {
+        self.mem.set_poisoned(poisoned)
+    }
+}
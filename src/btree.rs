@@ -0,0 +1,603 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+/// An ordered map of fixed-size keys and values over [`Memory`], e.g. a sorted index over the
+/// records of a [`crate::VecFile`] supporting range scans.
+///
+/// The map is a B+ tree of nodes of one page each: the first page holds the map header, inner
+/// nodes hold separator keys and the page numbers of their children, and leaves hold the entries
+/// and the page number of the next leaf, so a range scan walks the leaves in order. Removal does
+/// not merge underfull nodes; the pages of the map are only released by [`MemBTreeMap::clear`].
+///
+/// The pages are ordered by [`Ord`] of the key type, which must be the same for every process and
+/// run using the memory.
+pub struct MemBTreeMap<K: Plain + Ord, V: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<(K, V)>,
+}
+
+#[repr(C)]
+struct TreeHeader {
+    magic: [u8; 8],
+    key_size: u64,
+    value_size: u64,
+    page_size: u64,
+    /// The number of pages, including this header page.
+    pages: u64,
+    root: u64,
+    len: u64,
+    _reserved: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<TreeHeader>() == TreeHeader::LEN);
+
+impl TreeHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECBT";
+}
+
+#[repr(C)]
+struct NodeHeader {
+    kind: u16,
+    count: u16,
+    _reserved: u32,
+    /// The next leaf, or 0 for the last one. Unused by inner nodes.
+    next: u64,
+}
+
+impl NodeHeader {
+    const LEN: usize = 16;
+    const LEAF: u16 = 1;
+    const INNER: u16 = 2;
+
+    fn count(&self) -> usize {
+        u16::from_le(self.count) as usize
+    }
+
+    fn is_leaf(&self) -> bool {
+        u16::from_le(self.kind) == Self::LEAF
+    }
+}
+
+const PAGE: usize = 4096;
+
+/// Copy out the values of a slice of plain values.
+fn copied<T: Plain>(slice: &[T]) -> Vec<T> {
+    // SAFETY: Plain values are copied bitwise
+    slice
+        .iter()
+        .map(|v| unsafe { core::ptr::read(v) })
+        .collect()
+}
+
+fn broken() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, "broken memvec b-tree")
+}
+
+fn copy_bound<K: Plain>(bound: Bound<&K>) -> Bound<K> {
+    match bound {
+        Bound::Included(key) => Bound::Included(unsafe { core::ptr::read(key) }),
+        Bound::Excluded(key) => Bound::Excluded(unsafe { core::ptr::read(key) }),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<K: Plain + Ord, V: Plain, A: Memory<Error = std::io::Error>> MemBTreeMap<K, V, A> {
+    /// Create an empty map in empty memory, or open the one the memory holds.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if a page cannot hold two entries, and
+    /// opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no map of keys
+    /// and values of these sizes. The nodes are checked when they are read, so a broken one is
+    /// refused by [`MemBTreeMap::insert`] with [`std::io::ErrorKind::InvalidData`], ends an
+    /// iteration and panics elsewhere, instead of reading out of bounds.
+    ///
+    /// # Safety
+    /// The keys and values must hold valid bytes representations of K and V.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if Self::leaf_capacity() < 2 || Self::inner_capacity() < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "keys and values are too large for a memvec b-tree page",
+            ));
+        }
+        let key_size = core::mem::size_of::<K>() as u64;
+        let value_size = core::mem::size_of::<V>() as u64;
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(2 * PAGE)?;
+        } else if mem.len() < TreeHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec b-tree"));
+        }
+        check_align::<TreeHeader>(mem.as_ptr())
+            .and_then(|()| check_align::<K>(mem.as_ptr()))
+            .and_then(|()| check_align::<V>(mem.as_ptr()))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let mut this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        if fresh {
+            this.mem
+                .as_mut_ptr()
+                .cast::<TreeHeader>()
+                .write(TreeHeader {
+                    magic: TreeHeader::MAGIC,
+                    key_size: key_size.to_le(),
+                    value_size: value_size.to_le(),
+                    page_size: (PAGE as u64).to_le(),
+                    pages: 1u64.to_le(),
+                    root: 0,
+                    len: 0,
+                    _reserved: [0; 8],
+                });
+            this.mem.set_len(PAGE);
+            let root = this.alloc_page(NodeHeader::LEAF);
+            this.header_mut().root = root.to_le();
+            return Ok(this);
+        }
+        let header = this.header();
+        if header.magic != TreeHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec b-tree"));
+        }
+        if u64::from_le(header.key_size) != key_size
+            || u64::from_le(header.value_size) != value_size
+            || u64::from_le(header.page_size) != PAGE as u64
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec b-tree has a different key, value or page size",
+            ));
+        }
+        let pages = u64::from_le(header.pages);
+        let root = u64::from_le(header.root);
+        // the length is read from the memory too, so it must not exceed the mapped bytes
+        if pages.checked_mul(PAGE as u64) != Some(this.mem.len() as u64)
+            || this.mem.len() > core::ops::Deref::deref(&this.mem).len()
+            || root == 0
+            || root >= pages
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        if this.checked_node(root).is_none() {
+            return Err(broken());
+        }
+        Ok(this)
+    }
+
+    /// Make room for a page per level of the tree and one more, so that splitting nodes up to a
+    /// new root never fails halfway.
+    fn reserve_split(&mut self) -> std::io::Result<()> {
+        let (_, height) = self.descend(|_| 0).ok_or_else(broken)?;
+        let needed = (self.pages() as usize + height + 1)
+            .checked_mul(PAGE)
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+            })?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if needed > capacity {
+            self.mem.reserve(needed.max(capacity * 2))?;
+        }
+        Ok(())
+    }
+
+    /// Insert `value` for `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> std::io::Result<Option<V>> {
+        // a broken node on the path is refused here rather than panicking halfway through
+        self.try_leaf_for(&key).ok_or_else(broken)?;
+        self.reserve_split()?;
+        let root = self.root();
+        let (old, split) = self.insert_into(root, key, value);
+        if let Some((separator, right)) = split {
+            let new_root = self.alloc_page(NodeHeader::INNER);
+            self.write_inner(new_root, &[separator], &[root.to_le(), right.to_le()]);
+            self.header_mut().root = new_root.to_le();
+        }
+        if old.is_none() {
+            let len = self.len() as u64 + 1;
+            self.header_mut().len = len.to_le();
+        }
+        Ok(old)
+    }
+}
+
+impl<K: Plain + Ord, V: Plain, A: Memory> MemBTreeMap<K, V, A> {
+    fn leaf_capacity() -> usize {
+        let size = core::mem::size_of::<K>() + core::mem::size_of::<V>();
+        let room = PAGE - NodeHeader::LEN - core::mem::align_of::<K>() - core::mem::align_of::<V>();
+        room.checked_div(size)
+            .unwrap_or(usize::MAX)
+            .min(u16::MAX as usize)
+    }
+
+    fn inner_capacity() -> usize {
+        let size = core::mem::size_of::<K>() + 8;
+        let room = PAGE - NodeHeader::LEN - core::mem::align_of::<K>() - 16;
+        (room / size).min(u16::MAX as usize)
+    }
+
+    fn keys_offset() -> usize {
+        NodeHeader::LEN.next_multiple_of(core::mem::align_of::<K>())
+    }
+
+    fn values_offset() -> usize {
+        (Self::keys_offset() + Self::leaf_capacity() * core::mem::size_of::<K>())
+            .next_multiple_of(core::mem::align_of::<V>())
+    }
+
+    fn children_offset() -> usize {
+        (Self::keys_offset() + Self::inner_capacity() * core::mem::size_of::<K>())
+            .next_multiple_of(8)
+    }
+
+    fn header(&self) -> &TreeHeader {
+        unsafe { &*self.mem.as_ptr().cast::<TreeHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut TreeHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<TreeHeader>() }
+    }
+
+    fn pages(&self) -> u64 {
+        u64::from_le(self.header().pages)
+    }
+
+    fn root(&self) -> u64 {
+        u64::from_le(self.header().root)
+    }
+
+    fn page_ptr(&self, page: u64) -> *const u8 {
+        self.mem.as_ptr().wrapping_add(page as usize * PAGE)
+    }
+
+    fn page_mut_ptr(&mut self, page: u64) -> *mut u8 {
+        self.mem.as_mut_ptr().wrapping_add(page as usize * PAGE)
+    }
+
+    fn node(&self, page: u64) -> &NodeHeader {
+        unsafe { &*self.page_ptr(page).cast::<NodeHeader>() }
+    }
+
+    fn node_mut(&mut self, page: u64) -> &mut NodeHeader {
+        unsafe { &mut *self.page_mut_ptr(page).cast::<NodeHeader>() }
+    }
+
+    /// The node of `page` if it is a node within the map whose count fits in its page.
+    fn checked_node(&self, page: u64) -> Option<&NodeHeader> {
+        if page == 0 || page >= self.pages() {
+            return None;
+        }
+        let node = self.node(page);
+        let capacity = match u16::from_le(node.kind) {
+            NodeHeader::LEAF => Self::leaf_capacity(),
+            NodeHeader::INNER => Self::inner_capacity(),
+            _ => return None,
+        };
+        (node.count() <= capacity).then_some(node)
+    }
+
+    /// The number of keys of the node of `page`, which is read from the memory, so a broken node
+    /// is caught here rather than reading out of bounds.
+    fn count(&self, page: u64) -> usize {
+        match self.checked_node(page) {
+            Some(node) => node.count(),
+            None => panic!("broken memvec b-tree: node {page} out of bounds"),
+        }
+    }
+
+    fn keys(&self, page: u64) -> &[K] {
+        let count = self.count(page);
+        unsafe {
+            let keys = self.page_ptr(page).add(Self::keys_offset()).cast();
+            core::slice::from_raw_parts(keys, count)
+        }
+    }
+
+    fn values(&self, page: u64) -> &[V] {
+        let count = self.count(page);
+        unsafe {
+            let values = self.page_ptr(page).add(Self::values_offset()).cast();
+            core::slice::from_raw_parts(values, count)
+        }
+    }
+
+    fn children(&self, page: u64) -> &[u64] {
+        let count = self.count(page) + 1;
+        unsafe {
+            let children = self.page_ptr(page).add(Self::children_offset()).cast();
+            core::slice::from_raw_parts(children, count)
+        }
+    }
+
+    /// Append a page for an empty node. The memory must have room for it.
+    fn alloc_page(&mut self, kind: u16) -> u64 {
+        let page = self.pages();
+        let len = (page as usize + 1) * PAGE;
+        debug_assert!(len <= core::ops::Deref::deref(&self.mem).len());
+        self.mem.set_len(len);
+        self.header_mut().pages = (page + 1).to_le();
+        unsafe {
+            self.page_mut_ptr(page)
+                .cast::<NodeHeader>()
+                .write(NodeHeader {
+                    kind: kind.to_le(),
+                    count: 0,
+                    _reserved: 0,
+                    next: 0,
+                })
+        };
+        page
+    }
+
+    fn write_leaf(&mut self, page: u64, keys: &[K], values: &[V]) {
+        debug_assert!(keys.len() == values.len() && keys.len() <= Self::leaf_capacity());
+        unsafe {
+            let base = self.page_mut_ptr(page);
+            let keys_ptr = base.add(Self::keys_offset()).cast::<K>();
+            core::ptr::copy_nonoverlapping(keys.as_ptr(), keys_ptr, keys.len());
+            let values_ptr = base.add(Self::values_offset()).cast::<V>();
+            core::ptr::copy_nonoverlapping(values.as_ptr(), values_ptr, values.len());
+        }
+        self.node_mut(page).count = (keys.len() as u16).to_le();
+    }
+
+    fn write_inner(&mut self, page: u64, keys: &[K], children: &[u64]) {
+        debug_assert!(keys.len() + 1 == children.len() && keys.len() <= Self::inner_capacity());
+        unsafe {
+            let base = self.page_mut_ptr(page);
+            let keys_ptr = base.add(Self::keys_offset()).cast::<K>();
+            core::ptr::copy_nonoverlapping(keys.as_ptr(), keys_ptr, keys.len());
+            let children_ptr = base.add(Self::children_offset()).cast::<u64>();
+            core::ptr::copy_nonoverlapping(children.as_ptr(), children_ptr, children.len());
+        }
+        self.node_mut(page).count = (keys.len() as u16).to_le();
+    }
+
+    /// Descend from the root to a leaf through the children `pick` chooses by the keys of each
+    /// inner node, returning the leaf and the height of the tree, or `None` if a node on the way
+    /// is broken. A tree is no higher than its pages, so a cycle of children ends too.
+    fn descend(&self, mut pick: impl FnMut(&[K]) -> usize) -> Option<(u64, usize)> {
+        let mut page = self.root();
+        let mut height = 1;
+        while !self.checked_node(page)?.is_leaf() {
+            if height as u64 >= self.pages() {
+                return None;
+            }
+            let index = pick(self.keys(page));
+            page = u64::from_le(self.children(page)[index]);
+            height += 1;
+        }
+        Some((page, height))
+    }
+
+    fn try_leaf_for(&self, key: &K) -> Option<u64> {
+        self.descend(|keys| keys.partition_point(|separator| separator <= key))
+            .map(|(page, _)| page)
+    }
+
+    fn leaf_for(&self, key: &K) -> u64 {
+        self.try_leaf_for(key)
+            .unwrap_or_else(|| panic!("broken memvec b-tree: no leaf for a key"))
+    }
+
+    /// Insert into the subtree of `page`, returning the replaced value and, if the node was
+    /// split, the separator key and the page of the new right sibling.
+    fn insert_into(&mut self, page: u64, key: K, value: V) -> (Option<V>, Option<(K, u64)>) {
+        if self.node(page).is_leaf() {
+            let mut keys = copied(self.keys(page));
+            let mut values = copied(self.values(page));
+            match keys.binary_search(&key) {
+                Ok(index) => {
+                    let old = core::mem::replace(&mut values[index], value);
+                    self.write_leaf(page, &keys, &values);
+                    return (Some(old), None);
+                }
+                Err(index) => {
+                    keys.insert(index, key);
+                    values.insert(index, value);
+                }
+            }
+            if keys.len() <= Self::leaf_capacity() {
+                self.write_leaf(page, &keys, &values);
+                return (None, None);
+            }
+            let right = self.alloc_page(NodeHeader::LEAF);
+            let mid = keys.len() / 2;
+            self.write_leaf(right, &keys[mid..], &values[mid..]);
+            let next = self.node(page).next;
+            self.node_mut(right).next = next;
+            self.write_leaf(page, &keys[..mid], &values[..mid]);
+            self.node_mut(page).next = right.to_le();
+            let separator = keys.swap_remove(mid);
+            return (None, Some((separator, right)));
+        }
+
+        let mut keys = copied(self.keys(page));
+        let mut children = self.children(page).to_vec();
+        let index = keys.partition_point(|separator| *separator <= key);
+        let (old, split) = self.insert_into(u64::from_le(children[index]), key, value);
+        let Some((separator, child)) = split else {
+            return (old, None);
+        };
+        keys.insert(index, separator);
+        children.insert(index + 1, child.to_le());
+        if keys.len() <= Self::inner_capacity() {
+            self.write_inner(page, &keys, &children);
+            return (old, None);
+        }
+        let right = self.alloc_page(NodeHeader::INNER);
+        let mid = keys.len() / 2;
+        self.write_inner(right, &keys[mid + 1..], &children[mid + 1..]);
+        self.write_inner(page, &keys[..mid], &children[..mid + 1]);
+        (old, Some((keys.swap_remove(mid), right)))
+    }
+
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let page = self.leaf_for(key);
+        let index = self.keys(page).binary_search(key).ok()?;
+        Some(&self.values(page)[index])
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let page = self.leaf_for(key);
+        let index = self.keys(page).binary_search(key).ok()?;
+        unsafe {
+            let values = self
+                .page_mut_ptr(page)
+                .add(Self::values_offset())
+                .cast::<V>();
+            Some(&mut *values.add(index))
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Remove the entry of `key`, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let page = self.leaf_for(key);
+        let mut keys = copied(self.keys(page));
+        let mut values = copied(self.values(page));
+        let index = keys.binary_search(key).ok()?;
+        keys.remove(index);
+        let value = values.remove(index);
+        self.write_leaf(page, &keys, &values);
+        let len = self.len() as u64 - 1;
+        self.header_mut().len = len.to_le();
+        Some(value)
+    }
+
+    /// Remove all entries and release all pages but the header and an empty root.
+    pub fn clear(&mut self) {
+        let header = self.header_mut();
+        header.pages = 1u64.to_le();
+        header.len = 0;
+        self.mem.set_len(PAGE);
+        let root = self.alloc_page(NodeHeader::LEAF);
+        self.header_mut().root = root.to_le();
+    }
+
+    /// The entries with keys in `range`, in ascending order of the keys.
+    pub fn range(&self, range: impl RangeBounds<K>) -> BTreeRange<'_, K, V, A> {
+        // a broken tree yields nothing
+        let (page, index) = match range.start_bound() {
+            Bound::Unbounded => self.descend(|_| 0).map_or((0, 0), |(page, _)| (page, 0)),
+            Bound::Included(start) => self.try_leaf_for(start).map_or((0, 0), |page| {
+                (page, self.keys(page).partition_point(|key| key < start))
+            }),
+            Bound::Excluded(start) => self.try_leaf_for(start).map_or((0, 0), |page| {
+                (page, self.keys(page).partition_point(|key| key <= start))
+            }),
+        };
+        BTreeRange {
+            map: self,
+            page,
+            index,
+            leaves: self.pages(),
+            end: copy_bound(range.end_bound()),
+        }
+    }
+
+    /// All entries in ascending order of the keys.
+    pub fn iter(&self) -> BTreeRange<'_, K, V, A> {
+        self.range(..)
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Write the pages durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<K, V, A> core::fmt::Debug for MemBTreeMap<K, V, A>
+where
+    K: Plain + Ord + core::fmt::Debug,
+    V: Plain + core::fmt::Debug,
+    A: Memory,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over a range of the entries of a [`MemBTreeMap`], see [`MemBTreeMap::range`].
+pub struct BTreeRange<'m, K: Plain + Ord, V: Plain, A: Memory> {
+    map: &'m MemBTreeMap<K, V, A>,
+    /// The current leaf, or 0 past the end.
+    page: u64,
+    index: usize,
+    /// The leaves left to visit before the next links must have formed a cycle.
+    leaves: u64,
+    end: Bound<K>,
+}
+
+impl<'m, K: Plain + Ord, V: Plain, A: Memory> Iterator for BTreeRange<'m, K, V, A> {
+    type Item = (&'m K, &'m V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = self.map;
+        while self.page != 0 {
+            // a broken leaf or next link ends the iteration
+            if self.leaves == 0 || !map.checked_node(self.page).is_some_and(NodeHeader::is_leaf) {
+                self.page = 0;
+                return None;
+            }
+            let keys = map.keys(self.page);
+            let Some(key) = keys.get(self.index) else {
+                self.page = u64::from_le(map.node(self.page).next);
+                self.index = 0;
+                self.leaves -= 1;
+                continue;
+            };
+            let past_end = match &self.end {
+                Bound::Included(end) => key > end,
+                Bound::Excluded(end) => key >= end,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                self.page = 0;
+                return None;
+            }
+            let value = &map.values(self.page)[self.index];
+            self.index += 1;
+            return Some((key, value));
+        }
+        None
+    }
+}
+
+impl<'m, K: Plain + Ord, V: Plain, A: Memory> core::fmt::Debug for BTreeRange<'m, K, V, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BTreeRange")
+            .field("page", &self.page)
+            .field("index", &self.index)
+            .finish()
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{cursor::MemCursor, frozen::Frozen, mem_vec::MemVec, memory::Memory};
+use ::bytes::{buf::UninitSlice, Buf, BufMut};
+use core::ops::Deref;
+
+/// Drains the bytes from the position on, as [`std::io::Cursor`] does.
+impl<B: Deref<Target = [u8]>> Buf for MemCursor<B> {
+    fn remaining(&self) -> usize {
+        MemCursor::remaining(self).len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        MemCursor::remaining(self)
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(cnt <= Buf::remaining(self), "cannot advance past the end");
+        self.set_position(self.position() + cnt as u64);
+    }
+}
+
+/// Fills the spare capacity in place, growing the vector when it is full, as `Vec<u8>` does.
+///
+/// Growing may remap the memory; see [`Frozen`] for a writer which never does.
+unsafe impl<'a, A: 'a + Memory> BufMut for MemVec<'a, u8, A> {
+    fn remaining_mut(&self) -> usize {
+        self.max_len().min(isize::MAX as usize) - self.len()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        let len = self.len() + cnt;
+        assert!(len <= self.capacity(), "cannot advance past the capacity");
+        self.set_len(len);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        if self.len() == self.capacity() {
+            self.reserve(64);
+        }
+        UninitSlice::uninit(self.spare_capacity_mut())
+    }
+}
+
+/// Fills the spare capacity in place without growing, so the bytes already written stay where
+/// they are and the slices of [`Frozen::as_slice`] stay valid.
+unsafe impl<'v, 'a, A: 'a + Memory> BufMut for Frozen<'v, 'a, u8, A> {
+    fn remaining_mut(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        let len = self.len() + cnt;
+        assert!(len <= self.capacity(), "cannot advance past the capacity");
+        self.set_len(len);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::uninit(self.spare_capacity_mut())
+    }
+}
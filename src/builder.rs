@@ -0,0 +1,276 @@
+use crate::{
+    auto_flush::AutoFlush, error::with_path, header::Header, policy::GrowthPolicy,
+    read_only::ReadOnlyVecFile, vec_file::VecFile,
+};
+use std::{
+    fs::{File, TryLockError},
+    path::Path,
+};
+
+/// Options to open or create a [`VecFile`].
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// let vec_file = memvec::VecFile::builder()
+///     .create(true)
+///     .lock(true)
+///     .metadata_len(16)
+///     .open("records.memvec")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct VecFileBuilder {
+    create: bool,
+    create_new: bool,
+    truncate: bool,
+    lock: bool,
+    prefault: bool,
+    prefix_len: u64,
+    metadata_len: usize,
+    checksum: Option<bool>,
+    journal: Option<usize>,
+    auto_flush: Option<AutoFlush>,
+    durable: Option<usize>,
+    page_aligned: bool,
+    growth_policy: GrowthPolicy,
+    single_writer: bool,
+    mutex: bool,
+    u32_len_cap: bool,
+}
+
+impl VecFile {
+    pub fn builder() -> VecFileBuilder {
+        VecFileBuilder::new()
+    }
+}
+
+impl VecFileBuilder {
+    /// Options to open an existing file for reading and writing, without locking.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the file if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, failing with [`std::io::ErrorKind::AlreadyExists`] if it exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Discard the contents of an existing file.
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Take an advisory lock on the file: exclusive for read-write and shared for read-only
+    /// access. Fails with [`std::io::ErrorKind::WouldBlock`] if a conflicting lock is held.
+    ///
+    /// The lock is released when the file is closed.
+    pub fn lock(&mut self, lock: bool) -> &mut Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Read the whole data region into the page cache when it is mapped.
+    pub fn prefault(&mut self, prefault: bool) -> &mut Self {
+        self.prefault = prefault;
+        self
+    }
+
+    /// Leave the first `prefix_len` bytes of the file to the application, e.g. for an existing
+    /// header of another format, and place the vector file after them.
+    ///
+    /// The header starts at `prefix_len` rounded up to a multiple of 64 bytes, so the records keep
+    /// their alignment. The prefix is not recorded in the file and must be given on every open.
+    /// The bytes of the prefix are kept when the file is created, unless it is truncated.
+    pub fn prefix_len(&mut self, prefix_len: u64) -> &mut Self {
+        self.prefix_len = prefix_len;
+        self
+    }
+
+    fn header_offset(&self) -> u64 {
+        self.prefix_len.next_multiple_of(Header::DATA_ALIGN as u64)
+    }
+
+    /// Length of the user metadata region of a newly created file.
+    /// Ignored for existing files. See [`VecFile::create_with_metadata`].
+    pub fn metadata_len(&mut self, metadata_len: usize) -> &mut Self {
+        self.metadata_len = metadata_len;
+        self
+    }
+
+    /// Enable or disable the data checksum after opening. See [`VecFile::set_checksum`].
+    pub fn checksum(&mut self, enabled: bool) -> &mut Self {
+        self.checksum = Some(enabled);
+        self
+    }
+
+    /// Enable the write-ahead journal after opening. See [`VecFile::enable_journal`].
+    pub fn journal(&mut self, record_size: usize) -> &mut Self {
+        self.journal = Some(record_size);
+        self
+    }
+
+    /// Start the background flusher after opening. See [`VecFile::enable_auto_flush`].
+    pub fn auto_flush(&mut self, policy: AutoFlush) -> &mut Self {
+        self.auto_flush = Some(policy);
+        self
+    }
+
+    /// Order changes of the length durably for records of `record_size` bytes. See
+    /// [`VecFile::set_durable`].
+    pub fn durable(&mut self, record_size: usize) -> &mut Self {
+        self.durable = Some(record_size);
+        self
+    }
+
+    /// Grow the file to multiples of the page size. See [`crate::MmapFile::set_page_aligned`].
+    pub fn page_aligned(&mut self, page_aligned: bool) -> &mut Self {
+        self.page_aligned = page_aligned;
+        self
+    }
+
+    /// Grow vectors over the file by `policy`. See [`VecFile::set_growth_policy`].
+    pub fn growth_policy(&mut self, policy: GrowthPolicy) -> &mut Self {
+        self.growth_policy = policy;
+        self
+    }
+
+    /// Open in the single-writer/multi-reader mode, for a file shared between processes.
+    ///
+    /// [`VecFileBuilder::open`] takes an exclusive lock on the file, failing with
+    /// [`std::io::ErrorKind::WouldBlock`] while another writer holds it, and stores its process
+    /// id in the lock word of the header until the file is closed. A lock word left set by a
+    /// crashed writer is reported by [`VecFile::previous_writer_crashed`].
+    ///
+    /// [`VecFileBuilder::open_read_only`] takes no lock, so any number of readers map the file
+    /// while the writer works. Readers observe the writer with
+    /// [`ReadOnlyVecFile::writer_status`].
+    pub fn single_writer(&mut self, single_writer: bool) -> &mut Self {
+        self.single_writer = single_writer;
+        self
+    }
+
+    /// Create the file with a process-shared mutex in its header. See [`VecFile::lock_mutex`].
+    /// Ignored for existing files.
+    pub fn mutex(&mut self, mutex: bool) -> &mut Self {
+        self.mutex = mutex;
+        self
+    }
+
+    /// Create the file capped at `u32::MAX` records, so that structures indexing its records may
+    /// store 32-bit indices. Growing a [`crate::MemVec`] of it past the cap is a capacity
+    /// overflow, see [`crate::Memory::max_len`]. The header and the length are stored as for any
+    /// other file; only the cap is recorded. Ignored for existing files.
+    pub fn u32_len_cap(&mut self, u32_len_cap: bool) -> &mut Self {
+        self.u32_len_cap = u32_len_cap;
+        self
+    }
+
+    fn creates(&self) -> bool {
+        self.create || self.create_new || self.truncate
+    }
+
+    fn lock_file(&self, file: &File, shared: bool) -> std::io::Result<()> {
+        // a single writer always locks, its readers never do
+        let lock = if self.single_writer {
+            !shared
+        } else {
+            self.lock
+        };
+        if !lock {
+            return Ok(());
+        }
+        let result = if shared {
+            file.try_lock_shared()
+        } else {
+            file.try_lock()
+        };
+        result.map_err(|e| match e {
+            TryLockError::WouldBlock => std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "memvec file is locked by another user",
+            ),
+            TryLockError::Error(e) => e,
+        })
+    }
+
+    /// Open the vector file at `path` for reading and writing.
+    pub fn open(&self, path: impl AsRef<Path>) -> std::io::Result<VecFile> {
+        let path = path.as_ref();
+        File::options()
+            .read(true)
+            .write(true)
+            .create(self.create)
+            .create_new(self.create_new)
+            .truncate(self.truncate)
+            .open(path)
+            .and_then(|file| self._open(file, Some(path)))
+            .map_err(|e| with_path(e, path))
+    }
+
+    /// Open a vector file from an already opened `file`, which must be readable and writable.
+    ///
+    /// An empty file is initialized if any of the create options is set.
+    pub fn from_file(&self, file: File) -> std::io::Result<VecFile> {
+        self._open(file, None)
+    }
+
+    fn _open(&self, file: File, path: Option<&Path>) -> std::io::Result<VecFile> {
+        self.lock_file(&file, false)?;
+        let header_offset = self.header_offset();
+        if self.creates() && file.metadata()?.len() <= header_offset {
+            let mut features = 0;
+            if self.mutex {
+                features |= Header::FEATURE_MUTEX;
+            }
+            if self.u32_len_cap {
+                features |= Header::FEATURE_U32_LEN_CAP;
+            }
+            VecFile::_clear(&file, header_offset, self.metadata_len, features)?;
+        }
+        let mut vec_file = VecFile::_from_file(file, header_offset, self.prefault)?;
+        vec_file.locked = self.single_writer || self.lock;
+        if self.single_writer {
+            vec_file.acquire_writer()?;
+        }
+        if let Some(path) = path {
+            vec_file.path = Some(path.to_owned());
+            vec_file.replay_journal()?;
+        }
+        if let Some(enabled) = self.checksum {
+            vec_file.set_checksum(enabled);
+        }
+        if let Some(record_size) = self.journal {
+            vec_file.enable_journal(record_size)?;
+        }
+        vec_file.set_durable(self.durable);
+        vec_file.set_page_aligned(self.page_aligned);
+        vec_file.set_growth_policy(self.growth_policy);
+        if let Some(policy) = self.auto_flush {
+            vec_file.enable_auto_flush(policy)?;
+        }
+        Ok(vec_file)
+    }
+
+    /// Open the vector file at `path` with read-only access.
+    ///
+    /// Only [`VecFileBuilder::lock`], [`VecFileBuilder::single_writer`] and
+    /// [`VecFileBuilder::prefix_len`] apply; the file is never created.
+    pub fn open_read_only(&self, path: impl AsRef<Path>) -> std::io::Result<ReadOnlyVecFile> {
+        let path = path.as_ref();
+        File::open(path)
+            .and_then(|file| {
+                self.lock_file(&file, true)?;
+                ReadOnlyVecFile::_from_file(file, self.header_offset())
+            })
+            .map_err(|e| with_path(e, path))
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{copy::copy_bytes, mem_vec::MemVec, memory::Memory, plain::Plain};
+use core::{ops::Deref, ptr};
+
+/// A bulk load into a [`MemVec`], see [`MemVec::begin_bulk_load`].
+///
+/// The guard appends into the reserved capacity and keeps the new length to itself. Running out
+/// of room grows the memory by the expected length again, or by the count loaded so far if that
+/// is larger, rather than by the growth policy. When the guard finishes or drops, the length is
+/// published once and the capacity beyond it is trimmed following the shrink policy.
+///
+/// Elements are published even if the load panics or is abandoned: every element appended
+/// before is complete.
+pub struct BulkLoad<'v, 'a, T: Plain, A: 'a + Memory> {
+    vec: &'v mut MemVec<'a, T, A>,
+    /// The length including the elements not published yet.
+    len: usize,
+    start: usize,
+    expected_len: usize,
+    finished: bool,
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> BulkLoad<'v, 'a, T, A> {
+    pub(crate) fn new(vec: &'v mut MemVec<'a, T, A>, expected_len: usize) -> Self {
+        let len = vec.len();
+        Self {
+            vec,
+            len,
+            start: len,
+            expected_len,
+            finished: false,
+        }
+    }
+
+    /// The length of the vector including the loaded elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements loaded by this guard.
+    pub fn loaded(&self) -> usize {
+        self.len - self.start
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), A::Error> {
+        if additional > self.vec.capacity() - self.len {
+            let step = self.expected_len.max(self.loaded()).max(additional);
+            self.vec.grow_exact(self.len, step)?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, value: T) -> Result<(), A::Error> {
+        self.reserve(1)?;
+        unsafe { ptr::write(self.vec.as_mut_ptr().add(self.len), value) };
+        self.vec.zero_padding_at(self.len);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Append the elements of `other`. See [`MemVec::extend_from_slice`].
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), A::Error> {
+        self.reserve(other.len())?;
+        unsafe {
+            copy_bytes(
+                other.as_ptr() as *const u8,
+                self.vec.as_mut_ptr().add(self.len) as *mut u8,
+                core::mem::size_of_val(other),
+            );
+        }
+        for index in self.len..self.len + other.len() {
+            self.vec.zero_padding_at(index);
+        }
+        self.len += other.len();
+        Ok(())
+    }
+
+    /// Publish the length and trim the capacity, returning the error of trimming which dropping
+    /// the guard ignores.
+    pub fn finish(mut self) -> Result<(), A::Error> {
+        self.publish()
+    }
+
+    fn publish(&mut self) -> Result<(), A::Error> {
+        self.finished = true;
+        // the elements up to `len` are written and within the capacity
+        unsafe { self.vec.set_len(self.len) };
+        self.vec.try_shrink_with_policy(self.len)
+    }
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> Deref for BulkLoad<'v, 'a, T, A> {
+    type Target = [T];
+
+    /// The elements of the vector including the loaded ones.
+    fn deref(&self) -> &Self::Target {
+        unsafe { core::slice::from_raw_parts(self.vec.as_ptr(), self.len) }
+    }
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> Drop for BulkLoad<'v, 'a, T, A> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.publish();
+        }
+    }
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> core::fmt::Debug for BulkLoad<'v, 'a, T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BulkLoad")
+            .field("len", &self.len)
+            .field("loaded", &self.loaded())
+            .field("expected_len", &self.expected_len)
+            .finish()
+    }
+}
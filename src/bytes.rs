@@ -0,0 +1,66 @@
+use crate::{mem_vec::MemVec, memory::Memory};
+
+/// Searches of byte vectors in place, e.g. for delimiters in a mapped file.
+///
+/// With the `memchr` feature they use the SIMD routines of the `memchr` crate; otherwise plain
+/// loops.
+impl<'a, A: 'a + Memory> MemVec<'a, u8, A> {
+    /// The index of the first `byte`.
+    pub fn position_byte(&self, byte: u8) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        return memchr::memchr(byte, self);
+        #[cfg(not(feature = "memchr"))]
+        return self.iter().position(|&b| b == byte);
+    }
+
+    /// The index of the last `byte`.
+    pub fn rposition_byte(&self, byte: u8) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        return memchr::memrchr(byte, self);
+        #[cfg(not(feature = "memchr"))]
+        return self.iter().rposition(|&b| b == byte);
+    }
+
+    /// The index of the first occurrence of `needle`. An empty needle is found at 0.
+    pub fn find_subslice(&self, needle: &[u8]) -> Option<usize> {
+        #[cfg(feature = "memchr")]
+        return memchr::memmem::find(self, needle);
+        #[cfg(not(feature = "memchr"))]
+        return if needle.is_empty() {
+            Some(0)
+        } else {
+            self.windows(needle.len())
+                .position(|window| window == needle)
+        };
+    }
+
+    /// The number of occurrences of `byte`.
+    pub fn count_byte(&self, byte: u8) -> usize {
+        #[cfg(feature = "memchr")]
+        return memchr::memchr_iter(byte, self).count();
+        #[cfg(not(feature = "memchr"))]
+        return self.iter().filter(|&&b| b == byte).count();
+    }
+}
+
+/// Appends to the vector like `Vec<u8>` does, e.g. as the target of an encoder or of
+/// [`std::io::copy`]. [`std::io::Write::flush`] writes the vector durably, see [`MemVec::flush`].
+impl<'a, A: 'a + Memory> std::io::Write for MemVec<'a, u8, A>
+where
+    A::Error: Into<std::io::Error>,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.try_reserve(buf.len()).map_err(Into::into)?;
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        MemVec::flush(self).map_err(Into::into)
+    }
+}
@@ -0,0 +1,218 @@
+//! A C interface to [`VecFile`], so that components in other languages read and append the same
+//! files as Rust code.
+//!
+//! The declarations are in `include/memvec.h`. Build the shared library with
+//! `cargo rustc --release --features capi --crate-type cdylib`.
+//!
+//! A file is opened with the size of its records, which is not stored in the file and must match
+//! the element type of the Rust side: a `MemVec<T, VecFile>` is read with `sizeof(T)`. Functions
+//! failing return a null pointer or -1, and [`memvec_last_error`] describes the failure.
+
+use crate::{memory::Memory, vec_file::VecFile};
+use core::ffi::{c_char, c_int, CStr};
+use std::{cell::RefCell, ffi::CString};
+
+/// Create the file if it does not exist, for [`memvec_file_open`].
+pub const MEMVEC_CREATE: u32 = 1 << 0;
+/// Take an exclusive lock on the file, for [`memvec_file_open`]. See [`crate::VecFileBuilder::lock`].
+pub const MEMVEC_LOCK: u32 = 1 << 1;
+
+/// An open vector file of fixed-size records. Opaque to C.
+pub struct MemvecFile {
+    file: VecFile,
+    record_size: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(error: impl std::fmt::Display) {
+    // an interior nul cannot be passed to C; the message is cut there
+    let message = error.to_string();
+    let message = message.split('\0').next().unwrap_or_default();
+    let message = CString::new(message).expect("nul removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn status(result: std::io::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+impl MemvecFile {
+    fn open(path: &CStr, record_size: usize, flags: u32) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if record_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "record size must not be zero",
+            ));
+        }
+        let path = path
+            .to_str()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let file = VecFile::builder()
+            .create(flags & MEMVEC_CREATE != 0)
+            .lock(flags & MEMVEC_LOCK != 0)
+            .open(path)?;
+        let len_bytes = file.len().checked_mul(record_size);
+        if len_bytes.is_none_or(|len_bytes| len_bytes > core::ops::Deref::deref(&file).len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec file is shorter than its records of the given size",
+            ));
+        }
+        Ok(Self { file, record_size })
+    }
+
+    fn push(&mut self, record: &[u8]) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+        if record.len() != self.record_size {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "record length differs from the record size of the file",
+            ));
+        }
+        let len = self.file.len();
+        if len >= self.file.max_len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "capacity overflow"));
+        }
+        let start = len * self.record_size;
+        let end = start
+            .checked_add(self.record_size)
+            .filter(|&end| end <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let capacity = core::ops::Deref::deref(&self.file).len();
+        if end > capacity {
+            self.file
+                .reserve(end.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        self.file[start..end].copy_from_slice(record);
+        self.file.set_len(len + 1);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.file.len() {
+            return None;
+        }
+        let start = index * self.record_size;
+        Some(&self.file[start..start + self.record_size])
+    }
+}
+
+/// Open the vector file at the nul-terminated UTF-8 `path` with records of `record_size` bytes,
+/// or return null. `flags` is a combination of [`MEMVEC_CREATE`] and [`MEMVEC_LOCK`].
+///
+/// # Safety
+/// `path` must point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_open(
+    path: *const c_char,
+    record_size: usize,
+    flags: u32,
+) -> *mut MemvecFile {
+    if path.is_null() {
+        set_error("path is null");
+        return core::ptr::null_mut();
+    }
+    match MemvecFile::open(CStr::from_ptr(path), record_size, flags) {
+        Ok(file) => Box::into_raw(Box::new(file)),
+        Err(e) => {
+            set_error(e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+/// Append the `len` bytes at `data` as a record, which must be as long as the record size.
+/// Returns 0, or -1 on failure.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed, and `data` must point to `len`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_push_bytes(
+    file: *mut MemvecFile,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let record = if len == 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts(data, len)
+    };
+    status((*file).push(record))
+}
+
+/// The record at `index`, or null if it is out of bounds.
+///
+/// The record is read in place and stays valid until the next [`memvec_file_push_bytes`] or
+/// [`memvec_file_close`] on the file.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_get(file: *const MemvecFile, index: usize) -> *const u8 {
+    match (*file).get(index) {
+        Some(record) => record.as_ptr(),
+        None => core::ptr::null(),
+    }
+}
+
+/// The number of records.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_len(file: *const MemvecFile) -> usize {
+    (*file).file.len()
+}
+
+/// The size of the records the file was opened with.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_record_size(file: *const MemvecFile) -> usize {
+    (*file).record_size
+}
+
+/// Write the records and the length durably to the file. Returns 0, or -1 on failure.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_flush(file: *mut MemvecFile) -> c_int {
+    let MemvecFile { file, record_size } = &*file;
+    status(file.sync(0..file.len() * record_size))
+}
+
+/// Close the file. Null is ignored.
+///
+/// # Safety
+/// `file` must be returned by [`memvec_file_open`] and not closed before.
+#[no_mangle]
+pub unsafe extern "C" fn memvec_file_close(file: *mut MemvecFile) {
+    if !file.is_null() {
+        drop(Box::from_raw(file));
+    }
+}
+
+/// The message of the last failure on the calling thread, or null if nothing failed yet.
+///
+/// The string stays valid until the next failure on the thread.
+#[no_mangle]
+pub extern "C" fn memvec_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(core::ptr::null(), |message| message.as_ptr())
+    })
+}
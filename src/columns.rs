@@ -0,0 +1,214 @@
+use crate::{
+    mem_vec::MemVec,
+    memory::{Memory, MemoryConversionError},
+    plain::Plain,
+};
+
+/// A tuple of [`Plain`] types stored field by field in the columns of a [`MemColumns`].
+///
+/// It is implemented for tuples of up to 8 types; the columns are a tuple of [`MemVec`]s of the
+/// same arity.
+pub trait Columns<'a, A: 'a + Memory>: Sized {
+    /// The vectors of the columns.
+    type Vecs;
+
+    /// The length of the shortest column.
+    fn min_len(vecs: &Self::Vecs) -> usize;
+    /// The length of the longest column.
+    fn max_len(vecs: &Self::Vecs) -> usize;
+    fn try_reserve(vecs: &mut Self::Vecs, additional: usize) -> Result<(), A::Error>;
+    /// Push the fields of `row`, which the columns must have reserved room for.
+    fn push(vecs: &mut Self::Vecs, row: Self);
+    fn pop(vecs: &mut Self::Vecs) -> Option<Self>;
+    fn remove(vecs: &mut Self::Vecs, index: usize) -> Self;
+    fn swap_remove(vecs: &mut Self::Vecs, index: usize) -> Self;
+    fn get(vecs: &Self::Vecs, index: usize) -> Option<Self>;
+    fn truncate(vecs: &mut Self::Vecs, len: usize);
+    fn flush(vecs: &Self::Vecs) -> Result<(), A::Error>;
+}
+
+macro_rules! impl_columns {
+    ($(($($ty:ident $idx:tt),+))*) => {$(
+        impl<'a, A: 'a + Memory, $($ty: Plain),+> Columns<'a, A> for ($($ty,)+) {
+            type Vecs = ($(MemVec<'a, $ty, A>,)+);
+
+            fn min_len(vecs: &Self::Vecs) -> usize {
+                usize::MAX$(.min(vecs.$idx.len()))+
+            }
+
+            fn max_len(vecs: &Self::Vecs) -> usize {
+                0$(.max(vecs.$idx.len()))+
+            }
+
+            fn try_reserve(vecs: &mut Self::Vecs, additional: usize) -> Result<(), A::Error> {
+                $(vecs.$idx.try_reserve(additional)?;)+
+                Ok(())
+            }
+
+            fn push(vecs: &mut Self::Vecs, row: Self) {
+                $(vecs.$idx.push(row.$idx);)+
+            }
+
+            fn pop(vecs: &mut Self::Vecs) -> Option<Self> {
+                Some(($(vecs.$idx.pop()?,)+))
+            }
+
+            fn remove(vecs: &mut Self::Vecs, index: usize) -> Self {
+                ($(vecs.$idx.remove(index),)+)
+            }
+
+            fn swap_remove(vecs: &mut Self::Vecs, index: usize) -> Self {
+                ($(vecs.$idx.swap_remove(index),)+)
+            }
+
+            fn get(vecs: &Self::Vecs, index: usize) -> Option<Self> {
+                // plain values are copied bitwise
+                Some(($(unsafe { core::ptr::read(vecs.$idx.get(index)?) },)+))
+            }
+
+            fn truncate(vecs: &mut Self::Vecs, len: usize) {
+                $(vecs.$idx.truncate(len);)+
+            }
+
+            fn flush(vecs: &Self::Vecs) -> Result<(), A::Error> {
+                $(vecs.$idx.flush()?;)+
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_columns!(
+    (T0 0)
+    (T0 0, T1 1)
+    (T0 0, T1 1, T2 2)
+    (T0 0, T1 1, T2 2, T3 3)
+    (T0 0, T1 1, T2 2, T3 3, T4 4)
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5)
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6)
+    (T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7)
+);
+
+/// Rows of a tuple type `R` stored as a struct of arrays: one [`MemVec`] per field, e.g. in the
+/// segments of one [`crate::SegmentFile`] or in separate [`crate::VecFile`]s.
+///
+/// The columns are kept at the same length by pushing and removing whole rows. Every column
+/// stores its own length, so a crash between updating them may leave them uneven;
+/// [`MemColumns::from_vecs_truncating`] drops the rows which are not complete.
+pub struct MemColumns<'a, R: Columns<'a, A>, A: 'a + Memory> {
+    vecs: R::Vecs,
+}
+
+impl<'a, R: Columns<'a, A>, A: 'a + Memory> MemColumns<'a, R, A> {
+    /// Wrap the columns `vecs`, or return them if their lengths differ.
+    pub fn from_vecs(vecs: R::Vecs) -> Result<Self, (R::Vecs, MemoryConversionError)> {
+        if R::min_len(&vecs) != R::max_len(&vecs) {
+            return Err((vecs, MemoryConversionError::SizeMismatch));
+        }
+        Ok(Self { vecs })
+    }
+
+    /// Wrap the columns `vecs` after truncating them to the length of the shortest one.
+    pub fn from_vecs_truncating(mut vecs: R::Vecs) -> Self {
+        let len = R::min_len(&vecs);
+        R::truncate(&mut vecs, len);
+        Self { vecs }
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        R::min_len(&self.vecs)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append a row, reserving room in all columns first so that they stay at the same length
+    /// if reserving fails.
+    pub fn push(&mut self, row: R) -> Result<(), A::Error> {
+        R::try_reserve(&mut self.vecs, 1)?;
+        R::push(&mut self.vecs, row);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<R> {
+        R::pop(&mut self.vecs)
+    }
+
+    /// Remove the row at `index`, shifting the following rows.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> R {
+        R::remove(&mut self.vecs, index)
+    }
+
+    /// Remove the row at `index`, replacing it by the last row.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> R {
+        R::swap_remove(&mut self.vecs, index)
+    }
+
+    /// A copy of the row at `index`.
+    pub fn get(&self, index: usize) -> Option<R> {
+        R::get(&self.vecs, index)
+    }
+
+    /// Copies of the rows in order.
+    pub fn iter(&self) -> ColumnsIter<'_, 'a, R, A> {
+        ColumnsIter {
+            columns: self,
+            index: 0,
+        }
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        R::truncate(&mut self.vecs, len)
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// The columns, e.g. `columns().1` for the slice of the second fields.
+    pub fn columns(&self) -> &R::Vecs {
+        &self.vecs
+    }
+
+    pub fn into_vecs(self) -> R::Vecs {
+        self.vecs
+    }
+
+    /// Write the columns durably one after another. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        R::flush(&self.vecs)
+    }
+}
+
+impl<'a, R: Columns<'a, A>, A: 'a + Memory> core::fmt::Debug for MemColumns<'a, R, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemColumns")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// An iterator over copies of the rows of a [`MemColumns`], see [`MemColumns::iter`].
+#[derive(Debug)]
+pub struct ColumnsIter<'c, 'a, R: Columns<'a, A>, A: 'a + Memory> {
+    columns: &'c MemColumns<'a, R, A>,
+    index: usize,
+}
+
+impl<'c, 'a, R: Columns<'a, A>, A: 'a + Memory> Iterator for ColumnsIter<'c, 'a, R, A> {
+    type Item = R;
+
+    fn next(&mut self) -> Option<R> {
+        let row = self.columns.get(self.index)?;
+        self.index += 1;
+        Some(row)
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    memory::{check_align, Memory},
+    plain::Plain,
+    vec_file::VecFile,
+};
+use core::{marker::PhantomData, ops::DerefMut, ptr::NonNull};
+use std::sync::{Mutex, PoisonError};
+
+/// A vector file appended to by one thread at a time while any number of threads read it.
+///
+/// The length is published with release ordering after the record is written, and read with
+/// acquire ordering, so a reader never observes a length covering records whose bytes are not
+/// visible yet. The same holds for a [`crate::ReadOnlyVecFile`] of the file in another process.
+///
+/// The capacity is reserved when it is created and never grows, so the mapping never moves under
+/// the readers. The header is updated directly: the journal is not supported, and the durable
+/// mode and the background flusher of the file are not applied to appends.
+pub struct ConcurrentAppendVec<T: Plain> {
+    file: VecFile,
+    data: NonNull<T>,
+    capacity: usize,
+    append: Mutex<()>,
+    _marker: PhantomData<T>,
+}
+
+// appends are serialized by `append`, and records are only read through shared references
+unsafe impl<T: Plain + Send + Sync> Send for ConcurrentAppendVec<T> {}
+unsafe impl<T: Plain + Send + Sync> Sync for ConcurrentAppendVec<T> {}
+
+impl<T: Plain> ConcurrentAppendVec<T> {
+    /// Reserve room for `capacity` records in `file`.
+    ///
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn new(mut file: VecFile, capacity: usize) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if file.has_journal() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "journaled files cannot be appended concurrently",
+            ));
+        }
+        check_align::<T>(file.as_ptr()).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        // appends update the header directly
+        file.set_deferred_len(None);
+        let bytes = capacity
+            .checked_mul(core::mem::size_of::<T>())
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        Memory::reserve(&mut file, bytes)?;
+        let capacity = file.capacity() / core::mem::size_of::<T>().max(1);
+        if Memory::len(&file) > capacity {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                crate::MemoryConversionError::SizeMismatch,
+            ));
+        }
+        let data = NonNull::from(file.deref_mut()).cast();
+        Ok(Self {
+            file,
+            data,
+            capacity,
+            append: Mutex::new(()),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        let header = self.file.header();
+        header
+            .published_len()
+            .unwrap_or_else(|| header.written_state().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Append `value` and return its index, or return `value` back if the vector is full.
+    pub fn append(&self, value: T) -> Result<usize, T> {
+        let _guard = self.append.lock().unwrap_or_else(PoisonError::into_inner);
+        let len = self.len();
+        if len == self.capacity {
+            return Err(value);
+        }
+        // SAFETY: the slot is within the capacity and not yet visible to readers
+        unsafe { self.data.as_ptr().add(len).write(value) };
+        self.file.header().update_written(|state| {
+            state.len = len as u64 + 1;
+            state.generation = state.generation.wrapping_add(1);
+        });
+        Ok(len)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        // SAFETY: published records are never written again
+        (index < self.len()).then(|| unsafe { &*self.data.as_ptr().add(index) })
+    }
+
+    pub fn into_file(mut self) -> VecFile {
+        self.file.reload_len();
+        self.file
+    }
+}
+
+impl<T: Plain> core::fmt::Debug for ConcurrentAppendVec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ConcurrentAppendVec")
+            .field("file", &self.file)
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
@@ -0,0 +1,40 @@
+/// Copies of at least this many bytes bypass the cache.
+#[cfg(all(feature = "non-temporal", target_arch = "x86_64"))]
+const NON_TEMPORAL_THRESHOLD: usize = 4 << 20;
+
+/// Copy `len` bytes from `src` to `dst`.
+///
+/// With the `non-temporal` feature on x86_64, copies of at least 4 MiB use streaming stores,
+/// which write around the cache so a bulk load does not evict the working set; elsewhere this is
+/// [`core::ptr::copy_nonoverlapping`].
+///
+/// # Safety
+/// The same as [`core::ptr::copy_nonoverlapping`] for bytes.
+#[inline]
+pub(crate) unsafe fn copy_bytes(src: *const u8, dst: *mut u8, len: usize) {
+    #[cfg(all(feature = "non-temporal", target_arch = "x86_64"))]
+    if len >= NON_TEMPORAL_THRESHOLD {
+        return copy_streaming(src, dst, len);
+    }
+    core::ptr::copy_nonoverlapping(src, dst, len)
+}
+
+#[cfg(all(feature = "non-temporal", target_arch = "x86_64"))]
+unsafe fn copy_streaming(src: *const u8, dst: *mut u8, len: usize) {
+    use core::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_sfence, _mm_stream_si128};
+
+    // the head up to the first 16-byte aligned destination and the tail are copied normally
+    let head = dst.align_offset(16).min(len);
+    core::ptr::copy_nonoverlapping(src, dst, head);
+    let blocks = (len - head) / 16;
+    let (src_body, dst_body) = (src.add(head), dst.add(head));
+    for i in 0..blocks {
+        // SSE2 is part of x86_64
+        let block = _mm_loadu_si128(src_body.add(i * 16) as *const __m128i);
+        _mm_stream_si128(dst_body.add(i * 16) as *mut __m128i, block);
+    }
+    let copied = head + blocks * 16;
+    core::ptr::copy_nonoverlapping(src.add(copied), dst.add(copied), len - copied);
+    // streaming stores are weakly ordered; order them before any later store, e.g. of the length
+    _mm_sfence();
+}
@@ -0,0 +1,114 @@
+use crate::{mem_vec::MemVec, memory::Memory};
+use core::ops::Deref;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+
+/// A reading position in bytes, e.g. a [`MemVec<u8, _>`](MemVec) or a
+/// [`crate::ReadOnlyMemVec`] of a mapped file, implementing [`Read`], [`BufRead`] and [`Seek`].
+///
+/// Parsers and decompressors consume the bytes in place: [`BufRead::fill_buf`] returns the rest
+/// of the bytes without copying them. The bytes may be owned or borrowed, see
+/// [`MemVec::cursor`] and [`MemVec::into_cursor`].
+#[derive(Debug)]
+pub struct MemCursor<B> {
+    bytes: B,
+    pos: u64,
+}
+
+impl<B: Deref<Target = [u8]>> MemCursor<B> {
+    pub fn new(bytes: B) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Set the position, which may be past the end, where reads return nothing.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// The bytes from the position on.
+    pub fn remaining(&self) -> &[u8] {
+        let start =
+            usize::try_from(self.pos).map_or(self.bytes.len(), |pos| pos.min(self.bytes.len()));
+        &self.bytes[start..]
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.bytes
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+impl<B: Deref<Target = [u8]>> Read for MemCursor<B> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.remaining().len().min(buf.len());
+        buf[..len].copy_from_slice(&self.remaining()[..len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        let Some(bytes) = self.remaining().get(..buf.len()) else {
+            // consumes the rest like the default implementation
+            self.pos = self.pos.max(self.bytes.len() as u64);
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        };
+        buf.copy_from_slice(bytes);
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl<B: Deref<Target = [u8]>> BufRead for MemCursor<B> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<B: Deref<Target = [u8]>> Seek for MemCursor<B> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.bytes.len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<'a, A: 'a + Memory> MemVec<'a, u8, A> {
+    /// A cursor reading the bytes from the start.
+    pub fn cursor(&self) -> MemCursor<&[u8]> {
+        MemCursor::new(self.as_slice())
+    }
+
+    /// A cursor reading the bytes from the start, owning the vector.
+    pub fn into_cursor(self) -> MemCursor<Self> {
+        MemCursor::new(self)
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::marker::PhantomData;
+
+/// A double-ended queue of records over [`Memory`], e.g. a persistent FIFO work queue in a
+/// [`crate::VecFile`].
+///
+/// The memory starts with a deque header holding the index of the front slot and the length; the
+/// slots follow it and wrap around like those of [`std::collections::VecDeque`], so popping from
+/// either end only updates the header. Growing doubles the slots and moves the wrapped part behind
+/// the old ones. A value is written to its slot before the header covers it.
+pub struct MemDeque<T: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<T>,
+}
+
+#[repr(C)]
+struct DequeHeader {
+    magic: [u8; 8],
+    record_size: u64,
+    capacity: u64,
+    /// The slot of the front value.
+    head: u64,
+    len: u64,
+    _reserved: [u8; 24],
+}
+
+const _: () = assert!(core::mem::size_of::<DequeHeader>() == DequeHeader::LEN);
+
+impl DequeHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECDQ";
+}
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> MemDeque<T, A> {
+    /// Create an empty deque in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no deque of
+    /// records of this size.
+    ///
+    /// # Safety
+    /// The slots must hold valid bytes representations of T.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let record_size = core::mem::size_of::<T>() as u64;
+        if mem.len() == 0 {
+            mem.reserve(DequeHeader::LEN)?;
+            check_header_align::<T>(&mem)?;
+            mem.as_mut_ptr().cast::<DequeHeader>().write(DequeHeader {
+                magic: DequeHeader::MAGIC,
+                record_size: record_size.to_le(),
+                capacity: 0,
+                head: 0,
+                len: 0,
+                _reserved: [0; 24],
+            });
+            mem.set_len(DequeHeader::LEN);
+            return Ok(Self {
+                mem,
+                _marker: PhantomData,
+            });
+        }
+        if mem.len() < DequeHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec deque"));
+        }
+        check_header_align::<T>(&mem)?;
+        let this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != DequeHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec deque"));
+        }
+        if u64::from_le(header.record_size) != record_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec deque has a different record size",
+            ));
+        }
+        let capacity = u64::from_le(header.capacity);
+        let (head, len) = (u64::from_le(header.head), u64::from_le(header.len));
+        let bytes = capacity
+            .checked_mul(record_size)
+            .and_then(|bytes| bytes.checked_add(DequeHeader::LEN as u64));
+        // memory longer than the slots is left by a crash while growing
+        if !matches!(bytes, Some(bytes) if bytes <= this.mem.len() as u64)
+            || len > capacity
+            || (capacity > 0 && head >= capacity)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Reserve slots for at least `additional` more values.
+    pub fn reserve(&mut self, additional: usize) -> std::io::Result<()> {
+        let capacity = self.capacity();
+        let needed = self
+            .len()
+            .checked_add(additional)
+            .ok_or_else(capacity_overflow)?;
+        if needed <= capacity {
+            return Ok(());
+        }
+        let new_capacity = needed.max(capacity * 2).max(4);
+        let bytes = new_capacity
+            .checked_mul(core::mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(DequeHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(capacity_overflow)?;
+        self.mem.reserve(bytes)?;
+        self.mem.set_len(bytes);
+        let (head, len) = (self.head(), self.len());
+        let wrapped = (head + len).saturating_sub(capacity);
+        if wrapped > 0 {
+            // the new slots are at least as many as the old ones, so the wrapped part fits behind
+            // them
+            unsafe {
+                let slots = self.slots_mut();
+                core::ptr::copy_nonoverlapping(slots, slots.add(capacity), wrapped);
+            }
+        }
+        self.header_mut().capacity = (new_capacity as u64).to_le();
+        Ok(())
+    }
+
+    /// Append `value` to the back.
+    pub fn push_back(&mut self, value: T) -> std::io::Result<()> {
+        self.reserve(1)?;
+        let slot = self.slot(self.len());
+        unsafe { self.slots_mut().add(slot).write(value) };
+        self.set_len(self.len() + 1);
+        Ok(())
+    }
+
+    /// Prepend `value` to the front.
+    pub fn push_front(&mut self, value: T) -> std::io::Result<()> {
+        self.reserve(1)?;
+        let head = self.slot(self.capacity() - 1);
+        unsafe { self.slots_mut().add(head).write(value) };
+        let header = self.header_mut();
+        header.head = (head as u64).to_le();
+        header.len = (u64::from_le(header.len) + 1).to_le();
+        Ok(())
+    }
+}
+
+fn capacity_overflow() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+}
+
+fn check_header_align<T>(mem: &impl Memory) -> std::io::Result<()> {
+    check_align::<DequeHeader>(mem.as_ptr())
+        .and_then(|()| check_align::<T>(mem.as_ptr().wrapping_add(DequeHeader::LEN)))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+impl<T: Plain, A: Memory> MemDeque<T, A> {
+    fn header(&self) -> &DequeHeader {
+        unsafe { &*self.mem.as_ptr().cast::<DequeHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut DequeHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<DequeHeader>() }
+    }
+
+    fn slots(&self) -> *const T {
+        self.mem.as_ptr().wrapping_add(DequeHeader::LEN).cast()
+    }
+
+    fn slots_mut(&mut self) -> *mut T {
+        self.mem.as_mut_ptr().wrapping_add(DequeHeader::LEN).cast()
+    }
+
+    fn head(&self) -> usize {
+        u64::from_le(self.header().head) as usize
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.header_mut().len = (len as u64).to_le();
+    }
+
+    /// The slot of the value at `index`, which may be past the length.
+    fn slot(&self, index: usize) -> usize {
+        let slot = self.head() + index;
+        match slot.checked_sub(self.capacity()) {
+            Some(wrapped) => wrapped,
+            None => slot,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of slots, which grows on demand.
+    pub fn capacity(&self) -> usize {
+        u64::from_le(self.header().capacity) as usize
+    }
+
+    /// Remove and return the front value, or `None` if the deque is empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let head = self.head();
+        let value = unsafe { self.slots().add(head).read() };
+        let next = self.slot(1);
+        let len = self.len() - 1;
+        let header = self.header_mut();
+        header.head = (next as u64).to_le();
+        header.len = (len as u64).to_le();
+        Some(value)
+    }
+
+    /// Remove and return the back value, or `None` if the deque is empty.
+    pub fn pop_back(&mut self) -> Option<T> {
+        let len = self.len().checked_sub(1)?;
+        let value = unsafe { self.slots().add(self.slot(len)).read() };
+        self.set_len(len);
+        Some(value)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(unsafe { &*self.slots().add(self.slot(index)) })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len() {
+            return None;
+        }
+        let slot = self.slot(index);
+        Some(unsafe { &mut *self.slots_mut().add(slot) })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// The values in order as two slices, the second holding those which wrapped around.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (head, len) = (self.head(), self.len());
+        let first = len.min(self.capacity() - head);
+        unsafe {
+            (
+                core::slice::from_raw_parts(self.slots().add(head), first),
+                core::slice::from_raw_parts(self.slots(), len - first),
+            )
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        let (first, second) = self.as_slices();
+        first.iter().chain(second)
+    }
+
+    /// Remove all values, keeping the slots.
+    pub fn clear(&mut self) {
+        let header = self.header_mut();
+        header.head = 0;
+        header.len = 0;
+    }
+
+    /// Write the values and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain + core::fmt::Debug, A: Memory> core::fmt::Debug for MemDeque<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (first, second) = self.as_slices();
+        f.debug_list().entries(first).entries(second).finish()
+    }
+}
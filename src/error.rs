@@ -0,0 +1,187 @@
+#[cfg(feature = "mmap")]
+use crate::header::ChecksumMismatch;
+use crate::{
+    mem_vec::MemVec,
+    memory::{InvalidRecord, Memory, MemoryConversionError},
+    plain::Plain,
+    string::MemStringError,
+};
+
+/// An error of any operation of this crate.
+///
+/// The operations report the error type of their memory, mostly [`std::io::Error`], or a more
+/// specific one such as [`MemoryConversionError`]; all of them convert into this, so one
+/// conversion serves downstream error handling. An [`std::io::Error`] wrapping an error of this
+/// crate, as [`crate::VecFile::open`] returns for a checksum mismatch, is unwrapped into its
+/// variant. It converts back into an [`std::io::Error`] of the matching kind, so `?` works in
+/// functions returning either.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MemVecError {
+    /// A capacity exceeds `isize::MAX` bytes, the limit of `Vec` as well.
+    CapacityOverflow,
+    /// The memory backend failed.
+    Io(std::io::Error),
+    /// The memory does not fit the element type.
+    Conversion(MemoryConversionError),
+    /// Stored data is corrupted, e.g. [`crate::ChecksumMismatch`] or [`InvalidRecord`].
+    Corrupted(Box<dyn std::error::Error + Send + Sync>),
+    /// A lock on a file is held by someone else.
+    Lock(std::io::Error),
+    /// `source` with a description of what failed.
+    Context {
+        context: String,
+        source: Box<MemVecError>,
+    },
+}
+
+impl MemVecError {
+    /// Describe what failed, e.g. the path of a file.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without its contexts.
+    pub fn root(&self) -> &MemVecError {
+        match self {
+            Self::Context { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    fn io_kind(&self) -> std::io::ErrorKind {
+        use std::io::ErrorKind;
+        match self {
+            Self::CapacityOverflow => ErrorKind::OutOfMemory,
+            Self::Io(e) | Self::Lock(e) => e.kind(),
+            Self::Conversion(_) | Self::Corrupted(_) => ErrorKind::InvalidData,
+            Self::Context { source, .. } => source.io_kind(),
+        }
+    }
+}
+
+impl core::fmt::Display for MemVecError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CapacityOverflow => f.write_str("capacity overflow"),
+            Self::Io(e) => e.fmt(f),
+            Self::Conversion(e) => e.fmt(f),
+            Self::Corrupted(e) => write!(f, "corrupted memvec data: {e}"),
+            Self::Lock(e) => write!(f, "memvec file is locked: {e}"),
+            Self::Context { context, source } => write!(f, "{context}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for MemVecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CapacityOverflow => None,
+            Self::Io(e) | Self::Lock(e) => e.source(),
+            Self::Conversion(e) => Some(e),
+            Self::Corrupted(e) => Some(&**e),
+            Self::Context { source, .. } => Some(&**source),
+        }
+    }
+}
+
+/// Wrap `e` in [`MemVecError::Context`] naming the file at `path`, keeping its kind.
+///
+/// Converting the result into a [`MemVecError`] unwraps it into that context.
+pub(crate) fn with_path(e: std::io::Error, path: &std::path::Path) -> std::io::Error {
+    let kind = e.kind();
+    let e = MemVecError::from(e).context(path.display().to_string());
+    std::io::Error::new(kind, e)
+}
+
+impl From<std::io::Error> for MemVecError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::WouldBlock {
+            return Self::Lock(e);
+        }
+        let Some(inner) = e.get_ref() else {
+            return Self::Io(e);
+        };
+        if inner.is::<MemoryConversionError>() || inner.is::<MemVecError>() {
+            let inner = e.into_inner().unwrap();
+            return match inner.downcast::<MemoryConversionError>() {
+                Ok(e) => Self::Conversion(*e),
+                Err(inner) => *inner.downcast::<MemVecError>().unwrap(),
+            };
+        }
+        #[cfg(feature = "mmap")]
+        let corrupted = inner.is::<ChecksumMismatch>() || inner.is::<InvalidRecord>();
+        #[cfg(not(feature = "mmap"))]
+        let corrupted = inner.is::<InvalidRecord>();
+        if corrupted {
+            return Self::Corrupted(e.into_inner().unwrap());
+        }
+        Self::Io(e)
+    }
+}
+
+impl From<MemVecError> for std::io::Error {
+    fn from(e: MemVecError) -> Self {
+        match e {
+            MemVecError::Io(e) | MemVecError::Lock(e) => e,
+            e => std::io::Error::new(e.io_kind(), e),
+        }
+    }
+}
+
+impl From<MemoryConversionError> for MemVecError {
+    fn from(e: MemoryConversionError) -> Self {
+        Self::Conversion(e)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl From<ChecksumMismatch> for MemVecError {
+    fn from(e: ChecksumMismatch) -> Self {
+        Self::Corrupted(Box::new(e))
+    }
+}
+
+impl From<InvalidRecord> for MemVecError {
+    fn from(e: InvalidRecord) -> Self {
+        Self::Corrupted(Box::new(e))
+    }
+}
+
+impl From<MemStringError> for MemVecError {
+    fn from(e: MemStringError) -> Self {
+        match e {
+            MemStringError::Memory(e) => Self::Conversion(e),
+            MemStringError::Utf8(e) => Self::Corrupted(Box::new(e)),
+        }
+    }
+}
+
+impl<'a, T: Plain, A: 'a + Memory> MemVec<'a, T, A>
+where
+    A::Error: Into<MemVecError>,
+{
+    /// [`MemVec::try_reserve`] reporting a capacity overflow, including one past
+    /// [`Memory::max_len`], as [`MemVecError::CapacityOverflow`] rather than panicking.
+    pub fn checked_reserve(&mut self, additional: usize) -> Result<(), MemVecError> {
+        self.len()
+            .checked_add(additional)
+            .filter(|&len| len <= self.as_mem().max_len())
+            .and_then(|len| len.checked_mul(core::mem::size_of::<T>()))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or(MemVecError::CapacityOverflow)?;
+        self.try_reserve(additional).map_err(Into::into)
+    }
+
+    /// [`MemVec::push`] reporting errors rather than panicking.
+    pub fn checked_push(&mut self, value: T) -> Result<(), MemVecError> {
+        self.checked_reserve(1)?;
+        if self.push_within_capacity(value).is_err() {
+            unreachable!("reserved for push");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{heap::HeapMemory, memory::Memory};
+use core::ops::{Deref, DerefMut};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Memory of a file held on the heap and written back with regular writes, for platforms without
+/// `mmap` such as WASI.
+///
+/// Opening reads the whole file into a [`HeapMemory`]. [`Memory::sync`], e.g. by
+/// [`crate::MemVec::flush`], writes the synced bytes and the length to the file; the memory writes
+/// everything back again when it drops after a change. The file starts with a 64-byte header
+/// holding the length, followed by the data region.
+#[derive(Debug)]
+pub struct FileMemory {
+    heap: HeapMemory,
+    file: File,
+    /// Changed since the file was read or completely written back.
+    dirty: bool,
+}
+
+const MAGIC: [u8; 8] = *b"MEMVECFM";
+const HEADER_LEN: usize = 64;
+
+impl FileMemory {
+    /// Open the file at `path` for reading and writing, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .and_then(Self::from_file)
+            .map_err(|e| crate::error::with_path(e, path))
+    }
+
+    /// Read the memory from a readable and writable `file`. An empty file is initialized.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] if the file holds something else.
+    pub fn from_file(mut file: File) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let mut heap = HeapMemory::new();
+        let file_len = file.seek(SeekFrom::End(0))?;
+        if file_len == 0 {
+            let this = Self {
+                heap,
+                file,
+                dirty: false,
+            };
+            this.write_header()?;
+            return Ok(this);
+        }
+        let invalid = || Error::new(ErrorKind::InvalidData, "not a memvec file memory");
+        let data_len = file_len
+            .checked_sub(HEADER_LEN as u64)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(invalid)?;
+        let mut header = [0; HEADER_LEN];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        if header[..8] != MAGIC {
+            return Err(invalid());
+        }
+        let len = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let len = usize::try_from(len).map_err(|_| invalid())?;
+        heap.reserve(data_len)?;
+        file.read_exact(&mut heap)?;
+        heap.set_len(len);
+        Ok(Self {
+            heap,
+            file,
+            dirty: false,
+        })
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    fn write_header(&self) -> std::io::Result<()> {
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..16].copy_from_slice(&(self.heap.len() as u64).to_le_bytes());
+        self.write_at(&header, 0)
+    }
+
+    fn write_at(&self, bytes: &[u8], offset: u64) -> std::io::Result<()> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(bytes)
+    }
+
+    /// Write the whole data region and the length if anything changed.
+    fn write_back(&mut self) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.write_at(&self.heap, HEADER_LEN as u64)?;
+        self.write_header()?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Deref for FileMemory {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.heap
+    }
+}
+
+impl DerefMut for FileMemory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty = true;
+        &mut self.heap
+    }
+}
+
+impl Memory for FileMemory {
+    type Error = std::io::Error;
+
+    fn as_ptr(&self) -> *const u8 {
+        self.heap.as_ptr()
+    }
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.dirty = true;
+        self.heap.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.dirty = true;
+        self.heap.set_len(len)
+    }
+
+    fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
+        self.heap.reserve(capacity)
+    }
+
+    fn shrink(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity < self.heap.deref().len() {
+            self.heap.shrink(capacity)?;
+            self.file.set_len((HEADER_LEN + capacity) as u64)?;
+        }
+        Ok(())
+    }
+
+    fn sync(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        self.write_at(&self.heap[range.clone()], (HEADER_LEN + range.start) as u64)?;
+        self.write_header()?;
+        self.file.sync_data()
+    }
+}
+
+impl Drop for FileMemory {
+    fn drop(&mut self) {
+        let _ = self.write_back();
+    }
+}
@@ -0,0 +1,138 @@
+use crate::vec_file::VecFile;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// A locked process-shared mutex of a [`VecFile`], unlocked when dropped. See
+/// [`VecFile::lock_mutex`].
+#[derive(Debug)]
+pub struct FileMutexGuard<'f> {
+    word: &'f AtomicU32,
+    recovered: bool,
+}
+
+impl<'f> FileMutexGuard<'f> {
+    /// The mutex was taken over from a process which died holding it, so whatever it protects may
+    /// be half-changed.
+    pub fn recovered(&self) -> bool {
+        self.recovered
+    }
+}
+
+impl<'f> Drop for FileMutexGuard<'f> {
+    fn drop(&mut self) {
+        self.word.store(0, Ordering::Release);
+    }
+}
+
+impl VecFile {
+    /// Lock the process-shared mutex of the file, waiting while another process or thread holds
+    /// it.
+    ///
+    /// The mutex is meant for short critical sections of cooperating processes around structural
+    /// changes; a whole-file lock would exclude them for as long as the file is open. Its word in
+    /// the header holds the process id of the owner. A waiter finding that process gone takes the
+    /// mutex over, see [`FileMutexGuard::recovered`]. A process id reused by a new process keeps a
+    /// dead owner's mutex locked until that process exits too. Locking it again in the thread
+    /// holding it deadlocks.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if the file was created without
+    /// [`crate::VecFileBuilder::mutex`].
+    pub fn lock_mutex(&self) -> std::io::Result<FileMutexGuard<'_>> {
+        let word = self.require_mutex()?;
+        let mut spins = 0;
+        loop {
+            if let Some(guard) = try_lock(word) {
+                return Ok(guard);
+            }
+            if spins < 64 {
+                spins += 1;
+                core::hint::spin_loop();
+            } else {
+                std::thread::sleep(Duration::from_micros(50));
+            }
+        }
+    }
+
+    /// [`VecFile::lock_mutex`] without waiting, returning `None` if the mutex is held.
+    pub fn try_lock_mutex(&self) -> std::io::Result<Option<FileMutexGuard<'_>>> {
+        Ok(try_lock(self.require_mutex()?))
+    }
+
+    fn require_mutex(&self) -> std::io::Result<&AtomicU32> {
+        self.mutex_word().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "memvec file was created without a mutex",
+            )
+        })
+    }
+}
+
+fn try_lock(word: &AtomicU32) -> Option<FileMutexGuard<'_>> {
+    let pid = std::process::id();
+    let owner = match word.compare_exchange(0, pid.to_le(), Ordering::Acquire, Ordering::Relaxed) {
+        Ok(_) => {
+            return Some(FileMutexGuard {
+                word,
+                recovered: false,
+            })
+        }
+        Err(owner) => u32::from_le(owner),
+    };
+    if owner == pid || process_alive(owner) {
+        return None;
+    }
+    // only one of the waiters replaces the dead owner
+    word.compare_exchange(
+        owner.to_le(),
+        pid.to_le(),
+        Ordering::Acquire,
+        Ordering::Relaxed,
+    )
+    .ok()
+    .map(|_| FileMutexGuard {
+        word,
+        recovered: true,
+    })
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // signal 0 checks for existence; EPERM means it exists but belongs to another user
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use core::ffi::c_void;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit_handle: i32, pid: u32) -> *mut c_void;
+        fn GetExitCodeProcess(process: *mut c_void, exit_code: *mut u32) -> i32;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    const STILL_ACTIVE: u32 = 259;
+    const ERROR_INVALID_PARAMETER: i32 = 87;
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            // an invalid parameter means there is no such process; other failures, e.g. access
+            // denied, mean it exists
+            return std::io::Error::last_os_error().raw_os_error() != Some(ERROR_INVALID_PARAMETER);
+        }
+        let mut exit_code = 0;
+        let alive = GetExitCodeProcess(process, &mut exit_code) == 0 || exit_code == STILL_ACTIVE;
+        CloseHandle(process);
+        alive
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
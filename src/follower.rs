@@ -0,0 +1,94 @@
+use crate::{
+    memory::MemoryConversionError, plain::Plain, read_only::ReadOnlyVecFile, vec_file::VecFile,
+};
+use core::{marker::PhantomData, ops::Deref};
+use std::path::Path;
+
+/// A read-only view of a [`VecFile`] written by another process, which catches up with the writer
+/// on [`Follower::refresh`].
+///
+/// Between refreshes the view is a stable prefix of the records: its length only changes on
+/// refresh, and it covers only records whose writes were published, so it never shows a record
+/// being appended. The file is mapped again when the writer grew it past the mapping. Records may
+/// still be rewritten in place by the writer; see [`crate::ReadOnlyMemVec::read_consistent`].
+pub struct Follower<T: Plain> {
+    file: ReadOnlyVecFile,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Plain> Follower<T> {
+    /// Open the vector file at `path` read-only and follow it.
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::from_file(VecFile::open_read_only(path)?)
+            .map_err(|(_, e)| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn from_file(
+        file: ReadOnlyVecFile,
+    ) -> Result<Self, (ReadOnlyVecFile, MemoryConversionError)> {
+        if let Err(e) = crate::memory::check_align::<T>(file.as_ptr()) {
+            return Err((file, e));
+        }
+        let mut this = Self {
+            file,
+            len: 0,
+            _marker: PhantomData,
+        };
+        this.len = this.file.len().min(this.mapped_len());
+        Ok(this)
+    }
+
+    /// The number of records the mapping holds.
+    fn mapped_len(&self) -> usize {
+        match core::mem::size_of::<T>() {
+            0 => usize::MAX,
+            size => self.file.deref().len() / size,
+        }
+    }
+
+    /// Catch up with the writer, mapping the file again if it outgrew the mapping, and return the
+    /// number of records which became visible.
+    ///
+    /// Slices borrowed from the view end before, since the mapping may move.
+    pub fn refresh(&mut self) -> std::io::Result<usize> {
+        let len = self.file.len();
+        if len > self.mapped_len() {
+            self.file.remap()?;
+        }
+        let old_len = self.len;
+        // a writer which shrank the file leaves fewer records visible
+        self.len = len.min(self.mapped_len());
+        Ok(self.len.saturating_sub(old_len))
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.file.as_ptr().cast::<T>(), self.len) }
+    }
+
+    pub fn as_file(&self) -> &ReadOnlyVecFile {
+        &self.file
+    }
+
+    pub fn into_file(self) -> ReadOnlyVecFile {
+        self.file
+    }
+}
+
+impl<T: Plain> Deref for Follower<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T: core::fmt::Debug + Plain> core::fmt::Debug for Follower<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
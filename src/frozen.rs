@@ -0,0 +1,71 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+use core::{mem::MaybeUninit, ops::Deref};
+
+/// A [`MemVec`] borrowed without growing or shrinking.
+///
+/// Growing or shrinking may remap the memory, which invalidates every pointer into it. The guard
+/// holds the only borrow of the vector and exposes nothing which changes the capacity or the
+/// elements already in it; it only appends within the capacity. So the slices of
+/// [`Frozen::as_slice`] live as long as the vector is frozen, across later pushes, and pointers
+/// taken from the guard stay valid until it drops.
+///
+/// See [`MemVec::frozen`] and [`MemVec::reserve_exact_then_freeze`].
+pub struct Frozen<'v, 'a, T: Plain, A: 'a + Memory> {
+    pub(crate) vec: &'v mut MemVec<'a, T, A>,
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> Frozen<'v, 'a, T, A> {
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.vec.as_ptr()
+    }
+
+    /// The elements pushed so far, which are neither moved nor changed while the vector is
+    /// frozen.
+    pub fn as_slice(&self) -> &'v [T] {
+        // the guard never shrinks the vector or writes below its length
+        unsafe { core::slice::from_raw_parts(self.vec.as_ptr(), self.vec.len()) }
+    }
+
+    /// See [`MemVec::push_within_capacity`].
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        self.vec.push_within_capacity(value)
+    }
+
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        self.vec.spare_capacity_mut()
+    }
+
+    /// # Safety
+    /// Same as [`MemVec::set_len`]. Besides, `len` must not be less than the current length,
+    /// since the slices of [`Frozen::as_slice`] may still refer to the elements.
+    pub unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len >= self.vec.len());
+        self.vec.set_len(len)
+    }
+}
+
+impl<'v, 'a, T: Plain, A: 'a + Memory> Deref for Frozen<'v, 'a, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<'v, 'a, T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug for Frozen<'v, 'a, T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
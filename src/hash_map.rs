@@ -0,0 +1,404 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// A hash map of fixed-size keys and values over [`Memory`], e.g. a persistent index from ids to
+/// the records of a [`crate::VecFile`].
+///
+/// The memory starts with a map header, followed by a control byte per bucket and then by the
+/// arrays of keys and of values. Buckets are probed linearly, and removal shifts the following
+/// entries back instead of leaving tombstones. The map grows to twice its buckets when it is three
+/// quarters full, rehashing every entry; an interrupted growth leaves the memory inconsistent.
+///
+/// Keys are hashed with a fixed hash function rather than a random one, so the hashes stored in
+/// the memory stay valid for other processes and later runs. The [`Hash`] implementation of the
+/// key type must be the same for all of them, e.g. it must not hash a `usize` on platforms of
+/// different widths or integers on platforms of different byte orders.
+pub struct MemHashMap<K: Plain + Hash + Eq, V: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<(K, V)>,
+}
+
+#[repr(C)]
+struct MapHeader {
+    magic: [u8; 8],
+    key_size: u64,
+    value_size: u64,
+    /// The number of buckets, zero or a power of two.
+    buckets: u64,
+    len: u64,
+    _reserved: [u8; 24],
+}
+
+const _: () = assert!(core::mem::size_of::<MapHeader>() == MapHeader::LEN);
+
+impl MapHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECHM";
+}
+
+const EMPTY: u8 = 0;
+const FULL: u8 = 1;
+
+/// The byte offsets of the keys, the values and the end of a map of `buckets` buckets.
+struct Layout {
+    keys: usize,
+    values: usize,
+    end: usize,
+}
+
+impl Layout {
+    fn new<K, V>(buckets: usize) -> Option<Self> {
+        let keys = (MapHeader::LEN + buckets).next_multiple_of(core::mem::align_of::<K>());
+        let values = buckets
+            .checked_mul(core::mem::size_of::<K>())?
+            .checked_add(keys)?
+            .next_multiple_of(core::mem::align_of::<V>());
+        let end = buckets
+            .checked_mul(core::mem::size_of::<V>())?
+            .checked_add(values)?;
+        (end <= isize::MAX as usize).then_some(Self { keys, values, end })
+    }
+}
+
+/// FNV-1a, with the bits mixed at the end since buckets are chosen by the low bits.
+struct StableHasher(u64);
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = self.0;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^ (hash >> 33)
+    }
+}
+
+pub(crate) fn hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = StableHasher(0xcbf2_9ce4_8422_2325);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<K: Plain + Hash + Eq, V: Plain, A: Memory<Error = std::io::Error>> MemHashMap<K, V, A> {
+    /// Create an empty map in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no map of keys
+    /// and values of these sizes.
+    ///
+    /// # Safety
+    /// The keys and values must hold valid bytes representations of K and V.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let key_size = core::mem::size_of::<K>() as u64;
+        let value_size = core::mem::size_of::<V>() as u64;
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(MapHeader::LEN)?;
+        } else if mem.len() < MapHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec hash map"));
+        }
+        check_align::<MapHeader>(mem.as_ptr())
+            .and_then(|()| check_align::<K>(mem.as_ptr()))
+            .and_then(|()| check_align::<V>(mem.as_ptr()))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            mem.as_mut_ptr().cast::<MapHeader>().write(MapHeader {
+                magic: MapHeader::MAGIC,
+                key_size: key_size.to_le(),
+                value_size: value_size.to_le(),
+                buckets: 0,
+                len: 0,
+                _reserved: [0; 24],
+            });
+            mem.set_len(MapHeader::LEN);
+        }
+        let this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != MapHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec hash map"));
+        }
+        if u64::from_le(header.key_size) != key_size
+            || u64::from_le(header.value_size) != value_size
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec hash map has a different key or value size",
+            ));
+        }
+        let buckets = u64::from_le(header.buckets);
+        let valid = (buckets == 0 || buckets.is_power_of_two())
+            && usize::try_from(buckets)
+                .ok()
+                .and_then(Layout::new::<K, V>)
+                .is_some_and(|layout| layout.end == this.mem.len())
+            // the length is read from the memory too, so it must not exceed the mapped bytes
+            && this.mem.len() <= core::ops::Deref::deref(&this.mem).len()
+            && this.len() <= this.buckets() / 4 * 3
+            // so an empty bucket ends every probe
+            && (0..this.buckets())
+                .filter(|&bucket| this.control(bucket) != EMPTY)
+                .count()
+                == this.len();
+        if !valid {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Grow the buckets so that at least `additional` more entries fit without growing.
+    pub fn reserve(&mut self, additional: usize) -> std::io::Result<()> {
+        let needed = self
+            .len()
+            .checked_add(additional)
+            .ok_or_else(capacity_overflow)?;
+        if needed <= self.capacity() {
+            return Ok(());
+        }
+        let buckets = needed
+            .checked_mul(4)
+            .map(|buckets| (buckets / 3).max(8))
+            .and_then(usize::checked_next_power_of_two)
+            .ok_or_else(capacity_overflow)?;
+        self.rehash(buckets)
+    }
+
+    fn rehash(&mut self, buckets: usize) -> std::io::Result<()> {
+        let layout = Layout::new::<K, V>(buckets).ok_or_else(capacity_overflow)?;
+        // the entries are moved out, since the arrays move when the buckets grow
+        let entries: Vec<(K, V)> = self
+            .raw_iter()
+            .map(|bucket| unsafe { (self.key_ptr(bucket).read(), self.value_ptr(bucket).read()) })
+            .collect();
+        self.mem.reserve(layout.end)?;
+        self.mem.set_len(layout.end);
+        self.mem[MapHeader::LEN..MapHeader::LEN + buckets].fill(EMPTY);
+        let header = self.header_mut();
+        header.buckets = (buckets as u64).to_le();
+        header.len = 0;
+        for (key, value) in entries {
+            let bucket = match self.find(&key) {
+                Ok(_) => unreachable!("keys are unique"),
+                Err(bucket) => bucket,
+            };
+            unsafe { self.write_entry(bucket, key, value) };
+        }
+        Ok(())
+    }
+
+    /// Insert `value` for `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: K, value: V) -> std::io::Result<Option<V>> {
+        if let Some(Ok(bucket)) = (self.buckets() > 0).then(|| self.find(&key)) {
+            let old = unsafe { self.value_mut_ptr(bucket).replace(value) };
+            return Ok(Some(old));
+        }
+        self.reserve(1)?;
+        let bucket = match self.find(&key) {
+            Ok(_) => unreachable!("the key was not found before growing"),
+            Err(bucket) => bucket,
+        };
+        unsafe { self.write_entry(bucket, key, value) };
+        Ok(None)
+    }
+}
+
+fn capacity_overflow() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+}
+
+impl<K: Plain + Hash + Eq, V: Plain, A: Memory> MemHashMap<K, V, A> {
+    fn header(&self) -> &MapHeader {
+        unsafe { &*self.mem.as_ptr().cast::<MapHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut MapHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<MapHeader>() }
+    }
+
+    fn buckets(&self) -> usize {
+        u64::from_le(self.header().buckets) as usize
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::new::<K, V>(self.buckets()).expect("validated on open")
+    }
+
+    fn control(&self, bucket: usize) -> u8 {
+        self.mem[MapHeader::LEN + bucket]
+    }
+
+    fn key_ptr(&self, bucket: usize) -> *const K {
+        let offset = self.layout().keys + bucket * core::mem::size_of::<K>();
+        self.mem.as_ptr().wrapping_add(offset).cast()
+    }
+
+    fn value_ptr(&self, bucket: usize) -> *const V {
+        let offset = self.layout().values + bucket * core::mem::size_of::<V>();
+        self.mem.as_ptr().wrapping_add(offset).cast()
+    }
+
+    fn key_mut_ptr(&mut self, bucket: usize) -> *mut K {
+        let offset = self.layout().keys + bucket * core::mem::size_of::<K>();
+        self.mem.as_mut_ptr().wrapping_add(offset).cast()
+    }
+
+    fn value_mut_ptr(&mut self, bucket: usize) -> *mut V {
+        let offset = self.layout().values + bucket * core::mem::size_of::<V>();
+        self.mem.as_mut_ptr().wrapping_add(offset).cast()
+    }
+
+    /// The bucket holding `key`, or the empty bucket where it belongs. There must be an empty
+    /// bucket.
+    ///
+    /// # Panics
+    /// Panics if the control bytes in the memory are corrupt and leave no empty bucket, rather
+    /// than probing forever.
+    fn find(&self, key: &K) -> Result<usize, usize> {
+        let mask = self.buckets().wrapping_sub(1);
+        let mut bucket = hash(key) as usize & mask;
+        for _ in 0..self.buckets() {
+            if self.control(bucket) == EMPTY {
+                return Err(bucket);
+            }
+            if unsafe { &*self.key_ptr(bucket) } == key {
+                return Ok(bucket);
+            }
+            bucket = (bucket + 1) & mask;
+        }
+        panic!("broken memvec hash map: no empty bucket");
+    }
+
+    /// # Safety
+    /// The bucket must be empty.
+    unsafe fn write_entry(&mut self, bucket: usize, key: K, value: V) {
+        self.key_mut_ptr(bucket).write(key);
+        self.value_mut_ptr(bucket).write(value);
+        // the entry is complete before it is marked
+        self.mem[MapHeader::LEN + bucket] = FULL;
+        let len = self.len() + 1;
+        self.header_mut().len = (len as u64).to_le();
+    }
+
+    fn raw_iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.buckets()).filter(|&bucket| self.control(bucket) == FULL)
+    }
+
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of entries the map holds without growing.
+    pub fn capacity(&self) -> usize {
+        self.buckets() / 4 * 3
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        if self.is_empty() {
+            return None;
+        }
+        let bucket = self.find(key).ok()?;
+        Some(unsafe { &*self.value_ptr(bucket) })
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if self.is_empty() {
+            return None;
+        }
+        let bucket = self.find(key).ok()?;
+        Some(unsafe { &mut *self.value_mut_ptr(bucket) })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Remove the entry of `key`, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut hole = self.find(key).ok()?;
+        let value = unsafe { self.value_ptr(hole).read() };
+        let mask = self.buckets() - 1;
+        // shift back the following entries which would not be found across the hole
+        let mut bucket = (hole + 1) & mask;
+        while self.control(bucket) == FULL {
+            let home = hash(unsafe { &*self.key_ptr(bucket) }) as usize & mask;
+            if (bucket.wrapping_sub(home) & mask) >= (bucket.wrapping_sub(hole) & mask) {
+                unsafe {
+                    let key = self.key_ptr(bucket).read();
+                    let value = self.value_ptr(bucket).read();
+                    self.key_mut_ptr(hole).write(key);
+                    self.value_mut_ptr(hole).write(value);
+                }
+                hole = bucket;
+            }
+            bucket = (bucket + 1) & mask;
+        }
+        self.mem[MapHeader::LEN + hole] = EMPTY;
+        let len = self.len() - 1;
+        self.header_mut().len = (len as u64).to_le();
+        Some(value)
+    }
+
+    /// Remove all entries, keeping the buckets.
+    pub fn clear(&mut self) {
+        let buckets = self.buckets();
+        self.mem[MapHeader::LEN..MapHeader::LEN + buckets].fill(EMPTY);
+        self.header_mut().len = 0;
+    }
+
+    /// The entries in bucket order, which is arbitrary.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.raw_iter()
+            .map(|bucket| unsafe { (&*self.key_ptr(bucket), &*self.value_ptr(bucket)) })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Write the entries and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<K, V, A> core::fmt::Debug for MemHashMap<K, V, A>
+where
+    K: Plain + Hash + Eq + core::fmt::Debug,
+    V: Plain + core::fmt::Debug,
+    A: Memory,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
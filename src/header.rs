@@ -0,0 +1,422 @@
+use crate::checksum::Crc32;
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{fence, AtomicU32, AtomicU64, Ordering},
+};
+
+/// On-disk header of a [`crate::VecFile`].
+///
+/// The header occupies the first [`Header::LEN`] bytes of the file and the data region follows it.
+/// Integer fields are stored in little-endian byte order regardless of the platform, so the header
+/// is readable on both 32-bit and 64-bit machines. Only the byte order mark is native.
+///
+/// The mutable part of the header, [`State`], is written alternately to two slots with a sequence
+/// number and a CRC. The newer valid slot is the current state, so a torn write during an update
+/// leaves the previous state readable.
+#[repr(C)]
+pub(crate) struct Header {
+    magic: [u8; 8],
+    version: u32,
+    byte_order: u32,
+    metadata_len: u32,
+    /// Format features of the file, which are fixed at creation.
+    features: u32,
+    /// Capacity of the data region in bytes, if [`Header::FEATURE_CAPACITY`] is set.
+    capacity: u64,
+    /// The lock word: the process id of the single writer holding the file, or 0.
+    writer: AtomicU32,
+    /// Odd while records are rewritten in place by [`Header::seqlock_write`].
+    seqlock: AtomicU32,
+    /// The length published with release ordering after every update, if
+    /// [`Header::FEATURE_PUBLISHED_LEN`] is set.
+    published_len: AtomicU64,
+    /// Written through shared references by [`Header::update`].
+    slots: UnsafeCell<[Slot; 2]>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Slot {
+    seq: u64,
+    len: u64,
+    generation: u64,
+    flags: u32,
+    checksum: u32,
+    crc: u32,
+    _reserved: u32,
+}
+
+const _: () = assert!(core::mem::size_of::<Header>() == Header::LEN);
+
+/// The mutable part of [`Header`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct State {
+    pub len: u64,
+    /// Increased on every change of the length or the capacity.
+    pub generation: u64,
+    pub flags: u32,
+    pub checksum: u32,
+}
+
+impl State {
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    pub fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+impl Slot {
+    fn compute_crc(&self) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&self.seq.to_ne_bytes());
+        crc.update(&self.len.to_ne_bytes());
+        crc.update(&self.generation.to_ne_bytes());
+        crc.update(&self.flags.to_ne_bytes());
+        crc.update(&self.checksum.to_ne_bytes());
+        crc.finish()
+    }
+
+    fn is_valid(&self) -> bool {
+        u32::from_le(self.crc) == self.compute_crc()
+    }
+
+    fn seq(&self) -> u64 {
+        u64::from_le(self.seq)
+    }
+
+    fn state(&self) -> State {
+        State {
+            len: u64::from_le(self.len),
+            generation: u64::from_le(self.generation),
+            flags: u32::from_le(self.flags),
+            checksum: u32::from_le(self.checksum),
+        }
+    }
+
+    fn new(seq: u64, state: State) -> Self {
+        let mut slot = Self {
+            seq: seq.to_le(),
+            len: state.len.to_le(),
+            generation: state.generation.to_le(),
+            flags: state.flags.to_le(),
+            checksum: state.checksum.to_le(),
+            crc: 0,
+            _reserved: 0,
+        };
+        slot.crc = slot.compute_crc().to_le();
+        slot
+    }
+}
+
+impl Header {
+    pub const LEN: usize = 128;
+    pub const MAGIC: [u8; 8] = *b"MEMVEC\0\0";
+    pub const VERSION: u32 = 1;
+    /// Written in native byte order; reads as its byte-swapped value on a machine of the other
+    /// endianness.
+    pub const BYTE_ORDER_MARK: u32 = 0x0102_0304;
+    /// Alignment of the data region from the beginning of the file.
+    pub const DATA_ALIGN: usize = 64;
+
+    /// The capacity of the data region is recorded in the header rather than taken from the file
+    /// size.
+    pub const FEATURE_CAPACITY: u32 = 1 << 0;
+    /// The length is also published as an atomic, so readers in other threads or processes
+    /// never observe a length covering records whose bytes are not visible yet.
+    pub const FEATURE_PUBLISHED_LEN: u32 = 1 << 1;
+    /// A block of [`Header::EXT_LEN`] bytes holding the process-shared mutex follows the header,
+    /// before the user metadata region.
+    pub const FEATURE_MUTEX: u32 = 1 << 2;
+    /// The length is capped at `u32::MAX`, so the indices of the records fit in 32 bits. The
+    /// length is still stored in 64 bits.
+    pub const FEATURE_U32_LEN_CAP: u32 = 1 << 3;
+    /// The features which may be chosen at creation.
+    pub const OPTIONAL_FEATURES: u32 = Self::FEATURE_MUTEX | Self::FEATURE_U32_LEN_CAP;
+    const KNOWN_FEATURES: u32 = Self::FEATURE_CAPACITY
+        | Self::FEATURE_PUBLISHED_LEN
+        | Self::FEATURE_MUTEX
+        | Self::FEATURE_U32_LEN_CAP;
+    /// Length of the block enabled by [`Header::FEATURE_MUTEX`]. Its first 4 bytes are the mutex
+    /// word; the rest is reserved.
+    pub const EXT_LEN: usize = 64;
+
+    /// The data region is covered by a checksum.
+    pub const FLAG_CHECKSUM: u32 = 1 << 0;
+    /// The stored checksum matches the data region.
+    /// Cleared while the file is open for writing, set again when it is closed.
+    pub const FLAG_CHECKSUM_VALID: u32 = 1 << 1;
+    /// A mutation which rewrites records in place was interrupted, so the data may be
+    /// half-mutated.
+    pub const FLAG_POISONED: u32 = 1 << 2;
+
+    /// Initialize an empty file with the [`Header::OPTIONAL_FEATURES`] in `features`.
+    pub fn init(&mut self, metadata_len: u32, features: u32) {
+        debug_assert_eq!(features & !Self::OPTIONAL_FEATURES, 0);
+        self.magic = Self::MAGIC;
+        self.version = Self::VERSION.to_le();
+        self.byte_order = Self::BYTE_ORDER_MARK;
+        self.metadata_len = metadata_len.to_le();
+        let features = Self::FEATURE_CAPACITY | Self::FEATURE_PUBLISHED_LEN | features;
+        self.features = features.to_le();
+        self.capacity = 0;
+        self.writer = AtomicU32::new(0);
+        self.seqlock = AtomicU32::new(0);
+        self.published_len = AtomicU64::new(0);
+        self.slots = UnsafeCell::new([Slot::new(0, State::default()); 2]);
+    }
+
+    pub fn validate(&self) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+        if self.magic != Self::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec file"));
+        }
+        if self.byte_order != Self::BYTE_ORDER_MARK {
+            let message = if self.byte_order == Self::BYTE_ORDER_MARK.swap_bytes() {
+                "memvec file was created on a machine with different byte order"
+            } else {
+                "memvec file has a broken byte order mark"
+            };
+            return Err(Error::new(ErrorKind::InvalidData, message));
+        }
+        if self.version() != Self::VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported memvec file version {}", self.version()),
+            ));
+        }
+        if u32::from_le(self.features) & !Self::KNOWN_FEATURES != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec file uses unsupported features",
+            ));
+        }
+        let Some((_, slot)) = self.active_slot() else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "both memvec header slots are broken",
+            ));
+        };
+        if usize::try_from(slot.state().len).is_err() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec file is too large for this platform",
+            ));
+        }
+        if self.has_feature(Self::FEATURE_U32_LEN_CAP) && slot.state().len > u32::MAX as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec file capped at u32::MAX records holds more",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn version(&self) -> u32 {
+        u32::from_le(self.version)
+    }
+
+    /// The newer valid slot with its index.
+    fn active_slot(&self) -> Option<(usize, Slot)> {
+        // read without caching, since other processes sharing the mapping may update it
+        let slots = [0, 1]
+            .map(|i| unsafe { core::ptr::read_volatile(self.slots.get().cast::<Slot>().add(i)) });
+        (0..2)
+            .filter(|&i| slots[i].is_valid())
+            .max_by_key(|&i| slots[i].seq())
+            .map(|i| (i, slots[i]))
+    }
+
+    pub fn state(&self) -> State {
+        self.active_slot()
+            .map(|(_, slot)| slot.state())
+            .unwrap_or_default()
+    }
+
+    /// The newer slot with its index, by the sequence number only.
+    ///
+    /// Only for the writer after its first [`Header::update`], which leaves both slots valid;
+    /// slots are verified on open and the writer does not tear its own writes.
+    fn written_slot(&self) -> (usize, Slot) {
+        let slots = [0, 1]
+            .map(|i| unsafe { core::ptr::read_volatile(self.slots.get().cast::<Slot>().add(i)) });
+        let index = usize::from(slots[1].seq() > slots[0].seq());
+        (index, slots[index])
+    }
+
+    /// [`Header::state`] without verifying the slots. See [`Header::written_slot`].
+    pub fn written_state(&self) -> State {
+        self.written_slot().1.state()
+    }
+
+    /// Write the updated state to the inactive slot, which becomes the active one, then publish
+    /// the length.
+    ///
+    /// Takes `&self` so that the header can be updated while readers of the same mapping hold it;
+    /// the caller must be the only writer.
+    pub fn update(&self, f: impl FnOnce(&mut State)) {
+        let (index, seq, state) = match self.active_slot() {
+            Some((index, slot)) => (index, slot.seq(), slot.state()),
+            None => (1, 0, State::default()),
+        };
+        self.write_slot(index, seq, state, f);
+    }
+
+    /// [`Header::update`] without verifying the slots. See [`Header::written_slot`].
+    pub fn update_written(&self, f: impl FnOnce(&mut State)) {
+        let (index, slot) = self.written_slot();
+        self.write_slot(index, slot.seq(), slot.state(), f);
+    }
+
+    fn write_slot(&self, index: usize, seq: u64, mut state: State, f: impl FnOnce(&mut State)) {
+        f(&mut state);
+        let slot = Slot::new(seq.wrapping_add(1), state);
+        unsafe { core::ptr::write_volatile(self.slots.get().cast::<Slot>().add(1 - index), slot) };
+        if self.has_feature(Self::FEATURE_PUBLISHED_LEN) {
+            self.published_len
+                .store(state.len.to_le(), Ordering::Release);
+        }
+    }
+
+    pub fn has_feature(&self, feature: u32) -> bool {
+        u32::from_le(self.features) & feature != 0
+    }
+
+    /// The length published by the last update with acquire ordering, so the records it covers
+    /// are visible. `None` for files without [`Header::FEATURE_PUBLISHED_LEN`].
+    pub fn published_len(&self) -> Option<u64> {
+        self.has_feature(Self::FEATURE_PUBLISHED_LEN)
+            .then(|| u64::from_le(self.published_len.load(Ordering::Acquire)))
+    }
+
+    /// Enable [`Header::FEATURE_PUBLISHED_LEN`] for files created without it.
+    pub fn enable_published_len(&mut self) {
+        if !self.has_feature(Self::FEATURE_PUBLISHED_LEN) {
+            *self.published_len.get_mut() = self.state().len.to_le();
+            self.features |= Self::FEATURE_PUBLISHED_LEN.to_le();
+        }
+    }
+
+    /// Checksum of the length, the user metadata region and the data region.
+    pub fn compute_checksum(&self, metadata: &[u8], data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&self.state().len.to_le_bytes());
+        crc.update(metadata);
+        crc.update(data);
+        crc.finish()
+    }
+
+    pub fn verify_checksum(&self, metadata: &[u8], data: &[u8]) -> std::io::Result<()> {
+        let expected = self.state().checksum;
+        let found = self.compute_checksum(metadata, data);
+        if expected != found {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                ChecksumMismatch { expected, found },
+            ));
+        }
+        Ok(())
+    }
+
+    /// The recorded capacity of the data region in bytes, if any.
+    ///
+    /// Files written before the capacity was recorded have none; their data region extends to the
+    /// end of the file.
+    pub fn capacity(&self) -> Option<u64> {
+        if !self.has_feature(Self::FEATURE_CAPACITY) {
+            return None;
+        }
+        let capacity = unsafe { core::ptr::read_volatile(&self.capacity) };
+        Some(u64::from_le(capacity))
+    }
+
+    /// Record the capacity, enabling [`Header::FEATURE_CAPACITY`] for files without it.
+    pub fn set_capacity(&mut self, capacity: u64) {
+        self.features |= Self::FEATURE_CAPACITY.to_le();
+        unsafe { core::ptr::write_volatile(&mut self.capacity, capacity.to_le()) };
+    }
+
+    /// The process id stored in the lock word, or 0 if no single writer holds the file.
+    pub fn writer(&self) -> u32 {
+        u32::from_le(self.writer.load(Ordering::Acquire))
+    }
+
+    pub fn set_writer(&self, pid: u32) {
+        self.writer.store(pid.to_le(), Ordering::Release);
+    }
+
+    /// Run `write` with the sequence counter odd, so that concurrent [`Header::seqlock_read`]s
+    /// retry instead of returning what it wrote halfway.
+    ///
+    /// The caller must be the only writer. The counter is only compared, so it is kept in native
+    /// byte order.
+    pub fn seqlock_write<R>(&self, write: impl FnOnce() -> R) -> R {
+        let seq = self.seqlock.load(Ordering::Relaxed);
+        self.seqlock.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        let result = write();
+        self.seqlock.store(seq.wrapping_add(2), Ordering::Release);
+        result
+    }
+
+    /// Run `read` until no [`Header::seqlock_write`] overlapped it, and return that result.
+    ///
+    /// `read` must not assume that what it reads is consistent, since it is discarded otherwise.
+    pub fn seqlock_read<R>(&self, mut read: impl FnMut() -> R) -> R {
+        loop {
+            let seq = self.seqlock.load(Ordering::Acquire);
+            if seq & 1 == 0 {
+                let result = read();
+                fence(Ordering::Acquire);
+                if self.seqlock.load(Ordering::Relaxed) == seq {
+                    return result;
+                }
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Length of the user metadata region which directly follows the header.
+    pub fn metadata_len(&self) -> usize {
+        u32::from_le(self.metadata_len) as usize
+    }
+
+    /// Offset of the user metadata region, after the header and its extension block, if any.
+    pub fn metadata_offset(&self) -> usize {
+        if self.has_feature(Self::FEATURE_MUTEX) {
+            Self::LEN + Self::EXT_LEN
+        } else {
+            Self::LEN
+        }
+    }
+
+    /// Offset of the data region, after the header and the user metadata region.
+    pub fn data_offset(&self) -> usize {
+        (self.metadata_offset() + self.metadata_len()).next_multiple_of(Self::DATA_ALIGN)
+    }
+}
+
+/// The data region of a file does not match the checksum stored in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub expected: u32,
+    pub found: u32,
+}
+
+impl core::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "checksum mismatch: expected {:#010x}, found {:#010x}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
@@ -0,0 +1,111 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+use core::ops::{Deref, DerefMut};
+
+/// Memory on the heap, aligned to 64 bytes like the data region of a [`crate::VecFile`].
+///
+/// It maps nothing, so code using it runs under Miri, which cannot execute `mmap`. Growing may
+/// move the buffer like a remap does. The error type is [`std::io::Error`] as for the file
+/// backends, though it never fails.
+#[derive(Debug, Default)]
+pub struct HeapMemory {
+    chunks: Vec<Chunk>,
+    capacity: usize,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C, align(64))]
+struct Chunk([u8; 64]);
+
+impl HeapMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create empty memory; the counterpart of [`crate::VecFile::temp`] for [`TempMemory`].
+    pub fn temp() -> std::io::Result<Self> {
+        Ok(Self::new())
+    }
+}
+
+impl Deref for HeapMemory {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { core::slice::from_raw_parts(self.chunks.as_ptr().cast::<u8>(), self.capacity) }
+    }
+}
+
+impl DerefMut for HeapMemory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            core::slice::from_raw_parts_mut(self.chunks.as_mut_ptr().cast::<u8>(), self.capacity)
+        }
+    }
+}
+
+/// Safe since any bytes are a valid [`bytemuck::Pod`]; see [`MemVec::from_memory_pod`].
+#[cfg(feature = "bytemuck")]
+impl<'a, T: bytemuck::Pod> TryFrom<HeapMemory> for MemVec<'a, T, HeapMemory> {
+    type Error = (HeapMemory, crate::MemoryConversionError);
+
+    fn try_from(mem: HeapMemory) -> Result<Self, Self::Error> {
+        Self::from_memory_pod(mem)
+    }
+}
+
+impl<'a, T: Plain> From<MemVec<'a, T, HeapMemory>> for HeapMemory {
+    fn from(vec: MemVec<'a, T, HeapMemory>) -> Self {
+        vec.into_mem()
+    }
+}
+
+impl Memory for HeapMemory {
+    type Error = std::io::Error;
+
+    fn as_ptr(&self) -> *const u8 {
+        self.chunks.as_ptr().cast()
+    }
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.chunks.as_mut_ptr().cast()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity > self.capacity {
+            self.chunks
+                .resize(capacity.div_ceil(size_of::<Chunk>()), Chunk([0; 64]));
+            self.capacity = capacity;
+        }
+        Ok(())
+    }
+
+    fn shrink(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity < self.capacity {
+            self.chunks.truncate(capacity.div_ceil(size_of::<Chunk>()));
+            self.chunks.shrink_to_fit();
+            self.capacity = capacity;
+        }
+        Ok(())
+    }
+}
+
+/// [`crate::VecFile`], or [`HeapMemory`] under Miri and without the `mmap` feature.
+///
+/// Create it with `TempMemory::temp()`, so test suites of code using [`crate::MemVec`] run both
+/// on anonymous files and under Miri. Only [`Memory`] and `temp()` are common to both.
+#[cfg(all(not(miri), feature = "mmap"))]
+pub type TempMemory = crate::VecFile;
+/// [`crate::VecFile`], or [`HeapMemory`] under Miri and without the `mmap` feature.
+///
+/// Create it with `TempMemory::temp()`, so test suites of code using [`crate::MemVec`] run both
+/// on anonymous files and under Miri. Only [`Memory`] and `temp()` are common to both.
+#[cfg(any(miri, not(feature = "mmap")))]
+pub type TempMemory = HeapMemory;
@@ -0,0 +1,128 @@
+use crate::checksum::Crc32;
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Sidecar write-ahead journal of a [`crate::VecFile`].
+///
+/// Every entry records a change of the length together with the bytes of the records it covers.
+/// Entries are synced to the disk before the change is applied to the vector file, and replayed
+/// in order when the vector file is opened after a crash.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    file: File,
+    record_size: usize,
+    written: u64,
+}
+
+/// Entry header: magic, data offset, new length, bytes length and CRC of all of them and bytes.
+const ENTRY_HEADER_LEN: usize = 4 + 8 + 8 + 8 + 4;
+const ENTRY_MAGIC: u32 = 0x4c4e_524a; // "JRNL"
+
+impl Journal {
+    /// The journal is checkpointed when it grows beyond this size.
+    pub const CHECKPOINT_SIZE: u64 = 4 << 20;
+
+    pub fn path_for(path: &Path) -> PathBuf {
+        let mut journal_path = path.as_os_str().to_owned();
+        journal_path.push(".journal");
+        PathBuf::from(journal_path)
+    }
+
+    pub fn open(path: PathBuf, record_size: usize) -> std::io::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            file,
+            record_size,
+            written,
+        })
+    }
+
+    pub fn record_size(&self) -> usize {
+        self.record_size
+    }
+
+    pub fn needs_checkpoint(&self) -> bool {
+        self.written >= Self::CHECKPOINT_SIZE
+    }
+
+    /// Durably append an entry setting the length to `len` after writing `bytes` at `offset` of
+    /// the data region.
+    pub fn append(&mut self, offset: u64, len: u64, bytes: &[u8]) -> std::io::Result<()> {
+        let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN + bytes.len());
+        entry.extend_from_slice(&ENTRY_MAGIC.to_le_bytes());
+        entry.extend_from_slice(&offset.to_le_bytes());
+        entry.extend_from_slice(&len.to_le_bytes());
+        entry.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        let mut crc = Crc32::new();
+        crc.update(&entry);
+        crc.update(bytes);
+        entry.extend_from_slice(&crc.finish().to_le_bytes());
+        entry.extend_from_slice(bytes);
+        self.file.write_all(&entry)?;
+        self.file.sync_data()?;
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+
+    /// Discard all entries. Call only after the vector file is synced.
+    pub fn clear(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        self.written = 0;
+        Ok(())
+    }
+
+    /// Call `apply` with `(offset, len, bytes)` of every complete entry of the journal at `path`.
+    ///
+    /// Reading stops at the first incomplete or broken entry, which is the one being written when
+    /// the process crashed. Returns whether any entry was applied.
+    pub fn replay(
+        path: &Path,
+        mut apply: impl FnMut(u64, u64, &[u8]) -> std::io::Result<()>,
+    ) -> std::io::Result<bool> {
+        let mut journal = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let mut bytes = Vec::new();
+        journal.read_to_end(&mut bytes)?;
+
+        let u64_at = |b: &[u8], at: usize| u64::from_le_bytes(b[at..at + 8].try_into().unwrap());
+        let mut rest = bytes.as_slice();
+        let mut applied = false;
+        while rest.len() >= ENTRY_HEADER_LEN {
+            let (head, body) = rest.split_at(ENTRY_HEADER_LEN);
+            if u32::from_le_bytes(head[0..4].try_into().unwrap()) != ENTRY_MAGIC {
+                break;
+            }
+            let offset = u64_at(head, 4);
+            let len = u64_at(head, 12);
+            let Ok(bytes_len) = usize::try_from(u64_at(head, 20)) else {
+                break;
+            };
+            if body.len() < bytes_len {
+                break;
+            }
+            let (data, next) = body.split_at(bytes_len);
+            let mut crc = Crc32::new();
+            crc.update(&head[..ENTRY_HEADER_LEN - 4]);
+            crc.update(data);
+            if crc.finish() != u32::from_le_bytes(head[28..32].try_into().unwrap()) {
+                break;
+            }
+            apply(offset, len, data)?;
+            applied = true;
+            rest = next;
+        }
+        Ok(applied)
+    }
+}
@@ -1,10 +1,151 @@
+mod append_queue;
+#[cfg(feature = "rkyv")]
+mod archive;
+mod arena;
+#[cfg(feature = "arrow-buffer")]
+mod arrow;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "mmap")]
+mod auto_flush;
+#[cfg(feature = "mmap")]
+mod batch_sync;
+mod binary_heap;
+mod bit_vec;
+mod blob_store;
+#[cfg(feature = "mmap")]
+mod bloom;
+mod borrowed;
+mod btree;
+#[cfg(feature = "bytes")]
+mod buf;
+#[cfg(feature = "mmap")]
+mod builder;
+mod bulk_load;
+mod bytes;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod checksum;
+mod columns;
+#[cfg(feature = "mmap")]
+mod concurrent;
+mod copy;
+mod cursor;
+mod deque;
+mod error;
+mod file_memory;
+#[cfg(feature = "mmap")]
+mod file_mutex;
+#[cfg(feature = "mmap")]
+mod follower;
+mod frozen;
+mod hash_map;
+#[cfg(feature = "mmap")]
+mod header;
+mod heap;
+#[cfg(feature = "mmap")]
+mod journal;
+mod log;
+mod lru;
+mod matrix;
 mod mem_vec;
 mod memory;
+#[cfg(feature = "mmap")]
 mod mmap;
+mod padding;
+mod plain;
+mod policy;
+#[cfg(feature = "mmap")]
+mod prefetch;
+pub mod prelude;
+mod queue;
+#[cfg(feature = "mmap")]
+mod read_only;
+mod ring;
+mod roaring;
+#[cfg(feature = "mmap")]
+mod segment_file;
+#[cfg(feature = "mmap")]
+mod series;
+mod slab;
+mod sorted;
+mod string;
+mod sync_mem_vec;
+#[cfg(feature = "mmap")]
+mod vec_file;
+#[cfg(feature = "mmap")]
+mod watch;
+#[cfg(feature = "mmap")]
+mod windowed;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "mmap"))]
 mod tests;
 
+pub use append_queue::{AppendQueue, SlotState};
+pub use arena::{ArenaRef, ArenaSlice, MemArena};
+#[cfg(feature = "async")]
+pub use async_io::FollowerStream;
+#[cfg(feature = "mmap")]
+pub use auto_flush::AutoFlush;
+pub use binary_heap::MemBinaryHeap;
+pub use bit_vec::MemBitVec;
+pub use blob_store::BlobStore;
+#[cfg(feature = "mmap")]
+pub use bloom::MemBloomFilter;
+pub use borrowed::{BorrowedMemory, MemVecRef};
+pub use btree::{BTreeRange, MemBTreeMap};
+#[cfg(feature = "mmap")]
+pub use builder::VecFileBuilder;
+pub use bulk_load::BulkLoad;
+pub use columns::{Columns, ColumnsIter, MemColumns};
+#[cfg(feature = "mmap")]
+pub use concurrent::ConcurrentAppendVec;
+pub use cursor::MemCursor;
+pub use deque::MemDeque;
+pub use error::MemVecError;
+pub use file_memory::FileMemory;
+#[cfg(feature = "mmap")]
+pub use file_mutex::FileMutexGuard;
+#[cfg(feature = "mmap")]
+pub use follower::Follower;
+pub use frozen::Frozen;
+pub use hash_map::MemHashMap;
+#[cfg(feature = "mmap")]
+pub use header::ChecksumMismatch;
+pub use heap::{HeapMemory, TempMemory};
+pub use log::{LogIter, MemLog, MAX_RECORD_LEN};
+pub use lru::MemLruCache;
+pub use matrix::MemMatrix;
 pub use mem_vec::MemVec;
-pub use memory::Memory;
-pub use mmap::{MmapFile, VecFile};
+pub use memory::{InvalidRecord, Memory, MemoryConversionError, ReadOnlyMemory};
+#[cfg(feature = "mmap")]
+pub use mmap::{MapOptions, MmapFile, ReadOnlyMmapFile};
+pub use padding::NoPadding;
+pub use plain::Plain;
+pub use policy::{GrowthPolicy, ShrinkPolicy};
+#[cfg(feature = "mmap")]
+pub use prefetch::{Prefetcher, Scan};
+pub use queue::MemQueue;
+#[cfg(feature = "mmap")]
+pub use read_only::{ConsistentIter, ReadOnlyMemVec, ReadOnlyVecFile, WriterStatus};
+pub use ring::{RingBuffer, RingConsumer, RingProducer};
+pub use roaring::{MemRoaringSet, RoaringIter};
+#[cfg(feature = "mmap")]
+pub use segment_file::{Segment, SegmentFile};
+#[cfg(feature = "mmap")]
+pub use series::MemSeries;
+pub use slab::MemSlab;
+pub use sorted::SortedMemVec;
+pub use string::{MemString, MemStringError};
+pub use sync_mem_vec::{SyncMemVec, SyncReadGuard, SyncWriteGuard};
+#[cfg(feature = "mmap")]
+pub use vec_file::VecFile;
+#[cfg(feature = "mmap")]
+pub use watch::Watch;
+#[cfg(feature = "mmap")]
+pub use windowed::WindowedVecFile;
+
+/// The zerocopy traits and their derives, for types stored with
+/// [`MemVec::from_memory_zerocopy`].
+#[cfg(feature = "zerocopy")]
+pub use zerocopy::{AsBytes, FromBytes, FromZeroes, Unaligned};
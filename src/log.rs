@@ -0,0 +1,256 @@
+use crate::{
+    checksum::Crc32,
+    memory::{check_align, Memory, MemoryConversionError},
+};
+
+/// An append-only log of variable-length byte records over [`Memory`], e.g. serialized events in
+/// a [`crate::MmapFile`] or a [`crate::VecFile`].
+///
+/// The memory starts with a log header, followed by the frames of the records. A frame is a size
+/// word, a CRC-32 of the payload if the log was created with checksums, and the payload, padded to
+/// 8 bytes. The size word is written last and marks the frame complete, so a frame torn by a
+/// crash is recognized as incomplete, or by its checksum if the storage reordered the writes.
+/// Iteration stops at the first incomplete frame, and [`MemLog::recover`] finds the end of the
+/// log when its length was lost.
+pub struct MemLog<A: Memory> {
+    mem: A,
+}
+
+#[repr(C)]
+struct LogHeader {
+    magic: [u8; 8],
+    flags: u64,
+    _reserved: [u8; 48],
+}
+
+const _: () = assert!(core::mem::size_of::<LogHeader>() == LogHeader::LEN);
+
+impl LogHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECLG";
+    const FLAG_CHECKSUM: u64 = 1 << 0;
+}
+
+/// The size word and the checksum word.
+const FRAME_HEADER: usize = 8;
+/// Set in the size word of a complete frame, so that zeroed memory holds no frame.
+const COMPLETE: u32 = 1 << 31;
+
+/// The largest payload of a record.
+pub const MAX_RECORD_LEN: usize = (COMPLETE - 1) as usize;
+
+fn frame_len(payload: usize) -> usize {
+    (FRAME_HEADER + payload).next_multiple_of(8)
+}
+
+impl<A: Memory<Error = std::io::Error>> MemLog<A> {
+    /// Create an empty log in empty memory, or open the one the memory holds.
+    ///
+    /// `checksum` selects whether the frames of a new log carry a CRC-32; it is ignored for an
+    /// existing log. Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no
+    /// log.
+    pub fn new(mut mem: A, checksum: bool) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(LogHeader::LEN)?;
+        } else if mem.len() < LogHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec log"));
+        }
+        check_align::<LogHeader>(mem.as_ptr())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            let flags = if checksum {
+                LogHeader::FLAG_CHECKSUM
+            } else {
+                0
+            };
+            unsafe {
+                mem.as_mut_ptr().cast::<LogHeader>().write(LogHeader {
+                    magic: LogHeader::MAGIC,
+                    flags: flags.to_le(),
+                    _reserved: [0; 48],
+                })
+            };
+            mem.set_len(LogHeader::LEN);
+        }
+        let this = Self { mem };
+        if this.header().magic != LogHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec log"));
+        }
+        // the length is not checked further, since recovering may find another one
+        if this.mem.len() > core::ops::Deref::deref(&this.mem).len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Append a record and return its offset, which [`MemLog::get`] reads it back at.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if it is longer than [`MAX_RECORD_LEN`].
+    pub fn append(&mut self, payload: &[u8]) -> std::io::Result<usize> {
+        use std::io::{Error, ErrorKind};
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "memvec log record too long",
+            ));
+        }
+        let offset = self.mem.len();
+        let end = offset
+            .checked_add(frame_len(payload.len()))
+            .filter(|&end| end <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if end > capacity {
+            self.mem
+                .reserve(end.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        let crc = if self.has_checksum() {
+            let mut crc = Crc32::new();
+            crc.update(payload);
+            crc.finish()
+        } else {
+            0
+        };
+        let frame = &mut self.mem[offset..end];
+        frame[FRAME_HEADER..FRAME_HEADER + payload.len()].copy_from_slice(payload);
+        frame[FRAME_HEADER + payload.len()..].fill(0);
+        frame[4..8].copy_from_slice(&crc.to_le_bytes());
+        // the size last, which completes the frame
+        frame[..4].copy_from_slice(&(payload.len() as u32 | COMPLETE).to_le_bytes());
+        self.mem.set_len(end);
+        Ok(offset)
+    }
+
+    /// Find the end of the log by scanning the whole memory for the first incomplete frame, set
+    /// the length there and return the number of bytes the length moved by.
+    ///
+    /// Call it after a crash when the length of the memory was lost or may cover torn frames,
+    /// e.g. for a [`crate::MmapFile`] opened with its length set to the file size. The memory
+    /// after the end is zeroed, so that stale frames are not taken for records later.
+    pub fn recover(&mut self) -> usize {
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        let mut end = LogHeader::LEN;
+        while let Some((_, next)) = self.frame_at(end, capacity) {
+            end = next;
+        }
+        self.mem[end..capacity].fill(0);
+        let moved = self.mem.len().abs_diff(end);
+        self.mem.set_len(end);
+        moved
+    }
+}
+
+impl<A: Memory> MemLog<A> {
+    fn header(&self) -> &LogHeader {
+        unsafe { &*self.mem.as_ptr().cast::<LogHeader>() }
+    }
+
+    pub fn has_checksum(&self) -> bool {
+        u64::from_le(self.header().flags) & LogHeader::FLAG_CHECKSUM != 0
+    }
+
+    /// The payload of the complete frame at `offset` and the offset of the next frame, if the
+    /// frame ends before `limit`.
+    fn frame_at(&self, offset: usize, limit: usize) -> Option<(&[u8], usize)> {
+        let bytes = self.mem.get(offset..limit)?;
+        let size = u32::from_le_bytes(bytes.get(..4)?.try_into().unwrap());
+        if size & COMPLETE == 0 {
+            return None;
+        }
+        let len = (size & !COMPLETE) as usize;
+        let frame = bytes.get(..frame_len(len))?;
+        let payload = &frame[FRAME_HEADER..FRAME_HEADER + len];
+        if self.has_checksum() {
+            let mut crc = Crc32::new();
+            crc.update(payload);
+            if crc.finish() != u32::from_le_bytes(frame[4..8].try_into().unwrap()) {
+                return None;
+            }
+        }
+        Some((payload, offset + frame.len()))
+    }
+
+    /// The length of the frames in bytes, including the log header.
+    pub fn len_bytes(&self) -> usize {
+        self.mem.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mem.len() == LogHeader::LEN
+    }
+
+    /// The record at `offset`, as returned by [`MemLog::append`], or `None` if no complete frame
+    /// starts there.
+    ///
+    /// An offset inside a payload may happen to look like a frame; only pass offsets of records.
+    pub fn get(&self, offset: usize) -> Option<&[u8]> {
+        if offset < LogHeader::LEN || !offset.is_multiple_of(8) {
+            return None;
+        }
+        self.frame_at(offset, self.mem.len())
+            .map(|(payload, _)| payload)
+    }
+
+    /// The records with their offsets in order of appending, up to the first incomplete frame.
+    pub fn iter(&self) -> LogIter<'_, A> {
+        LogIter {
+            log: self,
+            offset: LogHeader::LEN,
+        }
+    }
+
+    /// Remove all records, zeroing their frames.
+    pub fn clear(&mut self) {
+        let len = self.mem.len();
+        self.mem[LogHeader::LEN..len].fill(0);
+        self.mem.set_len(LogHeader::LEN);
+    }
+
+    /// Write the frames and the length durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<A: Memory> core::fmt::Debug for MemLog<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemLog")
+            .field("len_bytes", &self.len_bytes())
+            .field("checksum", &self.has_checksum())
+            .finish()
+    }
+}
+
+/// An iterator over the records of a [`MemLog`], see [`MemLog::iter`].
+#[derive(Debug)]
+pub struct LogIter<'l, A: Memory> {
+    log: &'l MemLog<A>,
+    offset: usize,
+}
+
+impl<'l, A: Memory> LogIter<'l, A> {
+    /// The offset of the next frame, where appending continues if the iteration ended early.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'l, A: Memory> Iterator for LogIter<'l, A> {
+    type Item = (usize, &'l [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let log = self.log;
+        let (payload, next) = log.frame_at(self.offset, log.mem.len())?;
+        let offset = core::mem::replace(&mut self.offset, next);
+        Some((offset, payload))
+    }
+}
@@ -0,0 +1,543 @@
+use crate::{
+    hash_map::hash,
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::{hash::Hash, marker::PhantomData};
+
+/// A least-recently-used cache of fixed-size keys and values over [`Memory`], so that a warm
+/// cache in a [`crate::MmapFile`] or a [`crate::VecFile`] survives restarts.
+///
+/// The number of entries is fixed at creation, and the memory is allocated for all of them at
+/// once. It starts with a cache header, followed by the hash buckets, the links of the entries,
+/// and the arrays of keys and of values. Every entry is linked into the chain of its bucket and
+/// into the recency list, both by indices, so that lookups and updates of the recency take
+/// constant time. The entries are kept contiguous by moving the last one into the place of a
+/// removed one. An interrupted update leaves the memory inconsistent.
+///
+/// Keys are hashed with the same fixed hash function as [`crate::MemHashMap`], with the same
+/// requirements on their [`Hash`] implementation.
+pub struct MemLruCache<K: Plain + Hash + Eq, V: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<(K, V)>,
+}
+
+#[repr(C)]
+struct CacheHeader {
+    magic: [u8; 8],
+    key_size: u64,
+    value_size: u64,
+    /// The number of entries.
+    capacity: u64,
+    /// The number of buckets, a power of two.
+    buckets: u64,
+    len: u64,
+    /// The most recently used entry, plus one.
+    head: u64,
+    /// The least recently used entry, plus one.
+    tail: u64,
+}
+
+const _: () = assert!(core::mem::size_of::<CacheHeader>() == CacheHeader::LEN);
+
+impl CacheHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECLR";
+}
+
+/// The links of an entry, each the index of another entry plus one, or zero for none.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Link {
+    /// The more recently used entry.
+    prev: u64,
+    /// The less recently used entry.
+    next: u64,
+    /// The next entry in the chain of the bucket.
+    chain: u64,
+}
+
+/// The byte offsets of the links, the keys, the values and the end of a cache.
+struct Layout {
+    links: usize,
+    keys: usize,
+    values: usize,
+    end: usize,
+}
+
+impl Layout {
+    fn new<K, V>(capacity: usize, buckets: usize) -> Option<Self> {
+        let links = buckets.checked_mul(8)?.checked_add(CacheHeader::LEN)?;
+        let keys = capacity
+            .checked_mul(core::mem::size_of::<Link>())?
+            .checked_add(links)?
+            .next_multiple_of(core::mem::align_of::<K>());
+        let values = capacity
+            .checked_mul(core::mem::size_of::<K>())?
+            .checked_add(keys)?
+            .next_multiple_of(core::mem::align_of::<V>());
+        let end = capacity
+            .checked_mul(core::mem::size_of::<V>())?
+            .checked_add(values)?;
+        (end <= isize::MAX as usize).then_some(Self {
+            links,
+            keys,
+            values,
+            end,
+        })
+    }
+}
+
+fn to_index(link: u64) -> Option<usize> {
+    (link as usize).checked_sub(1)
+}
+
+fn to_link(index: Option<usize>) -> u64 {
+    index.map_or(0, |index| index as u64 + 1)
+}
+
+impl<K: Plain + Hash + Eq, V: Plain, A: Memory<Error = std::io::Error>> MemLruCache<K, V, A> {
+    /// Create an empty cache of `capacity` entries in empty memory, or open the one the memory
+    /// holds.
+    ///
+    /// Creating fails with [`std::io::ErrorKind::InvalidInput`] if `capacity` is zero. Opening
+    /// fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no cache of keys and
+    /// values of these sizes and `capacity` entries. The links between the entries are checked
+    /// when they are followed, so a broken one panics instead of reading out of bounds, and a
+    /// cycle of them panics instead of looping forever.
+    ///
+    /// # Safety
+    /// The keys and values must hold valid bytes representations of K and V.
+    pub unsafe fn new(mut mem: A, capacity: usize) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let key_size = core::mem::size_of::<K>() as u64;
+        let value_size = core::mem::size_of::<V>() as u64;
+        let fresh = mem.len() == 0;
+        if fresh {
+            if capacity == 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "LRU cache without entries",
+                ));
+            }
+            let buckets = capacity.checked_next_power_of_two();
+            let layout = buckets
+                .and_then(|buckets| Layout::new::<K, V>(capacity, buckets))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+            mem.reserve(layout.end)?;
+            mem.set_len(layout.end);
+        } else if mem.len() < CacheHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec LRU cache"));
+        }
+        check_align::<CacheHeader>(mem.as_ptr())
+            .and_then(|()| check_align::<K>(mem.as_ptr()))
+            .and_then(|()| check_align::<V>(mem.as_ptr()))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            mem.as_mut_ptr().cast::<CacheHeader>().write(CacheHeader {
+                magic: CacheHeader::MAGIC,
+                key_size: key_size.to_le(),
+                value_size: value_size.to_le(),
+                capacity: (capacity as u64).to_le(),
+                buckets: (capacity.next_power_of_two() as u64).to_le(),
+                len: 0,
+                head: 0,
+                tail: 0,
+            });
+        }
+        let mut this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != CacheHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec LRU cache"));
+        }
+        if u64::from_le(header.key_size) != key_size
+            || u64::from_le(header.value_size) != value_size
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec LRU cache has a different key or value size",
+            ));
+        }
+        if u64::from_le(header.capacity) != capacity as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec LRU cache has a different capacity",
+            ));
+        }
+        let buckets = u64::from_le(header.buckets);
+        let len = u64::from_le(header.len);
+        let valid = buckets.is_power_of_two()
+            && buckets >= capacity as u64
+            && usize::try_from(buckets)
+                .ok()
+                .and_then(|buckets| Layout::new::<K, V>(capacity, buckets))
+                .is_some_and(|layout| layout.end == this.mem.len())
+            && len <= capacity as u64
+            && u64::from_le(header.head) <= len
+            && u64::from_le(header.tail) <= len;
+        if !valid {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        if fresh {
+            this.clear();
+        }
+        Ok(this)
+    }
+}
+
+impl<K: Plain + Hash + Eq, V: Plain, A: Memory> MemLruCache<K, V, A> {
+    fn header(&self) -> &CacheHeader {
+        unsafe { &*self.mem.as_ptr().cast::<CacheHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut CacheHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<CacheHeader>() }
+    }
+
+    fn buckets(&self) -> usize {
+        u64::from_le(self.header().buckets) as usize
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::new::<K, V>(self.capacity(), self.buckets()).expect("validated on open")
+    }
+
+    fn bucket_of(&self, key: &K) -> usize {
+        hash(key) as usize & (self.buckets() - 1)
+    }
+
+    fn bucket(&self, bucket: usize) -> Option<usize> {
+        let ptr = self
+            .mem
+            .as_ptr()
+            .wrapping_add(CacheHeader::LEN + bucket * 8);
+        to_index(u64::from_le(unsafe { ptr.cast::<u64>().read() }))
+    }
+
+    fn set_bucket(&mut self, bucket: usize, index: Option<usize>) {
+        let ptr = self
+            .mem
+            .as_mut_ptr()
+            .wrapping_add(CacheHeader::LEN + bucket * 8);
+        unsafe { ptr.cast::<u64>().write(to_link(index).to_le()) };
+    }
+
+    /// The offset of the element at `index` of the array at `start`. Indices are read from the
+    /// memory, so a corrupt one is caught here rather than reading out of bounds.
+    fn entry_offset(&self, start: usize, index: usize, size: usize) -> usize {
+        assert!(
+            index < self.capacity(),
+            "broken memvec LRU cache: entry {index} out of bounds"
+        );
+        start + index * size
+    }
+
+    fn link(&self, index: usize) -> Link {
+        let offset = self.entry_offset(self.layout().links, index, core::mem::size_of::<Link>());
+        let link = unsafe { self.mem.as_ptr().add(offset).cast::<Link>().read() };
+        Link {
+            prev: u64::from_le(link.prev),
+            next: u64::from_le(link.next),
+            chain: u64::from_le(link.chain),
+        }
+    }
+
+    fn set_link(&mut self, index: usize, link: Link) {
+        let offset = self.entry_offset(self.layout().links, index, core::mem::size_of::<Link>());
+        let link = Link {
+            prev: link.prev.to_le(),
+            next: link.next.to_le(),
+            chain: link.chain.to_le(),
+        };
+        unsafe { self.mem.as_mut_ptr().add(offset).cast::<Link>().write(link) };
+    }
+
+    fn update_link(&mut self, index: usize, f: impl FnOnce(&mut Link)) {
+        let mut link = self.link(index);
+        f(&mut link);
+        self.set_link(index, link);
+    }
+
+    fn key_ptr(&self, index: usize) -> *const K {
+        let offset = self.entry_offset(self.layout().keys, index, core::mem::size_of::<K>());
+        self.mem.as_ptr().wrapping_add(offset).cast()
+    }
+
+    fn value_ptr(&self, index: usize) -> *const V {
+        let offset = self.entry_offset(self.layout().values, index, core::mem::size_of::<V>());
+        self.mem.as_ptr().wrapping_add(offset).cast()
+    }
+
+    fn key_mut_ptr(&mut self, index: usize) -> *mut K {
+        let offset = self.entry_offset(self.layout().keys, index, core::mem::size_of::<K>());
+        self.mem.as_mut_ptr().wrapping_add(offset).cast()
+    }
+
+    fn value_mut_ptr(&mut self, index: usize) -> *mut V {
+        let offset = self.entry_offset(self.layout().values, index, core::mem::size_of::<V>());
+        self.mem.as_mut_ptr().wrapping_add(offset).cast()
+    }
+
+    fn head(&self) -> Option<usize> {
+        to_index(u64::from_le(self.header().head))
+    }
+
+    fn tail(&self) -> Option<usize> {
+        to_index(u64::from_le(self.header().tail))
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.header_mut().len = (len as u64).to_le();
+    }
+
+    /// Count a step of a walk along a chain or the recency list, which never visits more entries
+    /// than there are unless a cycle in the memory makes it endless.
+    fn step(&self, steps: &mut usize) {
+        *steps += 1;
+        assert!(
+            *steps <= self.len(),
+            "broken memvec LRU cache: cyclic links"
+        );
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        let mut next = self.bucket(self.bucket_of(key));
+        let mut steps = 0;
+        while let Some(index) = next {
+            self.step(&mut steps);
+            if unsafe { &*self.key_ptr(index) } == key {
+                return Some(index);
+            }
+            next = to_index(self.link(index).chain);
+        }
+        None
+    }
+
+    /// Take the entry at `index` out of the recency list.
+    fn unlink(&mut self, index: usize) {
+        let link = self.link(index);
+        match to_index(link.prev) {
+            Some(prev) => self.update_link(prev, |l| l.next = link.next),
+            None => self.header_mut().head = link.next.to_le(),
+        }
+        match to_index(link.next) {
+            Some(next) => self.update_link(next, |l| l.prev = link.prev),
+            None => self.header_mut().tail = link.prev.to_le(),
+        }
+    }
+
+    /// Put the entry at `index`, which is not in the recency list, at its front.
+    fn push_front(&mut self, index: usize) {
+        let head = self.head();
+        self.update_link(index, |l| {
+            l.prev = 0;
+            l.next = to_link(head);
+        });
+        match head {
+            Some(head) => self.update_link(head, |l| l.prev = to_link(Some(index))),
+            None => self.header_mut().tail = to_link(Some(index)).to_le(),
+        }
+        self.header_mut().head = to_link(Some(index)).to_le();
+    }
+
+    fn touch(&mut self, index: usize) {
+        if self.head() != Some(index) {
+            self.unlink(index);
+            self.push_front(index);
+        }
+    }
+
+    /// Replace the reference to the entry at `from` in the chain of `bucket` by `to`.
+    fn rechain(&mut self, bucket: usize, from: usize, to: Option<usize>) {
+        let mut prev = None;
+        let mut next = self.bucket(bucket);
+        let mut steps = 0;
+        while let Some(index) = next {
+            self.step(&mut steps);
+            if index == from {
+                break;
+            }
+            prev = Some(index);
+            next = to_index(self.link(index).chain);
+        }
+        match prev {
+            Some(prev) => self.update_link(prev, |l| l.chain = to_link(to)),
+            None => self.set_bucket(bucket, to),
+        }
+    }
+
+    /// Remove the entry at `index` and move the last entry into its place.
+    fn remove_at(&mut self, index: usize) -> (K, V) {
+        let (key, value) = unsafe { (self.key_ptr(index).read(), self.value_ptr(index).read()) };
+        self.unlink(index);
+        let chain = to_index(self.link(index).chain);
+        self.rechain(self.bucket_of(&key), index, chain);
+        let last = self.len() - 1;
+        if index != last {
+            let link = self.link(last);
+            self.rechain(
+                self.bucket_of(unsafe { &*self.key_ptr(last) }),
+                last,
+                Some(index),
+            );
+            match to_index(link.prev) {
+                Some(prev) => self.update_link(prev, |l| l.next = to_link(Some(index))),
+                None => self.header_mut().head = to_link(Some(index)).to_le(),
+            }
+            match to_index(link.next) {
+                Some(next) => self.update_link(next, |l| l.prev = to_link(Some(index))),
+                None => self.header_mut().tail = to_link(Some(index)).to_le(),
+            }
+            unsafe {
+                let key = self.key_ptr(last).read();
+                let value = self.value_ptr(last).read();
+                self.key_mut_ptr(index).write(key);
+                self.value_mut_ptr(index).write(value);
+            }
+            self.set_link(index, link);
+        }
+        self.set_len(last);
+        (key, value)
+    }
+
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of entries, which is fixed at creation.
+    pub fn capacity(&self) -> usize {
+        u64::from_le(self.header().capacity) as usize
+    }
+
+    /// The value of `key`, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let index = self.find(key)?;
+        self.touch(index);
+        Some(unsafe { &*self.value_ptr(index) })
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = self.find(key)?;
+        self.touch(index);
+        Some(unsafe { &mut *self.value_mut_ptr(index) })
+    }
+
+    /// The value of `key`, without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let index = self.find(key)?;
+        Some(unsafe { &*self.value_ptr(index) })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Insert `value` for `key` as the most recently used entry.
+    ///
+    /// Returns the entry it replaced, which has the same key, or the least recently used entry
+    /// it evicted if the cache was full.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some(index) = self.find(&key) {
+            self.touch(index);
+            let old = unsafe {
+                let old_key = self.key_mut_ptr(index).replace(key);
+                (old_key, self.value_mut_ptr(index).replace(value))
+            };
+            return Some(old);
+        }
+        let evicted = if self.len() == self.capacity() {
+            self.pop_lru()
+        } else {
+            None
+        };
+        let index = self.len();
+        let bucket = self.bucket_of(&key);
+        unsafe {
+            self.key_mut_ptr(index).write(key);
+            self.value_mut_ptr(index).write(value);
+        }
+        let chain = to_link(self.bucket(bucket));
+        self.set_link(
+            index,
+            Link {
+                prev: 0,
+                next: 0,
+                chain,
+            },
+        );
+        self.set_bucket(bucket, Some(index));
+        self.push_front(index);
+        self.set_len(index + 1);
+        evicted
+    }
+
+    /// Remove the entry of `key`, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.find(key)?;
+        Some(self.remove_at(index).1)
+    }
+
+    /// Remove the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let tail = self.tail()?;
+        Some(self.remove_at(tail))
+    }
+
+    /// The least recently used entry, which is evicted next.
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        let tail = self.tail()?;
+        Some(unsafe { (&*self.key_ptr(tail), &*self.value_ptr(tail)) })
+    }
+
+    /// The entries from the most to the least recently used.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut next = self.head();
+        let mut steps = 0;
+        core::iter::from_fn(move || {
+            let index = next?;
+            self.step(&mut steps);
+            next = to_index(self.link(index).next);
+            Some(unsafe { (&*self.key_ptr(index), &*self.value_ptr(index)) })
+        })
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        let links = self.layout().links;
+        self.mem[CacheHeader::LEN..links].fill(0);
+        let header = self.header_mut();
+        header.len = 0;
+        header.head = 0;
+        header.tail = 0;
+    }
+
+    /// Write the entries and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<K, V, A> core::fmt::Debug for MemLruCache<K, V, A>
+where
+    K: Plain + Hash + Eq + core::fmt::Debug,
+    V: Plain + core::fmt::Debug,
+    A: Memory,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::marker::PhantomData;
+
+/// A dense row-major matrix over [`Memory`], e.g. a large matrix mapped from a
+/// [`crate::MmapFile`].
+///
+/// The memory starts with a matrix header holding the numbers of rows and columns, followed by
+/// the elements row by row. The number of columns is fixed at creation; rows are added and
+/// removed at the end like the records of a [`crate::MemVec`].
+pub struct MemMatrix<T: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<T>,
+}
+
+#[repr(C)]
+struct MatrixHeader {
+    magic: [u8; 8],
+    record_size: u64,
+    rows: u64,
+    cols: u64,
+    _reserved: [u8; 32],
+}
+
+const _: () = assert!(core::mem::size_of::<MatrixHeader>() == MatrixHeader::LEN);
+
+impl MatrixHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECMX";
+}
+
+fn capacity_overflow() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+}
+
+fn check_header_align<T>(mem: &impl Memory) -> std::io::Result<()> {
+    check_align::<MatrixHeader>(mem.as_ptr())
+        .and_then(|()| check_align::<T>(mem.as_ptr().wrapping_add(MatrixHeader::LEN)))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> MemMatrix<T, A> {
+    /// Create an empty matrix of `cols` columns in empty memory, or open the one the memory
+    /// holds.
+    ///
+    /// Creating fails with [`std::io::ErrorKind::InvalidInput`] if `cols` is zero. Opening fails
+    /// with [`std::io::ErrorKind::InvalidData`] if the memory holds no matrix of records of this
+    /// size and `cols` columns.
+    ///
+    /// # Safety
+    /// The elements must hold valid bytes representations of T.
+    pub unsafe fn new(mut mem: A, cols: usize) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let record_size = core::mem::size_of::<T>() as u64;
+        if mem.len() == 0 {
+            if cols == 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "matrix without columns",
+                ));
+            }
+            mem.reserve(MatrixHeader::LEN)?;
+            check_header_align::<T>(&mem)?;
+            mem.as_mut_ptr().cast::<MatrixHeader>().write(MatrixHeader {
+                magic: MatrixHeader::MAGIC,
+                record_size: record_size.to_le(),
+                rows: 0,
+                cols: (cols as u64).to_le(),
+                _reserved: [0; 32],
+            });
+            mem.set_len(MatrixHeader::LEN);
+            return Ok(Self {
+                mem,
+                _marker: PhantomData,
+            });
+        }
+        if mem.len() < MatrixHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec matrix"));
+        }
+        check_header_align::<T>(&mem)?;
+        let this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != MatrixHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec matrix"));
+        }
+        if u64::from_le(header.record_size) != record_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec matrix has a different record size",
+            ));
+        }
+        if u64::from_le(header.cols) != cols as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec matrix has a different number of columns",
+            ));
+        }
+        let bytes = u64::from_le(header.rows)
+            .checked_mul(cols as u64)
+            .and_then(|len| len.checked_mul(record_size))
+            .and_then(|bytes| bytes.checked_add(MatrixHeader::LEN as u64));
+        if bytes != Some(this.mem.len() as u64) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Resize to `rows` rows, filling the elements of the added ones with `value`.
+    pub fn resize_rows(&mut self, rows: usize, value: T) -> std::io::Result<()> {
+        let old_len = self.as_slice().len();
+        let len = rows
+            .checked_mul(self.cols())
+            .ok_or_else(capacity_overflow)?;
+        let bytes = len
+            .checked_mul(core::mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(MatrixHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(capacity_overflow)?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if bytes > capacity {
+            self.mem
+                .reserve(bytes.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        for index in old_len..len {
+            // plain values are copied bitwise
+            unsafe {
+                self.elements_mut()
+                    .add(index)
+                    .write(core::ptr::read(&value))
+            };
+        }
+        self.mem.set_len(bytes);
+        self.header_mut().rows = (rows as u64).to_le();
+        Ok(())
+    }
+
+    /// Append a row.
+    ///
+    /// # Panics
+    /// Panics if the length of `row` is not the number of columns.
+    pub fn push_row(&mut self, row: &[T]) -> std::io::Result<()> {
+        let cols = self.cols();
+        assert_eq!(row.len(), cols, "row length does not match {cols} columns");
+        let rows = self.rows();
+        self.resize_rows(rows + 1, unsafe { core::ptr::read(&row[0]) })?;
+        unsafe {
+            let ptr = self.elements_mut().add(rows * cols);
+            core::ptr::copy_nonoverlapping(row.as_ptr(), ptr, cols);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Plain, A: Memory> MemMatrix<T, A> {
+    fn header(&self) -> &MatrixHeader {
+        unsafe { &*self.mem.as_ptr().cast::<MatrixHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut MatrixHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<MatrixHeader>() }
+    }
+
+    fn elements_mut(&mut self) -> *mut T {
+        unsafe { self.mem.as_mut_ptr().add(MatrixHeader::LEN).cast() }
+    }
+
+    pub fn rows(&self) -> usize {
+        u64::from_le(self.header().rows) as usize
+    }
+
+    pub fn cols(&self) -> usize {
+        u64::from_le(self.header().cols) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows() == 0
+    }
+
+    /// The elements in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        let len = self.rows() * self.cols();
+        unsafe {
+            let ptr = self.mem.as_ptr().add(MatrixHeader::LEN).cast();
+            core::slice::from_raw_parts(ptr, len)
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let len = self.rows() * self.cols();
+        unsafe { core::slice::from_raw_parts_mut(self.elements_mut(), len) }
+    }
+
+    /// The elements of row `i`.
+    ///
+    /// # Panics
+    /// Panics if `i` is out of bounds.
+    pub fn row(&self, i: usize) -> &[T] {
+        let cols = self.cols();
+        &self.as_slice()[i * cols..][..cols]
+    }
+
+    pub fn row_mut(&mut self, i: usize) -> &mut [T] {
+        let cols = self.cols();
+        &mut self.as_mut_slice()[i * cols..][..cols]
+    }
+
+    /// The element in row `i` and column `j`.
+    pub fn get(&self, i: usize, j: usize) -> Option<&T> {
+        if i >= self.rows() || j >= self.cols() {
+            return None;
+        }
+        self.as_slice().get(i * self.cols() + j)
+    }
+
+    pub fn get_mut(&mut self, i: usize, j: usize) -> Option<&mut T> {
+        if i >= self.rows() || j >= self.cols() {
+            return None;
+        }
+        let cols = self.cols();
+        self.as_mut_slice().get_mut(i * cols + j)
+    }
+
+    pub fn iter_rows(&self) -> core::slice::ChunksExact<'_, T> {
+        self.as_slice().chunks_exact(self.cols())
+    }
+
+    /// Shorten to `rows` rows, doing nothing if it is not shorter.
+    pub fn truncate_rows(&mut self, rows: usize) {
+        if rows >= self.rows() {
+            return;
+        }
+        let bytes = MatrixHeader::LEN + rows * self.cols() * core::mem::size_of::<T>();
+        self.header_mut().rows = (rows as u64).to_le();
+        self.mem.set_len(bytes);
+    }
+
+    /// Write the elements and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain, A: Memory> core::ops::Index<(usize, usize)> for MemMatrix<T, A> {
+    type Output = T;
+
+    fn index(&self, (i, j): (usize, usize)) -> &T {
+        let (rows, cols) = (self.rows(), self.cols());
+        self.get(i, j)
+            .unwrap_or_else(|| panic!("index ({i}, {j}) out of bounds of {rows}x{cols} matrix"))
+    }
+}
+
+impl<T: Plain, A: Memory> core::ops::IndexMut<(usize, usize)> for MemMatrix<T, A> {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut T {
+        let (rows, cols) = (self.rows(), self.cols());
+        self.get_mut(i, j)
+            .unwrap_or_else(|| panic!("index ({i}, {j}) out of bounds of {rows}x{cols} matrix"))
+    }
+}
+
+impl<T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug for MemMatrix<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter_rows()).finish()
+    }
+}
@@ -1,34 +1,58 @@
-use crate::memory::{Memory, MemoryConversionError};
+use crate::{
+    bulk_load::BulkLoad,
+    copy::copy_bytes,
+    frozen::Frozen,
+    memory::{InvalidRecord, Memory, MemoryConversionError},
+    plain::Plain,
+    policy::{GrowthPolicy, ShrinkPolicy},
+};
 use core::{
     cmp::Ordering,
     hash::Hash,
     marker::PhantomData,
     mem::MaybeUninit,
-    ops::{Deref, DerefMut, Index, IndexMut},
-    ptr,
+    ops::{Bound, Deref, DerefMut, Index, IndexMut, Range, RangeBounds},
+    ptr::{self, NonNull},
     slice::{self, SliceIndex},
 };
 /// A memory-backed vector.
 ///
 /// See document of std::vec::Vec for copied methods
-pub struct MemVec<'a, T: Copy, A: 'a + Memory> {
+///
+/// # Thread safety
+/// Like `Vec`, it is [`Send`] and [`Sync`] when both `T` and the memory are. [`crate::VecFile`],
+/// [`crate::MmapFile`] and [`crate::HeapMemory`] are both; [`crate::Segment`] is neither. To share
+/// a vector between threads, see [`crate::SyncMemVec`].
+pub struct MemVec<'a, T: Plain, A: 'a + Memory> {
     mem: A,
-    _marker: PhantomData<&'a T>,
+    growth_policy: GrowthPolicy,
+    shrink_policy: ShrinkPolicy,
+    zero_grown: bool,
+    padding: &'static [Range<usize>],
+    // owns `T` values, so the auto traits follow `T` like `Vec`
+    _marker: PhantomData<(&'a (), T)>,
 }
 
-impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
+impl<'a, T: Plain, A: 'a + Memory> MemVec<'a, T, A> {
     /// Create a new memory-backed vector.
     /// # Safety
     /// The memory must represent valid len and bytes representations of T.
+    /// Types with padding bytes store uninitialized bytes; see [`crate::NoPadding`] and
+    /// [`MemVec::set_zero_padding`].
     pub unsafe fn try_from_memory(mem: A) -> Result<Self, (A, MemoryConversionError)> {
-        let (prefix, _, _suffix) = mem.deref().align_to::<T>();
-        if !prefix.is_empty() {
-            return Err((mem, MemoryConversionError::AlignMismatch));
+        const { assert!(!core::mem::needs_drop::<T>(), "Plain type with drop glue") };
+        if let Err(e) = crate::memory::check_align::<T>(mem.as_ptr()) {
+            return Err((mem, e));
         }
         // assert_eq!(_suffix.len(), 0);
 
+        let growth_policy = mem.growth_policy();
         let vec = Self {
             mem,
+            growth_policy,
+            shrink_policy: ShrinkPolicy::default(),
+            zero_grown: false,
+            padding: &[],
             _marker: PhantomData,
         };
         if vec.len() > vec.capacity() {
@@ -38,6 +62,29 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         Ok(vec)
     }
 
+    /// Create a new memory-backed vector of a [`bytemuck::Pod`] type.
+    ///
+    /// Unlike [`MemVec::try_from_memory`], this is safe, since any bytes are a valid `T`.
+    #[cfg(feature = "bytemuck")]
+    pub fn from_memory_pod(mem: A) -> Result<Self, (A, MemoryConversionError)>
+    where
+        T: bytemuck::Pod,
+    {
+        unsafe { Self::try_from_memory(mem) }
+    }
+
+    /// Create a new memory-backed vector of a type implementing the zerocopy traits.
+    ///
+    /// Like [`MemVec::from_memory_pod`], this is safe: any bytes are a valid `T`, and `T` has no
+    /// alignment requirement, so only [`MemoryConversionError::SizeMismatch`] can be returned.
+    #[cfg(feature = "zerocopy")]
+    pub fn from_memory_zerocopy(mem: A) -> Result<Self, (A, MemoryConversionError)>
+    where
+        T: zerocopy::FromBytes + zerocopy::AsBytes + zerocopy::Unaligned,
+    {
+        unsafe { Self::try_from_memory(mem) }
+    }
+
     pub fn into_mem(self) -> A {
         self.mem
     }
@@ -47,10 +94,115 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
     pub fn as_mem_mut(&mut self) -> &mut A {
         &mut self.mem
     }
+
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth_policy
+    }
+
+    /// Set the policy applied when pushes and [`MemVec::reserve`] need more capacity.
+    /// [`MemVec::reserve_exact`] is not affected.
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.growth_policy = policy;
+    }
+
+    pub fn shrink_policy(&self) -> ShrinkPolicy {
+        self.shrink_policy
+    }
+
+    /// Set the policy applied by [`MemVec::shrink_to_fit`] and [`MemVec::shrink_to`].
+    pub fn set_shrink_policy(&mut self, policy: ShrinkPolicy) {
+        self.shrink_policy = policy;
+    }
+
+    /// Zero the memory added whenever the capacity grows.
+    ///
+    /// Files are zero-filled when they are extended, but other memory may hold arbitrary bytes;
+    /// with this set, [`MemVec::spare_capacity_mut`] always starts zeroed regardless of the
+    /// memory. Zeroing touches every grown page.
+    pub fn set_zero_grown(&mut self, zero_grown: bool) {
+        self.zero_grown = zero_grown;
+    }
+
+    /// Zero the `padding` byte ranges of every element written by the methods of the vector.
+    ///
+    /// Padding bytes of a value are uninitialized; zeroing them keeps the stored bytes and the
+    /// checksums of the file deterministic for types which cannot implement
+    /// [`crate::NoPadding`]. Elements written through the slice, e.g. by indexing, are not covered;
+    /// see [`MemVec::zero_padding`].
+    ///
+    /// # Panics
+    /// Panics if a range is out of the bounds of `T`.
+    pub fn set_zero_padding(&mut self, padding: &'static [Range<usize>]) {
+        let size = core::mem::size_of::<T>();
+        assert!(padding.iter().all(|r| r.start <= r.end && r.end <= size));
+        self.padding = padding;
+    }
+
+    /// Zero the padding byte ranges set by [`MemVec::set_zero_padding`] in all elements.
+    pub fn zero_padding(&mut self) {
+        for index in 0..self.len() {
+            self.zero_padding_at(index);
+        }
+    }
+
+    pub(crate) fn zero_padding_at(&mut self, index: usize) {
+        debug_assert!(index < self.capacity());
+        // through the raw pointer of the memory, so slices of other elements stay valid
+        let element = self
+            .mem
+            .as_mut_ptr()
+            .wrapping_add(index * core::mem::size_of::<T>());
+        for range in self.padding {
+            unsafe { element.add(range.start).write_bytes(0, range.len()) };
+        }
+    }
+
+    /// Check every `stride`-th element, starting from the first, with `f`.
+    ///
+    /// Returns the index of the first element `f` rejects. A `stride` of 1 checks all elements;
+    /// larger strides sample large vectors cheaply.
+    pub fn validate(
+        &self,
+        stride: usize,
+        mut f: impl FnMut(&T) -> bool,
+    ) -> Result<(), InvalidRecord> {
+        assert!(stride > 0);
+        match self.iter().step_by(stride).position(|item| !f(item)) {
+            Some(position) => Err(InvalidRecord {
+                index: position * stride,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the elements and the length durably to the backing storage. See [`Memory::sync`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.flush_range(..)
+    }
+
+    /// Write the elements in `range` and the length durably to the backing storage.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds of the vector.
+    pub fn flush_range(&self, range: impl RangeBounds<usize>) -> Result<(), A::Error> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("range out of bounds"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("range out of bounds"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(start <= end && end <= self.len(), "range out of bounds");
+        let size = core::mem::size_of::<T>();
+        self.mem.sync(start * size..end * size)
+    }
 }
 
 // std::vec::Vec methods
-impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
+impl<'a, T: Plain, A: 'a + Memory> MemVec<'a, T, A> {
     fn as_buf(&self) -> &[T] {
         unsafe {
             let (prefix, slice, _suffix) = self.mem.deref().align_to::<T>();
@@ -74,11 +226,25 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         self.as_buf().len()
     }
 
+    /// The largest length of the vector, see [`Memory::max_len`].
+    pub fn max_len(&self) -> usize {
+        self.mem.max_len()
+    }
+
+    /// # Panics
+    /// Panics if the new capacity exceeds `isize::MAX` bytes or [`Memory::max_len`] elements, or
+    /// the memory fails to grow.
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
         self.try_reserve(additional).expect("reserve failed");
     }
 
+    /// Fails with [`Memory::capacity_overflow`] if the new capacity exceeds [`Memory::max_len`]
+    /// elements.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds `isize::MAX` bytes, as the error type of the memory
+    /// cannot express it.
     pub fn try_reserve(&mut self, additional: usize) -> Result<(), A::Error> {
         let len = self.len();
         if self.needs_to_grow(len, additional) {
@@ -88,10 +254,19 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         }
     }
 
+    /// # Panics
+    /// Panics if the new capacity exceeds `isize::MAX` bytes or [`Memory::max_len`] elements, or
+    /// the memory fails to grow.
     pub fn reserve_exact(&mut self, additional: usize) {
         self.try_reserve_exact(additional).expect("reserve failed");
     }
 
+    /// Fails with [`Memory::capacity_overflow`] if the new capacity exceeds [`Memory::max_len`]
+    /// elements.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds `isize::MAX` bytes, as the error type of the memory
+    /// cannot express it.
     pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), A::Error> {
         let len = self.len();
         if self.needs_to_grow(len, additional) {
@@ -107,19 +282,30 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         // by only calling it with a greater capacity.
         let len = self.mem.len();
         if self.capacity() > len {
-            self.mem
-                .shrink(len * core::mem::size_of::<T>())
-                .expect("shrink failed");
+            self.shrink_with_policy(len);
         }
     }
 
     pub fn shrink_to(&mut self, min_capacity: usize) {
         if self.capacity() > min_capacity {
             let new_cap = core::cmp::max(self.len(), min_capacity);
-            self.mem
-                .shrink(new_cap * core::mem::size_of::<T>())
-                .expect("shrink failed");
+            self.shrink_with_policy(new_cap);
+        }
+    }
+
+    fn shrink_with_policy(&mut self, new_cap: usize) {
+        self.try_shrink_with_policy(new_cap).expect("shrink failed");
+    }
+
+    pub(crate) fn try_shrink_with_policy(&mut self, new_cap: usize) -> Result<(), A::Error> {
+        let size = core::mem::size_of::<T>();
+        if let Some(capacity) = self
+            .shrink_policy
+            .target(self.mem.deref().len(), new_cap * size)
+        {
+            self.mem.shrink(capacity)?;
         }
+        Ok(())
     }
 
     pub fn truncate(&mut self, len: usize) {
@@ -133,7 +319,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
 
             let remaining_len = self.mem.len() - len;
             let s = ptr::slice_from_raw_parts_mut(self.as_mut_ptr().add(len), remaining_len);
-            *self.mem.len_mut() = len;
+            self.mem.set_len(len);
             ptr::drop_in_place(s);
         }
     }
@@ -148,14 +334,41 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         unsafe { self.as_buf_mut().get_unchecked_mut(..len) }
     }
 
+    /// The bytes of the elements, e.g. to hash, compress or send them.
+    ///
+    /// `T` has no padding, so every byte is initialized.
+    pub fn as_bytes(&self) -> &[u8]
+    where
+        T: crate::NoPadding,
+    {
+        let slice = self.as_slice();
+        unsafe { core::slice::from_raw_parts(slice.as_ptr().cast(), core::mem::size_of_val(slice)) }
+    }
+
+    /// The bytes of the elements, mutable since any bytes are a valid [`bytemuck::Pod`] `T`.
+    #[cfg(feature = "bytemuck")]
+    pub fn as_bytes_mut(&mut self) -> &mut [u8]
+    where
+        T: bytemuck::Pod,
+    {
+        bytemuck::cast_slice_mut(self.as_mut_slice())
+    }
+
     #[inline]
     pub fn as_ptr(&self) -> *const T {
-        self.mem.as_ptr() as *const _
+        self.mem.as_ptr().cast()
     }
 
+    /// Every element pointer is derived from this, since creating it reborrows the whole memory
+    /// mutably and invalidates pointers derived before. Callers take it once per operation.
     #[inline]
     pub fn as_mut_ptr(&mut self) -> *mut T {
-        self.mem.as_mut_ptr() as *mut _
+        self.buf_ptr().as_ptr()
+    }
+
+    #[inline]
+    fn buf_ptr(&mut self) -> NonNull<T> {
+        NonNull::from(self.mem.deref_mut()).cast()
     }
 
     /// # Safety
@@ -170,7 +383,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         if len > cap {
             assert_failed(len, cap);
         }
-        *self.mem.len_mut() = len;
+        self.mem.set_len(len);
     }
 
     #[inline]
@@ -232,6 +445,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
                 // element.
                 ptr::write(p, element);
             }
+            self.zero_padding_at(index);
             self.set_len(len + 1);
         }
     }
@@ -279,6 +493,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         F: FnMut(&mut T) -> bool,
     {
         let original_len = self.len();
+        let poisoned = self.mem.set_poisoned(true);
         // Avoid double drop if the drop guard is not executed,
         // since we may make some holes during the process.
         unsafe { self.set_len(0) };
@@ -294,23 +509,22 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         // This drop guard will be invoked when predicate or `drop` of element panicked.
         // It shifts unchecked elements to cover holes and `set_len` to the correct length.
         // In cases when predicate and `drop` never panick, it will be optimized out.
-        struct BackshiftOnDrop<'a, 'v, T: Copy, A: Memory> {
+        struct BackshiftOnDrop<'a, 'v, T: Plain, A: Memory> {
             v: &'a mut MemVec<'v, T, A>,
             processed_len: usize,
             deleted_cnt: usize,
             original_len: usize,
         }
 
-        impl<T: Copy, A: Memory> Drop for BackshiftOnDrop<'_, '_, T, A> {
+        impl<T: Plain, A: Memory> Drop for BackshiftOnDrop<'_, '_, T, A> {
             fn drop(&mut self) {
                 if self.deleted_cnt > 0 {
                     // SAFETY: Trailing unchecked items must be valid since we never touch them.
                     unsafe {
+                        let ptr = self.v.as_mut_ptr();
                         ptr::copy(
-                            self.v.as_ptr().add(self.processed_len),
-                            self.v
-                                .as_mut_ptr()
-                                .add(self.processed_len - self.deleted_cnt),
+                            ptr.add(self.processed_len),
+                            ptr.add(self.processed_len - self.deleted_cnt),
                             self.original_len - self.processed_len,
                         );
                     }
@@ -329,7 +543,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
             original_len,
         };
 
-        fn process_loop<F, T: Copy, A: Memory, const DELETED: bool>(
+        fn process_loop<F, T: Plain, A: Memory, const DELETED: bool>(
             original_len: usize,
             f: &mut F,
             g: &mut BackshiftOnDrop<'_, '_, T, A>,
@@ -337,8 +551,9 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
             F: FnMut(&mut T) -> bool,
         {
             while g.processed_len != original_len {
+                let ptr = g.v.as_mut_ptr();
                 // SAFETY: Unchecked element must be valid.
-                let cur = unsafe { &mut *g.v.as_mut_ptr().add(g.processed_len) };
+                let cur = unsafe { &mut *ptr.add(g.processed_len) };
                 if !f(cur) {
                     // Advance early to avoid double drop if `drop_in_place` panicked.
                     g.processed_len += 1;
@@ -356,7 +571,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
                     // SAFETY: `deleted_cnt` > 0, so the hole slot must not overlap with current element.
                     // We use copy for move, and never touch this element again.
                     unsafe {
-                        let hole_slot = g.v.as_mut_ptr().add(g.processed_len - g.deleted_cnt);
+                        let hole_slot = ptr.add(g.processed_len - g.deleted_cnt);
                         ptr::copy_nonoverlapping(cur, hole_slot, 1);
                     }
                 }
@@ -372,6 +587,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
 
         // All item are processed. This can be optimized to `set_len` by LLVM.
         drop(g);
+        self.mem.set_poisoned(poisoned);
     }
 
     #[inline]
@@ -391,9 +607,10 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         if len <= 1 {
             return;
         }
+        let poisoned = self.mem.set_poisoned(true);
 
         /* INVARIANT: vec.len() > read >= write > write-1 >= 0 */
-        struct FillGapOnDrop<'a, 'b, T: Copy, A: Memory> {
+        struct FillGapOnDrop<'a, 'b, T: Plain, A: Memory> {
             /* Offset of the element we want to check if it is duplicate */
             read: usize,
 
@@ -405,7 +622,7 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
             vec: &'a mut MemVec<'b, T, A>,
         }
 
-        impl<'a, 'b, T: Copy, A: Memory> Drop for FillGapOnDrop<'a, 'b, T, A> {
+        impl<'a, 'b, T: Plain, A: Memory> Drop for FillGapOnDrop<'a, 'b, T, A> {
             fn drop(&mut self) {
                 /* This code gets executed when `same_bucket` panics */
                 /* SAFETY: invariant guarantees that `read - write`
@@ -476,20 +693,70 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
              * when `same_bucket` is guaranteed to not panic, this bloats a little
              * the codegen, so we just do it manually */
             gap.vec.set_len(gap.write);
+            gap.vec.mem.set_poisoned(poisoned);
             core::mem::forget(gap);
         }
     }
 
+    /// # Panics
+    /// Panics if the vector holds [`Memory::max_len`] elements, or the memory fails to grow. See
+    /// [`MemVec::checked_push`] for a push reporting errors.
     #[inline]
     pub fn push(&mut self, value: T) {
         if self.len() == self.capacity() {
             self.reserve_for_push(self.len()).unwrap();
         }
+        if self.push_within_capacity(value).is_err() {
+            unreachable!("reserved for push");
+        }
+    }
+
+    /// Append `value` if there is spare capacity, without growing the memory, or return it.
+    ///
+    /// It writes through the raw pointer of the memory rather than [`MemVec::as_mut_ptr`], so the
+    /// slices of [`Frozen::as_slice`] stay valid.
+    #[inline]
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        let len = self.len();
+        if len == self.capacity() {
+            return Err(value);
+        }
         unsafe {
-            let end = self.as_mut_ptr().add(self.len());
+            let end = self.mem.as_mut_ptr().cast::<T>().add(len);
             ptr::write(end, value);
-            *self.mem.len_mut() += 1;
+            self.zero_padding_at(len);
+            self.mem.set_len(len + 1);
         }
+        Ok(())
+    }
+
+    /// Borrow the vector without growing or shrinking, so the memory is never remapped while the
+    /// guard lives and pointers into it stay valid.
+    pub fn frozen(&mut self) -> Frozen<'_, 'a, T, A> {
+        Frozen { vec: self }
+    }
+
+    /// Reserve room for `expected_len` more elements for a bulk load of them.
+    ///
+    /// The guard appends without publishing the length of the memory, so a [`crate::VecFile`]
+    /// updates and syncs its header once when the load finishes rather than for every element.
+    /// See [`BulkLoad`].
+    pub fn begin_bulk_load(
+        &mut self,
+        expected_len: usize,
+    ) -> Result<BulkLoad<'_, 'a, T, A>, A::Error> {
+        self.try_reserve_exact(expected_len)?;
+        Ok(BulkLoad::new(self, expected_len))
+    }
+
+    /// Reserve room for `additional` more elements and freeze the vector, so a bulk writer can
+    /// push them through [`Frozen::push_within_capacity`] without remapping.
+    pub fn reserve_exact_then_freeze(
+        &mut self,
+        additional: usize,
+    ) -> Result<Frozen<'_, 'a, T, A>, A::Error> {
+        self.try_reserve_exact(additional)?;
+        Ok(self.frozen())
     }
 
     #[inline]
@@ -498,19 +765,43 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
             None
         } else {
             unsafe {
-                *self.mem.len_mut() -= 1;
-                Some(ptr::read(self.as_mut_ptr().add(self.len())))
+                let len = self.len() - 1;
+                self.mem.set_len(len);
+                Some(ptr::read(self.as_mut_ptr().add(len)))
             }
         }
     }
 
+    /// Append the elements of `other`, reserving once.
+    ///
+    /// With the `non-temporal` feature, copies of several megabytes use streaming stores where
+    /// the target supports them, so bulk loads do not evict the working set from the cache.
+    #[cfg(not(no_global_oom_handling))]
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        let count = other.len();
+        if count == 0 {
+            return;
+        }
+        self.reserve(count);
+        let len = self.len();
+        unsafe {
+            copy_bytes(
+                other.as_ptr() as *const u8,
+                self.as_mut_ptr().add(len) as *mut u8,
+                core::mem::size_of_val(other),
+            );
+            self.zero_padding_range(len..len + count);
+            self.mem.set_len(len + count);
+        }
+    }
+
     // #[inline]
     // unsafe fn append_elements(&mut self, other: *const [T]) {
     //     let count = unsafe { (*other).len() };
     //     self.reserve(count);
     //     let len = self.len();
     //     unsafe { ptr::copy_nonoverlapping(other as *const T, self.as_mut_ptr().add(len), count) };
-    //     *self.mem.len_mut() += count;
+    //     self.mem.set_len(len + count);
     // }
 
     // drain
@@ -520,6 +811,15 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         self.truncate(0)
     }
 
+    /// Clear the vector and release all of its memory, regardless of the shrink policy.
+    ///
+    /// A file-backed vector is truncated back to its header, so a long-lived file does not stay
+    /// at its high-water mark.
+    pub fn clear_and_release(&mut self) -> Result<(), A::Error> {
+        self.clear();
+        self.mem.shrink(0)
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.mem.len()
@@ -543,14 +843,49 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         }
     }
 
+    /// Append `n` copies of `value`, reserving once.
+    ///
+    /// Byte-sized values are written with one `memset`; others are written in one pass with the
+    /// length set once, unlike repeated [`MemVec::push`].
+    #[cfg(not(no_global_oom_handling))]
+    pub fn push_n(&mut self, n: usize, value: T) {
+        if core::mem::size_of::<T>() == 1 && n > 0 {
+            self.reserve(n);
+            let len = self.len();
+            unsafe {
+                // a byte-sized plain value is a single initialized byte
+                let byte = ptr::read(&value as *const T as *const u8);
+                ptr::write_bytes(self.as_mut_ptr().add(len) as *mut u8, byte, n);
+                self.zero_padding_range(len..len + n);
+                self.mem.set_len(len + n);
+            }
+        } else {
+            self.extend_with(n, ExtendElement(value));
+        }
+    }
+
+    /// Append `n` values returned by `f`, reserving once and setting the length once.
+    #[cfg(not(no_global_oom_handling))]
+    pub fn fill_n<F>(&mut self, n: usize, f: F)
+    where
+        F: FnMut() -> T,
+    {
+        self.extend_with(n, ExtendFunc(f));
+    }
+
     #[inline]
     pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
         // Note:
         // This method is not implemented in terms of `split_at_spare_mut`,
         // to prevent invalidation of pointers to the buffer.
+        // The pointer comes from the memory rather than `as_mut_ptr`, which reborrows all of it.
         unsafe {
             core::slice::from_raw_parts_mut(
-                self.as_mut_ptr().add(self.len()) as *mut MaybeUninit<T>,
+                self.mem
+                    .as_mut_ptr()
+                    .cast::<T>()
+                    .add(self.len())
+                    .cast::<MaybeUninit<T>>(),
                 self.capacity() - self.len(),
             )
         }
@@ -563,9 +898,10 @@ trait ExtendWith<T> {
 }
 
 struct ExtendElement<T>(T);
-impl<T: Clone> ExtendWith<T> for ExtendElement<T> {
+impl<T: Plain> ExtendWith<T> for ExtendElement<T> {
     fn next(&mut self) -> T {
-        self.0.clone()
+        // plain values are copied bitwise
+        unsafe { ptr::read(&self.0) }
     }
     fn last(self) -> T {
         self.0
@@ -587,7 +923,7 @@ fn capacity_overflow() -> usize {
     panic!("capacity overflow");
 }
 
-impl<'a, T: Copy + std::cmp::PartialEq, A: 'a + Memory> MemVec<'a, T, A> {
+impl<'a, T: Plain + std::cmp::PartialEq, A: 'a + Memory> MemVec<'a, T, A> {
     #[inline]
     pub fn dedup(&mut self) {
         self.dedup_by(|a, b| a == b)
@@ -595,7 +931,7 @@ impl<'a, T: Copy + std::cmp::PartialEq, A: 'a + Memory> MemVec<'a, T, A> {
 }
 
 /// port ofRawVec utilities
-impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
+impl<'a, T: Plain, A: 'a + Memory> MemVec<'a, T, A> {
     pub(crate) const MIN_NON_ZERO_CAP: usize = if core::mem::size_of::<T>() == 1 {
         8
     } else if core::mem::size_of::<T>() <= 1024 {
@@ -623,79 +959,119 @@ impl<'a, T: Copy, A: 'a + Memory> MemVec<'a, T, A> {
         // }
 
         // Nothing we can really do about these checks, sadly.
-        let required_cap = len
-            .checked_add(additional)
-            .unwrap_or_else(capacity_overflow);
-
-        // This guarantees exponential growth. The doubling cannot overflow
-        // because `cap <= isize::MAX` and the type of `cap` is `usize`.
-        let cap = core::cmp::max(self.capacity() * 2, required_cap);
-        let cap = core::cmp::max(Self::MIN_NON_ZERO_CAP, cap);
-        self.mem.reserve(cap * core::mem::size_of::<T>())
+        let required_cap = self.required_cap(len, additional)?;
+
+        let size = core::mem::size_of::<T>();
+        let required_bytes = Self::capacity_bytes(required_cap);
+        // a policy overshooting the limit falls back to the limit, which `required_bytes` fits in
+        let target = self
+            .growth_policy
+            .target(self.capacity() * size, required_bytes)
+            .min(isize::MAX as usize);
+        // the same for the limit of the memory, which `required_cap` is within
+        let cap = core::cmp::max(Self::MIN_NON_ZERO_CAP, target / size).min(self.mem.max_len());
+        self.reserve_memory(Self::capacity_bytes(cap))
     }
 
     // The constraints on this method are much the same as those on
     // `grow_amortized`, but this method is usually instantiated less often so
     // it's less critical.
-    fn grow_exact(&mut self, len: usize, additional: usize) -> Result<(), A::Error> {
+    pub(crate) fn grow_exact(&mut self, len: usize, additional: usize) -> Result<(), A::Error> {
         // if core::mem::size_of::<T>() == 0 {
         //     // Since we return a capacity of `usize::MAX` when the type size is
         //     // 0, getting to here necessarily means the `RawVec` is overfull.
         //     return Error(CapacityOverflow.into());
         // }
 
+        let cap = self.required_cap(len, additional)?;
+        self.reserve_memory(Self::capacity_bytes(cap))
+    }
+
+    /// The capacity holding `additional` elements after `len`, or the error of the memory if it
+    /// exceeds [`Memory::max_len`].
+    fn required_cap(&self, len: usize, additional: usize) -> Result<usize, A::Error> {
         let cap = len
             .checked_add(additional)
             .unwrap_or_else(capacity_overflow);
-        self.mem.reserve(cap * core::mem::size_of::<T>())
+        if cap > self.mem.max_len() {
+            if let Some(err) = self.mem.capacity_overflow() {
+                return Err(err);
+            }
+        }
+        Ok(cap)
+    }
+
+    /// The size of `cap` elements in bytes, which must not exceed `isize::MAX` as in `Vec`, so the
+    /// backend never sees an overflown or unaddressable size.
+    fn capacity_bytes(cap: usize) -> usize {
+        cap.checked_mul(core::mem::size_of::<T>())
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .unwrap_or_else(capacity_overflow)
+    }
+
+    fn reserve_memory(&mut self, capacity: usize) -> Result<(), A::Error> {
+        let old_capacity = self.mem.deref().len();
+        self.mem.reserve(capacity)?;
+        if self.zero_grown {
+            if let Some(grown) = self.mem.deref_mut().get_mut(old_capacity..) {
+                grown.fill(0);
+            }
+        }
+        Ok(())
     }
 
     /// Extend the vector by `n` values, using the given generator.
     fn extend_with<E: ExtendWith<T>>(&mut self, n: usize, mut value: E) {
+        if n == 0 {
+            return;
+        }
         self.reserve(n);
+        let len = self.len();
 
         unsafe {
-            let mut ptr = self.as_mut_ptr().add(self.len());
-            // Write all elements except the last one
-            for _ in 1..n {
-                ptr::write(ptr, value.next());
-                ptr = ptr.offset(1);
-                // Increment the length in every step in case next() panics
-                *self.mem.len_mut() += 1;
+            // Write all elements through one pointer and set the length once at the end; plain
+            // values need no drop, so a panicking next() just leaves them uncounted.
+            let end = self.as_mut_ptr().add(len);
+            for i in 0..n - 1 {
+                ptr::write(end.add(i), value.next());
             }
+            // We can write the last element directly without cloning needlessly
+            ptr::write(end.add(n - 1), value.last());
+            self.zero_padding_range(len..len + n);
+            self.mem.set_len(len + n);
+        }
+    }
 
-            if n > 0 {
-                // We can write the last element directly without cloning needlessly
-                std::ptr::write(ptr, value.last());
-                *self.mem.len_mut() += 1;
+    fn zero_padding_range(&mut self, range: Range<usize>) {
+        if !self.padding.is_empty() {
+            for index in range {
+                self.zero_padding_at(index);
             }
-
-            // len set by scope guard
         }
     }
 }
 
-impl<'a, T: Copy, A: 'a + Memory> Deref for MemVec<'a, T, A> {
+impl<'a, T: Plain, A: 'a + Memory> Deref for MemVec<'a, T, A> {
     type Target = [T];
     fn deref(&self) -> &Self::Target {
         self.as_slice()
     }
 }
 
-impl<'a, T: Copy, A: 'a + Memory> DerefMut for MemVec<'a, T, A> {
+impl<'a, T: Plain, A: 'a + Memory> DerefMut for MemVec<'a, T, A> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.as_mut_slice()
     }
 }
 
-impl<'a, T: Copy + Hash, A: Memory> Hash for MemVec<'a, T, A> {
+impl<'a, T: Plain + Hash, A: Memory> Hash for MemVec<'a, T, A> {
     #[inline]
     fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         Hash::hash(&**self, state)
     }
 }
 
-impl<'a, T: Copy, I: SliceIndex<[T]>, A: Memory> Index<I> for MemVec<'a, T, A> {
+impl<'a, T: Plain, I: SliceIndex<[T]>, A: Memory> Index<I> for MemVec<'a, T, A> {
     type Output = I::Output;
 
     #[inline]
@@ -704,14 +1080,14 @@ impl<'a, T: Copy, I: SliceIndex<[T]>, A: Memory> Index<I> for MemVec<'a, T, A> {
     }
 }
 
-impl<'a, T: Copy, I: SliceIndex<[T]>, A: Memory> IndexMut<I> for MemVec<'a, T, A> {
+impl<'a, T: Plain, I: SliceIndex<[T]>, A: Memory> IndexMut<I> for MemVec<'a, T, A> {
     #[inline]
     fn index_mut(&mut self, index: I) -> &mut Self::Output {
         IndexMut::index_mut(&mut **self, index)
     }
 }
 
-impl<'a, 'm, T: Copy, A: Memory> IntoIterator for &'a MemVec<'m, T, A> {
+impl<'a, 'm, T: Plain, A: Memory> IntoIterator for &'a MemVec<'m, T, A> {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
 
@@ -720,7 +1096,7 @@ impl<'a, 'm, T: Copy, A: Memory> IntoIterator for &'a MemVec<'m, T, A> {
     }
 }
 
-impl<'a, 'm, T: Copy, A: Memory> IntoIterator for &'a mut MemVec<'m, T, A> {
+impl<'a, 'm, T: Plain, A: Memory> IntoIterator for &'a mut MemVec<'m, T, A> {
     type Item = &'a mut T;
     type IntoIter = slice::IterMut<'a, T>;
 
@@ -768,43 +1144,43 @@ impl<'a, 'm, T: Copy, A: Memory> IntoIterator for &'a mut MemVec<'m, T, A> {
 //     // }
 // }
 
-impl<'a, T: Copy + PartialEq, A: Memory> PartialEq for MemVec<'a, T, A> {
+impl<'a, T: Plain + PartialEq, A: Memory> PartialEq for MemVec<'a, T, A> {
     fn eq(&self, other: &Self) -> bool {
         self.as_slice() == other.as_slice()
     }
 }
 
-impl<'a, T: Copy + PartialOrd, A: Memory> PartialOrd for MemVec<'a, T, A> {
+impl<'a, T: Plain + PartialOrd, A: Memory> PartialOrd for MemVec<'a, T, A> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         PartialOrd::partial_cmp(&**self, &**other)
     }
 }
 
-impl<'a, T: Copy + Eq, A: Memory> Eq for MemVec<'a, T, A> {}
+impl<'a, T: Plain + Eq, A: Memory> Eq for MemVec<'a, T, A> {}
 
-impl<'a, T: Ord + Copy, A: Memory> Ord for MemVec<'a, T, A> {
+impl<'a, T: Ord + Plain, A: Memory> Ord for MemVec<'a, T, A> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
         Ord::cmp(&**self, &**other)
     }
 }
 
-// skip drop - T: Copy
+// skip drop - T: Plain
 
-impl<'a, T: core::fmt::Debug + Copy, A: Memory> core::fmt::Debug for MemVec<'a, T, A> {
+impl<'a, T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug for MemVec<'a, T, A> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         core::fmt::Debug::fmt(&**self, f)
     }
 }
 
-impl<'a, T: Copy, A: Memory> AsRef<[T]> for MemVec<'a, T, A> {
+impl<'a, T: Plain, A: Memory> AsRef<[T]> for MemVec<'a, T, A> {
     fn as_ref(&self) -> &[T] {
         self
     }
 }
 
-impl<'a, T: Copy, A: Memory> AsMut<[T]> for MemVec<'a, T, A> {
+impl<'a, T: Plain, A: Memory> AsMut<[T]> for MemVec<'a, T, A> {
     fn as_mut(&mut self) -> &mut [T] {
         self
     }
@@ -1,4 +1,9 @@
-use crate::MemVec;
+use crate::{
+    borrowed::{BorrowedMemory, MemVecRef},
+    plain::Plain,
+    policy::GrowthPolicy,
+    MemVec,
+};
 
 #[allow(clippy::len_without_is_empty)]
 pub trait Memory
@@ -10,13 +15,47 @@ where
     fn as_ptr(&self) -> *const u8;
     fn as_mut_ptr(&mut self) -> *mut u8;
     fn len(&self) -> usize;
-    fn len_mut(&mut self) -> &mut usize;
+    fn set_len(&mut self, len: usize);
     fn reserve(&mut self, capacity: usize) -> Result<(), Self::Error>;
     fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error>;
+    /// The largest length the memory can record, e.g. `u32::MAX` for a [`crate::VecFile`] created
+    /// with [`crate::VecFileBuilder::u32_len_cap`]. [`MemVec`] never grows its capacity past it.
+    fn max_len(&self) -> usize {
+        usize::MAX
+    }
+    /// The error [`MemVec::try_reserve`] returns for a capacity past [`Memory::max_len`].
+    ///
+    /// `None` by default, as memory without a limit never needs it. Memory overriding
+    /// [`Memory::max_len`] should override it too; otherwise [`MemVec`] leaves refusing the
+    /// capacity to [`Memory::reserve`].
+    fn capacity_overflow(&self) -> Option<Self::Error> {
+        None
+    }
+    /// The growth policy a [`MemVec`] over the memory starts with, e.g. the one configured by
+    /// [`crate::VecFileBuilder::growth_policy`]. See [`MemVec::set_growth_policy`].
+    fn growth_policy(&self) -> GrowthPolicy {
+        GrowthPolicy::default()
+    }
+    /// Write the bytes of `range` and the length durably to the backing storage.
+    ///
+    /// Does nothing by default, which is right for memory without backing storage.
+    fn sync(&self, range: core::ops::Range<usize>) -> Result<(), Self::Error> {
+        let _ = range;
+        Ok(())
+    }
+    /// Set whether the memory may hold half-mutated data and return the previous value.
+    ///
+    /// [`MemVec`] sets it before a mutation which calls back user code in the middle of moving
+    /// elements, e.g. [`MemVec::retain`], and restores it afterwards, so it stays set when the
+    /// callback panics. Does nothing and returns `false` by default.
+    fn set_poisoned(&mut self, poisoned: bool) -> bool {
+        let _ = poisoned;
+        false
+    }
     /// Create a MemVec object with memory.
     /// # Safety
     /// The memory must represent valid len and bytes representations of T.
-    unsafe fn try_into_memvec<'a, T: Copy>(
+    unsafe fn try_into_memvec<'a, T: Plain>(
         self,
     ) -> Result<MemVec<'a, T, Self>, (Self, MemoryConversionError)>
     where
@@ -24,10 +63,103 @@ where
     {
         MemVec::try_from_memory(self)
     }
+    /// Create a MemVec object borrowing the memory, which stays with its owner.
+    ///
+    /// Unlike [`Memory::try_into_memvec`], the memory is back in place once the vector drops.
+    /// # Safety
+    /// The memory must represent valid len and bytes representations of T.
+    unsafe fn try_as_memvec<T: Plain>(
+        &mut self,
+    ) -> Result<MemVecRef<'_, T, Self>, MemoryConversionError>
+    where
+        Self: Sized,
+    {
+        MemVec::try_from_memory(BorrowedMemory::new(self)).map_err(|(_, e)| e)
+    }
+    /// Create a MemVec object with memory, safely since any bytes are a valid [`bytemuck::Pod`].
+    #[cfg(feature = "bytemuck")]
+    fn into_vec_pod<'a, T: bytemuck::Pod>(
+        self,
+    ) -> Result<MemVec<'a, T, Self>, (Self, MemoryConversionError)>
+    where
+        Self: Sized,
+    {
+        MemVec::from_memory_pod(self)
+    }
+    /// Create a MemVec object with memory, safely for a type implementing the zerocopy traits.
+    #[cfg(feature = "zerocopy")]
+    fn into_vec_zerocopy<'a, T>(self) -> Result<MemVec<'a, T, Self>, (Self, MemoryConversionError)>
+    where
+        Self: Sized,
+        T: Plain + zerocopy::FromBytes + zerocopy::AsBytes + zerocopy::Unaligned,
+    {
+        MemVec::from_memory_zerocopy(self)
+    }
+}
+
+/// Memory which is only read, such as a read-only mapping of a file.
+///
+/// A [`crate::ReadOnlyMemVec`] over it has no mutating methods, so writing through a vector of a
+/// shared snapshot is a compile error rather than a fault at run time.
+#[allow(clippy::len_without_is_empty)]
+pub trait ReadOnlyMemory
+where
+    Self: core::ops::Deref<Target = [u8]>,
+{
+    /// The number of elements, as [`Memory::len`].
+    fn len(&self) -> usize;
 }
 
 #[derive(Debug)]
 pub enum MemoryConversionError {
-    AlignMismatch,
+    /// The memory is not aligned for the element type: it starts `offset` bytes past an `align`
+    /// bytes boundary.
+    ///
+    /// A mapping starts at the page-aligned base address plus the file offset modulo the page
+    /// size, so the header before the data must be padded to a multiple of `align`. For a
+    /// [`crate::MmapFile`], pass such an offset to [`crate::MapOptions::offset`]. The data of a
+    /// [`crate::VecFile`] is aligned to 64 bytes, which fits any element type up to that alignment.
+    AlignMismatch { offset: usize, align: usize },
+    /// The stored length covers more elements than the memory holds, i.e. the length is corrupted.
+    /// See [`crate::VecFile::recover_truncate`] to recover such a file.
     SizeMismatch,
 }
+
+impl core::fmt::Display for MemoryConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AlignMismatch { offset, align } => write!(
+                f,
+                "memory starts {offset} bytes past a {align} bytes boundary required by the element type"
+            ),
+            Self::SizeMismatch => f.write_str("length exceeds the capacity of the memory"),
+        }
+    }
+}
+
+impl std::error::Error for MemoryConversionError {}
+
+/// Check that memory starting at `ptr` is aligned for `T`.
+///
+/// The address is checked even for empty memory, since growing keeps the offset of the data.
+pub(crate) fn check_align<T>(ptr: *const u8) -> Result<(), MemoryConversionError> {
+    let align = core::mem::align_of::<T>();
+    match ptr.addr() % align {
+        0 => Ok(()),
+        offset => Err(MemoryConversionError::AlignMismatch { offset, align }),
+    }
+}
+
+/// An element rejected by the validation callback of [`MemVec::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRecord {
+    pub index: usize,
+}
+
+impl core::fmt::Display for InvalidRecord {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid record at index {}", self.index)
+    }
+}
+
+impl std::error::Error for InvalidRecord {}
@@ -1,29 +1,119 @@
-use crate::memory::Memory;
+use crate::{
+    error::with_path,
+    memory::{Memory, MemoryConversionError, ReadOnlyMemory},
+    plain::Plain,
+    policy::page_size,
+    read_only::ReadOnlyMemVec,
+};
 use core::ops::{Deref, DerefMut};
-use memmap2::{MmapMut, MmapOptions};
+use memmap2::{Mmap, MmapMut, MmapOptions};
 use std::{
-    fs::{File, OpenOptions},
-    path::Path,
+    fs::File,
+    path::{Path, PathBuf},
 };
 
-pub struct MmapFile<'a> {
+/// A file mapped with `options`, which is grown and shrunk by resizing the file and remapping it.
+///
+/// The mapping reaches the end of the file, unless it is created by [`MmapRegion::with_len`]. It
+/// may start with a prefix of fixed length, e.g. the header of a [`crate::VecFile`], which is
+/// mapped together with the region but not exposed by it.
+/// Except on Windows, where a mapped file cannot be truncated and shrinking remaps first,
+/// shrinking truncates the file without remapping: the mapping is kept and only its head up to
+/// `len` is exposed, so a later reserve within the old mapping does not remap either.
+pub(crate) struct MmapRegion {
     options: MmapOptions,
     mmap: MmapMut,
-    len: &'a mut usize,
-    file: File,
+    /// The length of the prefix mapped before the exposed bytes.
+    prefix: usize,
+    /// The exposed length of `mmap` after the prefix; the bytes after it may be beyond the end of
+    /// the file.
+    len: usize,
+    pub(crate) file: File,
+    /// The length of `file`, kept to avoid a stat on every resize.
+    file_len: u64,
+    /// The offset of a mapping of explicit length, which may end before the end of the file.
+    explicit_offset: Option<u64>,
+    /// The file after an explicit mapping ended with it when mapped, so whatever was added to it
+    /// since belongs to the region and may become capacity.
+    owns_tail: bool,
+    page_aligned: bool,
+    huge_pages: bool,
 }
 
-impl<'a> MmapFile<'a> {
-    pub fn new(file: File, len: &'a mut usize, data_options: MmapOptions) -> std::io::Result<Self> {
-        let mmap = unsafe { data_options.map_mut(&file) }?;
+impl MmapRegion {
+    pub fn new(file: File, options: MmapOptions) -> std::io::Result<Self> {
+        Self::with_prefix(file, options, 0)
+    }
+
+    /// Map the file with `options`, exposing the bytes after the first `prefix` ones.
+    pub fn with_prefix(file: File, options: MmapOptions, prefix: usize) -> std::io::Result<Self> {
+        let mmap = unsafe { options.map_mut(&file) }?;
+        let file_len = file.metadata()?.len();
+        let len = mmap.len().checked_sub(prefix).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than the prefix of the mapping",
+            )
+        })?;
         Ok(Self {
-            options: data_options,
-            mmap,
+            options,
+            prefix,
             len,
+            mmap,
             file,
+            file_len,
+            explicit_offset: None,
+            owns_tail: true,
+            page_aligned: false,
+            huge_pages: false,
         })
     }
 
+    /// Map `len` bytes after a prefix of `prefix` bytes at `offset`. The file may hold other data
+    /// after the mapping: growing fails with [`std::io::ErrorKind::InvalidInput`] unless the file
+    /// ended with the mapping when it was mapped, and so does shrinking truncate the file. See
+    /// [`MmapRegion::own_tail`].
+    pub fn with_len(
+        file: File,
+        mut options: MmapOptions,
+        offset: u64,
+        prefix: usize,
+        len: usize,
+    ) -> std::io::Result<Self> {
+        options.offset(offset).len(prefix + len);
+        let mut region = Self::with_prefix(file, options, prefix)?;
+        region.explicit_offset = Some(offset);
+        region.owns_tail = offset + (prefix + len) as u64 == region.file_len;
+        Ok(region)
+    }
+
+    /// Claim whatever follows the mapping in the file as slack, which growing may overwrite and
+    /// shrinking truncates, e.g. for a file whose data region is always last.
+    pub fn own_tail(&mut self) {
+        self.owns_tail = true;
+    }
+
+    /// Advise transparent huge pages for every mapping, including the current one.
+    pub fn set_huge_pages(&mut self, huge_pages: bool) {
+        self.huge_pages = huge_pages;
+        if huge_pages {
+            advise_huge_pages(&self.mmap);
+        }
+    }
+
+    fn map(&self) -> std::io::Result<MmapMut> {
+        let mmap = unsafe { self.options.map_mut(&self.file)? };
+        if self.huge_pages {
+            advise_huge_pages(&mmap);
+        }
+        Ok(mmap)
+    }
+
+    /// Round the file length up to a multiple of the page size when growing.
+    pub fn set_page_aligned(&mut self, page_aligned: bool) {
+        self.page_aligned = page_aligned;
+    }
+
     pub fn into_file(self) -> File {
         self.file
     }
@@ -31,248 +121,488 @@ impl<'a> MmapFile<'a> {
     pub fn file(&self) -> &File {
         &self.file
     }
-}
 
-impl<'a> core::fmt::Debug for MmapFile<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("MmapFile")
-            .field("options", &self.options)
-            .field("len", &self.len)
-            .field("file", &self.file)
-            .finish()
+    /// A pointer to the region which, unlike one taken from a slice of it, borrows none of it.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.mmap.as_ptr().wrapping_add(self.prefix)
     }
-}
 
-impl<'a> core::ops::Deref for MmapFile<'a> {
-    type Target = [u8];
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.mmap.as_mut_ptr().wrapping_add(self.prefix)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        self.mmap.deref()
+    /// The bytes mapped before the region.
+    pub fn prefix(&self) -> &[u8] {
+        // not through a slice of the whole mapping, which would overlap the region
+        unsafe { core::slice::from_raw_parts(self.mmap.as_ptr(), self.prefix) }
     }
-}
 
-impl<'a> core::ops::DerefMut for MmapFile<'a> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.mmap.deref_mut()
+    pub fn prefix_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.mmap.as_mut_ptr(), self.prefix) }
     }
-}
 
-impl<'a> Memory for MmapFile<'a>
-where
-    Self: Deref<Target = [u8]> + DerefMut<Target = [u8]>,
-{
-    type Error = std::io::Error;
+    /// Read the file length again, after the file was resized by other means than this region.
+    pub fn refresh_len(&mut self) -> std::io::Result<()> {
+        self.file_len = self.file.metadata()?.len();
+        Ok(())
+    }
 
-    fn as_ptr(&self) -> *const u8 {
-        self.mmap.as_ptr()
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.flush_range(0..self.len)
     }
-    fn as_mut_ptr(&mut self) -> *mut u8 {
-        self.mmap.as_mut_ptr()
+
+    /// Flush the prefix and the region without waiting.
+    #[cfg(feature = "async")]
+    pub fn flush_async(&self) -> std::io::Result<()> {
+        if self.prefix + self.len == 0 {
+            return Ok(());
+        }
+        self.mmap.flush_async_range(0, self.prefix + self.len)
     }
 
-    fn len(&self) -> usize {
-        *self.len
+    pub fn flush_range(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        self.flush_mapped(range.start + self.prefix..range.end + self.prefix)
     }
 
-    fn len_mut(&mut self) -> &mut usize {
-        self.len
+    pub fn flush_prefix(&self) -> std::io::Result<()> {
+        self.flush_mapped(0..self.prefix)
     }
 
-    fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
-        let additional_cap = capacity.wrapping_sub(self.mmap.len());
+    fn flush_mapped(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        if range.is_empty() {
+            return Ok(());
+        }
+        self.mmap.flush_range(range.start, range.len())
+    }
+
+    pub fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
+        let additional_cap = capacity.wrapping_sub(self.len);
         if (additional_cap as isize) < 0 {
             return Ok(());
         }
-        let bytes_len = self.file.metadata()?.len() + additional_cap as u64;
-        // eprintln!("new cap requested {} current {} gap {} total {}", capacity, self.deref().len(), additional_cap, bytes_len);
-        self.file.set_len(bytes_len)?;
-        assert_eq!(bytes_len, self.file.metadata()?.len());
-        self.mmap = unsafe { self.options.map_mut(&self.file)? };
+        if additional_cap > 0 && !self.owns_tail {
+            // growing would overwrite whatever follows the mapping
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot grow a mapping followed by other data in the file",
+            ));
+        }
+        let mut bytes_len = match self.explicit_offset {
+            Some(offset) => offset + (self.prefix + capacity) as u64,
+            None => self.file_len + additional_cap as u64,
+        };
+        let old_file_len = self.file_len;
+        if bytes_len > self.file_len {
+            if self.page_aligned {
+                bytes_len = bytes_len.next_multiple_of(page_size() as u64);
+            }
+            // eprintln!("new cap requested {} current {} gap {} total {}", capacity, self.deref().len(), additional_cap, bytes_len);
+            self.set_file_len(bytes_len)?;
+        }
+        let len = match self.explicit_offset {
+            Some(offset) => (bytes_len - offset) as usize - self.prefix,
+            None => self.len + (self.file_len - old_file_len) as usize,
+        };
+        if self.prefix + len <= self.mmap.len() {
+            // the file grew back under a mapping kept by shrink
+            self.len = len;
+            return Ok(());
+        }
+        if self.explicit_offset.is_some() {
+            self.options.len(self.prefix + len);
+        }
+        self.mmap = self.map()?;
+        self.len = self.mmap.len() - self.prefix;
         Ok(())
     }
 
-    fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
-        let redundant_cap = self.mmap.len().wrapping_sub(capacity);
+    /// Shrink the region to `capacity` bytes, truncating the file if it owns the tail.
+    ///
+    /// The mapping is kept, so the region stays as it was if the file cannot be truncated. On
+    /// Windows, where a mapped file cannot be truncated, the mapping is first replaced by one
+    /// ending at the new length; if the file still cannot be truncated, e.g. because another
+    /// handle maps it, the region is shrunk but the error is returned and the file keeps its
+    /// length.
+    pub fn shrink(&mut self, capacity: usize) -> std::io::Result<()> {
+        let redundant_cap = self.len.wrapping_sub(capacity);
         if (redundant_cap as isize) < 0 {
             return Ok(());
         }
-        let bytes_len = self.file.metadata()?.len() - redundant_cap as u64;
-        #[cfg(windows)]
-        {
-            self.mmap = MmapOptions::new().len(0).map_anon()?;
-
-            let set_len_result = self.file.set_len(bytes_len);
-            self.mmap = unsafe { self.options.map_mut(&self.file).expect("mmap is broken") };
-            set_len_result?;
+        let bytes_len = match self.explicit_offset {
+            // keep whatever follows the mapping unless it belongs to the region
+            Some(offset) => self
+                .owns_tail
+                .then_some(offset + (self.prefix + capacity) as u64),
+            None => Some(self.file_len - redundant_cap as u64),
+        };
+        // the pages past the new end are released with the file
+        if let Some(bytes_len) = bytes_len {
+            #[cfg(windows)]
+            const ERROR_USER_MAPPED_FILE: i32 = 1224;
+            match self.set_file_len(bytes_len) {
+                #[cfg(windows)]
+                Err(e) if e.raw_os_error() == Some(ERROR_USER_MAPPED_FILE) => {
+                    // the new mapping is made before the old one is dropped, so the region is
+                    // mapped throughout
+                    let mut options = self.options.clone();
+                    options.len(self.prefix + capacity);
+                    self.mmap = unsafe { options.map_mut(&self.file)? };
+                    if self.huge_pages {
+                        advise_huge_pages(&self.mmap);
+                    }
+                    self.len = capacity;
+                    self.set_file_len(bytes_len)?;
+                }
+                result => result?,
+            }
         }
-        #[cfg(not(windows))]
-        {
-            self.file.set_len(bytes_len)?;
-            self.mmap = unsafe { self.options.map_mut(&self.file)? };
+        if self.explicit_offset.is_some() {
+            self.options.len(self.prefix + capacity);
         }
+        self.len = capacity;
         Ok(())
     }
-}
 
-pub struct VecFile<'a> {
-    mmap_file: MmapFile<'a>,
-    #[allow(dead_code)]
-    len_mmap: MmapMut,
+    /// Resize the file to `len` bytes, keeping the recorded length if it fails.
+    fn set_file_len(&mut self, len: u64) -> std::io::Result<()> {
+        self.file.set_len(len)?;
+        self.file_len = len;
+        Ok(())
+    }
 }
 
-impl<'a> core::fmt::Debug for VecFile<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("VecFile")
-            .field("mmap_file", &self.mmap_file)
-            .finish()
+/// Advise the kernel to back `mmap` with transparent huge pages. Only Linux has such advice; a
+/// kernel built without them refuses it, which leaves the mapping as it is.
+fn advise_huge_pages(mmap: &MmapMut) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if !mmap.is_empty() {
+        let _ = mmap.advise(memmap2::Advice::HugePage);
     }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = mmap;
 }
 
-impl<'a> VecFile<'a> {
-    const HEADER_LEN: usize = core::mem::size_of::<u64>();
+/// Options of mapping a file as an [`MmapFile`].
+///
+/// The mapping reaches from the offset to the end of the file, or covers `len` bytes if set; then
+/// the file may hold other data after the mapping, and growing fails with
+/// [`std::io::ErrorKind::InvalidInput`] and shrinking leaves the file as it is unless the file
+/// ended with the mapping when it was mapped.
+#[derive(Clone, Debug, Default)]
+pub struct MapOptions {
+    offset: u64,
+    len: Option<usize>,
+    populate: bool,
+    huge_pages: bool,
+}
 
-    pub fn open_or_create(
-        path: impl AsRef<Path>,
-        init: impl FnOnce(&mut VecFile) -> Result<(), std::io::Error>,
-    ) -> std::io::Result<Self> {
-        let path = path.as_ref();
-        let existing = path.exists();
-        let file = if existing {
-            Self::_open(path, File::options().read(true).write(true))?
-        } else {
-            let mut file =
-                Self::_create(path, File::options().create(true).read(true).write(true))?;
-            if let Err(e) = init(&mut file) {
-                let _ = file;
-                let _ = std::fs::remove_file(path);
-                return Err(e);
-            }
-            file
-        };
+impl MapOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        Ok(file)
+    /// Map from `offset` bytes into the file, e.g. past a header of the application.
+    ///
+    /// The data must be aligned for the element type; see
+    /// [`crate::MemoryConversionError::AlignMismatch`].
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = offset;
+        self
     }
 
-    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
-        Self::_create(path.as_ref(), File::options().create(true).write(true))
+    /// Map `len` bytes rather than up to the end of the file.
+    pub fn len(&mut self, len: usize) -> &mut Self {
+        self.len = Some(len);
+        self
     }
 
-    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
-        let mut options = File::options();
-        options.read(true).write(true);
-        Self::_open(path.as_ref(), &options)
+    /// Read the whole mapping in when it is mapped. Only Linux supports it; elsewhere it is
+    /// ignored.
+    pub fn populate(&mut self, populate: bool) -> &mut Self {
+        self.populate = populate;
+        self
     }
 
-    fn _create(path: &Path, options: &OpenOptions) -> std::io::Result<Self> {
-        let file = options.open(path)?;
-        Self::clear(&file)?;
-        Self::from_file(file)
+    /// Advise the kernel to back the mapping with transparent huge pages, to cut the TLB misses
+    /// of random access to large files. Only Linux has such advice, and only some of its file
+    /// systems honor it; elsewhere, or if the kernel refuses it, it is ignored.
+    pub fn huge_pages(&mut self, huge_pages: bool) -> &mut Self {
+        self.huge_pages = huge_pages;
+        self
     }
 
-    fn _open(path: &Path, options: &OpenOptions) -> std::io::Result<Self> {
-        let file = options.open(path)?;
-        Self::from_file(file)
+    fn map(&self, file: File) -> std::io::Result<MmapRegion> {
+        let mut options = MmapOptions::new();
+        if self.populate {
+            options.populate();
+        }
+        let mut region = match self.len {
+            Some(len) => MmapRegion::with_len(file, options, self.offset, 0, len)?,
+            None => {
+                options.offset(self.offset);
+                MmapRegion::new(file, options)?
+            }
+        };
+        region.set_huge_pages(self.huge_pages);
+        Ok(region)
     }
 
-    /// Set header and the value of len to 0
-    pub fn clear(file: &File) -> std::io::Result<()> {
-        assert_eq!(0, file.metadata()?.len());
-        file.set_len(Self::HEADER_LEN as u64)?;
-        let len_mmap = Self::_len_mmap(file)?;
-        let len = unsafe { &mut *(len_mmap.deref().as_ptr() as *mut usize) };
-        *len = 0;
-        Ok(())
+    fn map_read_only(&self, file: &File) -> std::io::Result<Mmap> {
+        let mut options = MmapOptions::new();
+        options.offset(self.offset);
+        if let Some(len) = self.len {
+            options.len(len);
+        }
+        if self.populate {
+            options.populate();
+        }
+        let mmap = unsafe { options.map(file) }?;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.huge_pages && !mmap.is_empty() {
+            let _ = mmap.advise(memmap2::Advice::HugePage);
+        }
+        Ok(mmap)
+    }
+}
+
+impl core::fmt::Debug for MmapRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapRegion")
+            .field("options", &self.options)
+            .field("file", &self.file)
+            .finish()
     }
+}
 
-    pub fn from_file(file: File) -> std::io::Result<Self> {
-        let len_mmap = Self::_len_mmap(&file)?;
-        let len = unsafe { &mut *(len_mmap.deref().as_ptr() as *mut usize) };
+impl Deref for MmapRegion {
+    type Target = [u8];
 
-        let mut data_options = MmapOptions::new();
-        data_options.offset(Self::HEADER_LEN as u64);
+    fn deref(&self) -> &Self::Target {
+        // not through a slice of the whole mapping, which would overlap the prefix
+        unsafe { core::slice::from_raw_parts(self.mmap.as_ptr().add(self.prefix), self.len) }
+    }
+}
 
-        let mmap_file = MmapFile::new(file, len, data_options)?;
+impl DerefMut for MmapRegion {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            core::slice::from_raw_parts_mut(self.mmap.as_mut_ptr().add(self.prefix), self.len)
+        }
+    }
+}
+
+/// A file mapped from an offset to its end, or as configured by [`MapOptions`], whose length is
+/// kept by the caller.
+///
+/// It is [`Send`] and [`Sync`]: the mapping is owned, and the length is an exclusive borrow, so
+/// moving it to another thread moves the borrow along.
+pub struct MmapFile<'a> {
+    region: MmapRegion,
+    len: &'a mut usize,
+    /// Named in the errors, if the file was opened by path.
+    path: Option<PathBuf>,
+}
+
+impl<'a> MmapFile<'a> {
+    pub fn new(file: File, len: &'a mut usize, options: &MapOptions) -> std::io::Result<Self> {
+        let region = options.map(file)?;
         Ok(Self {
-            mmap_file,
-            len_mmap,
+            region,
+            len,
+            path: None,
         })
     }
 
-    fn _len_mmap(file: &File) -> std::io::Result<MmapMut> {
-        let mut len_options = MmapOptions::new();
-        len_options.len(Self::HEADER_LEN);
-        assert!(file.metadata()?.len() >= Self::HEADER_LEN as u64);
-        let len_mmap = unsafe { len_options.map_mut(file) }?;
-        {
-            // validation
-            let (prefix, body, suffix) = unsafe { len_mmap.deref().align_to::<u64>() };
-            assert_eq!(prefix.len(), 0);
-            assert_eq!(suffix.len(), 0);
-            assert_eq!(body.len(), 1);
+    /// Open the file at `path` for reading and writing and map it. Its errors, including those
+    /// of growing and shrinking it later, name the file.
+    pub fn open(
+        path: impl AsRef<Path>,
+        len: &'a mut usize,
+        options: &MapOptions,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let region = File::options()
+            .read(true)
+            .write(true)
+            .open(path)
+            .and_then(|file| options.map(file))
+            .map_err(|e| with_path(e, path))?;
+        Ok(Self {
+            region,
+            len,
+            path: Some(path.to_owned()),
+        })
+    }
+
+    /// The path the file was opened by.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn path_error(&self, e: std::io::Error) -> std::io::Error {
+        match &self.path {
+            Some(path) => with_path(e, path),
+            None => e,
         }
-        Ok(len_mmap)
     }
 
     pub fn into_file(self) -> File {
-        self.mmap_file.into_file()
+        self.region.into_file()
     }
 
     pub fn file(&self) -> &File {
-        self.mmap_file.file()
+        self.region.file()
+    }
+
+    /// Read the file length again after the file was resized through [`MmapFile::file`] or another
+    /// handle. The length is cached to avoid a system call on every reserve and shrink.
+    pub fn refresh_len(&mut self) -> std::io::Result<()> {
+        self.region.refresh_len()
+    }
+
+    /// The mapped capacity in bytes, which may exceed the requested one when page aligned.
+    pub fn capacity(&self) -> usize {
+        self.region.len()
+    }
+
+    /// Grow the file to multiples of the page size, so repeated small reservations neither
+    /// produce odd file lengths nor remap every time.
+    pub fn set_page_aligned(&mut self, page_aligned: bool) {
+        self.region.set_page_aligned(page_aligned);
+    }
+}
+
+impl<'a> core::fmt::Debug for MmapFile<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapFile")
+            .field("options", &self.region.options)
+            .field("len", &self.len)
+            .field("file", &self.region.file)
+            .field("path", &self.path)
+            .finish()
     }
 }
 
-impl<'a> core::ops::Deref for VecFile<'a> {
+impl<'a> core::ops::Deref for MmapFile<'a> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        self.mmap_file.deref()
+        self.region.deref()
     }
 }
 
-impl<'a> core::ops::DerefMut for VecFile<'a> {
+impl<'a> core::ops::DerefMut for MmapFile<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.mmap_file.deref_mut()
+        self.region.deref_mut()
     }
 }
 
-impl<'a> Memory for VecFile<'a>
+impl<'a> Memory for MmapFile<'a>
 where
     Self: Deref<Target = [u8]> + DerefMut<Target = [u8]>,
 {
     type Error = std::io::Error;
 
     fn as_ptr(&self) -> *const u8 {
-        self.mmap_file.as_ptr()
+        self.region.as_ptr()
     }
     fn as_mut_ptr(&mut self) -> *mut u8 {
-        self.mmap_file.as_mut_ptr()
+        self.region.as_mut_ptr()
     }
 
     fn len(&self) -> usize {
-        self.mmap_file.len()
+        *self.len
     }
 
-    fn len_mut(&mut self) -> &mut usize {
-        self.mmap_file.len_mut()
+    fn set_len(&mut self, len: usize) {
+        *self.len = len;
     }
 
     fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
-        self.mmap_file.reserve(capacity)
+        self.region
+            .reserve(capacity)
+            .map_err(|e| self.path_error(e))
     }
 
-    #[cfg(not(windows))]
     fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
-        self.mmap_file.shrink(capacity)
+        self.region.shrink(capacity).map_err(|e| self.path_error(e))
     }
 
-    #[cfg(windows)]
-    fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
-        self.len_mmap = MmapOptions::new().len(0).map_anon()?;
-        let shrink_result = self.mmap_file.shrink(capacity);
-        self.len_mmap = Self::_len_mmap(self.file()).expect("broken mmap");
-        let remapped_len = self.len_mmap.deref().as_ptr() as *mut usize;
-        self.mmap_file.len = unsafe { &mut *remapped_len };
-        shrink_result
+    /// Flushes only the data region; the length is owned by the caller.
+    fn sync(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        self.region
+            .flush_range(range)
+            .map_err(|e| self.path_error(e))
+    }
+}
+
+/// A file mapped read-only like an [`MmapFile`], holding `len` elements.
+///
+/// Requires only read access to the file. It is a [`ReadOnlyMemory`], so its vector, a
+/// [`ReadOnlyMemVec`], cannot be written to.
+pub struct ReadOnlyMmapFile {
+    mmap: Mmap,
+    file: File,
+    len: usize,
+}
+
+impl ReadOnlyMmapFile {
+    /// Map `file` as configured by `options`, except that growing and shrinking do not apply.
+    pub fn new(file: File, len: usize, options: &MapOptions) -> std::io::Result<Self> {
+        let mmap = options.map_read_only(&file)?;
+        Ok(Self { mmap, file, len })
+    }
+
+    /// Open the file at `path` for reading and map it, naming the file in the errors.
+    pub fn open(path: impl AsRef<Path>, len: usize, options: &MapOptions) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        File::open(path)
+            .and_then(|file| Self::new(file, len, options))
+            .map_err(|e| with_path(e, path))
+    }
+
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// The mapped length in bytes.
+    pub fn capacity(&self) -> usize {
+        self.mmap.len()
+    }
+
+    /// Create a read-only vector view of the elements.
+    /// # Safety
+    /// The mapping must hold `len` valid bytes representations of T.
+    pub unsafe fn try_into_memvec<T: Plain>(
+        self,
+    ) -> Result<ReadOnlyMemVec<T, Self>, (Self, MemoryConversionError)> {
+        ReadOnlyMemVec::try_from_memory(self)
+    }
+}
+
+impl core::fmt::Debug for ReadOnlyMmapFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOnlyMmapFile")
+            .field("len", &self.len)
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+impl Deref for ReadOnlyMmapFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.mmap
+    }
+}
+
+impl ReadOnlyMemory for ReadOnlyMmapFile {
+    fn len(&self) -> usize {
+        self.len
     }
 }
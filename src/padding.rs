@@ -0,0 +1,104 @@
+/// A type without padding bytes, whose stored bytes are fully determined by its value.
+///
+/// Padding bytes of a value are uninitialized, so storing a padded type leaks arbitrary bytes
+/// into the file and makes the checksums of equal data differ. Implement it for structs with
+/// [`crate::no_padding!`], which checks the layout at compile time. Types which cannot avoid
+/// padding can be stored with [`crate::MemVec::set_zero_padding`] instead.
+///
+/// # Safety
+/// `Self` must have no padding bytes.
+pub unsafe trait NoPadding: Copy {}
+
+macro_rules! impl_no_padding {
+    ($($ty:ty),*) => {
+        $(unsafe impl NoPadding for $ty {})*
+    };
+}
+
+impl_no_padding!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char
+);
+
+unsafe impl<T: NoPadding, const N: usize> NoPadding for [T; N] {}
+
+/// Define a struct and implement [`NoPadding`] for it after checking at compile time that every
+/// field type implements it and that the sizes of the fields add up to the size of the struct.
+///
+/// The check is made on the fields of the definition itself, so it cannot disagree with them.
+/// Generic structs are not supported; implement the trait by hand for them.
+///
+/// ```
+/// memvec::no_padding! {
+///     #[repr(C)]
+///     #[derive(Clone, Copy)]
+///     pub struct Event {
+///         pub time: u64,
+///         id: u32,
+///         kind: u32,
+///     }
+/// }
+///
+/// let mut vec = unsafe { memvec::HeapMemory::new().try_into_memvec::<Event>() }.unwrap();
+/// # use memvec::Memory;
+/// vec.push(Event { time: 1, id: 2, kind: 3 });
+/// assert_eq!(vec.as_bytes().len(), 16);
+/// ```
+///
+/// A struct with padding fails to compile:
+///
+/// ```compile_fail
+/// memvec::no_padding! {
+///     #[repr(C)]
+///     #[derive(Clone, Copy)]
+///     struct Event {
+///         id: u8,
+///         time: u64,
+///     }
+/// }
+/// ```
+///
+/// So does a struct with a field of a type with padding:
+///
+/// ```compile_fail
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct Padded {
+///     id: u8,
+///     time: u64,
+/// }
+///
+/// memvec::no_padding! {
+///     #[repr(C)]
+///     #[derive(Clone, Copy)]
+///     struct Event {
+///         inner: Padded,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! no_padding {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $field_ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: $field_ty),*
+        }
+
+        const _: () = {
+            fn assert_no_padding<T: $crate::NoPadding>() {}
+            let _ = || {
+                $(assert_no_padding::<$field_ty>();)*
+            };
+            assert!(
+                ::core::mem::size_of::<$name>() == 0 $(+ ::core::mem::size_of::<$field_ty>())*,
+                "type has padding bytes"
+            );
+        };
+        // the fields have no padding and fill the struct, so neither has it
+        unsafe impl $crate::NoPadding for $name {}
+    };
+}
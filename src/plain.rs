@@ -0,0 +1,18 @@
+/// A type whose values are plain bytes: they can be moved by copying their bytes and abandoned
+/// without being dropped.
+///
+/// [`crate::MemVec`] accepts any such type as its element. Every [`Copy`] type implements it;
+/// implement it for types which deliberately don't implement [`Copy`], e.g. a large array newtype
+/// whose copies should be explicit.
+///
+/// ```
+/// struct Block([u8; 4096]);
+/// unsafe impl memvec::Plain for Block {}
+/// ```
+///
+/// # Safety
+/// `Self` must have no drop glue and must not own resources, since elements left in memory are
+/// never dropped and their bytes may be read back as new values.
+pub unsafe trait Plain: Sized {}
+
+unsafe impl<T: Copy> Plain for T {}
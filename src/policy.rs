@@ -0,0 +1,84 @@
+/// Size of a memory page.
+pub(crate) fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        static PAGE_SIZE: std::sync::OnceLock<usize> = std::sync::OnceLock::new();
+        *PAGE_SIZE.get_or_init(|| unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize)
+    }
+    // every supported windows target uses 4 KiB pages
+    #[cfg(not(unix))]
+    {
+        4096
+    }
+}
+
+/// When and how far a [`crate::MemVec`] shrinks its memory on `shrink_to_fit` and `shrink_to`.
+///
+/// Shrinking a file-backed vector truncates and remaps the file, so workloads alternating pushes
+/// and shrinks may prefer to keep some slack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShrinkPolicy {
+    /// Shrink to the requested capacity.
+    #[default]
+    Exact,
+    /// Never shrink.
+    Never,
+    /// Shrink to the requested capacity only when it frees more than the given percentage of the
+    /// current capacity.
+    Slack(u32),
+    /// Shrink to the requested capacity rounded up to a multiple of the page size.
+    PageMultiple,
+}
+
+impl ShrinkPolicy {
+    /// The capacity in bytes to shrink `capacity` bytes of memory to when `requested` bytes are
+    /// requested, or `None` to keep it.
+    pub fn target(&self, capacity: usize, requested: usize) -> Option<usize> {
+        let target = match *self {
+            Self::Exact => requested,
+            Self::Never => return None,
+            Self::Slack(percent) => {
+                let slack = capacity.saturating_sub(requested) as u128;
+                if slack * 100 <= capacity as u128 * percent as u128 {
+                    return None;
+                }
+                requested
+            }
+            Self::PageMultiple => requested.next_multiple_of(page_size()),
+        };
+        (target < capacity).then_some(target)
+    }
+}
+
+/// How much a [`crate::MemVec`] grows its memory when a push or a reserve does not fit.
+///
+/// Doubling keeps pushes amortized O(1), but over-allocates large files by up to the size of the
+/// data; a smaller factor or fixed chunks bound the waste.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum GrowthPolicy {
+    /// Double the capacity.
+    #[default]
+    Double,
+    /// Multiply the capacity by the factor, which should be greater than 1.
+    Factor(f64),
+    /// Grow to a multiple of the given number of bytes.
+    Chunk(usize),
+    /// Compute the new capacity in bytes from the current and the required capacity in bytes.
+    Custom(fn(usize, usize) -> usize),
+}
+
+impl GrowthPolicy {
+    /// The capacity in bytes to grow `capacity` bytes of memory to when `required` bytes are
+    /// needed. Never less than `required`.
+    pub fn target(&self, capacity: usize, required: usize) -> usize {
+        let target = match *self {
+            Self::Double => capacity.saturating_mul(2),
+            Self::Factor(factor) => (capacity as f64 * factor) as usize,
+            Self::Chunk(chunk) => required
+                .checked_next_multiple_of(chunk.max(1))
+                .unwrap_or(required),
+            Self::Custom(f) => f(capacity, required),
+        };
+        core::cmp::max(target, required)
+    }
+}
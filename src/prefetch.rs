@@ -0,0 +1,218 @@
+use crate::{mem_vec::MemVec, plain::Plain, vec_file::VecFile};
+use std::{
+    fs::File,
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+};
+
+/// A background thread reading regions of files into the page cache ahead of a scan.
+///
+/// One prefetcher may serve many scans, see [`MemVec::scan_with`]; [`MemVec::scan`] starts one
+/// for a single scan. The thread reads each requested region with positioned reads into a scratch
+/// buffer, so the pages are resident by the time the scan faults them in, and stops when the
+/// prefetcher drops.
+#[derive(Debug)]
+pub struct Prefetcher {
+    sender: Option<mpsc::Sender<Request>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+struct Request {
+    file: Arc<File>,
+    offset: u64,
+    len: usize,
+}
+
+/// Size of the buffer the prefetch thread reads into.
+const SCRATCH_LEN: usize = 1 << 20;
+
+impl Prefetcher {
+    pub fn start() -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<Request>();
+        let thread = std::thread::Builder::new()
+            .name("memvec-prefetch".to_owned())
+            .spawn(move || {
+                let mut scratch = vec![0; SCRATCH_LEN];
+                for request in receiver {
+                    // a failed read only loses the prefetch; the scan faults the pages in itself
+                    let _ = read_region(&request, &mut scratch);
+                }
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    fn sender(&self) -> mpsc::Sender<Request> {
+        self.sender.clone().expect("prefetcher is running")
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        // the thread ends once every sender, including those of scans, is gone
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn read_region(request: &Request, scratch: &mut [u8]) -> std::io::Result<()> {
+    let mut done = 0;
+    while done < request.len {
+        let len = scratch.len().min(request.len - done);
+        let offset = request.offset + done as u64;
+        let read = read_at(&request.file, &mut scratch[..len], offset)?;
+        if read == 0 {
+            break;
+        }
+        done += read;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+/// No positioned reads without moving the cursor shared with other users of the file, e.g. on
+/// WASI; the regions are not read ahead.
+#[cfg(not(any(unix, windows)))]
+fn read_at(_file: &File, _buf: &mut [u8], _offset: u64) -> std::io::Result<usize> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// An iterator over the elements of a file-backed vector whose upcoming regions are read ahead by
+/// a [`Prefetcher`], see [`MemVec::scan`].
+///
+/// The elements are split into windows of about `window` bytes; while the scan is in one window,
+/// the following `ahead` windows are requested, so page-ins overlap with the iteration.
+pub struct Scan<'s, T: Plain> {
+    slice: &'s [T],
+    index: usize,
+    /// The elements before this index have been requested.
+    requested: usize,
+    window: usize,
+    ahead: usize,
+    file: Arc<File>,
+    data_offset: u64,
+    sender: mpsc::Sender<Request>,
+    // declared last so that the sender above drops before the prefetcher joins its thread
+    _prefetcher: Option<Prefetcher>,
+}
+
+impl<'s, T: Plain> Scan<'s, T> {
+    fn new(
+        vec: &'s MemVec<'_, T, VecFile>,
+        prefetcher: Option<Prefetcher>,
+        sender: mpsc::Sender<Request>,
+        window_bytes: usize,
+        ahead: usize,
+    ) -> std::io::Result<Self> {
+        let file = Arc::new(vec.as_mem().file().try_clone()?);
+        let mut scan = Self {
+            slice: vec.as_slice(),
+            index: 0,
+            requested: 0,
+            window: (window_bytes / core::mem::size_of::<T>().max(1)).max(1),
+            ahead: ahead.max(1),
+            file,
+            data_offset: vec.as_mem().data_file_offset(),
+            sender,
+            _prefetcher: prefetcher,
+        };
+        scan.request_ahead();
+        Ok(scan)
+    }
+
+    fn request_ahead(&mut self) {
+        let target = self
+            .index
+            .saturating_add(self.window.saturating_mul(self.ahead))
+            .min(self.slice.len());
+        while self.requested < target {
+            let end = (self.requested + self.window).min(self.slice.len());
+            let size = core::mem::size_of::<T>();
+            let request = Request {
+                file: self.file.clone(),
+                offset: self.data_offset + (self.requested * size) as u64,
+                len: (end - self.requested) * size,
+            };
+            if self.sender.send(request).is_err() {
+                // the prefetcher is gone; scan without it
+                self.requested = self.slice.len();
+                return;
+            }
+            self.requested = end;
+        }
+    }
+
+    /// The remaining elements.
+    pub fn as_slice(&self) -> &'s [T] {
+        &self.slice[self.index..]
+    }
+}
+
+impl<'s, T: Plain> Iterator for Scan<'s, T> {
+    type Item = &'s T;
+
+    fn next(&mut self) -> Option<&'s T> {
+        let item = self.slice.get(self.index)?;
+        if self.index.is_multiple_of(self.window) {
+            self.request_ahead();
+        }
+        self.index += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.slice.len() - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'s, T: Plain> ExactSizeIterator for Scan<'s, T> {}
+
+impl<'s, T: Plain> core::fmt::Debug for Scan<'s, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Scan")
+            .field("index", &self.index)
+            .field("len", &self.slice.len())
+            .field("requested", &self.requested)
+            .field("window", &self.window)
+            .field("ahead", &self.ahead)
+            .finish()
+    }
+}
+
+impl<'a, T: Plain> MemVec<'a, T, VecFile> {
+    /// Iterate over the elements while a new [`Prefetcher`] reads the next two windows of
+    /// `window_bytes` bytes ahead, for cold sequential scans of files larger than the page cache
+    /// holds.
+    pub fn scan(&self, window_bytes: usize) -> std::io::Result<Scan<'_, T>> {
+        let prefetcher = Prefetcher::start()?;
+        let sender = prefetcher.sender();
+        Scan::new(self, Some(prefetcher), sender, window_bytes, 2)
+    }
+
+    /// Iterate over the elements while `prefetcher` reads `ahead` windows of `window_bytes` bytes
+    /// ahead. See [`MemVec::scan`].
+    pub fn scan_with<'s>(
+        &'s self,
+        // borrowed for the scan, so it cannot join its thread while the scan still sends to it
+        prefetcher: &'s Prefetcher,
+        window_bytes: usize,
+        ahead: usize,
+    ) -> std::io::Result<Scan<'s, T>> {
+        Scan::new(self, None, prefetcher.sender(), window_bytes, ahead)
+    }
+}
@@ -0,0 +1,73 @@
+//! The vector and its policies, the memory traits, the backends and the error types.
+//!
+//! The traits are imported as well, so their methods such as [`Memory::try_into_memvec`] resolve
+//! without further imports:
+//!
+//! ```
+//! use memvec::prelude::*;
+//!
+//! let mut vec = unsafe { HeapMemory::new().try_into_memvec::<u32>() }.unwrap();
+//! vec.push(1);
+//! assert_eq!(vec.as_mem().len(), 1);
+//! ```
+
+pub use crate::{
+    BorrowedMemory, Columns, FileMemory, GrowthPolicy, HeapMemory, InvalidRecord, MemStringError,
+    MemVec, MemVecError, MemVecRef, Memory, MemoryConversionError, NoPadding, Plain,
+    ReadOnlyMemory, ShrinkPolicy, TempMemory,
+};
+#[cfg(feature = "mmap")]
+pub use crate::{
+    ChecksumMismatch, MapOptions, MmapFile, ReadOnlyMemVec, ReadOnlyMmapFile, ReadOnlyVecFile,
+    Segment, SegmentFile, VecFile, VecFileBuilder, WindowedVecFile,
+};
+
+#[cfg(test)]
+mod tests {
+    // nothing but the prelude, so a missing export or trait fails to compile
+    use crate::prelude::*;
+
+    #[test]
+    fn prelude_heap() {
+        let mut vec = unsafe { HeapMemory::new().try_into_memvec::<u32>() }.unwrap();
+        vec.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(Memory::len(vec.as_mem()), 3);
+        let err: MemoryConversionError = unsafe { vec.into_mem().try_into_memvec::<u64>() }
+            .map(drop)
+            .unwrap_err()
+            .1;
+        assert!(matches!(err, MemoryConversionError::SizeMismatch));
+
+        let mut vec = unsafe { HeapMemory::new().try_into_memvec::<u32>() }.unwrap();
+        vec.set_growth_policy(GrowthPolicy::Chunk(64));
+        vec.set_shrink_policy(ShrinkPolicy::Never);
+        vec.push(1);
+        assert_eq!(vec.capacity(), 16);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn prelude_vec_file() {
+        let mut path = std::env::temp_dir();
+        path.push("prelude.memvec");
+
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let vec_file = VecFileBuilder::new()
+                .create_new(true)
+                .open(&path)
+                .expect("create failed");
+            let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+            vec.push(7);
+        }
+        let vec_file: ReadOnlyVecFile = VecFile::open_read_only(&path).expect("open failed");
+        assert_eq!(ReadOnlyMemory::len(&vec_file), 1);
+        let vec: ReadOnlyMemVec<u64> = unsafe { vec_file.try_into_memvec() }.unwrap();
+        assert_eq!(vec[0], 7);
+        drop(vec);
+
+        std::fs::remove_file(path).expect("delete fail");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::marker::PhantomData;
+
+/// A first-in first-out queue of records over [`Memory`], e.g. a persistent work queue in a
+/// [`crate::VecFile`].
+///
+/// The memory starts with a queue header holding the index of the first live record and the end
+/// of the records, which follow it in order. Dequeuing only advances the head, leaving a dead
+/// prefix behind. Enqueuing slides the live records back to the start instead of growing the
+/// memory once the dead prefix is at least as long as them, so the records never overlap their
+/// old places and the header still covers the old ones until it is updated;
+/// [`MemQueue::compact`] does the same on demand and gives the freed memory back.
+pub struct MemQueue<T: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<T>,
+}
+
+#[repr(C)]
+struct QueueHeader {
+    magic: [u8; 8],
+    record_size: u64,
+    /// The index of the first live record.
+    head: u64,
+    /// The index past the last record.
+    tail: u64,
+    _reserved: [u8; 32],
+}
+
+const _: () = assert!(core::mem::size_of::<QueueHeader>() == QueueHeader::LEN);
+
+impl QueueHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECQU";
+}
+
+fn capacity_overflow() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+}
+
+fn check_header_align<T>(mem: &impl Memory) -> std::io::Result<()> {
+    check_align::<QueueHeader>(mem.as_ptr())
+        .and_then(|()| check_align::<T>(mem.as_ptr().wrapping_add(QueueHeader::LEN)))
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn bytes_for<T>(records: usize) -> Option<usize> {
+    records
+        .checked_mul(core::mem::size_of::<T>())
+        .and_then(|bytes| bytes.checked_add(QueueHeader::LEN))
+        .filter(|&bytes| bytes <= isize::MAX as usize)
+}
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> MemQueue<T, A> {
+    /// Create an empty queue in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no queue of
+    /// records of this size.
+    ///
+    /// # Safety
+    /// The records must hold valid bytes representations of T.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let record_size = core::mem::size_of::<T>() as u64;
+        if mem.len() == 0 {
+            mem.reserve(QueueHeader::LEN)?;
+            check_header_align::<T>(&mem)?;
+            mem.as_mut_ptr().cast::<QueueHeader>().write(QueueHeader {
+                magic: QueueHeader::MAGIC,
+                record_size: record_size.to_le(),
+                head: 0,
+                tail: 0,
+                _reserved: [0; 32],
+            });
+            mem.set_len(QueueHeader::LEN);
+            return Ok(Self {
+                mem,
+                _marker: PhantomData,
+            });
+        }
+        if mem.len() < QueueHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec queue"));
+        }
+        check_header_align::<T>(&mem)?;
+        let this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != QueueHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec queue"));
+        }
+        if u64::from_le(header.record_size) != record_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec queue has a different record size",
+            ));
+        }
+        let (head, tail) = (u64::from_le(header.head), u64::from_le(header.tail));
+        let bytes = usize::try_from(tail).ok().and_then(bytes_for::<T>);
+        if head > tail || bytes != Some(this.mem.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(this)
+    }
+
+    /// Append `value` to the back.
+    pub fn enqueue(&mut self, value: T) -> std::io::Result<()> {
+        let (head, len) = (self.head(), self.len());
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        let mut tail = head + len;
+        let mut bytes = bytes_for::<T>(tail + 1).ok_or_else(capacity_overflow)?;
+        if bytes > capacity && head >= len {
+            self.slide();
+            tail = len;
+            bytes = bytes_for::<T>(tail + 1).ok_or_else(capacity_overflow)?;
+        }
+        if bytes > capacity {
+            self.mem
+                .reserve(bytes.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        unsafe { self.records_mut().add(tail).write(value) };
+        self.mem.set_len(bytes);
+        self.header_mut().tail = ((tail + 1) as u64).to_le();
+        Ok(())
+    }
+
+    /// Slide the live records back to the start and shrink the memory to them.
+    ///
+    /// Unlike the compaction done by [`MemQueue::enqueue`], the live records may overlap their
+    /// old places, so a crash during it may leave them torn.
+    pub fn compact(&mut self) -> std::io::Result<()> {
+        if self.head() > 0 {
+            self.slide();
+        }
+        self.mem.shrink(self.mem.len())
+    }
+}
+
+impl<T: Plain, A: Memory> MemQueue<T, A> {
+    fn header(&self) -> &QueueHeader {
+        unsafe { &*self.mem.as_ptr().cast::<QueueHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut QueueHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<QueueHeader>() }
+    }
+
+    fn records(&self) -> *const T {
+        self.mem.as_ptr().wrapping_add(QueueHeader::LEN).cast()
+    }
+
+    fn records_mut(&mut self) -> *mut T {
+        self.mem.as_mut_ptr().wrapping_add(QueueHeader::LEN).cast()
+    }
+
+    /// The number of dequeued records before the live ones.
+    pub fn head(&self) -> usize {
+        u64::from_le(self.header().head) as usize
+    }
+
+    fn tail(&self) -> usize {
+        u64::from_le(self.header().tail) as usize
+    }
+
+    /// Move the live records to the start.
+    fn slide(&mut self) {
+        let (head, len) = (self.head(), self.len());
+        unsafe {
+            let records = self.records_mut();
+            core::ptr::copy(records.add(head), records, len);
+        }
+        let header = self.header_mut();
+        header.head = 0;
+        header.tail = (len as u64).to_le();
+        self.mem
+            .set_len(QueueHeader::LEN + len * core::mem::size_of::<T>());
+    }
+
+    pub fn len(&self) -> usize {
+        self.tail() - self.head()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of records the memory holds without growing, including the dequeued ones.
+    pub fn capacity(&self) -> usize {
+        let bytes = core::ops::Deref::deref(&self.mem).len() - QueueHeader::LEN;
+        bytes
+            .checked_div(core::mem::size_of::<T>())
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Remove and return the front value, or `None` if the queue is empty.
+    pub fn dequeue(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let head = self.head();
+        let value = unsafe { self.records().add(head).read() };
+        self.header_mut().head = ((head + 1) as u64).to_le();
+        Some(value)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
+    /// The live records in order.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.records().add(self.head()), self.len()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let (head, len) = (self.head(), self.len());
+        unsafe { core::slice::from_raw_parts_mut(self.records_mut().add(head), len) }
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Remove all values, keeping the memory.
+    pub fn clear(&mut self) {
+        let header = self.header_mut();
+        header.head = 0;
+        header.tail = 0;
+        self.mem.set_len(QueueHeader::LEN);
+    }
+
+    /// Write the records and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain + core::fmt::Debug, A: Memory> core::fmt::Debug for MemQueue<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
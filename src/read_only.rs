@@ -0,0 +1,357 @@
+use crate::{
+    error::with_path,
+    header::Header,
+    memory::{MemoryConversionError, ReadOnlyMemory},
+    plain::Plain,
+    vec_file::VecFile,
+};
+use core::{marker::PhantomData, mem::MaybeUninit, ops::Deref};
+use memmap2::{Mmap, MmapOptions};
+use std::{
+    fs::{File, TryLockError},
+    path::Path,
+};
+
+/// The single writer of a file, see [`ReadOnlyVecFile::writer_status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriterStatus {
+    /// No single writer holds the file.
+    Closed,
+    /// The process `pid` holds the file for writing.
+    Active { pid: u32 },
+    /// The process `pid` held the file for writing and exited without closing it.
+    Crashed { pid: u32 },
+}
+
+/// A [`VecFile`] mapped read-only.
+///
+/// Requires only read access to the file. Its [`Deref`] target is the data region.
+pub struct ReadOnlyVecFile {
+    mmap: Mmap,
+    file: File,
+    header_offset: u64,
+}
+
+impl core::fmt::Debug for ReadOnlyVecFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOnlyVecFile")
+            .field("file", &self.file)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl VecFile {
+    /// Open a vector file with read-only access.
+    ///
+    /// The file is validated like [`VecFile::from_file`], including the checksum.
+    pub fn open_read_only(path: impl AsRef<Path>) -> std::io::Result<ReadOnlyVecFile> {
+        let path = path.as_ref();
+        File::open(path)
+            .and_then(ReadOnlyVecFile::from_file)
+            .map_err(|e| with_path(e, path))
+    }
+}
+
+impl ReadOnlyVecFile {
+    pub fn from_file(file: File) -> std::io::Result<Self> {
+        Self::_from_file(file, 0)
+    }
+
+    /// [`ReadOnlyVecFile::from_file`] with the header at `header_offset`.
+    pub(crate) fn _from_file(file: File, header_offset: u64) -> std::io::Result<Self> {
+        if file.metadata()?.len() < header_offset + Header::LEN as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec header",
+            ));
+        }
+        let mmap = Self::map(&file, header_offset)?;
+        let this = Self {
+            mmap,
+            file,
+            header_offset,
+        };
+        let header = this.header();
+        header.validate()?;
+        if this.mmap.len() < header.data_offset() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec metadata region",
+            ));
+        }
+        if let Some(capacity) = header.capacity() {
+            if ((this.mmap.len() - header.data_offset()) as u64) < capacity {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "file is smaller than the recorded memvec capacity",
+                ));
+            }
+        }
+        let state = header.state();
+        if state.has_flag(Header::FLAG_CHECKSUM) && state.has_flag(Header::FLAG_CHECKSUM_VALID) {
+            header.verify_checksum(this.metadata(), &this)?;
+        }
+        Ok(this)
+    }
+
+    fn map(file: &File, header_offset: u64) -> std::io::Result<Mmap> {
+        let mmap = unsafe { MmapOptions::new().offset(header_offset).map(file) }?;
+        assert_eq!(
+            mmap.as_ptr().align_offset(core::mem::align_of::<Header>()),
+            0
+        );
+        Ok(mmap)
+    }
+
+    /// Map the file again to its current end, after a writer grew it.
+    pub(crate) fn remap(&mut self) -> std::io::Result<()> {
+        self.mmap = Self::map(&self.file, self.header_offset)?;
+        Ok(())
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.mmap.as_ptr().cast::<Header>()) }
+    }
+
+    /// The number of records as stored in the header.
+    ///
+    /// While a writer appends to the file, this is the length it published last, whose records
+    /// are visible to this reader.
+    pub fn len(&self) -> usize {
+        let header = self.header();
+        // validated to fit in usize on open
+        header.published_len().unwrap_or_else(|| header.state().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// See [`VecFile::generation`].
+    pub fn generation(&self) -> u64 {
+        self.header().state().generation
+    }
+
+    /// The state of the single writer of the file, see [`crate::VecFileBuilder::single_writer`].
+    ///
+    /// A writer which left its lock word set is alive if it still holds the lock of the file.
+    /// Checking it takes a shared lock for a moment, during which a writer cannot open the file.
+    pub fn writer_status(&self) -> std::io::Result<WriterStatus> {
+        let pid = self.header().writer();
+        if pid == 0 {
+            return Ok(WriterStatus::Closed);
+        }
+        match self.file.try_lock_shared() {
+            Ok(()) => {
+                self.file.unlock()?;
+                Ok(WriterStatus::Crashed { pid })
+            }
+            Err(TryLockError::WouldBlock) => Ok(WriterStatus::Active { pid }),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+
+    /// See [`VecFile::is_poisoned`].
+    pub fn is_poisoned(&self) -> bool {
+        self.header().state().has_flag(Header::FLAG_POISONED)
+    }
+
+    /// See [`VecFile::metadata`].
+    pub fn metadata(&self) -> &[u8] {
+        let header = self.header();
+        &self.mmap[header.metadata_offset()..][..header.metadata_len()]
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Create a read-only vector view of the records.
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn try_into_memvec<T: Plain>(
+        self,
+    ) -> Result<ReadOnlyMemVec<T>, (Self, MemoryConversionError)> {
+        ReadOnlyMemVec::try_from_file(self)
+    }
+}
+
+impl Deref for ReadOnlyVecFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        let header = self.header();
+        let data = &self.mmap[header.data_offset()..];
+        match header.capacity() {
+            // a writer may have grown the file past the mapping since it was validated on open
+            Some(capacity) => &data[..data.len().min(capacity as usize)],
+            None => data,
+        }
+    }
+}
+
+impl TryFrom<File> for ReadOnlyVecFile {
+    type Error = std::io::Error;
+
+    /// See [`ReadOnlyVecFile::from_file`].
+    fn try_from(file: File) -> std::io::Result<Self> {
+        Self::from_file(file)
+    }
+}
+
+/// Safe since any bytes are a valid [`bytemuck::Pod`].
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> TryFrom<ReadOnlyVecFile> for ReadOnlyMemVec<T> {
+    type Error = (ReadOnlyVecFile, MemoryConversionError);
+
+    fn try_from(file: ReadOnlyVecFile) -> Result<Self, Self::Error> {
+        unsafe { Self::try_from_file(file) }
+    }
+}
+
+impl<T: Plain> From<ReadOnlyMemVec<T>> for ReadOnlyVecFile {
+    fn from(vec: ReadOnlyMemVec<T>) -> Self {
+        vec.into_file()
+    }
+}
+
+impl ReadOnlyMemory for ReadOnlyVecFile {
+    fn len(&self) -> usize {
+        ReadOnlyVecFile::len(self)
+    }
+}
+
+/// A vector view of a [`ReadOnlyMemory`], by default a [`ReadOnlyVecFile`].
+///
+/// Unlike [`crate::MemVec`], it has no mutating methods at all, so writing to it fails to
+/// compile:
+///
+/// ```compile_fail
+/// fn clear(vec: &mut memvec::ReadOnlyMemVec<u64>) {
+///     vec[0] = 0;
+/// }
+/// ```
+pub struct ReadOnlyMemVec<T: Plain, M: ReadOnlyMemory = ReadOnlyVecFile> {
+    mem: M,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Plain, M: ReadOnlyMemory> ReadOnlyMemVec<T, M> {
+    /// # Safety
+    /// The memory must represent valid len and bytes representations of T.
+    pub unsafe fn try_from_memory(mem: M) -> Result<Self, (M, MemoryConversionError)> {
+        if let Err(e) = crate::memory::check_align::<T>(mem.as_ptr()) {
+            return Err((mem, e));
+        }
+        let (_prefix, body, _suffix) = mem.deref().align_to::<T>();
+        if mem.len() > body.len() {
+            return Err((mem, MemoryConversionError::SizeMismatch));
+        }
+        Ok(Self {
+            mem,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The elements, limited to the mapping made on open, which a writer may have outgrown.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            let (_prefix, body, _suffix) = self.mem.deref().align_to::<T>();
+            &body[..self.mem.len().min(body.len())]
+        }
+    }
+
+    pub fn as_mem(&self) -> &M {
+        &self.mem
+    }
+
+    pub fn into_mem(self) -> M {
+        self.mem
+    }
+}
+
+impl<T: Plain> ReadOnlyMemVec<T> {
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn try_from_file(
+        file: ReadOnlyVecFile,
+    ) -> Result<Self, (ReadOnlyVecFile, MemoryConversionError)> {
+        Self::try_from_memory(file)
+    }
+
+    /// A copy of the record at `index`, retried until no
+    /// [`MemVec::write_consistent`](crate::MemVec::write_consistent) of the writer overlapped it.
+    pub fn read_consistent(&self, index: usize) -> Option<T> {
+        let records = self.as_slice();
+        let record = records.get(index)?;
+        let value = self.mem.header().seqlock_read(|| unsafe {
+            // a torn copy may not be a valid T, so it stays uninit until it is known whole
+            core::ptr::read_volatile((record as *const T).cast::<MaybeUninit<T>>())
+        });
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Iterate over copies of the records made by [`ReadOnlyMemVec::read_consistent`].
+    ///
+    /// Each record is consistent on its own; records written between two steps are seen in their
+    /// new state.
+    pub fn iter_consistent(&self) -> ConsistentIter<'_, T> {
+        ConsistentIter {
+            vec: self,
+            index: 0,
+        }
+    }
+
+    pub fn as_file(&self) -> &ReadOnlyVecFile {
+        &self.mem
+    }
+
+    pub fn into_file(self) -> ReadOnlyVecFile {
+        self.mem
+    }
+}
+
+impl<T: Plain, M: ReadOnlyMemory> Deref for ReadOnlyMemVec<T, M> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T: core::fmt::Debug + Plain, M: ReadOnlyMemory> core::fmt::Debug for ReadOnlyMemVec<T, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Iterator returned by [`ReadOnlyMemVec::iter_consistent`].
+#[derive(Debug)]
+pub struct ConsistentIter<'a, T: Plain> {
+    vec: &'a ReadOnlyMemVec<T>,
+    index: usize,
+}
+
+impl<'a, T: Plain> Iterator for ConsistentIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let value = self.vec.read_consistent(self.index)?;
+        self.index += 1;
+        Some(value)
+    }
+}
+
+impl<'a, T: Plain, M: ReadOnlyMemory> IntoIterator for &'a ReadOnlyMemVec<T, M> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> core::slice::Iter<'a, T> {
+        self.iter()
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::{
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A fixed-capacity queue of one producer and one consumer, which may be different processes
+/// sharing the memory, e.g. mapping the same [`crate::VecFile`].
+///
+/// The memory starts with a ring header holding the head and tail indices, each on its own cache
+/// line; the slots follow it. The indices only ever increase, and the slot of an index is the
+/// index modulo the capacity. A pushed value is published by storing the tail with release
+/// ordering, and a popped slot is released by storing the head, so neither side ever locks.
+///
+/// Blocking operations poll the other side, spinning shortly and then yielding the thread, since
+/// there is no portable way to wait on memory shared between processes.
+pub struct RingBuffer<T: Plain, A: Memory> {
+    mem: A,
+    header: NonNull<RingHeader>,
+    slots: NonNull<T>,
+    capacity: u64,
+    _marker: PhantomData<T>,
+}
+
+// values are moved between the producer and the consumer, which are split off mutably
+unsafe impl<T: Plain + Send, A: Memory + Send> Send for RingBuffer<T, A> {}
+unsafe impl<T: Plain + Send, A: Memory + Sync> Sync for RingBuffer<T, A> {}
+
+#[repr(C)]
+struct RingHeader {
+    magic: [u8; 8],
+    capacity: u64,
+    record_size: u64,
+    _reserved: [u8; 40],
+    /// The index of the next slot to pop, written by the consumer.
+    head: AtomicU64,
+    _pad_head: [u8; 56],
+    /// The index of the next slot to push, written by the producer.
+    tail: AtomicU64,
+    _pad_tail: [u8; 56],
+}
+
+const _: () = assert!(core::mem::size_of::<RingHeader>() == RingHeader::LEN);
+
+impl RingHeader {
+    const LEN: usize = 192;
+    const MAGIC: [u8; 8] = *b"MEMVECRB";
+
+    fn head(&self) -> u64 {
+        u64::from_le(self.head.load(Ordering::Acquire))
+    }
+
+    fn tail(&self) -> u64 {
+        u64::from_le(self.tail.load(Ordering::Acquire))
+    }
+}
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> RingBuffer<T, A> {
+    /// Create a ring buffer of `capacity` slots in empty memory, or open the one the memory holds.
+    ///
+    /// The memory is reserved once and never resized afterwards. Opening fails with
+    /// [`std::io::ErrorKind::InvalidData`] if the stored ring differs in capacity or record size.
+    ///
+    /// # Safety
+    /// The slots must hold valid bytes representations of T, and at most one producer and one
+    /// consumer may use the ring at a time across all processes sharing the memory.
+    pub unsafe fn new(mut mem: A, capacity: usize) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if capacity == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "ring buffer capacity must not be zero",
+            ));
+        }
+        let bytes = capacity
+            .checked_mul(core::mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(RingHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "capacity overflow"))?;
+        let fresh = mem.len() == 0;
+        if !fresh && mem.len() != bytes {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        mem.reserve(bytes)?;
+        let base = mem.as_mut_ptr();
+        check_align::<RingHeader>(base)
+            .and_then(|()| check_align::<T>(base.wrapping_add(RingHeader::LEN)))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let header = NonNull::new(base.cast::<RingHeader>()).expect("mapped memory is not null");
+        let slots = header.cast::<u8>().add(RingHeader::LEN).cast::<T>();
+        let record_size = core::mem::size_of::<T>() as u64;
+        {
+            let header = header.as_ptr();
+            if fresh {
+                header.write(RingHeader {
+                    magic: RingHeader::MAGIC,
+                    capacity: (capacity as u64).to_le(),
+                    record_size: record_size.to_le(),
+                    _reserved: [0; 40],
+                    head: AtomicU64::new(0),
+                    _pad_head: [0; 56],
+                    tail: AtomicU64::new(0),
+                    _pad_tail: [0; 56],
+                });
+                mem.set_len(bytes);
+            } else if (*header).magic != RingHeader::MAGIC {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "not a memvec ring buffer",
+                ));
+            } else if u64::from_le((*header).capacity) != capacity as u64
+                || u64::from_le((*header).record_size) != record_size
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "memvec ring buffer has a different capacity or record size",
+                ));
+            }
+        }
+        Ok(Self {
+            mem,
+            header,
+            slots,
+            capacity: capacity as u64,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Plain, A: Memory> RingBuffer<T, A> {
+    fn header(&self) -> &RingHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// The number of queued values, which the other side may change at any time.
+    pub fn len(&self) -> usize {
+        let head = self.header().head();
+        let tail = self.header().tail();
+        tail.wrapping_sub(head).min(self.capacity) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Split the ring into its producer and consumer for use in two threads.
+    ///
+    /// A process which only pushes or only pops drops the other half.
+    pub fn split(&mut self) -> (RingProducer<'_, T, A>, RingConsumer<'_, T, A>) {
+        (RingProducer { ring: self }, RingConsumer { ring: self })
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain, A: Memory> core::fmt::Debug for RingBuffer<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RingBuffer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+/// The pushing half of a [`RingBuffer`].
+#[derive(Debug)]
+pub struct RingProducer<'r, T: Plain, A: Memory> {
+    ring: &'r RingBuffer<T, A>,
+}
+
+impl<'r, T: Plain, A: Memory> RingProducer<'r, T, A> {
+    /// Push `value`, or return it back if the ring is full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        let header = self.ring.header();
+        let tail = u64::from_le(header.tail.load(Ordering::Relaxed));
+        if tail.wrapping_sub(header.head()) >= self.ring.capacity {
+            return Err(value);
+        }
+        let slot = (tail % self.ring.capacity) as usize;
+        // SAFETY: the consumer released the slot before the head passed it
+        unsafe { self.ring.slots.as_ptr().add(slot).write(value) };
+        header
+            .tail
+            .store(tail.wrapping_add(1).to_le(), Ordering::Release);
+        Ok(())
+    }
+
+    /// Push `value`, waiting while the ring is full.
+    pub fn push(&mut self, mut value: T) {
+        let mut spins = 0;
+        loop {
+            match self.try_push(value) {
+                Ok(()) => return,
+                Err(v) => value = v,
+            }
+            backoff(&mut spins);
+        }
+    }
+}
+
+/// The popping half of a [`RingBuffer`].
+#[derive(Debug)]
+pub struct RingConsumer<'r, T: Plain, A: Memory> {
+    ring: &'r RingBuffer<T, A>,
+}
+
+impl<'r, T: Plain, A: Memory> RingConsumer<'r, T, A> {
+    /// Pop the oldest value, or `None` if the ring is empty.
+    pub fn try_pop(&mut self) -> Option<T> {
+        let header = self.ring.header();
+        let head = u64::from_le(header.head.load(Ordering::Relaxed));
+        if head == header.tail() {
+            return None;
+        }
+        let slot = (head % self.ring.capacity) as usize;
+        // SAFETY: the producer published the slot before the tail passed it
+        let value = unsafe { self.ring.slots.as_ptr().add(slot).read() };
+        header
+            .head
+            .store(head.wrapping_add(1).to_le(), Ordering::Release);
+        Some(value)
+    }
+
+    /// Pop the oldest value, waiting while the ring is empty.
+    pub fn pop(&mut self) -> T {
+        let mut spins = 0;
+        loop {
+            if let Some(value) = self.try_pop() {
+                return value;
+            }
+            backoff(&mut spins);
+        }
+    }
+}
+
+fn backoff(spins: &mut u32) {
+    if *spins < 64 {
+        *spins += 1;
+        core::hint::spin_loop();
+    } else {
+        std::thread::yield_now();
+    }
+}
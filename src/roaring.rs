@@ -0,0 +1,434 @@
+use crate::{
+    mem_vec::MemVec,
+    memory::{Memory, MemoryConversionError},
+};
+
+/// A compressed set of `u64` over two [`Memory`] regions in the style of a roaring bitmap, e.g. a
+/// membership set of record ids in two segments of one [`crate::SegmentFile`].
+///
+/// The values are grouped into containers by their upper 48 bits. The directory holds the
+/// containers sorted by key, and the heap holds their blocks of 16-bit words: a sorted array of
+/// the lower 16 bits of the values while a container holds up to 4096 of them, or a bitmap of
+/// all 65536 of them beyond that. Both take at most 8 KiB, so a container never needs more. An
+/// array growing out of its block moves to a new one of twice the size, leaving the old block
+/// unused; the unused blocks add up to less than the used ones.
+pub struct MemRoaringSet<'a, A: 'a + Memory> {
+    directory: MemVec<'a, Container, A>,
+    heap: MemVec<'a, u16, A>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Container {
+    /// The upper 48 bits of the values.
+    key: u64,
+    /// The offset of the block in the heap, in words.
+    offset: u64,
+    /// The length of the block in words.
+    capacity: u32,
+    /// The number of values, which are stored as a bitmap if it exceeds [`ARRAY_MAX`].
+    len: u32,
+}
+
+/// The most values a container stores as an array.
+const ARRAY_MAX: usize = 4096;
+/// The words of a bitmap, which is the largest block.
+const BITMAP_WORDS: usize = 4096;
+const MIN_CAPACITY: usize = 4;
+
+type Bitmap = [u16; BITMAP_WORDS];
+
+impl Container {
+    fn key(&self) -> u64 {
+        u64::from_le(self.key)
+    }
+
+    fn block(&self) -> core::ops::Range<usize> {
+        let offset = u64::from_le(self.offset) as usize;
+        offset..offset + u32::from_le(self.capacity) as usize
+    }
+
+    fn len(&self) -> usize {
+        u32::from_le(self.len) as usize
+    }
+
+    fn is_bitmap(&self) -> bool {
+        self.len() > ARRAY_MAX
+    }
+}
+
+fn split(value: u64) -> (u64, u16) {
+    (value >> 16, value as u16)
+}
+
+impl<'a, A: 'a + Memory<Error = std::io::Error>> MemRoaringSet<'a, A> {
+    /// Create an empty set in empty memories, or open the one they hold.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the directory is not sorted or
+    /// a container does not fit its block in the heap.
+    pub fn new(directory: A, heap: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        // any bytes are valid keys and words; the alignment and the length are checked
+        let directory = unsafe { MemVec::<Container, A>::try_from_memory(directory) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        let heap = unsafe { MemVec::<u16, A>::try_from_memory(heap) }
+            .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+        let sorted = directory.windows(2).all(|w| w[0].key() < w[1].key());
+        let valid = directory.iter().all(|c| {
+            let (offset, capacity) = (u64::from_le(c.offset), u32::from_le(c.capacity) as u64);
+            let fits = if c.is_bitmap() {
+                capacity == BITMAP_WORDS as u64 && c.len() <= 1 << 16
+            } else {
+                c.len() as u64 <= capacity && capacity <= BITMAP_WORDS as u64
+            };
+            fits && c.key() < 1 << 48 && offset + capacity <= heap.len() as u64
+        });
+        if !sorted || !valid {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        Ok(Self { directory, heap })
+    }
+
+    /// Append a zeroed block of `capacity` words to the heap, returning its offset.
+    fn alloc(&mut self, capacity: usize) -> std::io::Result<usize> {
+        let offset = self.heap.len();
+        self.heap.try_reserve(capacity)?;
+        self.heap.resize_with(offset + capacity, || 0);
+        Ok(offset)
+    }
+
+    /// Add the container of `key` at `index` with a block of `capacity` words.
+    fn insert_container(&mut self, index: usize, key: u64, capacity: usize) -> std::io::Result<()> {
+        self.directory.try_reserve(1)?;
+        let offset = self.alloc(capacity)?;
+        self.directory.insert(
+            index,
+            Container {
+                key: key.to_le(),
+                offset: (offset as u64).to_le(),
+                capacity: (capacity as u32).to_le(),
+                len: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Add `value`, returning whether it was not in the set.
+    pub fn insert(&mut self, value: u64) -> std::io::Result<bool> {
+        let (key, low) = split(value);
+        let index = match self.find(key) {
+            Ok(index) => index,
+            Err(index) => {
+                // an empty container, e.g. left by a crash, is harmless
+                self.insert_container(index, key, MIN_CAPACITY)?;
+                index
+            }
+        };
+        let container = self.directory[index];
+        if container.is_bitmap() {
+            let word = &mut self.heap[container.block()][low as usize / 16];
+            let mask = 1 << (low % 16);
+            if u16::from_le(*word) & mask != 0 {
+                return Ok(false);
+            }
+            *word = (u16::from_le(*word) | mask).to_le();
+            self.set_container_len(index, container.len() + 1);
+            return Ok(true);
+        }
+        let len = container.len();
+        let position = match self
+            .array(&container)
+            .binary_search_by_key(&low, |v| u16::from_le(*v))
+        {
+            Ok(_) => return Ok(false),
+            Err(position) => position,
+        };
+        if len == ARRAY_MAX {
+            let mut bitmap = self.load(index);
+            bitmap[low as usize / 16] |= 1 << (low % 16);
+            self.store(index, &bitmap, len + 1)?;
+            return Ok(true);
+        }
+        let mut block = container.block();
+        if len == block.len() {
+            let offset = self.alloc(block.len() * 2)?;
+            self.heap.copy_within(block.clone(), offset);
+            let container = &mut self.directory[index];
+            container.offset = (offset as u64).to_le();
+            container.capacity = ((block.len() * 2) as u32).to_le();
+            block = container.block();
+        }
+        let array = &mut self.heap[block][..len + 1];
+        array.copy_within(position..len, position + 1);
+        array[position] = low.to_le();
+        self.set_container_len(index, len + 1);
+        Ok(true)
+    }
+
+    /// Write `bitmap` holding `len` values to the container at `index`, as an array if they are
+    /// few enough, and remove the container if it is empty.
+    fn store(&mut self, index: usize, bitmap: &Bitmap, len: usize) -> std::io::Result<()> {
+        if len == 0 {
+            self.directory.remove(index);
+            return Ok(());
+        }
+        let capacity = if len > ARRAY_MAX {
+            BITMAP_WORDS
+        } else {
+            len.next_power_of_two().max(MIN_CAPACITY)
+        };
+        if self.directory[index].block().len() < capacity {
+            let offset = self.alloc(capacity)?;
+            let container = &mut self.directory[index];
+            container.offset = (offset as u64).to_le();
+            container.capacity = (capacity as u32).to_le();
+        }
+        let block = &mut self.heap[self.directory[index].block()];
+        if len > ARRAY_MAX {
+            for (word, &bits) in block.iter_mut().zip(bitmap) {
+                *word = bits.to_le();
+            }
+        } else {
+            for (word, value) in block.iter_mut().zip(bitmap_values(bitmap)) {
+                *word = value.to_le();
+            }
+        }
+        self.set_container_len(index, len);
+        Ok(())
+    }
+
+    /// Add the values of `other`.
+    pub fn union_with<B: Memory>(&mut self, other: &MemRoaringSet<'_, B>) -> std::io::Result<()> {
+        for (other_index, container) in other.directory.iter().enumerate() {
+            let index = match self.find(container.key()) {
+                Ok(index) => index,
+                Err(index) => {
+                    self.insert_container(index, container.key(), MIN_CAPACITY)?;
+                    index
+                }
+            };
+            let mut bitmap = self.load(index);
+            let theirs = other.load(other_index);
+            let mut len = 0;
+            for (word, bits) in bitmap.iter_mut().zip(theirs) {
+                *word |= bits;
+                len += word.count_ones() as usize;
+            }
+            self.store(index, &bitmap, len)?;
+        }
+        Ok(())
+    }
+
+    /// Keep only the values which are also in `other`.
+    pub fn intersect_with<B: Memory>(
+        &mut self,
+        other: &MemRoaringSet<'_, B>,
+    ) -> std::io::Result<()> {
+        // backwards, so that removing containers does not move the ones still to visit
+        for index in (0..self.directory.len()).rev() {
+            let Ok(other_index) = other.find(self.directory[index].key()) else {
+                self.directory.remove(index);
+                continue;
+            };
+            let mut bitmap = self.load(index);
+            let theirs = other.load(other_index);
+            let mut len = 0;
+            for (word, bits) in bitmap.iter_mut().zip(theirs) {
+                *word &= bits;
+                len += word.count_ones() as usize;
+            }
+            self.store(index, &bitmap, len)?;
+        }
+        Ok(())
+    }
+}
+
+/// The values of `bitmap` in ascending order.
+fn bitmap_values(bitmap: &Bitmap) -> impl Iterator<Item = u16> + '_ {
+    bitmap.iter().enumerate().flat_map(|(i, &bits)| {
+        let mut bits = bits;
+        core::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let bit = bits.trailing_zeros() as u16;
+            bits &= bits - 1;
+            Some(i as u16 * 16 + bit)
+        })
+    })
+}
+
+impl<'a, A: 'a + Memory> MemRoaringSet<'a, A> {
+    fn find(&self, key: u64) -> Result<usize, usize> {
+        self.directory.binary_search_by_key(&key, Container::key)
+    }
+
+    fn array(&self, container: &Container) -> &[u16] {
+        &self.heap[container.block()][..container.len()]
+    }
+
+    fn set_container_len(&mut self, index: usize, len: usize) {
+        self.directory[index].len = (len as u32).to_le();
+    }
+
+    /// The values of the container at `index` as a bitmap in native byte order.
+    fn load(&self, index: usize) -> Bitmap {
+        let container = &self.directory[index];
+        let mut bitmap = [0; BITMAP_WORDS];
+        if container.is_bitmap() {
+            for (bits, word) in bitmap.iter_mut().zip(&self.heap[container.block()]) {
+                *bits = u16::from_le(*word);
+            }
+        } else {
+            for &low in self.array(container) {
+                let low = u16::from_le(low);
+                bitmap[low as usize / 16] |= 1 << (low % 16);
+            }
+        }
+        bitmap
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        let (key, low) = split(value);
+        let Ok(index) = self.find(key) else {
+            return false;
+        };
+        let container = &self.directory[index];
+        if container.is_bitmap() {
+            let word = u16::from_le(self.heap[container.block()][low as usize / 16]);
+            word & 1 << (low % 16) != 0
+        } else {
+            self.array(container)
+                .binary_search_by_key(&low, |v| u16::from_le(*v))
+                .is_ok()
+        }
+    }
+
+    /// Remove `value`, returning whether it was in the set.
+    pub fn remove(&mut self, value: u64) -> bool {
+        let (key, low) = split(value);
+        let Ok(index) = self.find(key) else {
+            return false;
+        };
+        let container = self.directory[index];
+        let len = container.len();
+        if container.is_bitmap() {
+            let word = &mut self.heap[container.block()][low as usize / 16];
+            let mask = 1 << (low % 16);
+            if u16::from_le(*word) & mask == 0 {
+                return false;
+            }
+            *word = (u16::from_le(*word) & !mask).to_le();
+            if len - 1 == ARRAY_MAX {
+                // back to an array, which fits the block of the bitmap
+                let bitmap = self.load(index);
+                let block = &mut self.heap[container.block()];
+                for (word, value) in block.iter_mut().zip(bitmap_values(&bitmap)) {
+                    *word = value.to_le();
+                }
+            }
+            self.set_container_len(index, len - 1);
+            return true;
+        }
+        let Ok(position) = self
+            .array(&container)
+            .binary_search_by_key(&low, |v| u16::from_le(*v))
+        else {
+            return false;
+        };
+        if len == 1 {
+            self.directory.remove(index);
+        } else {
+            self.heap[container.block()][..len].copy_within(position + 1.., position);
+            self.set_container_len(index, len - 1);
+        }
+        true
+    }
+
+    /// The number of values.
+    pub fn len(&self) -> u64 {
+        self.directory.iter().map(|c| c.len() as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directory.iter().all(|c| c.len() == 0)
+    }
+
+    /// The values in ascending order.
+    pub fn iter(&self) -> RoaringIter<'_, 'a, A> {
+        RoaringIter {
+            set: self,
+            index: 0,
+            position: 0,
+        }
+    }
+
+    /// The length of the heap in bytes, including the unused blocks.
+    pub fn heap_bytes(&self) -> usize {
+        self.heap.len() * 2
+    }
+
+    pub fn clear(&mut self) {
+        self.directory.clear();
+        self.heap.clear();
+    }
+
+    /// Write the heap, then the directory durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.heap.flush()?;
+        self.directory.flush()
+    }
+
+    /// The memories of the directory and the heap.
+    pub fn into_memories(self) -> (A, A) {
+        (self.directory.into_mem(), self.heap.into_mem())
+    }
+}
+
+impl<'a, A: 'a + Memory> core::fmt::Debug for MemRoaringSet<'a, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the values of a [`MemRoaringSet`], see [`MemRoaringSet::iter`].
+#[derive(Debug)]
+pub struct RoaringIter<'s, 'a, A: 'a + Memory> {
+    set: &'s MemRoaringSet<'a, A>,
+    /// The container.
+    index: usize,
+    /// The position in the array or the bit in the bitmap of the container.
+    position: usize,
+}
+
+impl<'s, 'a, A: 'a + Memory> Iterator for RoaringIter<'s, 'a, A> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            let container = self.set.directory.get(self.index)?;
+            let high = container.key() << 16;
+            if container.is_bitmap() {
+                let block = &self.set.heap[container.block()];
+                while self.position < 1 << 16 {
+                    let word = u16::from_le(block[self.position / 16]) >> (self.position % 16);
+                    if word == 0 {
+                        self.position = (self.position / 16 + 1) * 16;
+                        continue;
+                    }
+                    let low = self.position + word.trailing_zeros() as usize;
+                    self.position = low + 1;
+                    return Some(high | low as u64);
+                }
+            } else if let Some(&low) = self.set.array(container).get(self.position) {
+                self.position += 1;
+                return Some(high | u16::from_le(low) as u64);
+            }
+            self.index += 1;
+            self.position = 0;
+        }
+    }
+}
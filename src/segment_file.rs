@@ -0,0 +1,402 @@
+use crate::memory::Memory;
+use core::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+};
+use memmap2::{MmapMut, MmapOptions};
+use std::{fs::File, path::Path, rc::Rc};
+
+/// A file holding several independently growable named segments.
+///
+/// Each segment is a [`Memory`] and can be turned into its own [`crate::MemVec`]. The file starts
+/// with a directory of [`SegmentFile::MAX_SEGMENTS`] entries; segment data follows it. A segment
+/// which cannot grow in place is moved to the end of the file, so space may be left unused
+/// between segments.
+///
+/// The file and its segments share state without locking, so none of them is [`Send`] or
+/// [`Sync`].
+pub struct SegmentFile {
+    inner: Rc<RefCell<Inner>>,
+}
+
+struct Inner {
+    file: File,
+    dir_mmap: MmapMut,
+    opened: [bool; SegmentFile::MAX_SEGMENTS],
+}
+
+#[repr(C)]
+struct Directory {
+    magic: [u8; 8],
+    version: u32,
+    byte_order: u32,
+    count: u32,
+    _reserved: [u8; 44],
+    entries: [Entry; SegmentFile::MAX_SEGMENTS],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Entry {
+    name: [u8; SegmentFile::MAX_NAME_LEN],
+    offset: u64,
+    capacity: u64,
+    len: u64,
+    _reserved: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<Directory>() == SegmentFile::DIRECTORY_LEN);
+
+impl Directory {
+    const MAGIC: [u8; 8] = *b"MEMVECSG";
+    const VERSION: u32 = 1;
+    const BYTE_ORDER_MARK: u32 = 0x0102_0304;
+
+    fn count(&self) -> usize {
+        u32::from_le(self.count) as usize
+    }
+
+    fn validate(&self, file_len: u64) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+        if self.magic != Self::MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a memvec segment file",
+            ));
+        }
+        if self.byte_order != Self::BYTE_ORDER_MARK {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec segment file was created on a machine with different byte order",
+            ));
+        }
+        if u32::from_le(self.version) != Self::VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "unsupported memvec segment file version",
+            ));
+        }
+        if self.count() > SegmentFile::MAX_SEGMENTS {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "broken memvec segment directory",
+            ));
+        }
+        for entry in &self.entries[..self.count()] {
+            // an empty segment is placed after the end of the file until it grows
+            let capacity = u64::from_le(entry.capacity);
+            let end = entry.offset().checked_add(capacity);
+            if entry.offset() < SegmentFile::DIRECTORY_LEN as u64
+                || (capacity > 0 && end.is_none_or(|end| end > file_len))
+                || usize::try_from(capacity).is_err()
+                || u64::from_le(entry.len) > capacity
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "memvec segment is out of the file",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn name(&self) -> &[u8] {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.name.len());
+        &self.name[..len]
+    }
+
+    fn offset(&self) -> u64 {
+        u64::from_le(self.offset)
+    }
+
+    fn capacity(&self) -> usize {
+        u64::from_le(self.capacity) as usize
+    }
+
+    fn len(&self) -> usize {
+        u64::from_le(self.len) as usize
+    }
+}
+
+impl Inner {
+    fn directory(&self) -> &Directory {
+        unsafe { &*(self.dir_mmap.as_ptr().cast::<Directory>()) }
+    }
+
+    fn directory_mut(&mut self) -> &mut Directory {
+        unsafe { &mut *(self.dir_mmap.as_mut_ptr().cast::<Directory>()) }
+    }
+
+    fn entry(&self, index: usize) -> &Entry {
+        &self.directory().entries[index]
+    }
+
+    fn entry_mut(&mut self, index: usize) -> &mut Entry {
+        &mut self.directory_mut().entries[index]
+    }
+
+    fn map(&self, index: usize) -> std::io::Result<MmapMut> {
+        let entry = self.entry(index);
+        self.map_extent(entry.offset(), entry.capacity())
+    }
+
+    fn map_extent(&self, offset: u64, len: usize) -> std::io::Result<MmapMut> {
+        unsafe {
+            MmapOptions::new()
+                .offset(offset)
+                .len(len)
+                .map_mut(&self.file)
+        }
+    }
+}
+
+impl SegmentFile {
+    pub const MAX_SEGMENTS: usize = 63;
+    pub const MAX_NAME_LEN: usize = 32;
+    const DIRECTORY_LEN: usize = 4096;
+    const SEGMENT_ALIGN: u64 = 64;
+
+    /// Create an empty segment file.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(path.as_ref())?;
+        file.set_len(Self::DIRECTORY_LEN as u64)?;
+        let mut dir_mmap = Self::_dir_mmap(&file)?;
+        {
+            let directory = unsafe { &mut *(dir_mmap.as_mut_ptr().cast::<Directory>()) };
+            directory.magic = Directory::MAGIC;
+            directory.version = Directory::VERSION.to_le();
+            directory.byte_order = Directory::BYTE_ORDER_MARK;
+        }
+        Ok(Self::from_parts(file, dir_mmap))
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::options().read(true).write(true).open(path.as_ref())?;
+        Self::from_file(file)
+    }
+
+    pub fn from_file(file: File) -> std::io::Result<Self> {
+        let file_len = file.metadata()?.len();
+        if file_len < Self::DIRECTORY_LEN as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec segment directory",
+            ));
+        }
+        let dir_mmap = Self::_dir_mmap(&file)?;
+        let this = Self::from_parts(file, dir_mmap);
+        this.inner.borrow().directory().validate(file_len)?;
+        Ok(this)
+    }
+
+    fn from_parts(file: File, dir_mmap: MmapMut) -> Self {
+        let inner = Inner {
+            file,
+            dir_mmap,
+            opened: [false; Self::MAX_SEGMENTS],
+        };
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+        }
+    }
+
+    fn _dir_mmap(file: &File) -> std::io::Result<MmapMut> {
+        let dir_mmap = unsafe { MmapOptions::new().len(Self::DIRECTORY_LEN).map_mut(file) }?;
+        assert_eq!(
+            dir_mmap
+                .as_ptr()
+                .align_offset(core::mem::align_of::<Directory>()),
+            0
+        );
+        Ok(dir_mmap)
+    }
+
+    /// Names of the segments in the order of creation.
+    pub fn segment_names(&self) -> Vec<String> {
+        let inner = self.inner.borrow();
+        let directory = inner.directory();
+        directory.entries[..directory.count()]
+            .iter()
+            .map(|entry| String::from_utf8_lossy(entry.name()).into_owned())
+            .collect()
+    }
+
+    /// Open the segment `name`, creating an empty one if it does not exist.
+    ///
+    /// A segment can be opened only once at a time; opening it again before the previous
+    /// [`Segment`] is dropped fails with [`std::io::ErrorKind::WouldBlock`].
+    pub fn segment(&self, name: &str) -> std::io::Result<Segment> {
+        use std::io::{Error, ErrorKind};
+        let mut inner = self.inner.borrow_mut();
+        let count = inner.directory().count();
+        let found = (0..count).find(|&i| inner.entry(i).name() == name.as_bytes());
+        let index = match found {
+            Some(index) => index,
+            None => {
+                if name.is_empty() || name.len() > Self::MAX_NAME_LEN || name.contains('\0') {
+                    return Err(Error::new(ErrorKind::InvalidInput, "invalid segment name"));
+                }
+                if count == Self::MAX_SEGMENTS {
+                    return Err(Error::other("too many segments"));
+                }
+                let offset = Self::end_offset(&inner.file)?;
+                let entry = inner.entry_mut(count);
+                entry.name = [0; Self::MAX_NAME_LEN];
+                entry.name[..name.len()].copy_from_slice(name.as_bytes());
+                entry.offset = offset.to_le();
+                entry.capacity = 0;
+                entry.len = 0;
+                inner.directory_mut().count = (count as u32 + 1).to_le();
+                count
+            }
+        };
+        if inner.opened[index] {
+            return Err(Error::new(ErrorKind::WouldBlock, "segment is already open"));
+        }
+        let mmap = inner.map(index)?;
+        inner.opened[index] = true;
+        Ok(Segment {
+            inner: self.inner.clone(),
+            index,
+            mmap,
+        })
+    }
+
+    fn end_offset(file: &File) -> std::io::Result<u64> {
+        Ok(file.metadata()?.len().next_multiple_of(Self::SEGMENT_ALIGN))
+    }
+}
+
+impl core::fmt::Debug for SegmentFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentFile")
+            .field("file", &self.inner.borrow().file)
+            .field("segments", &self.segment_names())
+            .finish()
+    }
+}
+
+/// A named segment of a [`SegmentFile`].
+pub struct Segment {
+    inner: Rc<RefCell<Inner>>,
+    index: usize,
+    mmap: MmapMut,
+}
+
+impl Segment {
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(self.inner.borrow().entry(self.index).name()).into_owned()
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        self.inner.borrow_mut().opened[self.index] = false;
+    }
+}
+
+impl core::fmt::Debug for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Segment")
+            .field("name", &self.name())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Deref for Segment {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.mmap.deref()
+    }
+}
+
+impl DerefMut for Segment {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.mmap.deref_mut()
+    }
+}
+
+impl Memory for Segment {
+    type Error = std::io::Error;
+
+    fn as_ptr(&self) -> *const u8 {
+        self.mmap.as_ptr()
+    }
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.mmap.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.borrow().entry(self.index).len()
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.inner.borrow_mut().entry_mut(self.index).len = (len as u64).to_le();
+    }
+
+    fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity <= self.mmap.len() {
+            return Ok(());
+        }
+        let mut inner = self.inner.borrow_mut();
+        let entry = *inner.entry(self.index);
+        let file_len = inner.file.metadata()?.len();
+        let in_place = entry.offset() + entry.capacity() as u64 == file_len;
+        let offset = if in_place {
+            entry.offset()
+        } else {
+            SegmentFile::end_offset(&inner.file)?
+        };
+        inner.file.set_len(offset + capacity as u64)?;
+        let mut mmap = inner.map_extent(offset, capacity)?;
+        if !in_place {
+            // move the data to the new extent; the old one is left unused
+            mmap[..entry.capacity()].copy_from_slice(&self.mmap);
+        }
+        // the directory points to the new extent only once it holds the data
+        let entry = inner.entry_mut(self.index);
+        entry.offset = offset.to_le();
+        entry.capacity = (capacity as u64).to_le();
+        self.mmap = mmap;
+        Ok(())
+    }
+
+    fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
+        if capacity >= self.mmap.len() {
+            return Ok(());
+        }
+        let mut inner = self.inner.borrow_mut();
+        let entry = *inner.entry(self.index);
+        let file_len = inner.file.metadata()?.len();
+        self.mmap = inner.map_extent(entry.offset(), capacity)?;
+        inner.entry_mut(self.index).capacity = (capacity as u64).to_le();
+        // files with mapped views cannot be truncated on windows
+        #[cfg(not(windows))]
+        if entry.offset() + entry.capacity() as u64 == file_len {
+            inner.file.set_len(entry.offset() + capacity as u64)?;
+        }
+        #[cfg(windows)]
+        let _ = (entry, file_len);
+        Ok(())
+    }
+
+    fn sync(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        if !range.is_empty() {
+            self.mmap.flush_range(range.start, range.len())?;
+        }
+        self.inner.borrow().dir_mmap.flush()
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{mem_vec::MemVec, plain::Plain, vec_file::VecFile};
+use std::{
+    collections::BTreeMap,
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
+};
+
+/// Records of a time series sharded by time into segment files, each a [`VecFile`], in one
+/// directory.
+///
+/// The time of a record is given by a key function. A segment holds the records of a bucket of
+/// `bucket_width` time units and is named after the start of its bucket, so that expired buckets
+/// are dropped by deleting their files. Within a segment, the records are kept in order of time
+/// for range queries; records of any bucket may be appended, but not before the last record of
+/// their bucket. The bucket width is stored in the metadata region of every segment, so a series
+/// is never reopened with buckets of another width.
+pub struct MemSeries<T: Plain, F> {
+    dir: PathBuf,
+    bucket_width: u64,
+    time: F,
+    segments: BTreeMap<u64, MemVec<'static, T, VecFile>>,
+}
+
+const EXTENSION: &str = "memvec";
+/// The metadata of a segment: a magic and the bucket width.
+const METADATA_LEN: usize = 16;
+const MAGIC: [u8; 8] = *b"MEMVECTS";
+
+impl<T: Plain, F: Fn(&T) -> u64> MemSeries<T, F> {
+    /// Open the series in `dir`, creating the directory if it does not exist.
+    ///
+    /// The segments in it are mapped as vectors of T, failing with
+    /// [`std::io::ErrorKind::InvalidData`] if one of them does not fit or was created with
+    /// another `bucket_width`.
+    ///
+    /// # Safety
+    /// The segment files must hold valid bytes representations of T.
+    pub unsafe fn open(dir: impl AsRef<Path>, bucket_width: u64, time: F) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        if bucket_width == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "empty time bucket"));
+        }
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let mut segments = BTreeMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != EXTENSION)
+            {
+                continue;
+            }
+            let Some(start) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            let vec_file = VecFile::open(&path)?;
+            let metadata = vec_file.metadata();
+            if metadata.len() != METADATA_LEN || metadata[..8] != MAGIC {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "not a memvec series segment",
+                ));
+            }
+            let width = u64::from_le_bytes(metadata[8..16].try_into().unwrap());
+            if width != bucket_width || start % bucket_width != 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "memvec series has a different bucket width",
+                ));
+            }
+            let vec = MemVec::try_from_memory(vec_file)
+                .map_err(|(_, e)| Error::new(ErrorKind::InvalidData, e))?;
+            segments.insert(start, vec);
+        }
+        Ok(Self {
+            dir,
+            bucket_width,
+            time,
+            segments,
+        })
+    }
+
+    fn segment_path(&self, start: u64) -> PathBuf {
+        self.dir.join(format!("{start:020}.{EXTENSION}"))
+    }
+
+    /// Append `value` to the segment of its time, creating the segment if needed.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if it is earlier than the last record of
+    /// the segment.
+    pub fn append(&mut self, value: T) -> std::io::Result<()> {
+        let time = (self.time)(&value);
+        let start = time - time % self.bucket_width;
+        if !self.segments.contains_key(&start) {
+            let mut vec_file =
+                VecFile::create_with_metadata(self.segment_path(start), METADATA_LEN)?;
+            let metadata = vec_file.metadata_mut();
+            metadata[..8].copy_from_slice(&MAGIC);
+            metadata[8..16].copy_from_slice(&self.bucket_width.to_le_bytes());
+            // an empty file fits any type
+            let vec = unsafe { MemVec::try_from_memory(vec_file) }
+                .map_err(|(_, e)| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            self.segments.insert(start, vec);
+        }
+        let segment = self.segments.get_mut(&start).expect("inserted above");
+        if segment.last().is_some_and(|last| (self.time)(last) > time) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "record earlier than the last one of its segment",
+            ));
+        }
+        segment.try_reserve(1)?;
+        segment.push(value);
+        Ok(())
+    }
+
+    /// The records with times in `range`, in order of time.
+    pub fn range(&self, range: impl RangeBounds<u64>) -> impl Iterator<Item = &T> + '_ {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => Bound::Included(start),
+            Bound::Excluded(&start) => Bound::Excluded(start),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => Bound::Included(end),
+            Bound::Excluded(&end) => Bound::Excluded(end),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        // the buckets which may overlap the range
+        let first = match start {
+            Bound::Included(time) | Bound::Excluded(time) => time - time % self.bucket_width,
+            Bound::Unbounded => 0,
+        };
+        let segments = match end {
+            Bound::Unbounded => self.segments.range(first..),
+            Bound::Included(time) | Bound::Excluded(time) => self.segments.range(first..=time),
+        };
+        segments.flat_map(move |(_, segment)| {
+            let from = segment.partition_point(|v| match start {
+                Bound::Included(start) => (self.time)(v) < start,
+                Bound::Excluded(start) => (self.time)(v) <= start,
+                Bound::Unbounded => false,
+            });
+            let to = segment.partition_point(|v| match end {
+                Bound::Included(end) => (self.time)(v) <= end,
+                Bound::Excluded(end) => (self.time)(v) < end,
+                Bound::Unbounded => true,
+            });
+            segment[from..to.max(from)].iter()
+        })
+    }
+
+    /// Delete the segments whose buckets end at or before `time`, returning how many.
+    pub fn drop_before(&mut self, time: u64) -> std::io::Result<usize> {
+        let expired: Vec<u64> = self
+            .segments
+            .keys()
+            .copied()
+            .take_while(|&start| start.saturating_add(self.bucket_width) <= time)
+            .collect();
+        for &start in &expired {
+            // unmapped before the file is deleted
+            drop(self.segments.remove(&start));
+            std::fs::remove_file(self.segment_path(start))?;
+        }
+        Ok(expired.len())
+    }
+}
+
+impl<T: Plain, F> MemSeries<T, F> {
+    /// The number of records in all segments.
+    pub fn len(&self) -> usize {
+        self.segments.values().map(|segment| segment.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.values().all(|segment| segment.is_empty())
+    }
+
+    /// The starts of the buckets of the segments, in ascending order.
+    pub fn buckets(&self) -> impl Iterator<Item = u64> + '_ {
+        self.segments.keys().copied()
+    }
+
+    /// The records of the segment of the bucket starting at `start`.
+    pub fn segment(&self, start: u64) -> Option<&[T]> {
+        self.segments.get(&start).map(|segment| &**segment)
+    }
+
+    pub fn bucket_width(&self) -> u64 {
+        self.bucket_width
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write all segments durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> std::io::Result<()> {
+        for segment in self.segments.values() {
+            segment.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Plain, F> core::fmt::Debug for MemSeries<T, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemSeries")
+            .field("dir", &self.dir)
+            .field("bucket_width", &self.bucket_width)
+            .field("buckets", &self.segments.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    memory::{check_align, Memory, MemoryConversionError},
+    plain::Plain,
+};
+use core::marker::PhantomData;
+
+/// A slab of records over [`Memory`], whose keys stay stable across removals and reopening, e.g.
+/// handles of an entity-component storage in a [`crate::VecFile`].
+///
+/// The memory starts with a slab header, followed by the slots. Each slot starts with a tag word
+/// telling whether it is occupied or else linking it into the list of vacant slots, whose head is
+/// in the header. [`MemSlab::insert`] reuses the most recently vacated slot before growing, so
+/// keys are reused after removal like those of a slab allocator.
+///
+/// It also serves as an object pool, handing out slots by [`MemSlab::acquire`] and taking them
+/// back by [`MemSlab::release`]. The keys are indices of slots which never move, so other
+/// persisted structures may store them.
+pub struct MemSlab<T: Plain, A: Memory> {
+    mem: A,
+    _marker: PhantomData<T>,
+}
+
+#[repr(C)]
+struct SlabHeader {
+    magic: [u8; 8],
+    record_size: u64,
+    /// The number of slots, occupied or vacant.
+    slots: u64,
+    /// The number of occupied slots.
+    len: u64,
+    /// The vacant slot inserted into next, plus one, or 0 if none is vacant.
+    vacant: u64,
+    _reserved: [u8; 24],
+}
+
+const _: () = assert!(core::mem::size_of::<SlabHeader>() == SlabHeader::LEN);
+
+impl SlabHeader {
+    const LEN: usize = 64;
+    const MAGIC: [u8; 8] = *b"MEMVECSL";
+}
+
+/// The tag of an occupied slot. A vacant one holds the next vacant slot plus one, or 0.
+const OCCUPIED: u64 = u64::MAX;
+
+impl<T: Plain, A: Memory<Error = std::io::Error>> MemSlab<T, A> {
+    /// Create an empty slab in empty memory, or open the one the memory holds.
+    ///
+    /// Opening fails with [`std::io::ErrorKind::InvalidData`] if the memory holds no slab of
+    /// records of this size, or its list of vacant slots starts past the slots or at an occupied
+    /// one.
+    ///
+    /// # Safety
+    /// Occupied slots must hold valid bytes representations of T.
+    pub unsafe fn new(mut mem: A) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let record_size = core::mem::size_of::<T>() as u64;
+        let fresh = mem.len() == 0;
+        if fresh {
+            mem.reserve(SlabHeader::LEN)?;
+        } else if mem.len() < SlabHeader::LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec slab"));
+        }
+        check_align::<SlabHeader>(mem.as_ptr())
+            .and_then(|()| check_align::<T>(mem.as_ptr().wrapping_add(SlabHeader::LEN)))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fresh {
+            mem.as_mut_ptr().cast::<SlabHeader>().write(SlabHeader {
+                magic: SlabHeader::MAGIC,
+                record_size: record_size.to_le(),
+                slots: 0,
+                len: 0,
+                vacant: 0,
+                _reserved: [0; 24],
+            });
+            mem.set_len(SlabHeader::LEN);
+        }
+        let this = Self {
+            mem,
+            _marker: PhantomData,
+        };
+        let header = this.header();
+        if header.magic != SlabHeader::MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a memvec slab"));
+        }
+        if u64::from_le(header.record_size) != record_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memvec slab has a different record size",
+            ));
+        }
+        let slots = u64::from_le(header.slots);
+        let bytes = slots
+            .checked_mul(Self::stride() as u64)
+            .and_then(|bytes| bytes.checked_add(SlabHeader::LEN as u64));
+        // the length is read from the memory too, so it must not exceed the mapped bytes
+        if bytes != Some(this.mem.len() as u64)
+            || this.mem.len() > core::ops::Deref::deref(&this.mem).len()
+            || this.len() > this.slots()
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                MemoryConversionError::SizeMismatch,
+            ));
+        }
+        this.vacant_head()?;
+        Ok(this)
+    }
+
+    /// Reserve memory for at least `additional` more slots.
+    pub fn reserve(&mut self, additional: usize) -> std::io::Result<()> {
+        let bytes = self
+            .slots()
+            .checked_add(additional)
+            .and_then(|slots| slots.checked_mul(Self::stride()))
+            .and_then(|bytes| bytes.checked_add(SlabHeader::LEN))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+            })?;
+        let capacity = core::ops::Deref::deref(&self.mem).len();
+        if bytes > capacity {
+            self.mem
+                .reserve(bytes.max(capacity.saturating_mul(2).min(isize::MAX as usize)))?;
+        }
+        Ok(())
+    }
+
+    /// Store `value` in a vacant slot, or in a new one if none is vacant, and return its key.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] if the list of vacant slots is broken.
+    pub fn insert(&mut self, value: T) -> std::io::Result<usize> {
+        let key = match self.vacant_head()? {
+            None => {
+                self.reserve(1)?;
+                let key = self.slots();
+                self.mem
+                    .set_len(SlabHeader::LEN + (key + 1) * Self::stride());
+                self.header_mut().slots = (key as u64 + 1).to_le();
+                key
+            }
+            Some(key) => {
+                let next = unsafe { self.tag_mut_ptr(key).read() };
+                self.header_mut().vacant = next;
+                key
+            }
+        };
+        unsafe {
+            self.value_mut_ptr(key).write(value);
+            self.tag_mut_ptr(key).write(OCCUPIED.to_le());
+        }
+        let len = self.len() as u64 + 1;
+        self.header_mut().len = len.to_le();
+        Ok(key)
+    }
+
+    /// Occupy a slot with the default value and return its key, like [`MemSlab::insert`].
+    pub fn acquire(&mut self) -> std::io::Result<usize>
+    where
+        T: Default,
+    {
+        self.insert(T::default())
+    }
+}
+
+impl<T: Plain, A: Memory> MemSlab<T, A> {
+    /// The distance of the slots, which start with a tag word and keep the alignment of T.
+    fn stride() -> usize {
+        let align = core::mem::align_of::<T>().max(8);
+        (8usize.next_multiple_of(core::mem::align_of::<T>()) + core::mem::size_of::<T>())
+            .next_multiple_of(align)
+    }
+
+    fn value_offset() -> usize {
+        8usize.next_multiple_of(core::mem::align_of::<T>())
+    }
+
+    fn header(&self) -> &SlabHeader {
+        unsafe { &*self.mem.as_ptr().cast::<SlabHeader>() }
+    }
+
+    fn header_mut(&mut self) -> &mut SlabHeader {
+        unsafe { &mut *self.mem.as_mut_ptr().cast::<SlabHeader>() }
+    }
+
+    fn slots(&self) -> usize {
+        u64::from_le(self.header().slots) as usize
+    }
+
+    fn slot_ptr(&self, key: usize) -> *const u8 {
+        self.mem
+            .as_ptr()
+            .wrapping_add(SlabHeader::LEN + key * Self::stride())
+    }
+
+    fn slot_mut_ptr(&mut self, key: usize) -> *mut u8 {
+        self.mem
+            .as_mut_ptr()
+            .wrapping_add(SlabHeader::LEN + key * Self::stride())
+    }
+
+    fn tag_mut_ptr(&mut self, key: usize) -> *mut u64 {
+        self.slot_mut_ptr(key).cast()
+    }
+
+    fn value_mut_ptr(&mut self, key: usize) -> *mut T {
+        self.slot_mut_ptr(key)
+            .wrapping_add(Self::value_offset())
+            .cast()
+    }
+
+    /// The vacant slot inserted into next, if any. The free list is read from the memory, so a
+    /// head past the slots or at an occupied one is refused rather than written through.
+    fn vacant_head(&self) -> std::io::Result<Option<usize>> {
+        let key = match u64::from_le(self.header().vacant) {
+            0 => return Ok(None),
+            vacant => vacant - 1,
+        };
+        if key >= self.slots() as u64
+            || unsafe { self.slot_ptr(key as usize).cast::<u64>().read() } == OCCUPIED
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "broken memvec slab free list",
+            ));
+        }
+        Ok(Some(key as usize))
+    }
+
+    fn is_occupied(&self, key: usize) -> bool {
+        key < self.slots() && unsafe { self.slot_ptr(key).cast::<u64>().read() } == OCCUPIED
+    }
+
+    /// The number of occupied slots.
+    pub fn len(&self) -> usize {
+        u64::from_le(self.header().len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        self.is_occupied(key)
+    }
+
+    pub fn get(&self, key: usize) -> Option<&T> {
+        if !self.is_occupied(key) {
+            return None;
+        }
+        Some(unsafe { &*self.slot_ptr(key).add(Self::value_offset()).cast::<T>() })
+    }
+
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        if !self.is_occupied(key) {
+            return None;
+        }
+        Some(unsafe { &mut *self.value_mut_ptr(key) })
+    }
+
+    /// Remove the value of `key` and make its slot vacant.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        if !self.is_occupied(key) {
+            return None;
+        }
+        let value = unsafe { self.value_mut_ptr(key).read() };
+        let vacant = self.header().vacant;
+        unsafe { self.tag_mut_ptr(key).write(vacant) };
+        // a corrupted length of 0 with an occupied slot stays 0
+        let len = (self.len() as u64).saturating_sub(1);
+        let header = self.header_mut();
+        header.vacant = (key as u64 + 1).to_le();
+        header.len = len.to_le();
+        Some(value)
+    }
+
+    /// Make the slot of `key` vacant for reuse, returning its value. The same as
+    /// [`MemSlab::remove`], as the counterpart of [`MemSlab::acquire`].
+    pub fn release(&mut self, key: usize) -> Option<T> {
+        self.remove(key)
+    }
+
+    /// The key the next insertion takes.
+    pub fn vacant_key(&self) -> usize {
+        match u64::from_le(self.header().vacant) {
+            0 => self.slots(),
+            vacant => vacant as usize - 1,
+        }
+    }
+
+    /// Remove all values and slots, keeping the reserved memory.
+    pub fn clear(&mut self) {
+        let header = self.header_mut();
+        header.slots = 0;
+        header.len = 0;
+        header.vacant = 0;
+        self.mem.set_len(SlabHeader::LEN);
+    }
+
+    /// The occupied slots with their keys, in ascending order of the keys.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        (0..self.slots()).filter_map(|key| self.get(key).map(|value| (key, value)))
+    }
+
+    /// Write the slots and the header durably. See [`Memory::sync`].
+    pub fn sync(&self) -> Result<(), A::Error> {
+        self.mem.sync(0..self.mem.len())
+    }
+
+    pub fn into_memory(self) -> A {
+        self.mem
+    }
+}
+
+impl<T: Plain + core::fmt::Debug, A: Memory> core::fmt::Debug for MemSlab<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
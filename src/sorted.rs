@@ -0,0 +1,151 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+use core::ops::{Bound, Deref, RangeBounds};
+
+/// A [`MemVec`] kept sorted by a key function, for lookups by binary search.
+///
+/// The order is not stored in the memory: it is checked or established when the wrapper is
+/// created, and kept by inserting every element at its place. The elements are only exposed as a
+/// shared slice, since changing them in place could break the order. Elements of equal keys keep
+/// their insertion order, unless [`SortedMemVec::set_dedup`] makes inserting replace them.
+pub struct SortedMemVec<'a, T: Plain, A: 'a + Memory, F> {
+    vec: MemVec<'a, T, A>,
+    key: F,
+    dedup: bool,
+}
+
+impl<'a, T: Plain, A: 'a + Memory, K: Ord, F: Fn(&T) -> K> SortedMemVec<'a, T, A, F> {
+    /// Wrap `vec` which is already sorted by `key`, or return it with the index of the first
+    /// element out of order.
+    pub fn from_vec(vec: MemVec<'a, T, A>, key: F) -> Result<Self, (MemVec<'a, T, A>, usize)> {
+        if let Some(index) = vec.windows(2).position(|w| key(&w[0]) > key(&w[1])) {
+            return Err((vec, index + 1));
+        }
+        Ok(Self {
+            vec,
+            key,
+            dedup: false,
+        })
+    }
+
+    /// Wrap `vec` after sorting it by `key`, keeping the order of elements of equal keys.
+    pub fn from_vec_sorting(mut vec: MemVec<'a, T, A>, key: F) -> Self {
+        vec.sort_by_key(&key);
+        Self {
+            vec,
+            key,
+            dedup: false,
+        }
+    }
+
+    /// Replace the element of an equal key on insert instead of adding another one.
+    ///
+    /// Elements of equal keys already in the vector are kept.
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+    }
+
+    /// The index of the first element of key `key`, or where it would be inserted.
+    pub fn search(&self, key: &K) -> Result<usize, usize> {
+        let index = self.vec.partition_point(|v| (self.key)(v) < *key);
+        match self.vec.get(index) {
+            Some(v) if (self.key)(v) == *key => Ok(index),
+            _ => Err(index),
+        }
+    }
+
+    pub fn get_by_key(&self, key: &K) -> Option<&T> {
+        self.search(key).ok().map(|index| &self.vec[index])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_ok()
+    }
+
+    /// Insert `value` at its place and return its index.
+    pub fn insert(&mut self, value: T) -> usize {
+        let key = (self.key)(&value);
+        if self.dedup {
+            if let Ok(index) = self.search(&key) {
+                self.vec[index] = value;
+                return index;
+            }
+        }
+        // after the elements of equal keys, to keep the insertion order
+        let index = self.vec.partition_point(|v| (self.key)(v) <= key);
+        self.vec.insert(index, value);
+        index
+    }
+
+    /// Remove the first element of key `key`.
+    pub fn remove_by_key(&mut self, key: &K) -> Option<T> {
+        let index = self.search(key).ok()?;
+        Some(self.vec.remove(index))
+    }
+
+    /// The elements with keys in `range`.
+    pub fn range(&self, range: impl RangeBounds<K>) -> &[T] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.vec.partition_point(|v| (self.key)(v) < *start),
+            Bound::Excluded(start) => self.vec.partition_point(|v| (self.key)(v) <= *start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.vec.partition_point(|v| (self.key)(v) <= *end),
+            Bound::Excluded(end) => self.vec.partition_point(|v| (self.key)(v) < *end),
+            Bound::Unbounded => self.vec.len(),
+        };
+        &self.vec[start..end.max(start)]
+    }
+}
+
+impl<'a, T: Plain, A: 'a + Memory, F> SortedMemVec<'a, T, A, F> {
+    pub fn remove(&mut self, index: usize) -> T {
+        self.vec.remove(index)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.vec.pop()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.vec.truncate(len)
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// Keep the elements for which `f` returns true.
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        self.vec.retain(f)
+    }
+
+    pub fn as_vec(&self) -> &MemVec<'a, T, A> {
+        &self.vec
+    }
+
+    pub fn into_vec(self) -> MemVec<'a, T, A> {
+        self.vec
+    }
+
+    /// Write the elements and the length durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.vec.flush()
+    }
+}
+
+impl<'a, T: Plain, A: 'a + Memory, F> Deref for SortedMemVec<'a, T, A, F> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.vec
+    }
+}
+
+impl<'a, T: core::fmt::Debug + Plain, A: 'a + Memory, F> core::fmt::Debug
+    for SortedMemVec<'a, T, A, F>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&*self.vec, f)
+    }
+}
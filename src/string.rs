@@ -0,0 +1,226 @@
+use crate::{
+    mem_vec::MemVec,
+    memory::{Memory, MemoryConversionError},
+};
+use core::ops::{Deref, DerefMut};
+
+/// A UTF-8 string backed by [`Memory`], like `String` is backed by `Vec<u8>`.
+///
+/// The bytes are validated once when the string is created from memory; every mutation keeps them
+/// valid.
+pub struct MemString<'a, A: Memory> {
+    vec: MemVec<'a, u8, A>,
+}
+
+/// The error of creating a [`MemString`] from memory.
+#[derive(Debug)]
+pub enum MemStringError {
+    Memory(MemoryConversionError),
+    Utf8(core::str::Utf8Error),
+}
+
+impl core::fmt::Display for MemStringError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Memory(e) => e.fmt(f),
+            Self::Utf8(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MemStringError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Memory(e) => Some(e),
+            Self::Utf8(e) => Some(e),
+        }
+    }
+}
+
+impl<'a, A: 'a + Memory> MemString<'a, A> {
+    /// Create a string over memory holding valid UTF-8.
+    pub fn from_memory(mem: A) -> Result<Self, (A, MemStringError)> {
+        // SAFETY: any bytes are a valid u8
+        let vec = unsafe { MemVec::try_from_memory(mem) }
+            .map_err(|(mem, e)| (mem, MemStringError::Memory(e)))?;
+        Self::from_vec(vec).map_err(|(vec, e)| (vec.into_mem(), MemStringError::Utf8(e)))
+    }
+
+    /// Create a string over a byte vector holding valid UTF-8.
+    pub fn from_vec(
+        vec: MemVec<'a, u8, A>,
+    ) -> Result<Self, (MemVec<'a, u8, A>, core::str::Utf8Error)> {
+        match core::str::from_utf8(&vec) {
+            Ok(_) => Ok(Self { vec }),
+            Err(e) => Err((vec, e)),
+        }
+    }
+
+    /// # Safety
+    /// The bytes must be valid UTF-8.
+    pub unsafe fn from_vec_unchecked(vec: MemVec<'a, u8, A>) -> Self {
+        Self { vec }
+    }
+
+    pub fn into_vec(self) -> MemVec<'a, u8, A> {
+        self.vec
+    }
+
+    pub fn as_vec(&self) -> &MemVec<'a, u8, A> {
+        &self.vec
+    }
+
+    /// # Safety
+    /// The bytes must be valid UTF-8 when the borrow ends.
+    pub unsafe fn as_mut_vec(&mut self) -> &mut MemVec<'a, u8, A> {
+        &mut self.vec
+    }
+
+    pub fn as_str(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(&self.vec) }
+    }
+
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { core::str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.vec
+    }
+
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional)
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), A::Error> {
+        self.vec.try_reserve(additional)
+    }
+
+    pub fn push_str(&mut self, string: &str) {
+        self.vec.reserve(string.len());
+        let len = self.vec.len();
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                string.as_ptr(),
+                self.vec.as_mut_ptr().add(len),
+                string.len(),
+            );
+            self.vec.set_len(len + string.len());
+        }
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        let len = self.len() - ch.len_utf8();
+        self.vec.truncate(len);
+        Some(ch)
+    }
+
+    /// Shorten the string to `new_len` bytes, doing nothing if it is not longer.
+    ///
+    /// # Panics
+    /// Panics if `new_len` does not lie on a char boundary.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(
+                self.as_str().is_char_boundary(new_len),
+                "new_len does not lie on a char boundary"
+            );
+            self.vec.truncate(new_len);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// Write the bytes and the length durably. See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.vec.flush()
+    }
+}
+
+impl<'a, A: 'a + Memory> Deref for MemString<'a, A> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a, A: 'a + Memory> DerefMut for MemString<'a, A> {
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<'a, A: 'a + Memory> AsRef<str> for MemString<'a, A> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a, A: 'a + Memory> AsRef<[u8]> for MemString<'a, A> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<'a, A: 'a + Memory> core::fmt::Write for MemString<'a, A> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<'a, 's, A: 'a + Memory> Extend<&'s str> for MemString<'a, A> {
+    fn extend<I: IntoIterator<Item = &'s str>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|s| self.push_str(s));
+    }
+}
+
+impl<'a, A: 'a + Memory> Extend<char> for MemString<'a, A> {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|ch| self.push(ch));
+    }
+}
+
+impl<'a, A: 'a + Memory> PartialEq<str> for MemString<'a, A> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a, 's, A: 'a + Memory> PartialEq<&'s str> for MemString<'a, A> {
+    fn eq(&self, other: &&'s str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'a, A: 'a + Memory> core::fmt::Display for MemString<'a, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<'a, A: 'a + Memory> core::fmt::Debug for MemString<'a, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
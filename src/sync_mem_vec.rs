@@ -0,0 +1,221 @@
+use crate::{mem_vec::MemVec, memory::Memory, plain::Plain};
+use core::ops::{Deref, DerefMut};
+use std::sync::{
+    Arc, LockResult, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+};
+
+/// A [`MemVec`] shared between threads behind a read-write lock.
+///
+/// Cloning it clones the handle, not the vector. Requires `T` and the memory to be [`Send`] and
+/// [`Sync`] to be shared. A panic while the vector is locked for writing poisons it as it does a
+/// [`RwLock`]: locking returns the guard inside a [`PoisonError`] until
+/// [`SyncMemVec::clear_poison`]. A [`crate::VecFile`] also records interrupted mutations itself,
+/// see [`crate::VecFile::is_poisoned`].
+///
+/// The guards are the views of the vector: a [`SyncReadGuard`] derefs to `[T]`, and a
+/// [`SyncWriteGuard`] is the handle for mutations, including those which remap the memory. Since a
+/// remap needs the write lock, no slice borrowed from a read guard can outlive the mapping it
+/// points to.
+#[doc(alias = "SharedMemVec")]
+pub struct SyncMemVec<'a, T: Plain, A: 'a + Memory> {
+    inner: Arc<RwLock<MemVec<'a, T, A>>>,
+}
+
+impl<'a, T: Plain, A: 'a + Memory> SyncMemVec<'a, T, A> {
+    pub fn new(vec: MemVec<'a, T, A>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(vec)),
+        }
+    }
+
+    /// Lock the vector for reading, blocking while a writer holds it.
+    ///
+    /// # Errors
+    /// Fails with the guard inside a [`PoisonError`] if a writer panicked, as
+    /// [`RwLock::read`] does.
+    pub fn read(&self) -> LockResult<SyncReadGuard<'_, 'a, T, A>> {
+        match self.inner.read() {
+            Ok(guard) => Ok(SyncReadGuard { guard }),
+            Err(err) => Err(PoisonError::new(SyncReadGuard {
+                guard: err.into_inner(),
+            })),
+        }
+    }
+
+    /// Lock the vector for writing, blocking while any other handle holds it.
+    ///
+    /// # Errors
+    /// Fails with the guard inside a [`PoisonError`] if a writer panicked, as
+    /// [`RwLock::write`] does.
+    pub fn write(&self) -> LockResult<SyncWriteGuard<'_, 'a, T, A>> {
+        match self.inner.write() {
+            Ok(guard) => Ok(SyncWriteGuard { guard }),
+            Err(err) => Err(PoisonError::new(SyncWriteGuard {
+                guard: err.into_inner(),
+            })),
+        }
+    }
+
+    /// Whether a writer panicked, see [`RwLock::is_poisoned`].
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    /// Accept the vector as a panicked writer left it, see [`RwLock::clear_poison`].
+    pub fn clear_poison(&self) {
+        self.inner.clear_poison()
+    }
+
+    /// Take the vector back if this is the last handle, whether or not it is poisoned.
+    pub fn try_unwrap(self) -> Result<MemVec<'a, T, A>, Self> {
+        Arc::try_unwrap(self.inner)
+            .map(|lock| lock.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|inner| Self { inner })
+    }
+}
+
+impl<'a, T: Plain, A: 'a + Memory> Clone for SyncMemVec<'a, T, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug for SyncMemVec<'a, T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // like `RwLock`, never block on a writer
+        match self.inner.try_read() {
+            Ok(vec) => core::fmt::Debug::fmt(&*vec, f),
+            Err(TryLockError::Poisoned(e)) => core::fmt::Debug::fmt(&*e.into_inner(), f),
+            Err(TryLockError::WouldBlock) => f.write_str("SyncMemVec { <locked> }"),
+        }
+    }
+}
+
+/// The elements of a [`SyncMemVec`] locked for reading.
+pub struct SyncReadGuard<'g, 'a, T: Plain, A: 'a + Memory> {
+    guard: RwLockReadGuard<'g, MemVec<'a, T, A>>,
+}
+
+impl<'g, 'a, T: Plain, A: 'a + Memory> Deref for SyncReadGuard<'g, 'a, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_slice()
+    }
+}
+
+impl<'g, 'a, T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug
+    for SyncReadGuard<'g, 'a, T, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A [`SyncMemVec`] locked for writing.
+///
+/// It derefs to the elements and offers the mutations of [`MemVec`] which change the length or
+/// the capacity, but not the memory itself; take the vector back with [`SyncMemVec::try_unwrap`]
+/// for that.
+pub struct SyncWriteGuard<'g, 'a, T: Plain, A: 'a + Memory> {
+    guard: RwLockWriteGuard<'g, MemVec<'a, T, A>>,
+}
+
+impl<'g, 'a, T: Plain, A: 'a + Memory> SyncWriteGuard<'g, 'a, T, A> {
+    pub fn capacity(&self) -> usize {
+        self.guard.capacity()
+    }
+
+    /// See [`MemVec::reserve`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.guard.reserve(additional)
+    }
+
+    /// See [`MemVec::try_reserve`].
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), A::Error> {
+        self.guard.try_reserve(additional)
+    }
+
+    /// See [`MemVec::reserve_exact`].
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.guard.reserve_exact(additional)
+    }
+
+    /// See [`MemVec::try_reserve_exact`].
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), A::Error> {
+        self.guard.try_reserve_exact(additional)
+    }
+
+    /// See [`MemVec::shrink_to_fit`].
+    pub fn shrink_to_fit(&mut self) {
+        self.guard.shrink_to_fit()
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.guard.push(value)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.guard.pop()
+    }
+
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.guard.extend_from_slice(other)
+    }
+
+    pub fn insert(&mut self, index: usize, element: T) {
+        self.guard.insert(index, element)
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        self.guard.remove(index)
+    }
+
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        self.guard.swap_remove(index)
+    }
+
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.guard.retain(f)
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.guard.truncate(len)
+    }
+
+    pub fn clear(&mut self) {
+        self.guard.clear()
+    }
+
+    /// See [`MemVec::flush`].
+    pub fn flush(&self) -> Result<(), A::Error> {
+        self.guard.flush()
+    }
+}
+
+impl<'g, 'a, T: Plain, A: 'a + Memory> Deref for SyncWriteGuard<'g, 'a, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_slice()
+    }
+}
+
+impl<'g, 'a, T: Plain, A: 'a + Memory> DerefMut for SyncWriteGuard<'g, 'a, T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.as_mut_slice()
+    }
+}
+
+impl<'g, 'a, T: core::fmt::Debug + Plain, A: Memory> core::fmt::Debug
+    for SyncWriteGuard<'g, 'a, T, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
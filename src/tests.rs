@@ -1,6 +1,7 @@
 use crate::*;
-use memmap2::MmapOptions;
-use std::{fs::File, io::Write};
+use std::{fs::File, io::Write, ops::Bound};
+
+// Tests mapping files are ignored under miri, which cannot execute mmap; see `heap_memory`.
 
 trait Record: Sized + Copy {
     fn new(id: usize) -> Self;
@@ -35,6 +36,7 @@ impl Record for Record41 {
 }
 
 #[test]
+#[cfg_attr(miri, ignore)]
 fn mmap_file() {
     let mut path = std::env::temp_dir();
     path.push("mmap.memvec");
@@ -49,14 +51,15 @@ fn mmap_file() {
         .read(true)
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&path)
         .expect("file failed");
     file.set_len(17).unwrap();
 
     let mut len: usize = 0;
-    let mut data_options = MmapOptions::new();
+    let mut data_options = MapOptions::new();
     data_options.offset(17); // random header
-    let mmap = MmapFile::new(file, &mut len, data_options.clone()).expect("mmap failed");
+    let mmap = MmapFile::new(file, &mut len, &data_options).expect("mmap failed");
 
     let mut vec = unsafe { mmap.try_into_memvec::<Record41>() }.unwrap();
     memvec_push10(&mut vec);
@@ -64,7 +67,7 @@ fn mmap_file() {
     let mut file = vec.into_mem().into_file();
     file.flush().expect("flush failed");
 
-    let mmap = MmapFile::new(file, &mut len, data_options).expect("mmap failed");
+    let mmap = MmapFile::new(file, &mut len, &data_options).expect("mmap failed");
     let mut vec = unsafe { mmap.try_into_memvec::<Record41>() }.unwrap();
     memvec_check10(&vec);
     vec.reserve(15);
@@ -75,6 +78,156 @@ fn mmap_file() {
 }
 
 #[test]
+#[cfg_attr(miri, ignore)]
+fn region_cached_file_len() {
+    use crate::mmap::MmapRegion;
+    use memmap2::MmapOptions;
+
+    let mut path = std::env::temp_dir();
+    path.push("region_file_len.bin");
+
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&path, [0; 16]).expect("write failed");
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .expect("open failed");
+    let mut region = MmapRegion::with_len(file, MmapOptions::new(), 0, 0, 16).expect("map failed");
+    region.reserve(32).expect("reserve failed");
+    region.shrink(24).expect("shrink failed");
+    assert_eq!(std::fs::metadata(&path).expect("stat failed").len(), 24);
+
+    // extended by other means, which is kept once the length is refreshed
+    region.file().set_len(40).expect("set_len failed");
+    region.refresh_len().expect("refresh failed");
+    region.reserve(32).expect("reserve failed");
+    assert_eq!(region.len(), 32);
+    assert_eq!(std::fs::metadata(&path).expect("stat failed").len(), 40);
+    drop(region);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn region_shrink_keeps_mapping() {
+    use crate::mmap::MmapRegion;
+    use memmap2::MmapOptions;
+
+    let mut path = std::env::temp_dir();
+    path.push("region_shrink.bin");
+
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&path, [7; 64]).expect("write failed");
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .expect("open failed");
+    let mut region = MmapRegion::new(file, MmapOptions::new()).expect("map failed");
+    let ptr = region.as_ptr();
+    // a file which cannot be truncated leaves the region as it was
+    let file = core::mem::replace(&mut region.file, File::open(&path).expect("open failed"));
+    region.shrink(16).expect_err("must fail to truncate");
+    assert_eq!(region.len(), 64);
+    region.file = file;
+
+    region.shrink(16).expect("shrink failed");
+    assert_eq!(region.as_ptr(), ptr);
+    assert_eq!(region[..], [7; 16]);
+    assert_eq!(std::fs::metadata(&path).expect("stat failed").len(), 16);
+    region.reserve(32).expect("reserve failed");
+    assert_eq!(region[..], [[7; 16], [0; 16]].concat());
+    drop(region);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mmap_file_align() {
+    let mut path = std::env::temp_dir();
+    path.push("mmap_align.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("file failed");
+    file.set_len(24).unwrap();
+
+    let mut len: usize = 0;
+    let mut data_options = MapOptions::new();
+    data_options.offset(17);
+    let mmap = MmapFile::new(file, &mut len, &data_options).expect("mmap failed");
+    // empty memory is rejected too, since pushing would write misaligned values
+    let result = unsafe { mmap.try_into_memvec::<u64>() };
+    let Err((mmap, err)) = result else {
+        panic!("misaligned memory accepted");
+    };
+    assert!(matches!(
+        err,
+        MemoryConversionError::AlignMismatch {
+            offset: 1,
+            align: 8
+        }
+    ));
+
+    let mut data_options = MapOptions::new();
+    data_options.offset(24); // padded header
+    let mmap = MmapFile::new(mmap.into_file(), &mut len, &data_options).expect("mmap failed");
+    let mut vec = unsafe { mmap.try_into_memvec::<u64>() }.unwrap();
+    vec.push(1);
+    assert_eq!(vec[0], 1);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn heap_memory() {
+    let mut vec = unsafe { HeapMemory::new().try_into_memvec::<Record41>() }.unwrap();
+    memvec_push10(&mut vec);
+    memvec_check10(&vec);
+    vec.reserve(15);
+    memvec_shrink10(&mut vec);
+
+    // runs on an anonymous file, or on the heap under miri
+    let mem = TempMemory::temp().expect("temp failed");
+    let mut vec = unsafe { MemVec::<u64, _>::try_from_memory(mem) }.unwrap();
+    assert_eq!(vec.as_ptr() as usize % 64, 0);
+    vec.resize_with(20, || 7);
+    vec.insert(0, 1);
+    vec.swap_remove(1);
+    vec.retain(|&x| x != 1);
+    vec.dedup();
+    assert_eq!(vec.as_slice(), &[7]);
+    vec.clear_and_release().unwrap();
+    assert_eq!(vec.capacity(), 0);
+}
+
+#[test]
+fn temp_memory() {
+    let mem = TempMemory::temp().expect("temp failed");
+    // under miri, the vector runs on the heap
+    #[cfg(miri)]
+    let _: &HeapMemory = &mem;
+    let mut vec = unsafe { MemVec::<u32, _>::try_from_memory(mem) }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3]);
+    vec.retain(|&x| x != 2);
+    assert_eq!(vec.as_slice(), &[1, 3]);
+    assert_eq!(vec.as_mem().len(), 2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
 fn memvec_file() {
     let mut path = std::env::temp_dir();
     path.push("memvec.memvec");
@@ -136,3 +289,4819 @@ fn memvec_shrink10<T: Record, A: Memory>(vec: &mut MemVec<T, A>) {
     vec.shrink_to_fit();
     assert_eq!(vec.capacity(), 10);
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_checksum() {
+    let mut path = std::env::temp_dir();
+    path.push("checksum.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut vec_file = VecFile::create(&path).expect("create failed");
+        vec_file.set_checksum(true);
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_push10(&mut vec);
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("checksum must match");
+        assert!(vec_file.has_checksum());
+        let vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_check10(&vec);
+    }
+
+    {
+        // flip a byte of the data region
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+    }
+
+    let err = VecFile::open(&path).expect_err("corruption must be detected");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // wrapped in the path of the file
+    let err = MemVecError::from(err);
+    let MemVecError::Corrupted(cause) = err.root() else {
+        panic!("checksum mismatch must be corruption");
+    };
+    assert!(cause.is::<ChecksumMismatch>());
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_checksum_mismatch_twice() {
+    let mut path = std::env::temp_dir();
+    path.push("checksum_twice.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut vec_file = VecFile::create(&path).expect("create failed");
+        vec_file.set_checksum(true);
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_push10(&mut vec);
+    }
+
+    {
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+    }
+
+    // the failed open must not store the checksum of the corrupted data
+    for _ in 0..2 {
+        let err = VecFile::open(&path).expect_err("corruption must be detected");
+        let err = MemVecError::from(err);
+        let MemVecError::Corrupted(cause) = err.root() else {
+            panic!("checksum mismatch must be corruption");
+        };
+        assert!(cause.is::<ChecksumMismatch>());
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_byte_order() {
+    let mut path = std::env::temp_dir();
+    path.push("byte_order.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    drop(VecFile::create(&path).expect("create failed"));
+    VecFile::open(&path).expect("same byte order must be accepted");
+
+    {
+        // pretend the file was written on a machine of the other endianness
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[12..16].reverse();
+        std::fs::write(&path, bytes).unwrap();
+    }
+
+    let err = VecFile::open(&path).expect_err("foreign byte order must be refused");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_upgrade() {
+    let mut path = std::env::temp_dir();
+    path.push("upgrade.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        // legacy format: native usize len in 8 bytes, followed by the records
+        let mut file = File::create(&path).unwrap();
+        let mut header = [0; 8];
+        header[..core::mem::size_of::<usize>()].copy_from_slice(&10usize.to_ne_bytes());
+        file.write_all(&header).unwrap();
+        for i in 0..10 {
+            let record = Record41::new(i);
+            let bytes: [u8; 41] = unsafe { core::mem::transmute(record) };
+            file.write_all(&bytes).unwrap();
+        }
+    }
+
+    VecFile::open(&path).expect_err("legacy format must be refused");
+    VecFile::upgrade(&path).expect("upgrade failed");
+    VecFile::upgrade(&path).expect("upgrading twice must be no-op");
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_metadata() {
+    let mut path = std::env::temp_dir();
+    path.push("metadata.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut vec_file = VecFile::create_with_metadata(&path, 5).expect("create failed");
+        assert_eq!(vec_file.metadata(), &[0; 5]);
+        vec_file.metadata_mut().copy_from_slice(b"v1.0\0");
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_push10(&mut vec);
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        assert_eq!(vec_file.metadata(), b"v1.0\0");
+        let vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file() {
+    let mut path = std::env::temp_dir();
+    path.push("segment.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let segment_file = SegmentFile::create(&path).expect("create failed");
+        let mut records = unsafe {
+            segment_file
+                .segment("records")
+                .unwrap()
+                .try_into_memvec::<Record41>()
+        }
+        .unwrap();
+        let mut ids = unsafe {
+            segment_file
+                .segment("ids")
+                .unwrap()
+                .try_into_memvec::<u64>()
+        }
+        .unwrap();
+        assert!(segment_file.segment("ids").is_err());
+        assert_eq!(records.capacity(), 0);
+        for i in 0..10 {
+            // interleaved growth moves the segments around
+            records.push(Record41::new(i));
+            ids.push(i as u64);
+        }
+        memvec_shrink10(&mut records);
+    }
+
+    {
+        let segment_file = SegmentFile::open(&path).expect("open failed");
+        assert_eq!(segment_file.segment_names(), ["records", "ids"]);
+        let records = unsafe {
+            segment_file
+                .segment("records")
+                .unwrap()
+                .try_into_memvec::<Record41>()
+        }
+        .unwrap();
+        let ids = unsafe {
+            segment_file
+                .segment("ids")
+                .unwrap()
+                .try_into_memvec::<u64>()
+        }
+        .unwrap();
+        memvec_check10(&records);
+        assert_eq!(ids.as_slice(), (0..10).collect::<Vec<u64>>());
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file_out_of_file() {
+    let mut path = std::env::temp_dir();
+    path.push("segment_out_of_file.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let mut segment = file.segment("a").expect("segment failed");
+        segment.reserve(100).expect("reserve failed");
+        segment.set_len(10);
+    }
+    let file_len = std::fs::metadata(&path).expect("stat failed").len();
+    {
+        // a directory entry reaching past the end of the file is refused
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .expect("open failed");
+        file.set_len(file_len - 1).expect("set_len failed");
+    }
+    let err = SegmentFile::open(&path).expect_err("segment is truncated");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    {
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .expect("open failed");
+        file.set_len(file_len).expect("set_len failed");
+    }
+    let file = SegmentFile::open(&path).expect("open failed");
+    assert_eq!(file.segment("a").expect("segment failed").len(), 10);
+    drop(file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file_move_keeps_data() {
+    let mut path = std::env::temp_dir();
+    path.push("segment_move.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let mut a = file.segment("a").expect("segment failed");
+        a.reserve(8).expect("reserve failed");
+        a[..8].copy_from_slice(b"segment!");
+        a.set_len(8);
+        let mut b = file.segment("b").expect("segment failed");
+        b.reserve(8).expect("reserve failed");
+        // `a` is no longer at the end of the file, so it moves
+        a.reserve(4096).expect("reserve failed");
+        assert_eq!(&a[..8], b"segment!");
+    }
+    let file = SegmentFile::open(&path).expect("open failed");
+    let a = file.segment("a").expect("segment failed");
+    assert_eq!(a.len(), 8);
+    assert_eq!(&a[..8], b"segment!");
+    drop(a);
+    drop(file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file_full_and_empty() {
+    let mut path = std::env::temp_dir();
+    path.push("segment_full.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        assert!(file.segment_names().is_empty());
+        let err = file.segment("").expect_err("empty name");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let long = "x".repeat(SegmentFile::MAX_NAME_LEN + 1);
+        let err = file.segment(&long).expect_err("long name");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let err = file.segment("a\0").expect_err("nul in name");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        let a = file.segment("a").expect("segment failed");
+        assert_eq!(a.len(), 0);
+        let err = file.segment("a").expect_err("segment is open");
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        drop(a);
+        for i in 1..SegmentFile::MAX_SEGMENTS {
+            file.segment(&i.to_string()).expect("segment failed");
+        }
+        file.segment("full").expect_err("too many segments");
+        file.segment("a").expect("segment failed");
+    }
+    let file = SegmentFile::open(&path).expect("open failed");
+    assert_eq!(file.segment_names().len(), SegmentFile::MAX_SEGMENTS);
+    assert_eq!(file.segment("a").expect("segment failed").len(), 0);
+    drop(file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file_corrupt() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut path = std::env::temp_dir();
+    path.push("segment_corrupt.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let corrupt = |offset: u64, bytes: &[u8]| {
+        {
+            let file = SegmentFile::create(&path).expect("create failed");
+            let mut segment = file.segment("a").expect("segment failed");
+            segment.reserve(100).expect("reserve failed");
+            segment.set_len(10);
+        }
+        let mut file = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .expect("open failed");
+        file.seek(SeekFrom::Start(offset)).expect("seek failed");
+        file.write_all(bytes).expect("write failed");
+        drop(file);
+        let err = SegmentFile::open(&path).expect_err("directory is corrupt");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    };
+    corrupt(0, b"X");
+    // more segments than the directory holds
+    corrupt(16, &(SegmentFile::MAX_SEGMENTS as u32 + 1).to_le_bytes());
+    // the first entry longer than its capacity
+    corrupt(112, &1000u64.to_le_bytes());
+    // the first entry inside the directory
+    corrupt(96, &0u64.to_le_bytes());
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn segment_file_interleaved_growth() {
+    let mut path = std::env::temp_dir();
+    path.push("segment_interleaved.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // segments growing at different rates keep moving past each other; none of them may
+    // ever overlap another
+    let check = |vecs: &[MemVec<u64, Segment>]| {
+        for (s, vec) in vecs.iter().enumerate() {
+            assert!(vec
+                .iter()
+                .enumerate()
+                .all(|(i, &v)| v == (i * 8 + s) as u64));
+        }
+    };
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let mut vecs: Vec<MemVec<u64, Segment>> = (0..8)
+            .map(|s| unsafe { file.segment(&s.to_string()).unwrap().try_into_memvec() }.unwrap())
+            .collect();
+        for round in 0..200 {
+            for (s, vec) in vecs.iter_mut().enumerate() {
+                for _ in 0..=(s * round % 5) {
+                    let i = vec.len();
+                    vec.push((i * 8 + s) as u64);
+                }
+            }
+            check(&vecs);
+        }
+        vecs[3].shrink_to_fit();
+        vecs[5].clear();
+        vecs[5].shrink_to_fit();
+        check(&vecs);
+    }
+    let file = SegmentFile::open(&path).expect("open failed");
+    let vecs: Vec<MemVec<u64, Segment>> = (0..8)
+        .map(|s| unsafe { file.segment(&s.to_string()).unwrap().try_into_memvec() }.unwrap())
+        .collect();
+    check(&vecs);
+    assert!(vecs[5].is_empty());
+    drop(vecs);
+    drop(file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_generation() {
+    let mut path = std::env::temp_dir();
+    path.push("generation.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let reader = VecFile::open(&path).expect("open failed");
+    let generation = reader.generation();
+    assert!(!reader.has_changed_since(generation));
+
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.push(1);
+    assert!(reader.has_changed_since(generation));
+    assert_eq!(reader.generation(), vec.as_mem().generation());
+
+    let generation = reader.generation();
+    vec[0] = 2;
+    assert!(!reader.has_changed_since(generation));
+
+    drop(vec);
+    drop(reader);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_torn_header() {
+    let mut path = std::env::temp_dir();
+    path.push("torn_header.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_push10(&mut vec);
+    }
+
+    let previous_len = {
+        // break the crc of the newer header slot as if its write was torn
+        let mut bytes = std::fs::read(&path).unwrap();
+        let seq = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let (newer, older) = if seq(48) > seq(88) {
+            (48, 88)
+        } else {
+            (88, 48)
+        };
+        let previous_len = u64::from_le_bytes(bytes[older + 8..older + 16].try_into().unwrap());
+        bytes[newer + 32] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        previous_len
+    };
+
+    {
+        let vec_file = VecFile::open(&path).expect("older header slot must be used");
+        assert_eq!(vec_file.len() as u64, previous_len);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_journal() {
+    let mut path = std::env::temp_dir();
+    path.push("journal.memvec");
+    let mut journal_path = path.clone().into_os_string();
+    journal_path.push(".journal");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&journal_path);
+
+    let stale = {
+        let mut vec_file = VecFile::create(&path).expect("create failed");
+        vec_file
+            .enable_journal(core::mem::size_of::<Record41>())
+            .expect("journal failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        // the state of the file on disk before the pushes reach it
+        let stale = std::fs::read(&path).unwrap();
+        memvec_push10(&mut vec);
+        // simulate a crash: nothing is synced or checkpointed
+        std::mem::forget(vec);
+        stale
+    };
+    std::fs::write(&path, stale).unwrap();
+    assert!(std::fs::metadata(&journal_path).unwrap().len() > 0);
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_check10(&vec);
+    }
+    assert_eq!(std::fs::metadata(&journal_path).unwrap().len(), 0);
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(journal_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_journal_wrong_record_size() {
+    let mut path = std::env::temp_dir();
+    path.push("journal_record_size.memvec");
+    let mut journal_path = path.clone().into_os_string();
+    journal_path.push(".journal");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&journal_path);
+
+    {
+        let mut vec_file = VecFile::create(&path).expect("create failed");
+        vec_file.reserve(64).expect("reserve failed");
+        // records larger than the region, whose lengths overflow in bytes
+        vec_file
+            .enable_journal(usize::MAX / 2)
+            .expect("journal failed");
+        vec_file.set_len(1);
+        vec_file.set_len(3);
+        assert_eq!(vec_file.len(), 3);
+        vec_file.checkpoint().expect("checkpoint failed");
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(journal_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_snapshot() {
+    let mut path = std::env::temp_dir();
+    path.push("snapshot_source.memvec");
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push("snapshot.memvec");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&snapshot_path);
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    vec_file.set_checksum(true);
+    let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+    memvec_push10(&mut vec);
+    vec.as_mem()
+        .snapshot_to(&snapshot_path)
+        .expect("snapshot failed");
+    vec.push(Record41::new(10));
+
+    {
+        let snapshot = VecFile::open(&snapshot_path).expect("open failed");
+        let snapshot = unsafe { snapshot.try_into_memvec::<Record41>() }.unwrap();
+        memvec_check10(&snapshot);
+    }
+    assert_eq!(vec.len(), 11);
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(snapshot_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_compact() {
+    let mut path = std::env::temp_dir();
+    path.push("compact.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+    memvec_push10(&mut vec);
+    for i in 10..100 {
+        vec.push(Record41::new(i));
+    }
+    vec.truncate(10);
+    assert!(vec.capacity() >= 100);
+    vec.compact().expect("compact failed");
+    assert_eq!(vec.capacity(), 10);
+    memvec_check10(&vec);
+    vec.push(Record41::new(10));
+    drop(vec);
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        assert_eq!(vec.pop().map(|r| r.validate(10)), Some(true));
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_read_only() {
+    let mut path = std::env::temp_dir();
+    path.push("read_only.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        memvec_push10(&mut vec);
+    }
+
+    let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&path, permissions.clone()).unwrap();
+
+    {
+        let vec_file = VecFile::open_read_only(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_into_memvec::<Record41>() }.unwrap();
+        assert_eq!(vec.len(), 10);
+        for (i, item) in vec.iter().enumerate() {
+            assert!(item.validate(i));
+        }
+    }
+
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    std::fs::set_permissions(&path, permissions).unwrap();
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_create_new() {
+    let mut path = std::env::temp_dir();
+    path.push("create_new.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create_new(&path).expect("create failed");
+    let err = VecFile::create_new(&path).expect_err("existing file must not be created");
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+    let mut initialized = false;
+    drop(
+        VecFile::open_or_create(&path, |_| {
+            initialized = true;
+            Ok(())
+        })
+        .expect("open failed"),
+    );
+    assert!(!initialized);
+
+    drop(vec_file);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_builder() {
+    let mut path = std::env::temp_dir();
+    path.push("builder.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let err = VecFile::builder()
+        .open(&path)
+        .expect_err("missing file must not be created");
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    let mut builder = VecFile::builder();
+    builder
+        .create(true)
+        .lock(true)
+        .metadata_len(16)
+        .checksum(true);
+    {
+        let vec_file = builder.open(&path).expect("create failed");
+        assert_eq!(vec_file.metadata().len(), 16);
+        assert!(vec_file.has_checksum());
+
+        let err = builder
+            .open(&path)
+            .expect_err("locked file must not be opened");
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let err = VecFile::builder()
+            .lock(true)
+            .open_read_only(&path)
+            .expect_err("locked file must not be shared");
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+    }
+    {
+        let vec_file = VecFile::builder()
+            .prefault(true)
+            .lock(true)
+            .open(&path)
+            .expect("open failed");
+        assert_eq!(vec_file.metadata().len(), 16);
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_prefix() {
+    let mut path = std::env::temp_dir();
+    path.push("prefix.memvec");
+    let legacy_header = b"LEGACY HEADER v1";
+
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&path, legacy_header).expect("write failed");
+
+    let mut builder = VecFile::builder();
+    builder.prefix_len(legacy_header.len() as u64);
+    {
+        let vec_file = builder
+            .clone()
+            .create(true)
+            .open(&path)
+            .expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+    }
+    assert!(std::fs::read(&path)
+        .expect("read failed")
+        .starts_with(legacy_header));
+    VecFile::open(&path).expect_err("header must not be at the beginning");
+    {
+        let vec_file = builder.open(&path).expect("open failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+    {
+        let vec_file = builder.open_read_only(&path).expect("open failed");
+        assert_eq!(vec_file.len(), 10);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_auto_flush() {
+    let mut path = std::env::temp_dir();
+    path.push("auto_flush.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let policy = AutoFlush {
+            interval: std::time::Duration::from_millis(10),
+            dirty_bytes: Some(core::mem::size_of::<Record41>() * 4),
+            record_size: core::mem::size_of::<Record41>(),
+        };
+        let vec_file = VecFile::builder()
+            .create(true)
+            .auto_flush(policy)
+            .open(&path)
+            .expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        vec.as_mem_mut().disable_auto_flush().expect("flush failed");
+        vec.as_mem_mut()
+            .enable_auto_flush(policy)
+            .expect("flusher start failed");
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_flush() {
+    let mut path = std::env::temp_dir();
+    path.push("flush.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    memvec_push10(&mut vec);
+    vec.flush().expect("flush failed");
+    vec.flush_range(2..=5).expect("flush failed");
+    vec.flush_range(10..).expect("flush failed");
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vec.flush_range(..11)));
+    assert!(result.is_err());
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_durable() {
+    let mut path = std::env::temp_dir();
+    path.push("durable.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::builder()
+            .create(true)
+            .durable(core::mem::size_of::<Record41>())
+            .open(&path)
+            .expect("create failed");
+        assert!(vec_file.is_durable());
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        let copy = VecFile::open_read_only(&path).expect("open failed");
+        assert_eq!(copy.len(), 10);
+        // a record size beyond the region syncs up to its end
+        vec.as_mem_mut().set_durable(Some(usize::MAX));
+        vec.push(Record41::new(10));
+        vec.flush().expect("flush failed");
+        assert_eq!(copy.len(), 11);
+        vec.pop();
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        assert!(!vec_file.is_durable());
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_poisoned() {
+    let mut path = std::env::temp_dir();
+    path.push("poisoned.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        vec.retain(|r| r.id != 3);
+        assert!(!vec.as_mem().is_poisoned());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vec.dedup_by(|a, _| if a.id == 5 { panic!() } else { false })
+        }));
+        assert!(result.is_err());
+        assert_eq!(vec.len(), 9);
+        assert!(vec.as_mem().is_poisoned());
+    }
+    {
+        let copy = VecFile::open_read_only(&path).expect("open failed");
+        assert!(copy.is_poisoned());
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        // a successful mutation does not hide the earlier interruption
+        vec.retain(|_| true);
+        assert!(vec.as_mem().is_poisoned());
+        vec.as_mem_mut().clear_poison();
+        assert!(!vec.as_mem().is_poisoned());
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_single_writer() {
+    use std::io::{Seek, SeekFrom};
+
+    let mut path = std::env::temp_dir();
+    path.push("single_writer.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let mut builder = VecFile::builder();
+    builder.create(true).single_writer(true);
+    {
+        let writer = builder.open(&path).expect("create failed");
+        assert!(!writer.previous_writer_crashed());
+        let err = builder.open(&path).expect_err("second writer opened");
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        let reader = builder.open_read_only(&path).expect("open failed");
+        let pid = std::process::id();
+        assert_eq!(
+            reader.writer_status().unwrap(),
+            WriterStatus::Active { pid }
+        );
+        drop(writer);
+        assert_eq!(reader.writer_status().unwrap(), WriterStatus::Closed);
+    }
+    {
+        // a lock word left by a writer which exited without closing the file
+        let mut file = File::options().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(32)).unwrap();
+        file.write_all(&12345u32.to_le_bytes()).unwrap();
+        let reader = builder.open_read_only(&path).expect("open failed");
+        assert_eq!(
+            reader.writer_status().unwrap(),
+            WriterStatus::Crashed { pid: 12345 }
+        );
+        let writer = builder.open(&path).expect("open failed");
+        assert!(writer.previous_writer_crashed());
+        drop(writer);
+        let writer = builder.open(&path).expect("open failed");
+        assert!(!writer.previous_writer_crashed());
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_recover_truncate() {
+    let mut path = std::env::temp_dir();
+    path.push("recover_truncate.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        vec.shrink_to_fit();
+        // corrupt the length
+        vec.as_mem_mut().set_len(1000);
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let result = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) };
+        let Err((mut vec_file, err)) = result else {
+            panic!("corrupted length must be refused");
+        };
+        assert!(matches!(err, MemoryConversionError::SizeMismatch));
+        assert_eq!(
+            vec_file.recover_truncate(core::mem::size_of::<Record41>()),
+            990
+        );
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_raw() {
+    let mut path = std::env::temp_dir();
+    path.push("raw.memvec");
+    let mut raw_path = std::env::temp_dir();
+    raw_path.push("raw.bin");
+    let record_size = core::mem::size_of::<Record41>();
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&raw_path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        vec.as_mem()
+            .export_raw(&raw_path, record_size)
+            .expect("export failed");
+    }
+    assert_eq!(
+        std::fs::metadata(&raw_path).expect("stat failed").len(),
+        10 * record_size as u64
+    );
+    {
+        let vec_file = VecFile::import_raw(&raw_path, &path, record_size).expect("import failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+    VecFile::import_raw(&raw_path, &path, record_size + 1).expect_err("partial record");
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(raw_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_adopt() {
+    let mut path = std::env::temp_dir();
+    path.push("adopt.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut raw = File::create(&path).expect("create failed");
+        for i in 0..10 {
+            let record = Record41::new(i);
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    &record as *const Record41 as *const u8,
+                    core::mem::size_of::<Record41>(),
+                )
+            };
+            raw.write_all(bytes).expect("write failed");
+        }
+    }
+    {
+        let vec_file =
+            VecFile::adopt(&path, core::mem::size_of::<Record41>()).expect("adopt failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+    VecFile::adopt(&path, core::mem::size_of::<Record41>()).expect_err("adopted twice");
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_shrink_policy() {
+    let mut path = std::env::temp_dir();
+    path.push("shrink_policy.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    memvec_push10(&mut vec);
+    vec.reserve_exact(10);
+    assert_eq!(vec.capacity(), 20);
+
+    vec.set_shrink_policy(ShrinkPolicy::Never);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 20);
+
+    vec.set_shrink_policy(ShrinkPolicy::Slack(60));
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 20);
+    vec.set_shrink_policy(ShrinkPolicy::Slack(30));
+    vec.shrink_to(12);
+    assert_eq!(vec.capacity(), 12);
+
+    vec.set_shrink_policy(ShrinkPolicy::PageMultiple);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 12);
+
+    vec.set_shrink_policy(ShrinkPolicy::Exact);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 10);
+    memvec_check10(&vec);
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_page_aligned() {
+    let mut path = std::env::temp_dir();
+    path.push("page_aligned.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::builder()
+        .create(true)
+        .page_aligned(true)
+        .open(&path)
+        .expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    memvec_push10(&mut vec);
+    let file_len = vec.as_mem().file().metadata().expect("stat failed").len();
+    assert_eq!(file_len % 4096, 0);
+    let capacity = vec.as_mem().capacity();
+    assert_eq!(vec.capacity(), capacity / core::mem::size_of::<Record41>());
+    assert!(vec.capacity() > 10);
+    memvec_check10(&vec);
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_growth_policy() {
+    let mut path = std::env::temp_dir();
+    path.push("growth_policy.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    let size = core::mem::size_of::<Record41>();
+
+    vec.set_growth_policy(GrowthPolicy::Chunk(size * 8));
+    memvec_push10(&mut vec);
+    assert_eq!(vec.capacity(), 16);
+
+    vec.set_growth_policy(GrowthPolicy::Factor(1.5));
+    vec.reserve(7);
+    assert_eq!(vec.capacity(), 24);
+
+    vec.set_growth_policy(GrowthPolicy::Custom(|capacity, _| capacity + 41 * 3));
+    vec.reserve(15);
+    assert_eq!(vec.capacity(), 27);
+    memvec_check10(&vec);
+    drop(vec);
+
+    // vectors over a file start with the policy of the file
+    let vec_file = VecFile::builder()
+        .create(true)
+        .truncate(true)
+        .growth_policy(GrowthPolicy::Chunk(size * 8))
+        .open(&path)
+        .expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    assert!(matches!(vec.growth_policy(), GrowthPolicy::Chunk(chunk) if chunk == size * 8));
+    memvec_push10(&mut vec);
+    assert_eq!(vec.capacity(), 16);
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_temp() {
+    let vec_file = VecFile::temp().expect("create failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    memvec_push10(&mut vec);
+    memvec_check10(&vec);
+    let err = vec
+        .as_mem_mut()
+        .enable_journal(core::mem::size_of::<Record41>())
+        .expect_err("anonymous file has no path");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_open_validated() {
+    let mut path = std::env::temp_dir();
+    path.push("open_validated.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        vec[6].id = 0;
+    }
+    {
+        let mut index = 0;
+        let result = unsafe {
+            VecFile::open_validated::<Record41>(&path, 1, |record| {
+                index += 1;
+                record.validate(index - 1)
+            })
+        };
+        let Err(err) = result else {
+            panic!("broken record must be found");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let err = err
+            .into_inner()
+            .unwrap()
+            .downcast::<InvalidRecord>()
+            .unwrap();
+        assert_eq!(err.index, 6);
+    }
+    {
+        let vec = unsafe { VecFile::open_validated::<Record41>(&path, 3, |record| record.a == 9) }
+            .expect("open failed");
+        assert_eq!(
+            vec.validate(5, |record| record.id < 5),
+            Err(InvalidRecord { index: 5 })
+        );
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_capacity() {
+    let mut path = std::env::temp_dir();
+    path.push("capacity.memvec");
+    let size = core::mem::size_of::<Record41>();
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+        vec.shrink_to_fit();
+    }
+    let file_len = std::fs::metadata(&path).expect("stat failed").len();
+    {
+        // preallocated space, or the space of an interrupted grow, is not capacity
+        let mut file = File::options()
+            .append(true)
+            .open(&path)
+            .expect("open failed");
+        file.write_all(b"trailer").expect("write failed");
+        file.set_len(file_len + 4096).expect("set_len failed");
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        assert_eq!(vec_file.capacity(), 10 * size);
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+        // but slack growing takes without extending the file
+        vec.try_reserve_exact(2).expect("reserve failed");
+        assert_eq!(vec.capacity(), 12);
+        assert_eq!(
+            std::fs::metadata(&path).expect("stat failed").len(),
+            file_len + 4096
+        );
+        memvec_check10(&vec);
+        vec.shrink_to_fit();
+    }
+    // shrinking releases the slack
+    assert_eq!(
+        std::fs::metadata(&path).expect("stat failed").len(),
+        file_len
+    );
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        assert_eq!(vec_file.capacity(), 10 * size);
+    }
+    assert_eq!(
+        VecFile::open_read_only(&path).expect("open failed").len(),
+        10
+    );
+    {
+        let file = File::options()
+            .write(true)
+            .open(&path)
+            .expect("open failed");
+        file.set_len(file_len - 1).expect("set_len failed");
+    }
+    let err = VecFile::open(&path).expect_err("file smaller than its capacity");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_windowed() {
+    let mut path = std::env::temp_dir();
+    path.push("windowed.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_push10(&mut vec);
+    }
+    {
+        let mut windowed =
+            unsafe { WindowedVecFile::<Record41>::open(&path, 3) }.expect("open failed");
+        assert_eq!(windowed.len(), 10);
+        for i in 0..10 {
+            assert!(windowed.get(i).expect("map failed").validate(i));
+        }
+        for i in 10..40 {
+            windowed.push(Record41::new(i)).expect("push failed");
+        }
+        let records = windowed.window(5..35).expect("map failed");
+        assert!(records.iter().enumerate().all(|(i, r)| r.validate(i + 5)));
+        windowed.set_max_windows(2);
+        assert_eq!(windowed.mapped_windows(), 1);
+        for _ in 0..3 {
+            assert!(windowed.get(1).expect("map failed").validate(1));
+            assert!(windowed.get(37).expect("map failed").validate(37));
+        }
+        assert_eq!(windowed.mapped_windows(), 2);
+        windowed.set(3, Record41::new(33)).expect("map failed");
+        windowed.flush().expect("flush failed");
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        assert_eq!(vec.len(), 40);
+        assert!(vec[3].validate(33));
+        assert!(vec[39].validate(39));
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn windowed_least_recently_used() {
+    let mut path = std::env::temp_dir();
+    path.push("windowed_lru.memvec");
+    let starts = |windowed: &WindowedVecFile<u64>| windowed.window_starts().collect::<Vec<_>>();
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec =
+            unsafe { MemVec::<u64, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+        vec.extend_from_slice(&(0..100).collect::<Vec<_>>());
+    }
+    let capacity = {
+        let mut windowed = unsafe { WindowedVecFile::<u64>::open(&path, 10) }.expect("open failed");
+        windowed.set_max_windows(2);
+        assert_eq!(windowed.get(5).expect("map failed"), 5);
+        assert_eq!(windowed.get(25).expect("map failed"), 25);
+        assert_eq!(starts(&windowed), [20, 0]);
+        // a hit makes the window the most recently used
+        windowed.set(7, 70).expect("map failed");
+        assert_eq!(starts(&windowed), [0, 20]);
+        // so the other one is unmapped for a new window
+        assert_eq!(windowed.get(45).expect("map failed"), 45);
+        assert_eq!(starts(&windowed), [40, 0]);
+        windowed.set(47, 470).expect("map failed");
+        // a range across windows maps a window of its own
+        assert_eq!(
+            windowed.window(38..42).expect("map failed"),
+            [38, 39, 40, 41]
+        );
+        assert_eq!(starts(&windowed), [30, 40]);
+        windowed.set_max_windows(1);
+        assert_eq!(starts(&windowed), [30]);
+        // growing past the capacity unmaps every window, and the push maps one again
+        let capacity = windowed.capacity();
+        for i in 100..=capacity {
+            windowed.push(i).expect("push failed");
+        }
+        assert!(windowed.capacity() > capacity);
+        assert_eq!(starts(&windowed), [capacity as usize / 10 * 10]);
+        windowed.flush().expect("flush failed");
+        capacity
+    };
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec =
+        unsafe { MemVec::<u64, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    // records written through unmapped windows are kept
+    assert_eq!(vec[7], 70);
+    assert_eq!(vec[47], 470);
+    assert_eq!(vec.len() as u64, capacity + 1);
+    assert!(vec[100..].iter().copied().eq(100..vec.len() as u64));
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_zero_grown() {
+    let mut path = std::env::temp_dir();
+    path.push("zero_grown.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    drop(VecFile::create(&path).expect("create failed"));
+    for zero_grown in [false, true] {
+        let mut vec_file = VecFile::open(&path).expect("open failed");
+        {
+            // garbage appended after the data region becomes capacity without extending the file
+            let mut file = File::options()
+                .append(true)
+                .open(&path)
+                .expect("open failed");
+            file.write_all(&[0xff; 41 * 8]).expect("write failed");
+        }
+        vec_file.refresh_len().expect("refresh failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        vec.set_zero_grown(zero_grown);
+        vec.reserve_exact(8);
+        let spare = vec.spare_capacity_mut();
+        let bytes =
+            unsafe { core::slice::from_raw_parts(spare.as_ptr() as *const u8, spare.len() * 41) };
+        assert_eq!(bytes.iter().all(|&b| b == 0), zero_grown);
+        vec.shrink_to_fit();
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_zero_padding() {
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Padded {
+        tag: u8,
+        value: u32,
+        flag: u8,
+    }
+    const PADDING: &[core::ops::Range<usize>] = &[1..4, 9..12];
+
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec =
+        unsafe { MemVec::<Padded, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    vec.set_zero_padding(PADDING);
+    vec.reserve_exact(4);
+    // dirty the spare capacity to make the padding of new elements observable
+    unsafe { core::ptr::write_bytes(vec.as_mut_ptr(), 0xff, 4) };
+    vec.push(Padded {
+        tag: 1,
+        value: 2,
+        flag: 0,
+    });
+    vec.insert(
+        0,
+        Padded {
+            tag: 3,
+            value: 4,
+            flag: 1,
+        },
+    );
+    vec.resize_with(4, || Padded {
+        tag: 5,
+        value: 6,
+        flag: 0,
+    });
+    let bytes = unsafe { core::slice::from_raw_parts(vec.as_ptr() as *const u8, 4 * 12) };
+    for record in bytes.chunks(12) {
+        assert_eq!(&record[1..4], &[0, 0, 0]);
+        assert_eq!(&record[9..12], &[0, 0, 0]);
+    }
+    assert_eq!(vec[0].tag, 3);
+    assert_eq!(vec[3].value, 6);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_capacity_overflow() {
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec = unsafe { MemVec::<[u8; 1 << 20], _>::try_from_memory(vec_file) }
+        .expect("vec file is corrupted");
+    for additional in [
+        usize::MAX,
+        (isize::MAX as usize >> 20) + 1,
+        usize::MAX >> 20,
+    ] {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vec.try_reserve(additional).ok();
+        }));
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vec.try_reserve_exact(additional).ok();
+        }));
+        assert!(result.is_err());
+    }
+    assert_eq!(vec.capacity(), 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_plain() {
+    // deliberately not Copy
+    #[derive(Debug, PartialEq)]
+    struct Block([u8; 16]);
+    unsafe impl Plain for Block {}
+
+    let mut path = std::env::temp_dir();
+    path.push("plain.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Block, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        for i in 0..4 {
+            vec.push(Block([i; 16]));
+        }
+        assert_eq!(vec.pop(), Some(Block([3; 16])));
+        vec.retain(|b| b.0[0] != 1);
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { MemVec::<Block, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        assert_eq!(vec.as_slice(), &[Block([0; 16]), Block([2; 16])]);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_frozen() {
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    {
+        let mut frozen = vec.reserve_exact_then_freeze(10).expect("reserve failed");
+        let base = frozen.as_ptr();
+        for i in 0..10 {
+            assert!(frozen.push_within_capacity(Record41::new(i)).is_ok());
+        }
+        assert!(frozen.push_within_capacity(Record41::new(10)).is_err());
+        // never remapped
+        assert_eq!(frozen.as_ptr(), base);
+        assert!(frozen[9].validate(9));
+    }
+    memvec_check10(&vec);
+}
+
+#[test]
+fn frozen_slice_across_pushes() {
+    let mut vec = unsafe { MemVec::<u64, _>::try_from_memory(HeapMemory::new()) }
+        .expect("memory is corrupted");
+    vec.push(1);
+    let mut frozen = vec.reserve_exact_then_freeze(2).expect("reserve failed");
+    let head = frozen.as_slice();
+    assert!(frozen.push_within_capacity(2).is_ok());
+    let all = frozen.as_slice();
+    assert!(frozen.push_within_capacity(3).is_ok());
+    assert_eq!(head, [1]);
+    assert_eq!(all, [1, 2]);
+    while frozen.len() < frozen.capacity() {
+        assert!(frozen.push_within_capacity(4).is_ok());
+    }
+    assert_eq!(frozen.push_within_capacity(5), Err(5));
+    assert_eq!(frozen[..3], [1, 2, 3]);
+    assert_eq!(head, [1]);
+}
+
+#[test]
+fn memvec_frozen_spare_capacity() {
+    // on the heap, so miri checks that writing the spare capacity keeps the slices valid
+    let mut vec = unsafe { MemVec::<u64, _>::try_from_memory(HeapMemory::new()) }
+        .expect("memory is corrupted");
+    vec.push(1);
+    let mut frozen = vec.reserve_exact_then_freeze(2).expect("reserve failed");
+    let head = frozen.as_slice();
+    let spare = frozen.spare_capacity_mut();
+    spare[0].write(2);
+    spare[1].write(3);
+    unsafe { frozen.set_len(3) };
+    assert_eq!(head, [1]);
+    assert_eq!(frozen[..], [1, 2, 3]);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_append_vec() {
+    let mut path = std::env::temp_dir();
+    path.push("concurrent_append.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let vec = unsafe { ConcurrentAppendVec::<u64>::new(vec_file, 1000) }.expect("reserve failed");
+    assert!(vec.capacity() >= 1000);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..1000 {
+                assert_eq!(vec.append(i), Ok(i as usize));
+            }
+        });
+        for _ in 0..2 {
+            scope.spawn(|| loop {
+                let len = vec.len();
+                for i in 0..len {
+                    assert_eq!(vec.get(i), Some(&(i as u64)));
+                }
+                if len == 1000 {
+                    break;
+                }
+            });
+        }
+    });
+    assert_eq!(vec.get(1000), None);
+
+    let reader = VecFile::open_read_only(&path).expect("open failed");
+    let records = unsafe { reader.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(records.len(), 1000);
+    assert_eq!(records[999], 999);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn sync_mem_vec() {
+    static_assertions::assert_impl_all!(VecFile: Send, Sync);
+    static_assertions::assert_impl_all!(MmapFile<'static>: Send, Sync);
+    static_assertions::assert_impl_all!(MemVec<'static, u64, VecFile>: Send, Sync);
+    static_assertions::assert_not_impl_any!(MemVec<'static, *const u8, VecFile>: Send, Sync);
+    static_assertions::assert_not_impl_any!(Segment: Send, Sync);
+
+    let vec_file = VecFile::temp().expect("temp failed");
+    let vec = unsafe { MemVec::<u64, _>::try_from_memory(vec_file) }.unwrap();
+    let vec = SyncMemVec::new(vec);
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let vec = vec.clone();
+            std::thread::spawn(move || {
+                for j in 0..100 {
+                    vec.write().unwrap().push(i * 100 + j);
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let mut vec = vec.try_unwrap().expect("handles left");
+    vec.sort();
+    assert!(vec.iter().copied().eq(0..400));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_clear_and_release() {
+    let mut path = std::env::temp_dir();
+    path.push("clear_and_release.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let header_len = vec_file.file().metadata().expect("stat failed").len();
+    let mut vec =
+        unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }.expect("vec file is corrupted");
+    vec.set_shrink_policy(ShrinkPolicy::Never);
+    memvec_push10(&mut vec);
+    vec.clear_and_release().expect("release failed");
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.capacity(), 0);
+    let file_len = vec.as_mem().file().metadata().expect("stat failed").len();
+    assert_eq!(file_len, header_len);
+
+    drop(vec);
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_append_file() {
+    let mut path = std::env::temp_dir();
+    path.push("append_file.memvec");
+    let mut other_path = std::env::temp_dir();
+    other_path.push("append_file_other.memvec");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&other_path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        let other_file = VecFile::create(&other_path).expect("create failed");
+        let mut other = unsafe { MemVec::<Record41, _>::try_from_memory(other_file) }
+            .expect("vec file is corrupted");
+        for i in 0..4 {
+            vec.push(Record41::new(i));
+        }
+        for i in 4..10 {
+            other.push(Record41::new(i));
+        }
+        vec.append_file(&other).expect("append failed");
+        memvec_check10(&vec);
+        assert_eq!(other.len(), 6);
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { MemVec::<Record41, _>::try_from_memory(vec_file) }
+            .expect("vec file is corrupted");
+        memvec_check10(&vec);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(other_path).expect("delete fail");
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_pod() {
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Pair {
+        a: u32,
+        b: u32,
+    }
+    unsafe impl bytemuck::Zeroable for Pair {}
+    unsafe impl bytemuck::Pod for Pair {}
+
+    let mut path = std::env::temp_dir();
+    path.push("pod.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = vec_file
+            .into_vec_pod::<Pair>()
+            .expect("vec file is corrupted");
+        vec.push(Pair { a: 1, b: 2 });
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = MemVec::<Pair, _>::from_memory_pod(vec_file).expect("vec file is corrupted");
+        assert_eq!((vec[0].a, vec[0].b), (1, 2));
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[cfg(feature = "zerocopy")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn memvec_zerocopy() {
+    #[repr(C)]
+    #[derive(Clone, Copy, AsBytes, FromBytes, FromZeroes, Unaligned)]
+    struct Packed {
+        id: u8,
+        a: [u8; 8],
+    }
+
+    let mut path = std::env::temp_dir();
+    path.push("zerocopy.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = vec_file
+            .into_vec_zerocopy::<Packed>()
+            .expect("vec file is corrupted");
+        vec.push(Packed {
+            id: 3,
+            a: 9u64.to_le_bytes(),
+        });
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec =
+            MemVec::<Packed, _>::from_memory_zerocopy(vec_file).expect("vec file is corrupted");
+        assert_eq!(vec[0].id, 3);
+        assert_eq!(u64::from_le_bytes(vec[0].a), 9);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn seqlock_read_consistent() {
+    let mut path = std::env::temp_dir();
+    path.push("seqlock.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { MemVec::<[u64; 8], _>::try_from_memory(vec_file) }.unwrap();
+    vec.resize_with(4, || [0; 8]);
+    let reader = VecFile::open_read_only(&path).expect("open failed");
+    let records = unsafe { reader.try_into_memvec::<[u64; 8]>() }.unwrap();
+    assert_eq!(records.read_consistent(4), None);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 1..=10000 {
+                vec.write_consistent(|records| records[i as usize % 4] = [i; 8]);
+            }
+        });
+        for _ in 0..100 {
+            for record in records.iter_consistent() {
+                assert!(record.iter().all(|&x| x == record[0]), "{record:?}");
+            }
+        }
+    });
+    assert_eq!(records.iter_consistent().count(), 4);
+    assert_eq!(records.read_consistent(0), Some([10000; 8]));
+    drop(records);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ring_buffer() {
+    let mut path = std::env::temp_dir();
+    path.push("ring.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut ring = unsafe { RingBuffer::<u64, _>::new(vec_file, 4) }.expect("create failed");
+        let (mut producer, _) = ring.split();
+        for i in 0..4 {
+            producer.push(i);
+        }
+        assert_eq!(producer.try_push(4), Err(4));
+        assert_eq!(ring.len(), 4);
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let err = unsafe { RingBuffer::<u64, _>::new(vec_file, 8) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut ring = unsafe { RingBuffer::<u64, _>::new(vec_file, 4) }.expect("open failed");
+    let (mut producer, mut consumer) = ring.split();
+    assert_eq!(consumer.try_pop(), Some(0));
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for i in 4..10000 {
+                producer.push(i);
+            }
+        });
+        for i in 1..10000 {
+            assert_eq!(consumer.pop(), i);
+        }
+        assert_eq!(consumer.try_pop(), None);
+    });
+    assert!(ring.is_empty());
+    drop(ring);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ring_buffer_shared() {
+    let mut path = std::env::temp_dir();
+    path.push("ring_shared.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // the producer and the consumer map the file separately, as two processes would; a capacity
+    // which does not divide the index range makes every slot reuse go through the modulo
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut producer_ring =
+        unsafe { RingBuffer::<[u64; 2], _>::new(vec_file, 5) }.expect("create failed");
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut consumer_ring =
+        unsafe { RingBuffer::<[u64; 2], _>::new(vec_file, 5) }.expect("open failed");
+    let (mut producer, _) = producer_ring.split();
+    let (_, mut consumer) = consumer_ring.split();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for i in 0..5000u64 {
+                // both words of a record are written before the tail publishes it
+                producer.push([i, !i]);
+            }
+        });
+        for i in 0..5000u64 {
+            assert_eq!(consumer.pop(), [i, !i]);
+        }
+    });
+    assert!(producer_ring.is_empty() && consumer_ring.is_empty());
+    drop((producer_ring, consumer_ring));
+
+    {
+        // a tail far beyond the head counts as full rather than overrunning the slots
+        let mut vec_file = VecFile::open(&path).expect("open failed");
+        vec_file[128..136].copy_from_slice(&u64::MAX.to_le_bytes());
+    }
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut ring = unsafe { RingBuffer::<[u64; 2], _>::new(vec_file, 5) }.expect("open failed");
+    assert_eq!(ring.len(), 5);
+    assert_eq!(ring.split().0.try_push([0; 2]), Err([0; 2]));
+    drop(ring);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn append_queue() {
+    let mut path = std::env::temp_dir();
+    path.push("append_queue.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let queue = unsafe { AppendQueue::<u32, _>::new(vec_file, 1000) }.expect("create failed");
+    // another process maps the same file
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let other = unsafe { AppendQueue::<u32, _>::new(vec_file, 1000) }.expect("open failed");
+    std::thread::scope(|scope| {
+        for t in 0..4 {
+            let queue = if t % 2 == 0 { &queue } else { &other };
+            scope.spawn(move || {
+                for i in 0..250 {
+                    queue.append(t * 1000 + i).unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(queue.append(0), Err(0));
+    let mut records: Vec<u32> = other.iter().map(|(_, &x)| x).collect();
+    assert_eq!(records.len(), 1000);
+    records.sort();
+    records.dedup();
+    assert_eq!(records.len(), 1000);
+    drop((queue, other));
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let queue = unsafe { AppendQueue::<u32, _>::new(vec_file, 4) }.expect("create failed");
+    queue.append(1).unwrap();
+    assert_eq!(queue.slot_state(1), None);
+    {
+        // a producer reserves slot 1 and dies before committing it
+        let mut mem = VecFile::open(&path).expect("open failed");
+        mem[64..72].copy_from_slice(&2u64.to_le_bytes());
+    }
+    assert_eq!(queue.slot_state(1), Some(SlotState::Empty));
+    assert_eq!(queue.iter().count(), 1);
+    assert!(queue.abandon(1));
+    assert_eq!(queue.get(1), None);
+    assert_eq!(queue.append(2), Ok(2));
+    assert_eq!(queue.iter().count(), 2);
+    drop(queue);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn append_queue_reservation_race() {
+    let mut path = std::env::temp_dir();
+    path.push("append_queue_race.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // more appends than slots, from producers on two mappings, while a consumer abandons
+    // whatever it finds uncommitted
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let queue = unsafe { AppendQueue::<u64, _>::new(vec_file, 1000) }.expect("create failed");
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let other = unsafe { AppendQueue::<u64, _>::new(vec_file, 1000) }.expect("open failed");
+    let done = std::sync::atomic::AtomicBool::new(false);
+    let results: Vec<Vec<(u64, Result<usize, u64>)>> = std::thread::scope(|scope| {
+        let producers: Vec<_> = (0..4u64)
+            .map(|t| {
+                let queue = if t % 2 == 0 { &queue } else { &other };
+                scope.spawn(move || {
+                    (0..400)
+                        .map(|i| (t * 1000 + i, queue.append(t * 1000 + i)))
+                        .collect()
+                })
+            })
+            .collect();
+        scope.spawn(|| {
+            while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                for index in 0..other.len() {
+                    other.abandon(index);
+                }
+            }
+        });
+        let results = producers
+            .into_iter()
+            .map(|producer| producer.join().unwrap())
+            .collect();
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        results
+    });
+
+    // every slot was reserved once and ends committed or abandoned
+    assert_eq!(queue.len(), 1000);
+    assert!((0..1000).all(|index| queue.slot_state(index) != Some(SlotState::Empty)));
+    let mut indices = Vec::new();
+    for (value, result) in results.into_iter().flatten() {
+        match result {
+            // a committed record is where its producer was told
+            Ok(index) => {
+                assert_eq!(other.get(index), Some(&value));
+                indices.push(index);
+            }
+            // one rejected by the full queue is handed back intact
+            Err(rejected) => assert_eq!(rejected, value),
+        }
+    }
+    let committed = indices.len();
+    indices.sort();
+    indices.dedup();
+    assert_eq!(indices.len(), committed);
+    assert_eq!(queue.iter().count(), committed);
+    assert_eq!(
+        (0..1000)
+            .filter(|&index| queue.slot_state(index) == Some(SlotState::Abandoned))
+            .count(),
+        1000 - committed
+    );
+    drop((queue, other));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn read_only_watch() {
+    let mut path = std::env::temp_dir();
+    path.push("watch.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.reserve(100);
+    let reader = VecFile::open_read_only(&path).expect("open failed");
+    let mut watch = reader.watch();
+    assert_eq!(
+        watch.wait_timeout(std::time::Duration::from_millis(1)),
+        None
+    );
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            vec.push(1);
+        });
+        let generation = watch.wait();
+        assert_eq!(generation, reader.generation());
+    });
+    assert_eq!(reader.len(), 1);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_mutex() {
+    use std::io::{Seek, SeekFrom};
+
+    let mut path = std::env::temp_dir();
+    path.push("mutex.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let err = vec_file.lock_mutex().expect_err("file has no mutex");
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    {
+        let mut vec_file = VecFile::builder()
+            .create(true)
+            .truncate(true)
+            .metadata_len(8)
+            .mutex(true)
+            .open(&path)
+            .expect("create failed");
+        vec_file.metadata_mut().copy_from_slice(b"metadata");
+        let guard = vec_file.lock_mutex().unwrap();
+        assert!(!guard.recovered());
+        assert!(vec_file.try_lock_mutex().unwrap().is_none());
+        drop(guard);
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.push(1);
+    }
+
+    {
+        // a process which died holding the mutex
+        let mut file = File::options().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(128)).unwrap();
+        file.write_all(&0x7fff_fff0u32.to_le_bytes()).unwrap();
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    assert_eq!(vec_file.metadata(), b"metadata");
+    let guard = vec_file
+        .try_lock_mutex()
+        .unwrap()
+        .expect("dead owner kept the mutex");
+    assert!(guard.recovered());
+    drop(guard);
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), &[1]);
+    let reader = VecFile::open_read_only(&path).expect("open failed");
+    assert_eq!(reader.metadata(), b"metadata");
+    drop((vec, reader));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn follower_refresh() {
+    let mut path = std::env::temp_dir();
+    path.push("follower.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    for i in 0..3 {
+        vec.push(i);
+    }
+    let mut follower = unsafe { Follower::<u64>::open(&path) }.expect("open failed");
+    assert_eq!(follower.as_slice(), &[0, 1, 2]);
+
+    vec.resize_with(10000, || 7);
+    assert_eq!(follower.len(), 3);
+    assert_eq!(follower.refresh().unwrap(), 9997);
+    assert_eq!(follower.len(), 10000);
+    assert_eq!(follower[9999], 7);
+    assert_eq!(follower.refresh().unwrap(), 0);
+    drop((vec, follower));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[cfg(feature = "async")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_async() {
+    let mut path = std::env::temp_dir();
+    path.push("async.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let vec_file = VecFile::open_or_create_async(&path, |_| Ok(()))
+            .await
+            .expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.reserve_async(1000).await.unwrap();
+        assert_eq!(vec.capacity(), 1000);
+        let capacity = vec.capacity();
+        vec.resize_with(1000, || 7);
+        assert_eq!(vec.capacity(), capacity);
+        vec.flush_async().await.unwrap();
+    });
+
+    let vec_file = runtime
+        .block_on(VecFile::open_async(&path))
+        .expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.len(), 1000);
+    assert_eq!(vec[999], 7);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_flush_ranges() {
+    let mut path = std::env::temp_dir();
+    path.push("flush_ranges.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.resize_with(100000, || 0);
+        vec[10] = 1;
+        vec[20] = 2;
+        vec[99999] = 3;
+        vec.flush_ranges(&[20..21, 10..11, 5..15, 99999..100000, 50..50])
+            .expect("flush failed");
+        vec.readahead(&[0..10, 50000..60000, 99000..200000])
+            .expect("readahead failed");
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!((vec[10], vec[20], vec[99999]), (1, 2, 3));
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[cfg(feature = "async")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn follower_stream() {
+    let mut path = std::env::temp_dir();
+    path.push("follower_stream.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.push(0);
+    let follower = unsafe { Follower::<u64>::open(&path) }.expect("open failed");
+    let mut stream = follower.into_stream();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 1..1000 {
+                vec.push(i);
+            }
+        });
+        runtime.block_on(async {
+            for i in 0..1000 {
+                let record = core::future::poll_fn(|cx| {
+                    futures_core::Stream::poll_next(core::pin::Pin::new(&mut stream), cx)
+                })
+                .await;
+                assert_eq!(record.unwrap().unwrap(), i);
+            }
+        });
+    });
+    drop((vec, stream));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_deque() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_deque.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut deque = unsafe { MemDeque::<u64, _>::new(vec_file) }.expect("create failed");
+        for i in 0..3 {
+            deque.push_back(i).unwrap();
+        }
+        assert_eq!(deque.pop_front(), Some(0));
+        // wraps around the slots, then grows
+        deque.push_back(3).unwrap();
+        deque.push_back(4).unwrap();
+        assert_eq!(deque.capacity(), 4);
+        deque.push_front(1000).unwrap();
+        deque.push_back(5).unwrap();
+        assert_eq!(deque.capacity(), 8);
+        assert_eq!(
+            deque.iter().copied().collect::<Vec<_>>(),
+            [1000, 1, 2, 3, 4, 5]
+        );
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let err = unsafe { MemDeque::<u32, _>::new(vec_file) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut deque = unsafe { MemDeque::<u64, _>::new(vec_file) }.expect("open failed");
+    assert_eq!(deque.len(), 6);
+    assert_eq!(deque.front(), Some(&1000));
+    assert_eq!(deque.back(), Some(&5));
+    assert_eq!(deque.pop_back(), Some(5));
+    *deque.get_mut(0).unwrap() = 0;
+    let mut popped = Vec::new();
+    while let Some(value) = deque.pop_front() {
+        popped.push(value);
+    }
+    assert_eq!(popped, [0, 1, 2, 3, 4]);
+    assert!(deque.is_empty());
+    drop(deque);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_string() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_string.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut string = MemString::from_memory(vec_file).expect("create failed");
+        string.push_str("hello, ");
+        string.push('世');
+        string.push('界');
+        assert_eq!(string, "hello, 世界");
+        assert_eq!(string.pop(), Some('界'));
+        string.truncate(string.len() - '世'.len_utf8());
+        core::fmt::Write::write_fmt(&mut string, format_args!("world {}", 1)).unwrap();
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let mut string = MemString::from_memory(vec_file).expect("open failed");
+        assert_eq!(string.as_str(), "hello, world 1");
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            string.push('é');
+            string.truncate(string.len() - 1);
+        }));
+        assert!(result.is_err());
+        // corrupt the text by a lone continuation byte
+        unsafe { string.as_mut_vec() }.push(0x80);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let (_, err) = MemString::from_memory(vec_file).unwrap_err();
+    assert!(matches!(err, MemStringError::Utf8(e) if e.valid_up_to() == 16));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_hash_map() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_hash_map.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut map = unsafe { MemHashMap::<u64, u32, _>::new(vec_file) }.expect("create failed");
+        for i in 0..1000 {
+            assert_eq!(map.insert(i, i as u32 * 2).unwrap(), None);
+        }
+        assert_eq!(map.insert(7, 0).unwrap(), Some(14));
+        for i in (0..1000).step_by(2) {
+            assert_eq!(map.remove(&i), Some(if i == 7 { 0 } else { i as u32 * 2 }));
+        }
+        assert_eq!(map.remove(&0), None);
+        assert_eq!(map.len(), 500);
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let err = unsafe { MemHashMap::<u32, u32, _>::new(vec_file) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut map = unsafe { MemHashMap::<u64, u32, _>::new(vec_file) }.expect("open failed");
+    assert_eq!(map.len(), 500);
+    for i in 0..1000 {
+        let expected = match i {
+            7 => Some(0),
+            i if i % 2 == 1 => Some(i as u32 * 2),
+            _ => None,
+        };
+        assert_eq!(map.get(&i).copied(), expected);
+    }
+    *map.get_mut(&1).unwrap() = 100;
+    assert_eq!(
+        map.iter().map(|(_, v)| *v as u64).sum::<u64>(),
+        500_000 - 14 - 2 + 100
+    );
+    map.clear();
+    assert!(map.is_empty());
+    assert!(!map.contains_key(&1));
+    drop(map);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_hash_map_probing() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_hash_map_probing.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut map = unsafe { MemHashMap::<u64, u64, _>::new(vec_file) }.expect("create failed");
+    map.reserve(6).unwrap();
+    let capacity = map.capacity();
+    // keys probing from the last bucket over the end of the buckets
+    let last = (capacity / 3 * 4 - 1) as u64;
+    let keys: Vec<u64> = (0..)
+        .filter(|key| crate::hash_map::hash(key) & last == last)
+        .take(capacity)
+        .collect();
+    for &key in &keys {
+        map.insert(key, !key).unwrap();
+    }
+    // removing shifts the rest of the cluster back, so churn neither loses keys nor grows the map
+    for round in 0..100 {
+        let removed = keys[round * 7 % keys.len()];
+        assert_eq!(map.remove(&removed), Some(!removed));
+        assert!(keys
+            .iter()
+            .filter(|&&key| key != removed)
+            .all(|key| map.get(key) == Some(&!key)));
+        assert_eq!(map.get(&removed), None);
+        map.insert(removed, !removed).unwrap();
+    }
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.len(), keys.len());
+
+    // control bytes corrupted to leave no empty bucket are refused instead of probing forever
+    let mut vec_file = map.into_memory();
+    let buckets = capacity / 3 * 4;
+    vec_file[64..64 + buckets].fill(1);
+    let err = unsafe { MemHashMap::<u64, u64, _>::new(vec_file) }.expect_err("buckets are full");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn mem_hash_map_corrupt() {
+    let map = || {
+        let mut map =
+            unsafe { MemHashMap::<u64, u64, _>::new(HeapMemory::new()) }.expect("create failed");
+        for key in 0..10 {
+            map.insert(key, key).unwrap();
+        }
+        map.into_memory()
+    };
+
+    // a length of more entries than the control bytes mark
+    let mut mem = map();
+    mem[32..40].copy_from_slice(&11u64.to_le_bytes());
+    let err = unsafe { MemHashMap::<u64, u64, _>::new(mem) }.expect_err("length is broken");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // a length past the mapped bytes
+    let mut mem = map();
+    mem.shrink(64).unwrap();
+    let err = unsafe { MemHashMap::<u64, u64, _>::new(mem) }.expect_err("memory is short");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_btree_map() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_btree_map.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut map =
+            unsafe { MemBTreeMap::<u64, [u64; 8], _>::new(vec_file) }.expect("create failed");
+        // a shuffled order splits leaves and inner nodes in the middle
+        for i in 0..20000u64 {
+            let key = i * 7919 % 20000;
+            assert_eq!(map.insert(key, [key; 8]).unwrap(), None);
+        }
+        assert_eq!(map.insert(5, [0; 8]).unwrap(), Some([5; 8]));
+        for key in (0..20000).step_by(3) {
+            assert!(map.remove(&key).is_some());
+        }
+        assert_eq!(map.remove(&3), None);
+    }
+
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let err = unsafe { MemBTreeMap::<u32, [u64; 8], _>::new(vec_file) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut map = unsafe { MemBTreeMap::<u64, [u64; 8], _>::new(vec_file) }.expect("open failed");
+    let expected: Vec<u64> = (0..20000).filter(|key| key % 3 != 0).collect();
+    assert_eq!(map.len(), expected.len());
+    assert!(map.iter().map(|(key, _)| *key).eq(expected.iter().copied()));
+    assert!(map
+        .range(100..=200)
+        .map(|(key, _)| *key)
+        .eq((100..=200).filter(|key| key % 3 != 0)));
+    assert_eq!(
+        map.range((std::ops::Bound::Excluded(19998), std::ops::Bound::Unbounded))
+            .count(),
+        1
+    );
+    assert_eq!(map.get(&5), Some(&[0; 8]));
+    assert_eq!(map.get(&6), None);
+    map.get_mut(&7).unwrap()[0] = 0;
+    assert_eq!(map.get(&7).unwrap()[..2], [0, 7]);
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.first_key_value(), None);
+    map.insert(1, [1; 8]).unwrap();
+    assert_eq!(map.first_key_value(), Some((&1, &[1; 8])));
+    drop(map);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn mem_btree_map_corrupt() {
+    const PAGE: usize = 4096;
+    // the children of an inner node of u64 keys follow its 253 keys
+    const CHILDREN: usize = 16 + 253 * 8;
+    let map = || {
+        let mut map =
+            unsafe { MemBTreeMap::<u64, u64, _>::new(HeapMemory::new()) }.expect("create failed");
+        for key in 0..1000 {
+            map.insert(key, key).unwrap();
+        }
+        let mem = map.into_memory();
+        let root = u64::from_le_bytes(mem[40..48].try_into().unwrap()) as usize;
+        (mem, root)
+    };
+    let open = |mem| unsafe { MemBTreeMap::<u64, u64, _>::new(mem) };
+
+    // a root whose count exceeds its page
+    let (mut mem, root) = map();
+    mem[root * PAGE + 2..][..2].copy_from_slice(&u16::MAX.to_le_bytes());
+    let err = open(mem).expect_err("root is broken");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // a child past the pages, and a child which is its own parent
+    for child in [1000, root as u64] {
+        let (mut mem, root) = map();
+        mem[root * PAGE + CHILDREN..][..8].copy_from_slice(&child.to_le_bytes());
+        let mut map = open(mem).expect("open failed");
+        let err = map.insert(0, 0).expect_err("child is broken");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(map.iter().count(), 0);
+        assert_eq!(map.len(), 1000);
+    }
+
+    // a next link past the pages, and a leaf which is its own next
+    for next in [1000, 2] {
+        let (mut mem, _) = map();
+        // the first leaf is the first root
+        mem[2 * PAGE + 8..][..8].copy_from_slice(&(next as u64).to_le_bytes());
+        let map = open(mem).expect("open failed");
+        // ends, rather than cycling forever
+        let count = map.iter().count();
+        assert!(count > 0 && count < 10000, "{count}");
+        assert!(map.iter().take(10).map(|(key, _)| *key).eq(0..10));
+    }
+
+    // a length past the mapped bytes
+    let (mut mem, _) = map();
+    mem.shrink(PAGE).unwrap();
+    let err = open(mem).expect_err("memory is short");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_slab() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_slab.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut slab = unsafe { MemSlab::<[u8; 3], _>::new(vec_file) }.expect("create failed");
+        for i in 0..10 {
+            assert_eq!(slab.insert([i; 3]).unwrap(), i as usize);
+        }
+        assert_eq!(slab.remove(3), Some([3; 3]));
+        assert_eq!(slab.remove(7), Some([7; 3]));
+        assert_eq!(slab.remove(7), None);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut slab = unsafe { MemSlab::<[u8; 3], _>::new(vec_file) }.expect("open failed");
+    assert_eq!(slab.len(), 8);
+    assert!(!slab.contains(3));
+    assert_eq!(slab.get(4), Some(&[4; 3]));
+    // the most recently vacated slot first
+    assert_eq!(slab.insert([70; 3]).unwrap(), 7);
+    assert_eq!(slab.insert([30; 3]).unwrap(), 3);
+    assert_eq!(slab.insert([10; 3]).unwrap(), 10);
+    slab.get_mut(0).unwrap()[0] = 100;
+    assert_eq!(
+        slab.iter()
+            .map(|(key, value)| (key, value[0]))
+            .collect::<Vec<_>>(),
+        [
+            (0, 100),
+            (1, 1),
+            (2, 2),
+            (3, 30),
+            (4, 4),
+            (5, 5),
+            (6, 6),
+            (7, 70),
+            (8, 8),
+            (9, 9),
+            (10, 10)
+        ]
+    );
+    slab.clear();
+    assert!(slab.is_empty());
+    assert_eq!(slab.insert([0; 3]).unwrap(), 0);
+    // as an object pool
+    assert_eq!(slab.vacant_key(), 1);
+    assert_eq!(slab.acquire().unwrap(), 1);
+    assert_eq!(slab.get(1), Some(&[0; 3]));
+    assert_eq!(slab.release(0), Some([0; 3]));
+    assert_eq!(slab.vacant_key(), 0);
+    assert_eq!(slab.acquire().unwrap(), 0);
+    drop(slab);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_bit_vec() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_bit_vec.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut bits = MemBitVec::new(vec_file).expect("create failed");
+        for i in 0..200 {
+            bits.push(i % 3 == 0).unwrap();
+        }
+        assert!(!bits.set(1, true));
+        assert!(bits.set(3, false));
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut bits = MemBitVec::new(vec_file).expect("open failed");
+    assert_eq!(bits.len(), 200);
+    assert_eq!(bits.get(1), Some(true));
+    assert_eq!(bits.get(3), Some(false));
+    assert_eq!(bits.get(200), None);
+    assert_eq!(bits.count_ones(), 67);
+    assert_eq!(bits.count_zeros(), 133);
+    assert_eq!(bits.rank(7), 3);
+    assert_eq!(bits.rank(200), 67);
+    assert_eq!(bits.select(0), Some(0));
+    assert_eq!(bits.select(2), Some(6));
+    assert_eq!(bits.select(67), None);
+    assert_eq!(bits.iter_ones().take(4).collect::<Vec<_>>(), [0, 1, 6, 9]);
+    // the truncated bits do not come back on growing
+    bits.truncate(70);
+    bits.resize(130, false).unwrap();
+    assert_eq!(bits.count_ones(), 24);
+    bits.resize(140, true).unwrap();
+    assert_eq!(bits.rank(140), 34);
+    bits.clear();
+    assert!(bits.is_empty());
+    drop(bits);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_arena() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_arena.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let (number, text, handles) = {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut arena = unsafe { MemArena::new(vec_file) }.expect("create failed");
+        let byte = arena.alloc(1u8).unwrap();
+        let number = arena.alloc(2u64).unwrap();
+        assert_eq!(number.offset() % 8, 0);
+        let text = arena.alloc_slice(b"hello").unwrap();
+        // handles are plain values stored in the arena
+        let handles = arena.alloc((number, text)).unwrap();
+        *arena.get_mut(byte).unwrap() = 3;
+        assert_eq!(arena.get(byte), Some(&3));
+        (number, text, handles)
+    };
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut arena = unsafe { MemArena::new(vec_file) }.expect("open failed");
+    assert_eq!(arena.get(handles), Some(&(number, text)));
+    assert_eq!(arena.get(number), Some(&2));
+    arena.slice_mut(text).unwrap()[0] = b'j';
+    assert_eq!(arena.slice(text), Some(&b"jello"[..]));
+    let used = arena.used();
+    arena.reset();
+    assert!(arena.used() < used);
+    assert_eq!(arena.get(number), None);
+    assert_eq!(arena.slice(text), None);
+    let number = arena.alloc(4u64).unwrap();
+    assert_eq!(arena.get(number), Some(&4));
+    drop(arena);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn sorted_mem_vec() {
+    let mut path = std::env::temp_dir();
+    path.push("sorted_mem_vec.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<(u32, u32)>() }.unwrap();
+        vec.push((5, 0));
+        vec.push((1, 0));
+        let (vec, index) = SortedMemVec::from_vec(vec, |v: &(u32, u32)| v.0).unwrap_err();
+        assert_eq!(index, 1);
+        let mut sorted = SortedMemVec::from_vec_sorting(vec, |v: &(u32, u32)| v.0);
+        assert_eq!(sorted.insert((3, 0)), 1);
+        assert_eq!(sorted.insert((3, 1)), 2);
+        sorted.set_dedup(true);
+        assert_eq!(sorted.insert((5, 1)), 3);
+        assert_eq!(sorted.insert((9, 0)), 4);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<(u32, u32)>() }.unwrap();
+    let mut sorted = SortedMemVec::from_vec(vec, |v: &(u32, u32)| v.0).unwrap();
+    assert_eq!(*sorted, [(1, 0), (3, 0), (3, 1), (5, 1), (9, 0)]);
+    assert_eq!(sorted.search(&3), Ok(1));
+    assert_eq!(sorted.search(&4), Err(3));
+    assert_eq!(sorted.get_by_key(&5), Some(&(5, 1)));
+    assert_eq!(sorted.range(3..=5), [(3, 0), (3, 1), (5, 1)]);
+    assert_eq!(sorted.range(4..), [(5, 1), (9, 0)]);
+    assert_eq!(sorted.range(6..6), []);
+    assert_eq!(sorted.remove_by_key(&3), Some((3, 0)));
+    assert!(!sorted.contains_key(&2));
+    drop(sorted);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_log() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_log.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("create failed");
+    let mut len: usize = 0;
+    let mmap = MmapFile::new(file, &mut len, &MapOptions::new()).expect("mmap failed");
+    let mut log = MemLog::new(mmap, true).expect("create failed");
+    let first = log.append(b"{\"event\":1}").unwrap();
+    let second = log.append(b"").unwrap();
+    let third = log.append(&[7; 100]).unwrap();
+    assert_eq!(log.get(first), Some(&b"{\"event\":1}"[..]));
+    assert_eq!(log.get(second), Some(&[][..]));
+    assert_eq!(log.get(third + 1), None);
+    let end = log.len_bytes();
+    // a torn frame: the payload was written, the size was not
+    let mut mmap = log.into_memory();
+    mmap[end + 8] = 1;
+    let file = mmap.into_file();
+
+    // the length was lost in the crash
+    let mut len = file.metadata().unwrap().len() as usize;
+    let mmap = MmapFile::new(file, &mut len, &MapOptions::new()).expect("mmap failed");
+    let mut log = MemLog::new(mmap, false).expect("open failed");
+    assert!(log.has_checksum());
+    log.recover();
+    assert_eq!(log.len_bytes(), end);
+    let records: Vec<_> = log
+        .iter()
+        .map(|(offset, payload)| (offset, payload.len()))
+        .collect();
+    assert_eq!(records, [(first, 11), (second, 0), (third, 100)]);
+
+    // a corrupted payload ends the log there
+    let mut mmap = log.into_memory();
+    mmap[third + 8] = 0;
+    let mut log = MemLog::new(mmap, true).expect("open failed");
+    let mut iter = log.iter();
+    assert_eq!(iter.by_ref().count(), 2);
+    assert_eq!(iter.offset(), third);
+    log.recover();
+    assert_eq!(log.len_bytes(), third);
+    log.clear();
+    assert!(log.is_empty());
+    drop(log);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn blob_store() {
+    let mut path = std::env::temp_dir();
+    path.push("blob_store.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let mut store = BlobStore::from_segment_file(&file).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.push_bytes(b"hello").unwrap(), 0);
+        assert_eq!(store.push_bytes(b"").unwrap(), 1);
+        for i in 0..100 {
+            store.push_bytes(format!("blob {i}").as_bytes()).unwrap();
+        }
+        assert_eq!(store.len(), 102);
+        assert_eq!(store.get(0), Some(&b"hello"[..]));
+        assert_eq!(store.get(1), Some(&b""[..]));
+        assert_eq!(store.get(101), Some(&b"blob 99"[..]));
+        assert_eq!(store.get(102), None);
+        store.flush().unwrap();
+    }
+    {
+        let file = SegmentFile::open(&path).expect("open failed");
+        let mut store = BlobStore::from_segment_file(&file).unwrap();
+        assert_eq!(store.len(), 102);
+        assert_eq!(store.iter().len(), 102);
+        assert_eq!(store.iter().nth(2), Some(&b"blob 0"[..]));
+        store.truncate(2);
+        assert_eq!(store.heap_len(), 5);
+        assert_eq!(store.push_bytes(b"again").unwrap(), 2);
+        let (index, mut heap) = store.into_memories();
+        // bytes of a torn push, not covered by an entry
+        heap.reserve(64).unwrap();
+        heap.set_len(20);
+        let store = BlobStore::new(index, heap).unwrap();
+        assert_eq!(store.heap_len(), 10);
+        assert_eq!(
+            store.iter().collect::<Vec<_>>(),
+            [&b"hello"[..], b"", b"again"]
+        );
+        let (mut index, heap) = store.into_memories();
+        index[..8].copy_from_slice(&100u64.to_le_bytes());
+        assert_eq!(
+            BlobStore::new(index, heap).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_binary_heap() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_binary_heap.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u32>() }.unwrap();
+        for value in [3, 1, 4, 1, 5] {
+            vec.push(value);
+        }
+        let mut heap = MemBinaryHeap::from_vec(vec);
+        assert_eq!(heap.peek(), Some(&5));
+        for value in [9, 2, 6] {
+            heap.push(value);
+        }
+        assert_eq!(heap.pop(), Some(9));
+        assert_eq!(heap.len(), 7);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u32>() }.unwrap();
+    let mut heap = MemBinaryHeap::from_vec(vec);
+    assert_eq!(heap.pop(), Some(6));
+    assert_eq!(heap.pop(), Some(5));
+    assert_eq!(*heap.into_sorted_vec(), [1, 1, 2, 3, 4]);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_matrix() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_matrix.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut matrix = unsafe { MemMatrix::<f64, _>::new(vec_file, 3) }.expect("create failed");
+        matrix.resize_rows(2, 0.0).unwrap();
+        matrix[(1, 2)] = 1.5;
+        matrix.push_row(&[7.0, 8.0, 9.0]).unwrap();
+        assert_eq!((matrix.rows(), matrix.cols()), (3, 3));
+        assert_eq!(matrix.row(1), [0.0, 0.0, 1.5]);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let err = unsafe { MemMatrix::<f64, _>::new(vec_file, 4) }.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut matrix = unsafe { MemMatrix::<f64, _>::new(vec_file, 3) }.expect("open failed");
+    assert_eq!(matrix.get(2, 0), Some(&7.0));
+    assert_eq!(matrix.get(0, 3), None);
+    assert_eq!(matrix.get(3, 0), None);
+    *matrix.get_mut(0, 0).unwrap() = -1.0;
+    assert_eq!(
+        matrix
+            .iter_rows()
+            .map(|row| row.iter().sum())
+            .collect::<Vec<f64>>(),
+        [-1.0, 1.5, 24.0]
+    );
+    matrix.truncate_rows(1);
+    matrix.resize_rows(2, 2.0).unwrap();
+    assert_eq!(matrix.as_slice(), [-1.0, 0.0, 0.0, 2.0, 2.0, 2.0]);
+    drop(matrix);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_queue() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_queue.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut queue = unsafe { MemQueue::<u64, _>::new(vec_file) }.expect("create failed");
+        for i in 0..4 {
+            queue.enqueue(i).unwrap();
+        }
+        assert_eq!(queue.dequeue(), Some(0));
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.head(), 2);
+        assert_eq!(queue.as_slice(), [2, 3]);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut queue = unsafe { MemQueue::<u64, _>::new(vec_file) }.expect("open failed");
+    assert_eq!(queue.front(), Some(&2));
+    assert_eq!(queue.get(1), Some(&3));
+    // the dead prefix is as long as the live records, so they slide back instead of growing
+    let capacity = queue.capacity();
+    while queue.head() + queue.len() < capacity {
+        queue.enqueue(0).unwrap();
+    }
+    while queue.head() < queue.len() {
+        queue.dequeue().unwrap();
+    }
+    let len = queue.len();
+    queue.enqueue(100).unwrap();
+    assert_eq!((queue.head(), queue.len()), (0, len + 1));
+    assert_eq!(queue.capacity(), capacity);
+    assert_eq!(queue.iter().last(), Some(&100));
+    while queue.len() > 1 {
+        queue.dequeue().unwrap();
+    }
+    queue.compact().unwrap();
+    assert_eq!((queue.head(), queue.capacity()), (0, 1));
+    assert_eq!(queue.dequeue(), Some(100));
+    assert_eq!(queue.dequeue(), None);
+    drop(queue);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_columns() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_columns.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let times = unsafe { file.segment("time").unwrap().try_into_memvec::<u64>() }.unwrap();
+        let values = unsafe { file.segment("value").unwrap().try_into_memvec::<f32>() }.unwrap();
+        let mut columns = MemColumns::<(u64, f32), _>::from_vecs((times, values)).unwrap();
+        for i in 0..5 {
+            columns.push((i, i as f32 / 2.0)).unwrap();
+        }
+        assert_eq!(columns.remove(1), (1, 0.5));
+        assert_eq!(columns.swap_remove(0), (0, 0.0));
+        assert_eq!(columns.pop(), Some((3, 1.5)));
+        assert_eq!(*columns.columns().0, [4, 2]);
+        assert_eq!(*columns.columns().1, [2.0, 1.0]);
+        let (times, mut values) = columns.into_vecs();
+        // a row torn by a crash
+        values.push(9.0);
+        let (times, values) = MemColumns::<(u64, f32), _>::from_vecs((times, values))
+            .unwrap_err()
+            .0;
+        let columns = MemColumns::<(u64, f32), _>::from_vecs_truncating((times, values));
+        columns.flush().unwrap();
+    }
+
+    let file = SegmentFile::open(&path).expect("open failed");
+    let times = unsafe { file.segment("time").unwrap().try_into_memvec::<u64>() }.unwrap();
+    let values = unsafe { file.segment("value").unwrap().try_into_memvec::<f32>() }.unwrap();
+    let mut columns = MemColumns::<(u64, f32), _>::from_vecs((times, values)).unwrap();
+    assert_eq!(columns.iter().collect::<Vec<_>>(), [(4, 2.0), (2, 1.0)]);
+    assert_eq!(columns.get(2), None);
+    columns.clear();
+    assert!(columns.is_empty());
+    drop(columns);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_roaring_set() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_roaring_set.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let file = SegmentFile::create(&path).expect("create failed");
+        let mut set =
+            MemRoaringSet::new(file.segment("dir").unwrap(), file.segment("heap").unwrap())
+                .unwrap();
+        assert!(set.insert(7).unwrap());
+        assert!(!set.insert(7).unwrap());
+        assert!(set.insert(u64::MAX).unwrap());
+        // grows an array into a bitmap
+        for i in 0..5000 {
+            set.insert((1 << 20) + i * 3).unwrap();
+        }
+        assert_eq!(set.len(), 5002);
+        assert!(set.contains((1 << 20) + 300));
+        assert!(!set.contains((1 << 20) + 301));
+        assert!(set.remove((1 << 20) + 300));
+        assert!(!set.remove((1 << 20) + 300));
+        set.flush().unwrap();
+    }
+
+    let file = SegmentFile::open(&path).expect("open failed");
+    let mut set =
+        MemRoaringSet::new(file.segment("dir").unwrap(), file.segment("heap").unwrap()).unwrap();
+    assert_eq!(set.len(), 5001);
+    let values = set.iter().collect::<Vec<_>>();
+    assert_eq!(values.len(), 5001);
+    assert!(values.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!((values[0], values[5000]), (7, u64::MAX));
+    // shrinks the bitmap back into an array
+    for i in 1000..5000 {
+        set.remove((1 << 20) + i * 3);
+    }
+    assert_eq!(set.len(), 1001);
+    assert!(set.contains((1 << 20) + 999 * 3));
+
+    let mut other = MemRoaringSet::new(
+        file.segment("other_dir").unwrap(),
+        file.segment("other_heap").unwrap(),
+    )
+    .unwrap();
+    for value in [7, 8, (1 << 20) + 3, (1 << 20) + 4, 1 << 40] {
+        other.insert(value).unwrap();
+    }
+    set.union_with(&other).unwrap();
+    assert_eq!(set.len(), 1004);
+    assert!(set.contains(1 << 40) && set.contains(8));
+    other.intersect_with(&set).unwrap();
+    assert_eq!(other.len(), 5);
+    set.intersect_with(&other).unwrap();
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        [7, 8, (1 << 20) + 3, (1 << 20) + 4, 1 << 40]
+    );
+    assert!(!set.remove(u64::MAX));
+    set.clear();
+    assert!(set.is_empty());
+    drop((set, other, file));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_lru_cache() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_lru_cache.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut cache =
+            unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 3) }.expect("create failed");
+        assert_eq!(cache.insert(1, 10), None);
+        assert_eq!(cache.insert(2, 20), None);
+        assert_eq!(cache.insert(3, 30), None);
+        assert_eq!(cache.get(&1), Some(&10));
+        // 2 is the least recently used now
+        assert_eq!(cache.insert(4, 40), Some((2, 20)));
+        assert_eq!(cache.insert(3, 31), Some((3, 30)));
+        assert_eq!(
+            cache.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(),
+            [(3, 31), (4, 40), (1, 10)]
+        );
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let err = unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 4) }.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut cache = unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 3) }.expect("open failed");
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.peek_lru(), Some((&1, &10)));
+    assert_eq!(cache.peek(&4), Some(&40));
+    // removing moves the last entry into the hole
+    assert_eq!(cache.remove(&3), Some(31));
+    assert_eq!(cache.remove(&3), None);
+    assert!(cache.contains_key(&1) && cache.contains_key(&4));
+    *cache.get_mut(&1).unwrap() += 1;
+    assert_eq!(cache.pop_lru(), Some((4, 40)));
+    for i in 100..200 {
+        cache.insert(i, i as u32);
+    }
+    assert_eq!(
+        cache.iter().map(|(&k, _)| k).collect::<Vec<_>>(),
+        [199, 198, 197]
+    );
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.get(&199), None);
+    drop(cache);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_lru_cache_cyclic_links() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_lru_cache_cyclic.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // 4 buckets, then the links of the entries: prev, next and chain
+    let link = |entry: usize, field: usize| 64 + 4 * 8 + entry * 24 + field * 8;
+    let corrupt = |offset: usize, target: u64| {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut cache =
+            unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 4) }.expect("create failed");
+        cache.insert(1, 10);
+        cache.insert(2, 20);
+        let mut vec_file = cache.into_memory();
+        vec_file[offset..offset + 8].copy_from_slice(&(target + 1).to_le_bytes());
+        unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 4) }.expect("open failed")
+    };
+    let panics =
+        |f: &mut dyn FnMut()| std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err();
+
+    // the least recently used entry leads back to the most recently used one
+    let cache = corrupt(link(0, 1), 1);
+    assert!(panics(&mut || {
+        cache.iter().count();
+    }));
+    assert!(panics(&mut || {
+        let _ = format!("{cache:?}");
+    }));
+
+    // an entry chained to itself
+    let mut cache = corrupt(link(0, 2), 0);
+    let bucket = |key: &u64| crate::hash_map::hash(key) & 3;
+    let absent = (3..).find(|key| bucket(key) == bucket(&1)).unwrap();
+    assert!(panics(&mut || {
+        cache.get(&absent);
+    }));
+    assert!(panics(&mut || {
+        cache.insert(absent, 30);
+    }));
+    drop(cache);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_lru_cache_churn() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_lru_cache_churn.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // the cache must keep the same recency order as a plain list, most recent first,
+    // while removals keep moving the last entry into holes
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut cache = unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 8) }.expect("create failed");
+    let mut model: Vec<(u64, u32)> = Vec::new();
+    for i in 0..2000u64 {
+        let key = i * 7 % 13;
+        match i % 5 {
+            0 | 1 => {
+                let evicted = if model.iter().any(|&(k, _)| k == key) || model.len() < 8 {
+                    None
+                } else {
+                    model.pop()
+                };
+                let old = model
+                    .iter()
+                    .position(|&(k, _)| k == key)
+                    .map(|at| model.remove(at));
+                model.insert(0, (key, i as u32));
+                assert_eq!(cache.insert(key, i as u32), old.or(evicted));
+            }
+            2 => {
+                let at = model.iter().position(|&(k, _)| k == key);
+                let expected = at.map(|at| model.remove(at).1);
+                assert_eq!(cache.remove(&key), expected);
+            }
+            3 => {
+                let at = model.iter().position(|&(k, _)| k == key);
+                let expected = at.map(|at| model.remove(at));
+                if let Some(entry) = expected {
+                    model.insert(0, entry);
+                }
+                assert_eq!(cache.get(&key).copied(), expected.map(|(_, v)| v));
+            }
+            _ => assert_eq!(cache.pop_lru(), model.pop()),
+        }
+        assert_eq!(cache.len(), model.len());
+        assert!(cache
+            .iter()
+            .map(|(&k, &v)| (k, v))
+            .eq(model.iter().copied()));
+    }
+
+    // the order survives reopening
+    let vec_file = cache.into_memory();
+    let cache = unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 8) }.expect("open failed");
+    assert!(cache
+        .iter()
+        .map(|(&k, &v)| (k, v))
+        .eq(model.iter().copied()));
+    drop(cache);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_lru_cache_corrupt() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_lru_cache_corrupt.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let corrupt = |offset: usize, bytes: &[u8]| {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut cache =
+            unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 4) }.expect("create failed");
+        cache.insert(1, 10);
+        cache.insert(2, 20);
+        let mut vec_file = cache.into_memory();
+        vec_file[offset..offset + bytes.len()].copy_from_slice(bytes);
+        unsafe { MemLruCache::<u64, u32, _>::new(vec_file, 4) }
+    };
+    let invalid = |offset: usize, bytes: &[u8]| {
+        let err = corrupt(offset, bytes).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    };
+    invalid(0, b"X");
+    // buckets not a power of two
+    invalid(32, &[3]);
+    // more entries than the capacity
+    invalid(40, &[5]);
+    // head or tail past the entries
+    invalid(48, &[3]);
+    invalid(56, &[3]);
+
+    // links and buckets are only checked when they are followed
+    let panics =
+        |f: &mut dyn FnMut()| std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err();
+    let bucket = 64 + (crate::hash_map::hash(&1u64) as usize & 3) * 8;
+    let mut cache = corrupt(bucket, &u64::MAX.to_le_bytes()).expect("open failed");
+    assert!(panics(&mut || {
+        cache.get(&1);
+    }));
+    // the prev link of the least recently used entry
+    let mut cache = corrupt(64 + 4 * 8, &1000u64.to_le_bytes()).expect("open failed");
+    assert!(panics(&mut || {
+        cache.get(&1);
+    }));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_bloom_filter() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_bloom_filter.memvec");
+    let mut other_path = std::env::temp_dir();
+    other_path.push("mem_bloom_filter_other.memvec");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&other_path);
+
+    let (bits, hashes) = MemBloomFilter::optimal_parameters(1000, 0.01);
+    assert_eq!((bits, hashes), (9586, 7));
+    {
+        let mut filter = MemBloomFilter::create(&path, bits, hashes).expect("create failed");
+        for i in 0..1000u64 {
+            filter.insert(&i);
+        }
+        assert!(!filter.insert(&0u64));
+        filter.flush().unwrap();
+    }
+
+    let mut filter = MemBloomFilter::open(&path).expect("open failed");
+    assert_eq!((filter.bits(), filter.hashes()), (bits, hashes));
+    assert!((0..1000u64).all(|i| filter.contains(&i)));
+    let false_positives = (1000..11000u64).filter(|i| filter.contains(i)).count();
+    assert!(false_positives < 300, "{false_positives}");
+
+    let mut other = MemBloomFilter::create(&other_path, bits, hashes).expect("create failed");
+    other.insert("record");
+    filter.merge(&other).unwrap();
+    assert!(filter.contains("record"));
+    other.clear();
+    assert_eq!(other.count_ones(), 0);
+    drop(other);
+    let other = MemBloomFilter::create(&other_path, bits + 1, hashes).expect("create failed");
+    assert!(filter.merge(&other).is_err());
+    drop((filter, other));
+    assert!(MemBloomFilter::from_vec_file(VecFile::create(&other_path).unwrap()).is_err());
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(other_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_series() {
+    let mut dir = std::env::temp_dir();
+    dir.push("mem_series");
+
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let time = |record: &(u64, u32)| record.0;
+    {
+        let mut series = unsafe { MemSeries::open(&dir, 100, time) }.expect("open failed");
+        for t in (0..500).step_by(10) {
+            series.append((t, t as u32 * 2)).unwrap();
+        }
+        // a late record of an earlier bucket is fine, of its own bucket is not
+        series.append((150, 0)).unwrap_err();
+        series.append((199, 1)).unwrap();
+        series.flush().unwrap();
+    }
+
+    let err = unsafe { MemSeries::open(&dir, 50, time) }.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let mut series = unsafe { MemSeries::open(&dir, 100, time) }.expect("open failed");
+    assert_eq!(series.len(), 51);
+    assert_eq!(
+        series.buckets().collect::<Vec<_>>(),
+        [0, 100, 200, 300, 400]
+    );
+    assert_eq!(series.segment(100).unwrap().last(), Some(&(199, 1)));
+    let times = series.range(185..=210).map(|r| r.0).collect::<Vec<_>>();
+    assert_eq!(times, [190, 199, 200, 210]);
+    assert_eq!(series.range(495..).count(), 0);
+    assert_eq!(series.range(..).count(), 51);
+    assert_eq!(
+        series
+            .range((Bound::Excluded(480), Bound::Unbounded))
+            .count(),
+        1
+    );
+
+    assert_eq!(series.drop_before(250).unwrap(), 2);
+    assert_eq!(series.buckets().collect::<Vec<_>>(), [200, 300, 400]);
+    assert_eq!(series.range(..300).map(|r| r.0).next(), Some(200));
+    drop(series);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+    std::fs::remove_dir_all(dir).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_push_n() {
+    let mut path = std::env::temp_dir();
+    path.push("push_n.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        vec.push(1);
+        vec.push_n(1 << 20, 0xAB);
+        vec.push_n(0, 0);
+        assert_eq!(vec.len(), (1 << 20) + 1);
+        assert!(vec[1..].iter().all(|&b| b == 0xAB));
+        vec.truncate(16);
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.truncate(2);
+    vec.push_n(3, 7);
+    let mut i = 0;
+    vec.fill_n(3, || {
+        i += 1;
+        i
+    });
+    assert_eq!(vec[2..], [7, 7, 7, 1, 2, 3]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_extend_from_slice() {
+    let mut path = std::env::temp_dir();
+    path.push("extend_from_slice.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // large enough for streaming stores, and misaligned by the first element
+    let large: Vec<u32> = (0..(3 << 20) / 2).collect();
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u32>() }.unwrap();
+        vec.push(7);
+        vec.extend_from_slice(&[1, 2, 3]);
+        vec.extend_from_slice(&[]);
+        vec.extend_from_slice(&large[1..]);
+        vec.flush().unwrap();
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u32>() }.unwrap();
+    assert_eq!(vec[..4], [7, 1, 2, 3]);
+    assert_eq!(vec[4..], large[1..]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_shrink_and_regrow() {
+    let mut path = std::env::temp_dir();
+    path.push("shrink_and_regrow.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.extend_from_slice(&(0..100000).collect::<Vec<_>>());
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        vec.truncate(10);
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 10);
+        let shrunk_len = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(full_len - shrunk_len, 99990 * 8);
+
+        // growing back within the old mapping reads the file's zeros, not the old records
+        vec.reserve_exact(100);
+        assert_eq!(vec.capacity(), 110);
+        assert!(vec
+            .spare_capacity_mut()
+            .iter()
+            .all(|v| unsafe { v.assume_init() } == 0));
+        vec.push_n(100, 1);
+        vec.flush().unwrap();
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.len(), 110);
+    assert_eq!(vec[..10], (0..10).collect::<Vec<_>>());
+    assert!(vec[10..].iter().all(|&v| v == 1));
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_bulk_load() {
+    let mut path = std::env::temp_dir();
+    path.push("bulk_load.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.push(7);
+        let generation = vec.as_mem().generation();
+        let mut load = vec.begin_bulk_load(1000).unwrap();
+        assert_eq!(load.capacity(), 1001);
+        for i in 0..1500 {
+            load.push(i).unwrap();
+        }
+        load.extend_from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!((load.len(), load.loaded()), (1504, 1503));
+        assert_eq!(load[1500], 1499);
+        load.finish().unwrap();
+        // the length is published once, not per element
+        assert!(vec.as_mem().generation() - generation < 10);
+        assert_eq!((vec.len(), vec.capacity()), (1504, 1504));
+
+        // dropping the guard publishes too
+        let mut load = vec.begin_bulk_load(10).unwrap();
+        load.push(42).unwrap();
+        drop(load);
+        assert_eq!((vec.len(), vec.capacity()), (1505, 1505));
+    }
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec[..3], [7, 0, 1]);
+    assert_eq!(vec[1501..], [1, 2, 3, 42]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_scan() {
+    let mut path = std::env::temp_dir();
+    path.push("scan.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&(0..100000).collect::<Vec<_>>());
+
+    let scan = vec.scan(4096).unwrap();
+    assert_eq!(scan.len(), 100000);
+    assert!(scan.copied().eq(0..100000));
+
+    let prefetcher = Prefetcher::start().unwrap();
+    let mut scan = vec.scan_with(&prefetcher, 1000, 4).unwrap();
+    assert_eq!(scan.nth(99990), Some(&99990));
+    assert_eq!(scan.as_slice().len(), 9);
+    drop(scan);
+    assert_eq!(
+        vec.scan_with(&prefetcher, 0, 0).unwrap().sum::<u64>(),
+        4999950000
+    );
+    drop((prefetcher, vec));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_byte_search() {
+    let mut path = std::env::temp_dir();
+    path.push("byte_search.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+    assert_eq!(vec.position_byte(b'\n'), None);
+    assert_eq!(vec.find_subslice(b""), Some(0));
+    for line in ["first", "second", "third"] {
+        vec.extend_from_slice(line.as_bytes());
+        vec.push(b'\n');
+    }
+    assert_eq!(vec.position_byte(b'\n'), Some(5));
+    assert_eq!(vec.rposition_byte(b'\n'), Some(18));
+    assert_eq!(vec.count_byte(b'\n'), 3);
+    assert_eq!(vec.count_byte(b'x'), 0);
+    assert_eq!(vec.find_subslice(b"d\nth"), Some(11));
+    assert_eq!(vec.find_subslice(b"fourth"), None);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_deferred_len() {
+    let mut path = std::env::temp_dir();
+    path.push("deferred_len.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    vec_file.set_deferred_len(Some(10));
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    let published = |path: &std::path::Path| {
+        let vec_file = VecFile::open(path).expect("open failed");
+        vec_file.len()
+    };
+    for i in 0..15 {
+        vec.push(i);
+    }
+    assert_eq!(vec.len(), 15);
+    assert_eq!(published(&path), 10);
+    vec.flush().unwrap();
+    assert_eq!(published(&path), 15);
+    vec.push(15);
+    assert_eq!(published(&path), 15);
+    drop(vec);
+    assert_eq!(published(&path), 16);
+
+    // publishing on flushes only
+    let mut vec_file = VecFile::open(&path).expect("open failed");
+    vec_file.set_deferred_len(Some(0));
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.truncate(3);
+    vec.push(3);
+    assert_eq!(published(&path), 16);
+    vec.as_mem_mut().set_deferred_len(None);
+    assert_eq!(published(&path), 4);
+    vec.push(4);
+    assert_eq!(published(&path), 5);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_error() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_vec_error.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.checked_push(1).unwrap();
+    vec.checked_push(2).unwrap();
+    assert!(matches!(
+        vec.checked_reserve(usize::MAX / 8),
+        Err(MemVecError::CapacityOverflow)
+    ));
+    drop(vec);
+
+    // an error of this crate wrapped in an I/O error is unwrapped
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let e: MemVecError = unsafe { VecFile::open_validated::<u64>(&path, 1, |&v| v < 2) }
+        .unwrap_err()
+        .into();
+    assert!(matches!(e, MemVecError::Corrupted(_)), "{e:?}");
+    let e: MemVecError = unsafe { vec_file.try_into_memvec::<[u64; 64]>() }
+        .unwrap_err()
+        .1
+        .into();
+    assert!(matches!(e, MemVecError::Conversion(_)));
+    let e = e.context("opening records");
+    assert!(matches!(e.root(), MemVecError::Conversion(_)));
+    assert!(e.to_string().starts_with("opening records: "));
+    let e = std::io::Error::from(e);
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    assert!(matches!(MemVecError::from(e), MemVecError::Context { .. }));
+
+    let e = MemVecError::from(std::fs::File::open(path.with_extension("missing")).unwrap_err());
+    assert!(matches!(&e, MemVecError::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mmap_file_map_options() {
+    let mut path = std::env::temp_dir();
+    path.push("map_options.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .expect("file failed");
+    // a header before the mapping and a trailer after it
+    file.set_len(8 + 32 + 8).unwrap();
+
+    let mut len: usize = 0;
+    let mut options = MapOptions::new();
+    options.offset(8).len(32).populate(true).huge_pages(true);
+    let mmap = MmapFile::new(file, &mut len, &options).expect("mmap failed");
+    assert_eq!(mmap.capacity(), 32);
+    let mut vec = unsafe { mmap.try_into_memvec::<u64>() }.unwrap();
+    vec.push_n(4, 7);
+    // shrinking keeps the trailer
+    vec.truncate(2);
+    vec.shrink_to_fit();
+    let file = vec.into_mem().into_file();
+    assert_eq!(file.metadata().unwrap().len(), 48);
+    drop(file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mmap_file_read_only() {
+    let mut path = std::env::temp_dir();
+    path.push("mmap_read_only.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut file = File::create(&path).expect("file failed");
+        file.write_all(&[0; 8]).unwrap();
+        for value in [1u64, 2, 3] {
+            file.write_all(&value.to_ne_bytes()).unwrap();
+        }
+    }
+
+    // read access only
+    let file = File::open(&path).expect("file failed");
+    let mut options = MapOptions::new();
+    options.offset(8);
+    let mmap = ReadOnlyMmapFile::new(file, 3, &options).expect("mmap failed");
+    assert_eq!(mmap.capacity(), 24);
+    let vec = unsafe { mmap.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(&*vec, &[1, 2, 3]);
+
+    let file = File::open(&path).expect("file failed");
+    let mmap = ReadOnlyMmapFile::new(file, 4, &options).expect("mmap failed");
+    let (_, e) = unsafe { mmap.try_into_memvec::<u64>() }.unwrap_err();
+    assert!(matches!(e, MemoryConversionError::SizeMismatch));
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_ref() {
+    let mut path = std::env::temp_dir();
+    path.push("mem_vec_ref.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    struct App {
+        events: VecFile,
+    }
+
+    {
+        let mut app = App {
+            events: VecFile::create(&path).expect("create failed"),
+        };
+        for round in 0..3u64 {
+            let mut vec: MemVecRef<u64, _> = unsafe { app.events.try_as_memvec() }.unwrap();
+            vec.push(round);
+            vec.extend_from_slice(&[round * 10]);
+        }
+        assert_eq!(Memory::len(&app.events), 6);
+
+        let (_, body, _) = unsafe { app.events.align_to::<u64>() };
+        assert_eq!(&body[..6], &[0, 0, 1, 10, 2, 20]);
+    }
+    {
+        let mut vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_as_memvec::<u64>() }.unwrap();
+        assert_eq!(&*vec, &[0, 0, 1, 10, 2, 20]);
+        assert_eq!(vec.as_mem().get().len(), 6);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_error_path() {
+    let mut path = std::env::temp_dir();
+    path.push("error_path.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let err = VecFile::open(&path).expect_err("file must not exist");
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().starts_with(&path.display().to_string()));
+    let err = MemVecError::from(err);
+    assert!(matches!(err, MemVecError::Context { .. }));
+    assert!(matches!(err.root(), MemVecError::Io(_)));
+
+    let err = VecFile::open_read_only(&path).expect_err("file must not exist");
+    assert!(err.to_string().contains(&*path.display().to_string()));
+
+    let mut len = 0;
+    let err = MmapFile::open(&path, &mut len, &MapOptions::new()).expect_err("file must not exist");
+    assert!(err.to_string().contains(&*path.display().to_string()));
+
+    let vec_file = VecFile::create(&path).expect("create failed");
+    assert_eq!(vec_file.path(), Some(path.as_path()));
+    drop(vec_file);
+    let mmap = MmapFile::open(&path, &mut len, &MapOptions::new()).expect("mmap failed");
+    assert_eq!(mmap.path(), Some(path.as_path()));
+    drop(mmap);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_conversions() {
+    let mut path = std::env::temp_dir();
+    path.push("conversions.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    fn push_all<A>(mem: A, values: &[u32]) -> A
+    where
+        A: Memory + core::fmt::Debug + From<MemVec<'static, u32, A>> + 'static,
+    {
+        let mut vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+        vec.extend_from_slice(values);
+        vec.into()
+    }
+
+    {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .expect("file failed");
+        VecFile::clear(&file).unwrap();
+        let vec_file = VecFile::try_from(file).expect("vec file failed");
+        let vec_file = push_all(vec_file, &[1, 2, 3]);
+        assert_eq!(Memory::len(&vec_file), 3);
+    }
+    {
+        let file = File::open(&path).expect("file failed");
+        let read_only = ReadOnlyVecFile::try_from(file).expect("vec file failed");
+        let vec = unsafe { read_only.try_into_memvec::<u32>() }.unwrap();
+        assert_eq!(&*vec, &[1, 2, 3]);
+        let read_only = ReadOnlyVecFile::from(vec);
+        assert_eq!(read_only.len(), 3);
+    }
+    let heap = push_all(HeapMemory::new(), &[4, 5]);
+    assert_eq!(Memory::len(&heap), 2);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg(feature = "bytemuck")]
+#[cfg_attr(miri, ignore)]
+fn vec_file_try_into_pod_vec() {
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec: MemVec<u64, VecFile> = vec_file.try_into().expect("vec failed");
+    vec.push(7);
+    let vec: MemVec<u64, HeapMemory> = HeapMemory::new().try_into().expect("vec failed");
+    assert!(vec.is_empty());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_u32_len_cap() {
+    let mut path = std::env::temp_dir();
+    path.push("u32_len_cap.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::builder()
+            .create(true)
+            .u32_len_cap(true)
+            .open(&path)
+            .expect("create failed");
+        assert!(vec_file.has_u32_len_cap());
+        let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        assert_eq!(vec.max_len(), u32::MAX as usize);
+        vec.checked_push(1).expect("push failed");
+        let err = vec
+            .checked_reserve(u32::MAX as usize)
+            .expect_err("must exceed the limit");
+        assert!(matches!(err, MemVecError::CapacityOverflow));
+        let err = vec
+            .try_reserve(u32::MAX as usize)
+            .expect_err("must exceed the limit");
+        assert!(matches!(
+            MemVecError::from(err),
+            MemVecError::CapacityOverflow
+        ));
+        assert_eq!(&*vec, &[1]);
+    }
+    {
+        // fixed at creation
+        let vec_file = VecFile::builder()
+            .create(true)
+            .open(&path)
+            .expect("open failed");
+        assert!(vec_file.has_u32_len_cap());
+        assert_eq!(Memory::len(&vec_file), 1);
+    }
+    {
+        // the cap holds for pushes through windows too
+        let windowed = unsafe { WindowedVecFile::<u8>::open(&path, 16) }.expect("open failed");
+        assert_eq!(windowed.max_len(), u32::MAX as usize);
+    }
+    assert!(!VecFile::temp().expect("temp failed").has_u32_len_cap());
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_io_write() {
+    let mut path = std::env::temp_dir();
+    path.push("io_write.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        let name = "memvec";
+        write!(vec, "{name}-{}", 42).unwrap();
+        let copied = std::io::copy(&mut &b" copied"[..], &mut vec).unwrap();
+        assert_eq!(copied, 7);
+        std::io::Write::flush(&mut vec).unwrap();
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        assert_eq!(&*vec, b"memvec-42 copied");
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_cursor() {
+    use std::io::{BufRead, Read, Seek, SeekFrom};
+
+    let mut path = std::env::temp_dir();
+    path.push("cursor.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        vec.extend_from_slice(b"MAGIClines\nof text\nend");
+
+        let mut cursor = vec.cursor();
+        let mut magic = [0; 5];
+        cursor.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, b"MAGIC");
+        let mut line = String::new();
+        cursor.read_line(&mut line).unwrap();
+        assert_eq!(line, "lines\n");
+        assert_eq!(cursor.position(), 11);
+
+        let mut cursor = vec.into_cursor();
+        assert_eq!(cursor.seek(SeekFrom::End(-3)).unwrap(), 19);
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"end");
+        assert!(cursor.seek(SeekFrom::Current(-24)).is_err());
+        cursor.set_position(100);
+        assert_eq!(cursor.read(&mut magic).unwrap(), 0);
+        assert!(cursor.read_exact(&mut magic).is_err());
+    }
+    {
+        let reader = VecFile::open_read_only(&path).expect("open failed");
+        let vec = unsafe { reader.try_into_memvec::<u8>() }.unwrap();
+        let lines: Vec<String> = MemCursor::new(vec).lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["MAGIClines", "of text", "end"]);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg(feature = "bytes")]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_bytes_buf() {
+    use ::bytes::{Buf, BufMut};
+
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+    vec.put_slice(b"GET ");
+    vec.put_u32(0x2f_0a_0d_0a);
+    assert_eq!(&*vec, b"GET /\n\r\n");
+
+    vec.reserve_exact(4);
+    let spare = vec.capacity() - vec.len();
+    let mut frozen = vec.frozen();
+    assert_eq!(frozen.remaining_mut(), spare);
+    frozen.put_u8(b'!');
+    assert_eq!(vec.last(), Some(&b'!'));
+
+    let mut cursor = vec.cursor();
+    assert_eq!(cursor.get_u32(), u32::from_be_bytes(*b"GET "));
+    cursor.advance(1);
+    assert_eq!(Buf::remaining(&cursor), 4);
+    assert_eq!(cursor.chunk(), b"\n\r\n!");
+}
+
+#[test]
+#[cfg(feature = "bytes")]
+fn frozen_bytes_buf_mut() {
+    use ::bytes::BufMut;
+
+    // on the heap, so miri checks that filling the chunks keeps the slices valid
+    let mut vec = unsafe { MemVec::<u8, _>::try_from_memory(HeapMemory::new()) }
+        .expect("memory is corrupted");
+    vec.put_slice(b"GET ");
+    let mut frozen = vec.reserve_exact_then_freeze(4).expect("reserve failed");
+    let head = frozen.as_slice();
+    frozen.put_slice(b"/\r\n");
+    frozen.put_u8(b'!');
+    assert_eq!(head, b"GET ");
+    assert_eq!(&frozen[..], b"GET /\r\n!");
+}
+
+#[test]
+#[cfg(feature = "arrow-buffer")]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_arrow_buffer() {
+    let vec_file = VecFile::temp().expect("temp failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<i64>() }.unwrap();
+    vec.extend_from_slice(&[3, -1, 4]);
+    let ptr = vec.as_ptr();
+
+    let buffer = vec.into_scalar_buffer();
+    // the mapping itself, not a copy
+    assert_eq!(buffer.as_ptr(), ptr);
+    assert_eq!(&*buffer, &[3, -1, 4]);
+    let bytes = buffer.clone().into_inner();
+    drop(buffer);
+    assert_eq!(bytes.len(), 24);
+    assert_eq!(bytes.typed_data::<i64>(), &[3, -1, 4]);
+
+    let empty = unsafe { HeapMemory::new().try_into_memvec::<i64>() }.unwrap();
+    assert!(empty.into_scalar_buffer().is_empty());
+}
+
+#[test]
+#[cfg(feature = "rkyv")]
+#[cfg_attr(miri, ignore)]
+fn mem_vec_rkyv_archive() {
+    #[derive(rkyv::Archive, rkyv::Serialize)]
+    struct Event {
+        id: u64,
+        name: String,
+        tags: Vec<u32>,
+    }
+
+    let mut path = std::env::temp_dir();
+    path.push("rkyv.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let event = Event {
+        id: 7,
+        name: "deploy".to_owned(),
+        tags: vec![1, 2, 3],
+    };
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        vec.push_archived(&event).unwrap();
+    }
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let vec = unsafe { vec_file.try_into_memvec::<u8>() }.unwrap();
+        let archived = vec.access_archived::<Event>().unwrap();
+        assert_eq!(archived.id, 7);
+        assert_eq!(archived.name, "deploy");
+        assert_eq!(archived.tags.as_slice(), &[1, 2, 3]);
+    }
+
+    let mut log = MemLog::new(HeapMemory::new(), true).expect("create failed");
+    let first = log.append_archived(&event).unwrap();
+    let second = log.append(b"not an archive").unwrap();
+    let archived = log.get_archived::<Event>(first).unwrap().unwrap();
+    assert_eq!(archived.name, "deploy");
+    let Some(Err(err)) = log.get_archived::<Event>(second) else {
+        panic!("validated a record which is no archive");
+    };
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(log.get_archived::<Event>(second + 1).is_none());
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg(feature = "capi")]
+#[cfg_attr(miri, ignore)]
+fn capi_vec_file() {
+    use crate::capi::*;
+
+    let mut path = std::env::temp_dir();
+    path.push("capi.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+    unsafe {
+        assert!(memvec_file_open(c_path.as_ptr(), 8, 0).is_null());
+        assert!(!memvec_last_error().is_null());
+
+        let file = memvec_file_open(c_path.as_ptr(), 8, MEMVEC_CREATE);
+        assert!(!file.is_null());
+        for i in 0..100u64 {
+            let record = i.to_ne_bytes();
+            assert_eq!(memvec_file_push_bytes(file, record.as_ptr(), 8), 0);
+        }
+        assert_eq!(memvec_file_push_bytes(file, [0u8; 3].as_ptr(), 3), -1);
+        assert_eq!(memvec_file_len(file), 100);
+        assert_eq!(memvec_file_flush(file), 0);
+        memvec_file_close(file);
+    }
+
+    // the same file as a MemVec of the record type
+    {
+        let vec_file = VecFile::open(&path).expect("open failed");
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        assert_eq!(vec.as_slice(), (0..100).collect::<Vec<_>>());
+        vec.push(100);
+    }
+
+    unsafe {
+        let file = memvec_file_open(c_path.as_ptr(), 8, MEMVEC_LOCK);
+        assert_eq!(memvec_file_len(file), 101);
+        let record = memvec_file_get(file, 100);
+        assert_eq!(core::slice::from_raw_parts(record, 8), 100u64.to_ne_bytes());
+        assert!(memvec_file_get(file, 101).is_null());
+        memvec_file_close(file);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn file_memory() {
+    let mut path = std::env::temp_dir();
+    path.push("file_memory.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mem = FileMemory::open(&path).expect("create failed");
+        let mut vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+        vec.extend_from_slice(&(0..1000).collect::<Vec<_>>());
+        vec.flush().expect("flush failed");
+        vec[0] = 42;
+        // written back on drop
+    }
+    {
+        let mem = FileMemory::open(&path).expect("open failed");
+        let mut vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+        assert_eq!(vec.len(), 1000);
+        assert_eq!(vec[0], 42);
+        assert_eq!(vec[999], 999);
+        vec.truncate(10);
+        vec.shrink_to_fit();
+        vec.flush().expect("flush failed");
+    }
+    {
+        let mem = FileMemory::open(&path).expect("open failed");
+        let vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+        assert_eq!(vec.as_slice(), [42, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 64 + 40);
+
+    std::fs::write(&path, b"not a memvec").unwrap();
+    let err = FileMemory::open(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn file_memory_without_mmap() {
+    let mut path = std::env::temp_dir();
+    path.push("file_memory_without_mmap.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mem = FileMemory::open(&path).expect("create failed");
+        let mut vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+        vec.extend_from_slice(&[1, 2, 3]);
+    }
+    let mem = FileMemory::open(&path).expect("open failed");
+    let vec = unsafe { mem.try_into_memvec::<u32>() }.unwrap();
+    assert_eq!(vec.as_slice(), &[1, 2, 3]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn mem_vec_as_bytes() {
+    let mut vec = unsafe { HeapMemory::new().try_into_memvec::<u16>() }.unwrap();
+    assert!(vec.as_bytes().is_empty());
+    vec.extend_from_slice(&[1, 0x0302]);
+    assert_eq!(
+        vec.as_bytes(),
+        [1u16.to_ne_bytes(), 0x0302u16.to_ne_bytes()].concat()
+    );
+    // the initialized elements only, not the capacity
+    vec.reserve(100);
+    assert_eq!(vec.as_bytes().len(), 4);
+
+    #[cfg(feature = "bytemuck")]
+    {
+        vec.as_bytes_mut().fill(0xff);
+        assert_eq!(vec.as_slice(), [u16::MAX, u16::MAX]);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_deferred_len_snapshot() {
+    let mut path = std::env::temp_dir();
+    path.push("deferred_len_snapshot.memvec");
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push("deferred_len_snapshot.memvec.snapshot");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&snapshot_path);
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    vec_file.set_deferred_len(Some(0));
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3]);
+    vec.as_mem()
+        .snapshot_to(&snapshot_path)
+        .expect("snapshot failed");
+    {
+        let snapshot = VecFile::open(&snapshot_path).expect("open failed");
+        let snapshot = unsafe { snapshot.try_into_memvec::<u64>() }.unwrap();
+        assert_eq!(snapshot.as_slice(), [1, 2, 3]);
+    }
+
+    vec.push(4);
+    vec.compact().expect("compact failed");
+    assert_eq!(vec.as_slice(), [1, 2, 3, 4]);
+    drop(vec);
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), [1, 2, 3, 4]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(snapshot_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_compact_keeps_lock() {
+    let mut path = std::env::temp_dir();
+    path.push("compact_lock.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let mut builder = VecFile::builder();
+    builder.create(true).single_writer(true);
+    let writer = builder.open(&path).expect("create failed");
+    let mut vec = unsafe { writer.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3]);
+    vec.compact().expect("compact failed");
+    let err = builder.open(&path).expect_err("second writer opened");
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    let reader = builder.open_read_only(&path).expect("open failed");
+    assert_eq!(
+        reader.writer_status().unwrap(),
+        WriterStatus::Active {
+            pid: std::process::id()
+        }
+    );
+    drop(vec);
+    assert_eq!(reader.writer_status().unwrap(), WriterStatus::Closed);
+    drop(reader);
+
+    let mut locked = VecFile::builder();
+    locked.lock(true);
+    let mut vec_file = locked.open(&path).expect("open failed");
+    vec_file.compact(24).expect("compact failed");
+    let err = locked.open(&path).expect_err("locked file opened");
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    drop(vec_file);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[cfg_attr(miri, ignore)]
+fn vec_file_auto_flush_compact() {
+    let mut path = std::env::temp_dir();
+    path.push("auto_flush_compact.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    // the files this process holds which were unlinked while open
+    let deleted = |path: &std::path::Path| {
+        let deleted = format!("{} (deleted)", path.display());
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|fd| std::fs::read_link(fd.unwrap().path()).ok())
+            .filter(|target| target.as_os_str() == deleted.as_str())
+            .count()
+    };
+    let vec_file = VecFile::builder()
+        .create(true)
+        .auto_flush(AutoFlush::every(std::time::Duration::from_millis(1)))
+        .open(&path)
+        .expect("create failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3]);
+    vec.compact().expect("compact failed");
+    // the flusher follows the compacted file instead of holding on to the replaced one
+    assert_eq!(deleted(&path), 0);
+    vec.push(4);
+    vec.as_mem_mut().disable_auto_flush().expect("flush failed");
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_append_vec_into_file_len() {
+    let mut path = std::env::temp_dir();
+    path.push("concurrent_append_len.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    vec_file.set_deferred_len(Some(0));
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2]);
+    let vec_file = vec.into_mem();
+    let vec = unsafe { ConcurrentAppendVec::<u64>::new(vec_file, 10) }.expect("reserve failed");
+    assert_eq!(vec.len(), 2);
+    assert_eq!(vec.append(3), Ok(2));
+    let vec_file = vec.into_file();
+    assert_eq!(Memory::len(&vec_file), 3);
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.push(4);
+    drop(vec);
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), [1, 2, 3, 4]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+#[cfg(target_os = "linux")]
+fn vec_file_journal_error() {
+    let mut path = std::env::temp_dir();
+    path.push("journal_error.memvec");
+    let mut journal_path = path.clone().into_os_string();
+    journal_path.push(".journal");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&journal_path);
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    // every write of the journal fails with ENOSPC
+    std::os::unix::fs::symlink("/dev/full", &journal_path).expect("symlink failed");
+    vec_file.enable_journal(8).expect("journal failed");
+    let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    vec.push(1);
+    vec.push(2);
+    assert_eq!(vec.as_slice(), [1, 2]);
+    assert!(vec.flush().is_err());
+    vec.flush().expect("flush failed");
+    drop(vec);
+    std::fs::remove_file(&journal_path).expect("delete fail");
+
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), [1, 2]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_resize_keeps_header() {
+    let mut path = std::env::temp_dir();
+    path.push("resize_keeps_header.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let mut vec_file = VecFile::builder()
+            .create(true)
+            .prefix_len(3)
+            .metadata_len(16)
+            .open(&path)
+            .expect("create failed");
+        vec_file.metadata_mut().copy_from_slice(&[7; 16]);
+        let mut vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+        vec.extend_from_slice(&(0..1000).collect::<Vec<_>>());
+        vec.truncate(10);
+        vec.shrink_to_fit();
+        assert_eq!(vec.as_mem().metadata(), [7; 16]);
+        vec.extend_from_slice(&(10..2000).collect::<Vec<_>>());
+        assert_eq!(vec.as_mem().metadata(), [7; 16]);
+        vec.as_mem_mut().metadata_mut()[0] = 8;
+    }
+
+    let vec_file = VecFile::builder()
+        .prefix_len(3)
+        .open(&path)
+        .expect("open failed");
+    assert_eq!(vec_file.metadata()[..2], [8, 7]);
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), (0..2000).collect::<Vec<_>>());
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_open_or_create_race() {
+    let mut path = std::env::temp_dir();
+    path.push("open_or_create_race.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let err = VecFile::open_or_create(&path, |_| Err(std::io::Error::other("init failed")))
+        .expect_err("init must fail");
+    assert_eq!(err.to_string(), "init failed");
+    assert!(!path.exists());
+
+    let inits = std::sync::atomic::AtomicUsize::new(0);
+    let kept: Vec<u64> = std::thread::scope(|scope| {
+        let racers: Vec<_> = (0..8)
+            .map(|_| {
+                scope.spawn(|| {
+                    let vec_file = VecFile::open_or_create(&path, |vec_file| {
+                        let init = inits.fetch_add(1, std::sync::atomic::Ordering::Relaxed) as u64;
+                        Memory::reserve(vec_file, 8)?;
+                        vec_file[..8].copy_from_slice(&init.to_ne_bytes());
+                        Memory::set_len(vec_file, 1);
+                        Ok(())
+                    })
+                    .expect("open failed");
+                    // never observed before it is initialized
+                    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+                    assert_eq!(vec.len(), 1);
+                    vec[0]
+                })
+            })
+            .collect();
+        racers
+            .into_iter()
+            .map(|racer| racer.join().unwrap())
+            .collect()
+    });
+    // every caller opened the same file
+    assert!(kept.iter().all(|&init| init == kept[0]));
+    assert!(kept[0] < inits.into_inner() as u64);
+
+    let name = path.file_name().unwrap().to_str().unwrap();
+    for entry in std::fs::read_dir(std::env::temp_dir()).unwrap() {
+        let entry_name = entry.unwrap().file_name();
+        let entry_name = entry_name.to_string_lossy();
+        assert!(!entry_name.starts_with(name) || entry_name == name);
+    }
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn memvec_pointer_provenance() {
+    // on the heap, so miri checks the pointers derived from the memory
+    let mut vec = unsafe { HeapMemory::new().try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+    vec.retain(|&x| x % 2 == 0);
+    assert_eq!(vec.as_slice(), &[2, 4, 6]);
+
+    // one base pointer serves several writes
+    let ptr = vec.as_mut_ptr();
+    unsafe {
+        ptr.write(10);
+        ptr.add(2).write(30);
+    }
+    assert_eq!(vec.as_slice(), &[10, 4, 30]);
+
+    vec.insert(1, 5);
+    assert_eq!(vec.remove(0), 10);
+    assert_eq!(vec.swap_remove(0), 5);
+    vec.push(4);
+    vec.dedup();
+    assert_eq!(vec.as_slice(), &[30, 4]);
+    let spare = vec.spare_capacity_mut();
+    spare[0].write(40);
+    unsafe { vec.set_len(3) };
+    assert_eq!(vec.as_bytes().len(), 24);
+    assert_eq!(vec.as_slice(), &[30, 4, 40]);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn sync_mem_vec_views() {
+    let vec_file = VecFile::temp().expect("temp failed");
+    let vec = unsafe { MemVec::<u64, _>::try_from_memory(vec_file) }.unwrap();
+    let vec = SyncMemVec::new(vec);
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let vec = vec.clone();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    // the slice stays mapped while the guard is held, even as the writer remaps
+                    let slice: &[u64] = &vec.read().unwrap();
+                    assert!(slice.iter().copied().eq(0..slice.len() as u64));
+                }
+            })
+        })
+        .collect();
+    for i in 0..1000 {
+        let mut writer = vec.write().unwrap();
+        writer.reserve_exact(1);
+        writer.push(i);
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    let mut writer = vec.write().unwrap();
+    writer[0] = 1;
+    writer.truncate(1);
+    assert_eq!(writer.pop(), Some(1));
+    assert_eq!(format!("{vec:?}"), "SyncMemVec { <locked> }");
+    drop(writer);
+
+    // a panicking writer poisons the vector, which is still reachable through the error
+    let handle = vec.clone();
+    std::thread::spawn(move || {
+        let mut writer = handle.write().unwrap();
+        writer.push(2);
+        panic!("writer panicked");
+    })
+    .join()
+    .unwrap_err();
+    assert!(vec.is_poisoned());
+    let reader = vec.read().expect_err("vector is poisoned").into_inner();
+    assert_eq!(reader[..], [2]);
+    drop(reader);
+    vec.clear_poison();
+    assert_eq!(vec.write().unwrap().len(), 1);
+}
+
+#[cfg(feature = "async")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_open_async() {
+    let mut path = std::env::temp_dir();
+    path.push("open_async.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let err = runtime
+        .block_on(VecFile::open_async(&path))
+        .expect_err("no file yet");
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    // a failing init leaves no file behind
+    runtime
+        .block_on(VecFile::open_or_create_async(&path, |_| {
+            Err(std::io::Error::other("init failed"))
+        }))
+        .expect_err("init failed");
+    assert!(!path.exists());
+
+    let vec_file = runtime
+        .block_on(VecFile::open_or_create_async(&path, |vec_file| {
+            Memory::reserve(vec_file, 8)?;
+            vec_file[..8].copy_from_slice(&7u64.to_ne_bytes());
+            Memory::set_len(vec_file, 1);
+            Ok(())
+        }))
+        .expect("create failed");
+    assert_eq!(Memory::len(&vec_file), 1);
+    drop(vec_file);
+    // an existing file is opened as it is
+    let vec_file = runtime
+        .block_on(VecFile::open_or_create_async(&path, |_| {
+            panic!("init of an existing file")
+        }))
+        .expect("open failed");
+    let vec = unsafe { vec_file.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(vec.as_slice(), &[7]);
+    drop(vec);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn mem_slab_pool() {
+    let mut path = std::env::temp_dir();
+    path.push("slab_pool.memvec");
+
+    let _ = std::fs::remove_file(&path);
+
+    {
+        let vec_file = VecFile::create(&path).expect("create failed");
+        let mut pool = unsafe { MemSlab::<u64, _>::new(vec_file) }.expect("create failed");
+        let keys: Vec<usize> = (0..4).map(|_| pool.acquire().unwrap()).collect();
+        assert_eq!(keys, [0, 1, 2, 3]);
+        *pool.get_mut(1).unwrap() = 11;
+        *pool.get_mut(2).unwrap() = 22;
+        assert_eq!(pool.release(1), Some(11));
+        assert_eq!(pool.release(1), None);
+        assert_eq!(pool.release(2), Some(22));
+    }
+
+    // the free list survives reopening, and the other keys stay put
+    let vec_file = VecFile::open(&path).expect("open failed");
+    let mut pool = unsafe { MemSlab::<u64, _>::new(vec_file) }.expect("open failed");
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.vacant_key(), 2);
+    assert_eq!(pool.acquire().unwrap(), 2);
+    assert_eq!(pool.acquire().unwrap(), 1);
+    assert_eq!(pool.get(1), Some(&0));
+    assert_eq!(pool.acquire().unwrap(), 4);
+    assert!((0..5).all(|key| pool.contains(key)));
+    drop(pool);
+
+    std::fs::remove_file(path).expect("delete fail");
+}
+
+#[test]
+fn mem_slab_corrupt() {
+    // the tag of the slot of `key`, or the head of the free list if `None`
+    let corrupt = |mem: &mut HeapMemory, key: Option<usize>, word: u64| {
+        let offset = match key {
+            Some(key) => 64 + key * 16,
+            None => 32,
+        };
+        mem[offset..][..8].copy_from_slice(&word.to_le_bytes());
+    };
+    let slab = || {
+        let mut slab = unsafe { MemSlab::<u64, _>::new(HeapMemory::new()) }.expect("create failed");
+        for i in 0..4 {
+            slab.insert(i).unwrap();
+        }
+        slab.remove(1);
+        slab.remove(2);
+        slab.into_memory()
+    };
+
+    // a head past the slots or at an occupied slot
+    for head in [5, 1] {
+        let mut mem = slab();
+        corrupt(&mut mem, None, head);
+        let err = unsafe { MemSlab::<u64, _>::new(mem) }.expect_err("head is broken");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // a link past the slots or to an occupied slot is refused once it becomes the head
+    for next in [100, 1] {
+        let mut mem = slab();
+        corrupt(&mut mem, Some(2), next);
+        let mut slab = unsafe { MemSlab::<u64, _>::new(mem) }.expect("open failed");
+        assert_eq!(slab.insert(20).unwrap(), 2);
+        let err = slab.insert(10).expect_err("link is broken");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(slab.len(), 3);
+        assert_eq!(slab.get(0), Some(&0));
+        assert_eq!(slab.get(3), Some(&3));
+    }
+
+    // a length past the mapped bytes
+    let mut mem = slab();
+    mem.shrink(64).unwrap();
+    let err = unsafe { MemSlab::<u64, _>::new(mem) }.expect_err("memory is short");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // a length of 0 with occupied slots does not wrap on removal
+    let mut mem = slab();
+    mem[24..32].copy_from_slice(&0u64.to_le_bytes());
+    let mut slab = unsafe { MemSlab::<u64, _>::new(mem) }.expect("open failed");
+    assert_eq!(slab.remove(0), Some(0));
+    assert_eq!(slab.len(), 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_compact_stale_temp() {
+    let mut path = std::env::temp_dir();
+    path.push("compact_stale_temp.memvec");
+    let mut stale_path = path.clone().into_os_string();
+    stale_path.push(".tmp");
+
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&stale_path, b"left by a crash").expect("write failed");
+
+    let mut vec_file = VecFile::create(&path).expect("create failed");
+    vec_file.reserve(64).expect("reserve failed");
+    let err = vec_file
+        .compact(128)
+        .expect_err("compacted past the data region");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    // a temporary file left behind by a crash neither blocks the rewrite nor is removed by it
+    vec_file.compact(32).expect("compact failed");
+    assert_eq!(vec_file.capacity(), 32);
+    assert_eq!(
+        std::fs::read(&stale_path).expect("read failed"),
+        b"left by a crash"
+    );
+    drop(vec_file);
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(stale_path).expect("delete fail");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn vec_file_snapshot_unlocked() {
+    let mut path = std::env::temp_dir();
+    path.push("snapshot_unlocked_source.memvec");
+    let mut snapshot_path = std::env::temp_dir();
+    snapshot_path.push("snapshot_unlocked.memvec");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&snapshot_path);
+
+    let mut builder = VecFile::builder();
+    builder.create(true).single_writer(true).mutex(true);
+    let writer = builder.open(&path).expect("create failed");
+    let mut vec = unsafe { writer.try_into_memvec::<u64>() }.unwrap();
+    vec.extend_from_slice(&[1, 2, 3]);
+    {
+        let _guard = vec.as_mem().lock_mutex().unwrap();
+        vec.as_mem()
+            .snapshot_to(&snapshot_path)
+            .expect("snapshot failed");
+    }
+
+    let reader = VecFile::open_read_only(&snapshot_path).expect("open failed");
+    assert_eq!(reader.writer_status().unwrap(), WriterStatus::Closed);
+    drop(reader);
+    let snapshot = builder.open(&snapshot_path).expect("open failed");
+    assert!(!snapshot.previous_writer_crashed());
+    let guard = snapshot
+        .try_lock_mutex()
+        .unwrap()
+        .expect("mutex of the snapshot is held");
+    assert!(!guard.recovered());
+    drop(guard);
+    let snapshot = unsafe { snapshot.try_into_memvec::<u64>() }.unwrap();
+    assert_eq!(snapshot.as_slice(), &[1, 2, 3]);
+    drop((vec, snapshot));
+
+    std::fs::remove_file(path).expect("delete fail");
+    std::fs::remove_file(snapshot_path).expect("delete fail");
+}
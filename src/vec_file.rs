@@ -0,0 +1,1395 @@
+use crate::{
+    auto_flush::{AutoFlush, Flusher},
+    error::with_path,
+    header::Header,
+    journal::Journal,
+    mem_vec::MemVec,
+    memory::Memory,
+    mmap::MmapRegion,
+    plain::Plain,
+    policy::GrowthPolicy,
+};
+use core::{
+    ops::{Deref, DerefMut},
+    sync::atomic::AtomicU32,
+};
+use memmap2::{MmapMut, MmapOptions};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+/// A vector file: a header holding the length followed by the data region.
+///
+/// It is [`Send`] and [`Sync`]. Handles in other threads or processes mapping the same file are
+/// not synchronized with it; see [`VecFile::generation`].
+pub struct VecFile {
+    /// The data region, mapped together with the header and the metadata region before it.
+    region: MmapRegion,
+    /// Offset of the header; the bytes before it belong to the application.
+    header_offset: u64,
+    pub(crate) path: Option<PathBuf>,
+    journal: Option<Journal>,
+    flusher: Option<Flusher>,
+    /// The size of a record when changes of the length are durably ordered.
+    durable: Option<usize>,
+    /// See [`VecFile::set_growth_policy`].
+    growth_policy: GrowthPolicy,
+    /// Holds an exclusive lock on the file. See [`crate::VecFileBuilder::lock`].
+    pub(crate) locked: bool,
+    /// Holds the lock word of the header. See [`crate::VecFileBuilder::single_writer`].
+    single_writer: bool,
+    previous_writer_crashed: bool,
+    /// See [`VecFile::set_deferred_len`].
+    deferred_len: Option<usize>,
+    /// The length, which the header holds too unless it is deferred. The handle is the writer,
+    /// so the header slots are verified only on open and recovery.
+    len: usize,
+    /// Changes of the length since it was last published.
+    unpublished: usize,
+    /// The first failure to journal or sync a change of the length, which cannot fail, to be
+    /// returned by the next sync.
+    error: Mutex<Option<std::io::Error>>,
+}
+
+impl core::fmt::Debug for VecFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VecFile")
+            .field("region", &self.region)
+            .field("len", &self.len())
+            .field("path", &self.path)
+            .field("journal", &self.journal)
+            .field("flusher", &self.flusher)
+            .field("durable", &self.durable)
+            .field("single_writer", &self.single_writer)
+            .finish()
+    }
+}
+
+impl VecFile {
+    const HEADER_LEN: usize = Header::LEN;
+
+    /// Open the vector file at `path`, or create it and run `init` if it does not exist.
+    ///
+    /// The file is built and initialized under a temporary name next to `path` and linked into
+    /// place only if `path` still does not exist, so nobody sees the file before it is
+    /// initialized. Racing callers may each run `init` on a file of their own, but only one of the
+    /// files is kept and every caller opens that one. If `init` fails, only the temporary file is
+    /// removed.
+    ///
+    /// On file systems without hard links, e.g. FAT, an empty file is created at `path` instead
+    /// and the initialized file is renamed over it, so a racing open may find the empty file and
+    /// fail with [`std::io::ErrorKind::InvalidData`].
+    pub fn open_or_create(
+        path: impl AsRef<Path>,
+        init: impl FnOnce(&mut VecFile) -> Result<(), std::io::Error>,
+    ) -> std::io::Result<Self> {
+        use std::io::ErrorKind;
+
+        let path = path.as_ref();
+        match Self::open(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            result => return result,
+        }
+        let temp_path = temp_path(path);
+        let result = (|| {
+            let mut vec_file = Self::create_new(&temp_path)?;
+            vec_file.path = Some(path.to_owned());
+            init(&mut vec_file)?;
+            vec_file._checkpoint()?;
+            // closed before the temporary name is removed, which Windows refuses for open files
+            drop(vec_file);
+            // fails rather than replaces if a racing caller placed its file first
+            std::io::Result::Ok(match std::fs::hard_link(&temp_path, path) {
+                // Linux reports EPERM for file systems without links
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::Unsupported | ErrorKind::PermissionDenied
+                    ) =>
+                {
+                    File::options()
+                        .write(true)
+                        .create_new(true)
+                        .open(path)
+                        .and_then(|_| std::fs::rename(&temp_path, path))
+                }
+                linked => linked,
+            })
+        })();
+        let _ = std::fs::remove_file(&temp_path);
+        match result? {
+            Ok(()) => Self::open(path),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Self::open(path),
+            Err(e) => Err(with_path(e, path)),
+        }
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::create_with_metadata(path, 0)
+    }
+
+    /// Create a vector file which reserves `metadata_len` bytes in its header for the application.
+    ///
+    /// The region is zero-filled and accessible through [`VecFile::metadata`] and
+    /// [`VecFile::metadata_mut`]. Its length is fixed for the lifetime of the file.
+    pub fn create_with_metadata(
+        path: impl AsRef<Path>,
+        metadata_len: usize,
+    ) -> std::io::Result<Self> {
+        Self::builder()
+            .create(true)
+            .truncate(true)
+            .metadata_len(metadata_len)
+            .open(path)
+    }
+
+    /// Create a vector file, failing with [`std::io::ErrorKind::AlreadyExists`] if `path` exists.
+    ///
+    /// Unlike [`VecFile::create`], at most one of racing callers succeeds, so only one of them
+    /// writes the header.
+    pub fn create_new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::builder().create_new(true).open(path)
+    }
+
+    /// Create an anonymous vector file in the temporary directory. See [`VecFile::temp_in`].
+    pub fn temp() -> std::io::Result<Self> {
+        Self::temp_in(std::env::temp_dir())
+    }
+
+    /// Create an anonymous vector file in `dir`, which vanishes when it is closed.
+    ///
+    /// The file has no name, or its name is removed right after creation, so it is reclaimed
+    /// even if the process is killed. On Linux it is created with `O_TMPFILE` where the file
+    /// system supports it; on Windows it is deleted on close.
+    pub fn temp_in(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = Self::temp_file(dir.as_ref())?;
+        Self::_clear(&file, 0, 0, 0)?;
+        Self::from_file(file)
+    }
+
+    fn temp_file(dir: &Path) -> std::io::Result<File> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            let result = File::options()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_TMPFILE)
+                .mode(0o600)
+                .open(dir);
+            match result {
+                Ok(file) => return Ok(file),
+                // the file system does not support O_TMPFILE; fall back to a removed named file
+                Err(e) if matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        loop {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.subsec_nanos());
+            let name = format!(
+                ".memvec-{}-{}-{}.tmp",
+                std::process::id(),
+                nanos,
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = dir.join(name);
+            let mut options = File::options();
+            options.create_new(true).read(true).write(true);
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::OpenOptionsExt;
+                const FILE_SHARE_READ: u32 = 0x1;
+                const FILE_SHARE_WRITE: u32 = 0x2;
+                const FILE_SHARE_DELETE: u32 = 0x4;
+                const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
+                options
+                    .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE)
+                    .custom_flags(FILE_FLAG_DELETE_ON_CLOSE);
+            }
+            match options.open(&path) {
+                Ok(file) => {
+                    #[cfg(not(windows))]
+                    std::fs::remove_file(&path)?;
+                    return Ok(file);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Open the vector file at `path`. See [`VecFile::from_file`].
+    ///
+    /// Errors of files opened by path, here and later on, name the file: converted into a
+    /// [`crate::MemVecError`], the cause is under [`crate::MemVecError::Context`].
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::builder().open(path)
+    }
+
+    /// Open a vector file as a vector of `T`, checking every `stride`-th record with `f`.
+    ///
+    /// A file whose length does not fit in its data region, or whose record is rejected by `f`,
+    /// is refused with [`std::io::ErrorKind::InvalidData`], the latter wrapping
+    /// [`crate::InvalidRecord`]. See [`MemVec::validate`].
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn open_validated<'a, T: Plain>(
+        path: impl AsRef<Path>,
+        stride: usize,
+        f: impl FnMut(&T) -> bool,
+    ) -> std::io::Result<MemVec<'a, T, Self>> {
+        let vec = MemVec::try_from_memory(Self::open(path)?)
+            .map_err(|(_, e)| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        vec.validate(stride, f)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(vec)
+    }
+
+    /// Set header and the value of len to 0
+    pub fn clear(file: &File) -> std::io::Result<()> {
+        Self::_clear(file, 0, 0, 0)
+    }
+
+    /// Initialize the header at `header_offset` with the optional `features`, keeping the bytes
+    /// before it.
+    pub(crate) fn _clear(
+        file: &File,
+        header_offset: u64,
+        metadata_len: usize,
+        features: u32,
+    ) -> std::io::Result<()> {
+        let metadata_len = u32::try_from(metadata_len).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "metadata region is too large",
+            )
+        })?;
+        assert!(file.metadata()?.len() <= header_offset);
+        file.set_len(header_offset + Self::HEADER_LEN as u64)?;
+        let mut header_mmap = Self::_header_mmap(file, header_offset, Self::HEADER_LEN)?;
+        let header = Self::_header_mut(&mut header_mmap);
+        header.init(metadata_len, features);
+        file.set_len(header_offset + header.data_offset() as u64)?;
+        Ok(())
+    }
+
+    /// Open a vector file.
+    ///
+    /// If the file has a checksum which was stored when it was closed last time, the data region
+    /// is verified against it. A mismatch is reported as [`std::io::ErrorKind::InvalidData`]
+    /// wrapping [`crate::ChecksumMismatch`].
+    ///
+    /// Records are stored in native byte order, so a file created on a machine of the other
+    /// endianness is refused with [`std::io::ErrorKind::InvalidData`].
+    pub fn from_file(file: File) -> std::io::Result<Self> {
+        Self::_from_file(file, 0, false)
+    }
+
+    /// [`VecFile::from_file`] with the header at `header_offset`, prefaulting the data region if
+    /// `populate` is set.
+    pub(crate) fn _from_file(
+        file: File,
+        header_offset: u64,
+        populate: bool,
+    ) -> std::io::Result<Self> {
+        let region = Self::map_file(file, header_offset, populate)?;
+        // verified before the handle exists, whose drop would store the checksum of corrupted data
+        let header = Self::_header(region.prefix());
+        let state = header.state();
+        if state.has_flag(Header::FLAG_CHECKSUM) && state.has_flag(Header::FLAG_CHECKSUM_VALID) {
+            let (offset, len) = (header.metadata_offset(), header.metadata_len());
+            header.verify_checksum(&region.prefix()[offset..][..len], region.deref())?;
+        }
+        let mut vec_file = Self {
+            region,
+            header_offset,
+            path: None,
+            journal: None,
+            flusher: None,
+            durable: None,
+            growth_policy: GrowthPolicy::default(),
+            locked: false,
+            single_writer: false,
+            previous_writer_crashed: false,
+            deferred_len: None,
+            len: 0,
+            unpublished: 0,
+            error: Mutex::new(None),
+        };
+        // validated to fit in usize by the header
+        vec_file.len = state.len as usize;
+        // the data may be modified from now on
+        let header = vec_file.header_mut();
+        header.enable_published_len();
+        header.update(|state| state.set_flag(Header::FLAG_CHECKSUM_VALID, false));
+        Ok(vec_file)
+    }
+
+    /// Validate the header and map it together with the data region.
+    fn map_file(file: File, header_offset: u64, populate: bool) -> std::io::Result<MmapRegion> {
+        if file.metadata()?.len() < header_offset + Self::HEADER_LEN as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec header",
+            ));
+        }
+        let header_mmap = Self::_header_mmap(&file, header_offset, Self::HEADER_LEN)?;
+        Self::_header(&header_mmap).validate()?;
+        let data_offset = Self::_header(&header_mmap).data_offset();
+        if file.metadata()?.len() < header_offset + data_offset as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec metadata region",
+            ));
+        }
+
+        // one mapping of the header and the data region, so resizing remaps once
+        let mut options = MmapOptions::new();
+        if populate {
+            options.populate();
+        }
+
+        let data_start = header_offset + data_offset as u64;
+        let capacity = Self::_header(&header_mmap).capacity();
+        drop(header_mmap);
+        let region = match capacity {
+            Some(capacity) => {
+                if file.metadata()?.len() < data_start + capacity {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "file is smaller than the recorded memvec capacity",
+                    ));
+                }
+                let capacity = usize::try_from(capacity).map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "memvec file is too large for this platform",
+                    )
+                })?;
+                let mut region =
+                    MmapRegion::with_len(file, options, header_offset, data_offset, capacity)?;
+                // the data region is last, so whatever follows it, e.g. the space of a grow or
+                // a shrink interrupted before the capacity was recorded, is slack
+                region.own_tail();
+                region
+            }
+            None => {
+                options.offset(header_offset);
+                MmapRegion::with_prefix(file, options, data_offset)?
+            }
+        };
+        Ok(region)
+    }
+
+    /// Convert a file of the legacy format into the current format.
+    ///
+    /// The legacy format stores `len` as a native `usize` in the first 8 bytes, followed by the
+    /// data region. Those files are not portable and are refused by [`VecFile::open`] until they
+    /// are upgraded. The converted file is written next to `path` and renamed over it, so `path`
+    /// is never left half-converted. Files already in the current format are left untouched.
+    pub fn upgrade(path: impl AsRef<Path>) -> std::io::Result<()> {
+        const LEGACY_HEADER_LEN: u64 = core::mem::size_of::<u64>() as u64;
+
+        let path = path.as_ref();
+        let mut legacy = File::open(path)?;
+        let mut legacy_header = [0; LEGACY_HEADER_LEN as usize];
+        legacy.read_exact(&mut legacy_header)?;
+        if legacy_header == Header::MAGIC {
+            return Ok(());
+        }
+        let mut len = [0; core::mem::size_of::<usize>()];
+        len.copy_from_slice(&legacy_header[..core::mem::size_of::<usize>()]);
+        let len = usize::from_ne_bytes(len);
+        let data_len = legacy.metadata()?.len().saturating_sub(LEGACY_HEADER_LEN);
+
+        Self::replace_file(path, |upgraded| {
+            Self::clear(upgraded)?;
+            let mut header_mmap = Self::_header_mmap(upgraded, 0, Self::HEADER_LEN)?;
+            let header = Self::_header_mut(&mut header_mmap);
+            header.set_capacity(data_len);
+            header.update(|state| state.len = len as u64);
+            header_mmap.flush()?;
+            upgraded.seek(SeekFrom::Start(Self::HEADER_LEN as u64))?;
+            legacy.seek(SeekFrom::Start(LEGACY_HEADER_LEN))?;
+            std::io::copy(&mut legacy, upgraded)?;
+            Ok(())
+        })
+        .map(drop)
+    }
+
+    /// Convert the headerless packed array of `record_size` bytes records at `path` into a vector
+    /// file in place.
+    ///
+    /// The records are shifted towards the end of the file to make room for the header, so no
+    /// second copy of the file is needed. The conversion is not crash-safe: if it is interrupted,
+    /// the file is left damaged. Use [`VecFile::import_raw`] when there is room for a copy.
+    pub fn adopt(path: impl AsRef<Path>, record_size: usize) -> std::io::Result<Self> {
+        const CHUNK_LEN: u64 = 1 << 20;
+
+        assert!(record_size > 0);
+        let path = path.as_ref();
+        let mut file = File::options().read(true).write(true).open(path)?;
+        let data_len = file.metadata()?.len();
+        if data_len % record_size as u64 != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw file is not a whole number of records",
+            ));
+        }
+        let len = data_len / record_size as u64;
+        if usize::try_from(data_len).is_err() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw file is too large for this platform",
+            ));
+        }
+        let mut magic = [0; Header::MAGIC.len()];
+        if file.read_exact(&mut magic).is_ok() && magic == Header::MAGIC {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "file is already a memvec file",
+            ));
+        }
+
+        let data_offset = Self::HEADER_LEN as u64;
+        file.set_len(data_offset + data_len)?;
+        let mut chunk = vec![0; CHUNK_LEN.min(data_len) as usize];
+        let mut end = data_len;
+        while end > 0 {
+            let start = end.saturating_sub(CHUNK_LEN);
+            let chunk = &mut chunk[..(end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(chunk)?;
+            file.seek(SeekFrom::Start(data_offset + start))?;
+            file.write_all(chunk)?;
+            end = start;
+        }
+        file.sync_data()?;
+
+        let mut header_mmap = Self::_header_mmap(&file, 0, Self::HEADER_LEN)?;
+        let header = Self::_header_mut(&mut header_mmap);
+        header.init(0, 0);
+        header.set_capacity(data_len);
+        header.update(|state| state.len = len);
+        header_mmap.flush()?;
+        drop((header_mmap, file));
+        Self::open(path)
+    }
+
+    /// Write a consistent copy of the file to `path`.
+    ///
+    /// The vector stays open and writable; the copy reflects the state at the time of the call.
+    /// The copy is written next to `path` and renamed over it, so `path` never holds a partial
+    /// snapshot. If the checksum is enabled, the snapshot carries a valid checksum. The copy has
+    /// no single writer and its process-shared mutex is unlocked, whoever holds those of this file.
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        // the copy of the header must hold the length
+        self.publish_len();
+        Self::replace_file(path.as_ref(), |snapshot| {
+            self.copy_prefix(snapshot)?;
+            snapshot.write_all(self.region.prefix())?;
+            snapshot.write_all(&self.region)?;
+            let copy = Self::_from_file(snapshot.try_clone()?, self.header_offset, false)?;
+            // the lock word and the mutex word name this process, which holds neither of the copy
+            copy.header().set_writer(0);
+            if let Some(word) = copy.mutex_word() {
+                word.store(0, core::sync::atomic::Ordering::Release);
+            }
+            // stores the checksum on drop
+            drop(copy);
+            Ok(())
+        })
+        .map(drop)
+    }
+
+    /// Create a vector file at `path` holding the records of the headerless file at `raw_path`.
+    ///
+    /// `raw_path` must be a plain packed array of records of `record_size` bytes; it is left
+    /// untouched. An existing file at `path` is overwritten.
+    pub fn import_raw(
+        raw_path: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+        record_size: usize,
+    ) -> std::io::Result<Self> {
+        assert!(record_size > 0);
+        let mut raw = File::open(raw_path)?;
+        let data_len = usize::try_from(raw.metadata()?.len()).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw file is too large for this platform",
+            )
+        })?;
+        if data_len % record_size != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "raw file is not a whole number of records",
+            ));
+        }
+        let mut vec_file = Self::create(path)?;
+        vec_file.reserve(data_len)?;
+        raw.read_exact(&mut vec_file.region[..data_len])?;
+        vec_file.set_len(data_len / record_size);
+        Ok(vec_file)
+    }
+
+    /// Write the records of `record_size` bytes to `path` as a headerless packed array.
+    ///
+    /// The file is written next to `path` and renamed over it, like [`VecFile::snapshot_to`].
+    pub fn export_raw(&self, path: impl AsRef<Path>, record_size: usize) -> std::io::Result<()> {
+        let data_len = self
+            .len()
+            .checked_mul(record_size)
+            .filter(|&data_len| data_len <= self.region.len())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "length exceeds the data region",
+                )
+            })?;
+        Self::replace_file(path.as_ref(), |raw| raw.write_all(&self.region[..data_len])).map(drop)
+    }
+
+    /// Rewrite the file so that the data region holds exactly its first `data_len` bytes.
+    ///
+    /// The compacted file is written next to the original one and atomically renamed over it,
+    /// then mapped in place of the original. Unlike [`Memory::shrink`], the whole file is
+    /// rewritten, so the space of the file is reclaimed even if the file system keeps it
+    /// fragmented. Only files opened or created by path can be compacted. On Windows, a mapped
+    /// file cannot be replaced and this fails leaving the file unchanged.
+    ///
+    /// A lock taken by [`crate::VecFileBuilder::lock`] or
+    /// [`crate::VecFileBuilder::single_writer`] is taken on the compacted file before it is
+    /// renamed into place, so no other writer can open it in between.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `data_len` exceeds the data region.
+    ///
+    /// See also [`crate::MemVec::compact`], which computes `data_len` from the length.
+    pub fn compact(&mut self, data_len: usize) -> std::io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "compaction requires a file opened by path",
+            ));
+        };
+        if data_len > self.region.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "compacted length exceeds the data region",
+            ));
+        }
+        self.publish_len();
+        if self.journal.is_some() {
+            self.checkpoint()?;
+        }
+        let file = Self::replace_file(&path, |compacted| {
+            if self.locked {
+                compacted.try_lock().map_err(std::io::Error::from)?;
+            }
+            self.copy_prefix(compacted)?;
+            // a lock word naming this process stays right, since the lock is kept
+            compacted.write_all(self.region.prefix())?;
+            compacted.write_all(&self.region[..data_len])?;
+            let mut header_mmap =
+                Self::_header_mmap(compacted, self.header_offset, Self::HEADER_LEN)?;
+            Self::_header_mut(&mut header_mmap).set_capacity(data_len as u64);
+            header_mmap.flush()
+        })?;
+        self.region = Self::map_file(file, self.header_offset, false)?;
+        self.capacity_changed();
+        if let Some(flusher) = &self.flusher {
+            flusher.retarget(self.file().try_clone()?);
+        }
+        Ok(())
+    }
+
+    /// Write the application bytes before the header to `to`.
+    fn copy_prefix(&self, to: &mut File) -> std::io::Result<()> {
+        let mut file = self.file();
+        file.seek(SeekFrom::Start(0))?;
+        std::io::copy(&mut file.take(self.header_offset), to)?;
+        Ok(())
+    }
+
+    /// Create a file under a unique name next to `path` with `write`, sync it and rename it over
+    /// `path`, returning it open for reading and writing.
+    fn replace_file(
+        path: &Path,
+        write: impl FnOnce(&mut File) -> std::io::Result<()>,
+    ) -> std::io::Result<File> {
+        let temp_path = temp_path(path);
+        // only a file this call created is removed on failure
+        let mut file = File::options()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .open(&temp_path)?;
+        let result = (|| {
+            write(&mut file)?;
+            file.sync_all()?;
+            std::fs::rename(&temp_path, path)?;
+            Ok(file)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    /// Map the header at `offset` and the metadata region; `len` is the offset of the data region
+    /// from the header.
+    fn _header_mmap(file: &File, offset: u64, len: usize) -> std::io::Result<MmapMut> {
+        let mut header_options = MmapOptions::new();
+        header_options.offset(offset).len(len);
+        let header_mmap = unsafe { header_options.map_mut(file) }?;
+        {
+            // validation
+            let (prefix, body, _suffix) = unsafe { header_mmap.deref().align_to::<Header>() };
+            assert_eq!(prefix.len(), 0);
+            assert!(!body.is_empty());
+        }
+        Ok(header_mmap)
+    }
+
+    fn _header(header_mmap: &[u8]) -> &Header {
+        unsafe { &*(header_mmap.as_ptr().cast::<Header>()) }
+    }
+
+    fn _header_mut(header_mmap: &mut [u8]) -> &mut Header {
+        unsafe { &mut *(header_mmap.as_mut_ptr().cast::<Header>()) }
+    }
+
+    pub(crate) fn header(&self) -> &Header {
+        Self::_header(self.region.prefix())
+    }
+
+    /// Start writing back the dirty pages of the data region and the header without waiting.
+    #[cfg(feature = "async")]
+    pub(crate) fn flush_start(&self) -> std::io::Result<()> {
+        self.take_error()?;
+        self.publish_len();
+        self.region.flush_async()
+    }
+
+    /// Write the bytes of `range` of the data region durably, without the header.
+    pub(crate) fn sync_data_range(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        self.region.flush_range(range)
+    }
+
+    pub(crate) fn sync_header(&self) -> std::io::Result<()> {
+        self.take_error()?;
+        self.publish_len();
+        self.region.flush_prefix()
+    }
+
+    /// The word of the process-shared mutex, if the file has one.
+    pub(crate) fn mutex_word(&self) -> Option<&AtomicU32> {
+        self.header()
+            .has_feature(Header::FEATURE_MUTEX)
+            // the extension block follows the header
+            .then(|| unsafe {
+                &*self
+                    .region
+                    .prefix()
+                    .as_ptr()
+                    .add(Header::LEN)
+                    .cast::<AtomicU32>()
+            })
+    }
+
+    fn header_mut(&mut self) -> &mut Header {
+        Self::_header_mut(self.region.prefix_mut())
+    }
+
+    /// The user metadata region reserved by [`VecFile::create_with_metadata`].
+    pub fn metadata(&self) -> &[u8] {
+        let header = self.header();
+        let (offset, len) = (header.metadata_offset(), header.metadata_len());
+        &self.region.prefix()[offset..][..len]
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut [u8] {
+        let header = self.header();
+        let (offset, len) = (header.metadata_offset(), header.metadata_len());
+        &mut self.region.prefix_mut()[offset..][..len]
+    }
+
+    /// A counter increased on every change of the length or the capacity of the file.
+    ///
+    /// Every handle mapping the same file observes the same counter, so a reader can cheaply find
+    /// out whether a writer touched the file by comparing it with a previously taken value.
+    /// Modifications of existing records through the mapping are not counted.
+    pub fn generation(&self) -> u64 {
+        self.header().written_state().generation
+    }
+
+    /// Whether the file has changed since `generation` was taken by [`VecFile::generation`].
+    pub fn has_changed_since(&self, generation: u64) -> bool {
+        self.generation() != generation
+    }
+
+    /// Whether a mutation rewriting records in place, e.g. [`MemVec::retain`], was interrupted by
+    /// a panic or a crash, so the records may be half-mutated.
+    ///
+    /// The flag is stored in the header and stays set until [`VecFile::clear_poison`] is called.
+    pub fn is_poisoned(&self) -> bool {
+        self.header()
+            .written_state()
+            .has_flag(Header::FLAG_POISONED)
+    }
+
+    /// Whether the file was created with [`crate::VecFileBuilder::u32_len_cap`].
+    pub fn has_u32_len_cap(&self) -> bool {
+        self.header().has_feature(Header::FEATURE_U32_LEN_CAP)
+    }
+
+    /// Clear the flag of [`VecFile::is_poisoned`], after the records were checked or repaired.
+    pub fn clear_poison(&mut self) {
+        self.header_mut()
+            .update_written(|state| state.set_flag(Header::FLAG_POISONED, false));
+    }
+
+    /// Whether the data region is covered by a checksum.
+    pub fn has_checksum(&self) -> bool {
+        self.header()
+            .written_state()
+            .has_flag(Header::FLAG_CHECKSUM)
+    }
+
+    /// Enable or disable the data checksum.
+    ///
+    /// When enabled, the checksum is stored when the file is closed and verified when it is
+    /// opened again. A file which was not closed cleanly (e.g. the process crashed) has no valid
+    /// checksum and is not verified on the next open.
+    pub fn set_checksum(&mut self, enabled: bool) {
+        self.header_mut().update_written(|state| {
+            state.set_flag(Header::FLAG_CHECKSUM, enabled);
+            state.set_flag(Header::FLAG_CHECKSUM_VALID, false);
+        });
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        self.header()
+            .compute_checksum(self.metadata(), self.region.deref())
+    }
+
+    /// Store the checksum of the current data region into the header.
+    ///
+    /// Any modification after this call invalidates the stored checksum until it is stored again.
+    pub fn update_checksum(&mut self) {
+        if !self.has_checksum() {
+            return;
+        }
+        let checksum = self.compute_checksum();
+        self.header_mut().update_written(|state| {
+            state.checksum = checksum;
+            state.set_flag(Header::FLAG_CHECKSUM_VALID, true);
+        });
+    }
+
+    /// Verify the data region against the checksum stored in the header.
+    pub fn verify_checksum(&self) -> std::io::Result<()> {
+        self.header()
+            .verify_checksum(self.metadata(), self.region.deref())
+    }
+
+    /// Enable the write-ahead journal for records of `record_size` bytes.
+    ///
+    /// Every change of the length is durably recorded, together with the bytes of the appended
+    /// records, in a sidecar file next to the vector file before it is applied. When the file is
+    /// opened by path after a crash, the journal is replayed, so no acknowledged push is lost.
+    /// Modifications of existing records through the mapping are not journaled.
+    ///
+    /// The journal is checkpointed, i.e. the vector file is synced and the journal is emptied,
+    /// when it grows large, on [`VecFile::checkpoint`] and when the file is closed. Only files
+    /// opened or created by path can be journaled. Since a change of the length cannot fail, a
+    /// failed journal write is returned by the next sync, e.g. [`MemVec::flush`], or checkpoint.
+    pub fn enable_journal(&mut self, record_size: usize) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "journal requires a file opened by path",
+            ));
+        };
+        self.journal = Some(Journal::open(Journal::path_for(path), record_size)?);
+        Ok(())
+    }
+
+    /// Sync the file and empty the journal.
+    pub fn checkpoint(&mut self) -> std::io::Result<()> {
+        self.take_error()?;
+        self._checkpoint()
+    }
+
+    fn _checkpoint(&mut self) -> std::io::Result<()> {
+        self.publish_len();
+        self.region.flush()?;
+        self.region.flush_prefix()?;
+        if let Some(journal) = &mut self.journal {
+            journal.clear()?;
+        }
+        Ok(())
+    }
+
+    /// Cut the length down to the number of whole records of `record_size` bytes the data region
+    /// holds, returning the number of records dropped.
+    ///
+    /// This is the escape hatch for a file whose length is corrupted beyond its data region, which
+    /// [`MemVec::try_from_memory`] refuses with [`crate::MemoryConversionError::SizeMismatch`].
+    pub fn recover_truncate(&mut self, record_size: usize) -> usize {
+        assert!(record_size > 0);
+        let len = self.len();
+        let max_len = self.region.len() / record_size;
+        if len <= max_len {
+            return 0;
+        }
+        self.len = max_len;
+        self.header_mut().update(|state| {
+            state.len = max_len as u64;
+            state.generation = state.generation.wrapping_add(1);
+        });
+        len - max_len
+    }
+
+    /// The offset of the data region from the beginning of the file.
+    pub(crate) fn data_file_offset(&self) -> u64 {
+        self.header_offset + self.header().data_offset() as u64
+    }
+
+    /// The mapped capacity of the data region in bytes.
+    pub fn capacity(&self) -> usize {
+        self.region.len()
+    }
+
+    /// See [`crate::MmapFile::refresh_len`].
+    pub fn refresh_len(&mut self) -> std::io::Result<()> {
+        self.region.refresh_len()
+    }
+
+    /// See [`crate::MmapFile::set_page_aligned`].
+    pub fn set_page_aligned(&mut self, page_aligned: bool) {
+        self.region.set_page_aligned(page_aligned);
+    }
+
+    /// Set the growth policy of the vectors created over the file from now on, which each may set
+    /// its own by [`MemVec::set_growth_policy`]. The policy is not recorded in the file.
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.growth_policy = policy;
+    }
+
+    /// Take the lock word of the header for this process. The caller holds an exclusive lock on
+    /// the file, so a lock word left set means the last writer did not close the file.
+    pub(crate) fn acquire_writer(&mut self) -> std::io::Result<()> {
+        self.previous_writer_crashed = self.header().writer() != 0;
+        self.header().set_writer(std::process::id());
+        self.region.flush_prefix()?;
+        self.single_writer = true;
+        Ok(())
+    }
+
+    /// Whether the file was opened as the single writer and the previous single writer had not
+    /// closed it, e.g. because its process crashed.
+    ///
+    /// The lock word does not tell how far the crashed writer got, so records it appended may
+    /// be incomplete unless the file is durable or journaled.
+    pub fn previous_writer_crashed(&self) -> bool {
+        self.previous_writer_crashed
+    }
+
+    pub(crate) fn has_journal(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Whether changes of the length are durably ordered. See [`VecFile::set_durable`].
+    pub fn is_durable(&self) -> bool {
+        self.durable.is_some()
+    }
+
+    /// Order changes of the length durably for records of `record_size` bytes, or stop it with
+    /// `None`.
+    ///
+    /// When enabled, the appended records are synced before the length is advanced, and the
+    /// header is synced right after, so a crash never exposes a length covering records which did
+    /// not reach the disk. Every change of the length costs two syncs of the touched pages. A
+    /// failed sync does not stop the change and is returned by the next sync, e.g.
+    /// [`MemVec::flush`].
+    pub fn set_durable(&mut self, record_size: Option<usize>) {
+        self.durable = record_size;
+    }
+
+    /// Keep changes of the length in the handle and write them to the header only every
+    /// `publish_every` changes, on flushes and when the file is closed; `Some(0)` publishes only
+    /// on the latter. `None`, the default, writes every change through.
+    ///
+    /// Every change of the length otherwise dirties the header page, which doubles the pages
+    /// written back for appends of small records. Meanwhile other handles, e.g.
+    /// [`crate::Follower`], and a crash see the last published length and
+    /// [`VecFile::generation`] is bumped only on publication. Durable and journaled files order
+    /// every change of the length, so they publish every change regardless.
+    pub fn set_deferred_len(&mut self, publish_every: Option<usize>) {
+        self.publish_len();
+        self.deferred_len = publish_every;
+        self.unpublished = 0;
+    }
+
+    /// Write the deferred length to the header, if it differs.
+    fn publish_len(&self) {
+        if self.deferred_len.is_none() {
+            return;
+        }
+        let len = self.len;
+        let old_len = self.header().written_state().len as usize;
+        if old_len == len {
+            return;
+        }
+        self.header().update_written(|state| {
+            state.len = len as u64;
+            state.generation = state.generation.wrapping_add(1);
+        });
+        if let Some(flusher) = &self.flusher {
+            flusher.record_len_change(old_len, len);
+        }
+    }
+
+    /// Start a background thread flushing the file to the disk as configured by `policy`.
+    ///
+    /// Without it, modifications reach the disk only when the operating system writes them back,
+    /// so a crash of the machine may lose an unbounded amount of data. The thread flushes once
+    /// more and stops when the file is closed. Replaces the previous policy, if any.
+    pub fn enable_auto_flush(&mut self, policy: AutoFlush) -> std::io::Result<()> {
+        self.disable_auto_flush()?;
+        self.flusher = Some(Flusher::start(self.file().try_clone()?, policy)?);
+        Ok(())
+    }
+
+    /// Stop the background flusher, returning the last error it encountered.
+    pub fn disable_auto_flush(&mut self) -> std::io::Result<()> {
+        match self.flusher.take() {
+            Some(flusher) => flusher.stop(),
+            None => Ok(()),
+        }
+    }
+
+    /// Keep the first error of a change of the length for [`VecFile::take_error`].
+    fn record_error(&mut self, e: std::io::Error) {
+        let error = self.error.get_mut().unwrap_or_else(PoisonError::into_inner);
+        error.get_or_insert(e);
+    }
+
+    /// Return the error recorded since the last call, if any.
+    fn take_error(&self) -> std::io::Result<()> {
+        let error = self
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        error.map_or(Ok(()), |e| Err(self.path_error(e)))
+    }
+
+    /// Take the length from the header after it was updated around the handle, e.g. by
+    /// [`crate::ConcurrentAppendVec`].
+    pub(crate) fn reload_len(&mut self) {
+        self.len = self.header().written_state().len as usize;
+    }
+
+    /// Record the capacity of the region and bump the generation after a resize.
+    fn capacity_changed(&mut self) {
+        let capacity = self.region.len() as u64;
+        let header = self.header_mut();
+        header.set_capacity(capacity);
+        header.update_written(|state| state.generation = state.generation.wrapping_add(1));
+    }
+
+    pub(crate) fn replay_journal(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let journal_path = Journal::path_for(path);
+        let replayed = Journal::replay(&journal_path, |offset, len, bytes| {
+            let end = offset as usize + bytes.len();
+            if end > self.region.len() {
+                self.region.reserve(end)?;
+                self.capacity_changed();
+            }
+            self.region[offset as usize..end].copy_from_slice(bytes);
+            self.len = len as usize;
+            self.header_mut().update(|state| {
+                state.len = len;
+                state.generation = state.generation.wrapping_add(1);
+            });
+            Ok(())
+        })?;
+        if replayed {
+            self.region.flush()?;
+            self.region.flush_prefix()?;
+            File::options()
+                .write(true)
+                .open(&journal_path)?
+                .set_len(0)?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.publish_len();
+        self.update_checksum();
+        if self.journal.is_some() {
+            let _ = self._checkpoint();
+        }
+        let _ = self.disable_auto_flush();
+        if self.single_writer {
+            self.header().set_writer(0);
+            let _ = self.region.flush_prefix();
+        }
+    }
+
+    pub fn into_file(self) -> File {
+        let mut this = core::mem::ManuallyDrop::new(self);
+        this.close();
+        // SAFETY: `this` is never used or dropped again
+        let (region, path, journal, flusher, error) = unsafe {
+            (
+                core::ptr::read(&this.region),
+                core::ptr::read(&this.path),
+                core::ptr::read(&this.journal),
+                core::ptr::read(&this.flusher),
+                core::ptr::read(&this.error),
+            )
+        };
+        drop((path, journal, flusher, error));
+        region.into_file()
+    }
+
+    /// The path the file was opened or created by, which its errors name.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn file(&self) -> &File {
+        self.region.file()
+    }
+}
+
+impl Drop for VecFile {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl core::ops::Deref for VecFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.region.deref()
+    }
+}
+
+impl core::ops::DerefMut for VecFile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.region.deref_mut()
+    }
+}
+
+impl VecFile {
+    /// Name the file in `e`, if it was opened by path.
+    pub(crate) fn path_error(&self, e: std::io::Error) -> std::io::Error {
+        match &self.path {
+            Some(path) => with_path(e, path),
+            None => e,
+        }
+    }
+
+    /// Shrink the region, recording the smaller capacity before the file is truncated.
+    ///
+    /// Growing records the capacity after the file is extended, so the recorded capacity never
+    /// exceeds the file: a crash in between leaves only slack past it, not a file refused as
+    /// smaller than its capacity.
+    fn shrink_region(&mut self, capacity: usize) -> std::io::Result<()> {
+        if capacity < self.region.len() {
+            self.header_mut().set_capacity(capacity as u64);
+        }
+        let result = self.region.shrink(capacity);
+        // the capacity of the region, which is kept if it could not be shrunk
+        self.capacity_changed();
+        result
+    }
+}
+
+impl Memory for VecFile
+where
+    Self: Deref<Target = [u8]> + DerefMut<Target = [u8]>,
+{
+    type Error = std::io::Error;
+
+    fn as_ptr(&self) -> *const u8 {
+        self.region.as_ptr()
+    }
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.region.as_mut_ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// # Panics
+    /// Panics if `len` exceeds [`Memory::max_len`], which [`MemVec`] never grows past.
+    fn set_len(&mut self, len: usize) {
+        assert!(len <= self.max_len(), "length exceeds the u32 length cap");
+        if let Some(publish_every) = self.deferred_len {
+            if self.journal.is_none() && self.durable.is_none() {
+                self.len = len;
+                self.unpublished += 1;
+                if self.unpublished == publish_every {
+                    self.unpublished = 0;
+                    self.publish_len();
+                }
+                return;
+            }
+            // the change is ordered after the deferred ones
+            self.publish_len();
+        }
+        let old_len = self.len;
+        if let Some(journal) = &mut self.journal {
+            // cut to the region for a wrong record size, like the durable range below
+            let record_size = journal.record_size();
+            let end = len.saturating_mul(record_size).min(self.region.len());
+            let offset = old_len.min(len).saturating_mul(record_size).min(end);
+            let bytes = if len > old_len {
+                &self.region[offset..end]
+            } else {
+                &[]
+            };
+            if let Err(e) = journal.append(offset as u64, len as u64, bytes) {
+                self.record_error(e);
+            }
+        }
+        if let Some(record_size) = self.durable.filter(|_| len > old_len) {
+            // only the appended records; the range is cut to the region for a wrong record size
+            let end = len.saturating_mul(record_size).min(self.region.len());
+            let start = old_len.saturating_mul(record_size).min(end);
+            if let Err(e) = self.region.flush_range(start..end) {
+                self.record_error(e);
+            }
+        }
+        self.len = len;
+        self.header_mut().update_written(|state| {
+            state.len = len as u64;
+            state.generation = state.generation.wrapping_add(1);
+        });
+        if self.durable.is_some() {
+            if let Err(e) = self.region.flush_prefix() {
+                self.record_error(e);
+            }
+        }
+        if let Some(flusher) = &self.flusher {
+            flusher.record_len_change(old_len, len);
+        }
+        if self.journal.as_ref().is_some_and(Journal::needs_checkpoint) {
+            if let Err(e) = self._checkpoint() {
+                self.record_error(e);
+            }
+        }
+    }
+
+    fn max_len(&self) -> usize {
+        if self.has_u32_len_cap() {
+            u32::MAX as usize
+        } else {
+            usize::MAX
+        }
+    }
+
+    fn capacity_overflow(&self) -> Option<std::io::Error> {
+        Some(crate::MemVecError::CapacityOverflow.into())
+    }
+
+    fn growth_policy(&self) -> GrowthPolicy {
+        self.growth_policy
+    }
+
+    fn reserve(&mut self, capacity: usize) -> std::io::Result<()> {
+        self.region
+            .reserve(capacity)
+            .map_err(|e| self.path_error(e))?;
+        self.capacity_changed();
+        Ok(())
+    }
+
+    fn shrink(&mut self, capacity: usize) -> Result<(), Self::Error> {
+        self.shrink_region(capacity).map_err(|e| self.path_error(e))
+    }
+
+    fn sync(&self, range: core::ops::Range<usize>) -> std::io::Result<()> {
+        self.region
+            .flush_range(range)
+            .and_then(|()| self.sync_header())
+            .map_err(|e| self.path_error(e))
+    }
+
+    fn set_poisoned(&mut self, poisoned: bool) -> bool {
+        let old = self.is_poisoned();
+        if old != poisoned {
+            self.header_mut()
+                .update_written(|state| state.set_flag(Header::FLAG_POISONED, poisoned));
+            if self.durable.is_some() {
+                if let Err(e) = self.region.flush_prefix() {
+                    self.record_error(e);
+                }
+            }
+        }
+        old
+    }
+}
+
+impl TryFrom<File> for VecFile {
+    type Error = std::io::Error;
+
+    /// See [`VecFile::from_file`].
+    fn try_from(file: File) -> std::io::Result<Self> {
+        Self::from_file(file)
+    }
+}
+
+/// Safe since any bytes are a valid [`bytemuck::Pod`]; see [`MemVec::from_memory_pod`].
+#[cfg(feature = "bytemuck")]
+impl<'a, T: bytemuck::Pod> TryFrom<VecFile> for MemVec<'a, T, VecFile> {
+    type Error = (VecFile, crate::MemoryConversionError);
+
+    fn try_from(vec_file: VecFile) -> Result<Self, Self::Error> {
+        Self::from_memory_pod(vec_file)
+    }
+}
+
+impl<'a, T: Plain> From<MemVec<'a, T, VecFile>> for VecFile {
+    /// The file keeps the length of the vector, see [`MemVec::into_mem`].
+    fn from(vec: MemVec<'a, T, VecFile>) -> Self {
+        vec.into_mem()
+    }
+}
+
+impl<'a, T: Plain> MemVec<'a, T, VecFile> {
+    /// Append all elements of `other`.
+    ///
+    /// On Linux the bytes are copied inside the kernel with `copy_file_range`, which file
+    /// systems supporting reflinks may turn into extent sharing. Elsewhere, or when the kernel
+    /// refuses, e.g. for files on different file systems, the elements are copied through the
+    /// mappings.
+    pub fn append_file(&mut self, other: &MemVec<'_, T, VecFile>) -> std::io::Result<()> {
+        let len = self.len();
+        let additional = other.len();
+        self.try_reserve(additional)?;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let copied = {
+            let size = core::mem::size_of::<T>();
+            let src = other.as_mem();
+            let dst = self.as_mem();
+            copy_file_range(
+                src.file(),
+                src.data_file_offset(),
+                dst.file(),
+                dst.data_file_offset() + (len * size) as u64,
+                additional * size,
+            )?
+        };
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let copied = false;
+        if !copied {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    other.as_ptr(),
+                    self.as_mut_ptr().add(len),
+                    additional,
+                )
+            };
+        }
+        unsafe { self.set_len(len + additional) };
+        Ok(())
+    }
+
+    /// Rewrite the backing file so that the capacity equals the length.
+    ///
+    /// See [`VecFile::compact`].
+    pub fn compact(&mut self) -> std::io::Result<()> {
+        let data_len = self.len() * core::mem::size_of::<T>();
+        self.as_mem_mut().compact(data_len)
+    }
+
+    /// Mutate records in place under the seqlock of the file, so that readers using
+    /// [`crate::ReadOnlyMemVec::read_consistent`] never observe a record half-written by `f`.
+    ///
+    /// Mutations through [`DerefMut`](core::ops::DerefMut) do not take the seqlock.
+    pub fn write_consistent<R>(&mut self, f: impl FnOnce(&mut [T]) -> R) -> R {
+        let records: *mut [T] = &mut **self;
+        self.as_mem()
+            .header()
+            .seqlock_write(|| f(unsafe { &mut *records }))
+    }
+}
+
+/// A name next to `path` which no other call of this process uses, and no other process uses
+/// since it holds the process id.
+fn temp_path(path: &Path) -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    PathBuf::from(temp_path)
+}
+
+/// Copy `len` bytes with `copy_file_range`, returning `false` if the kernel cannot copy between
+/// the files at all.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn copy_file_range(
+    src: &File,
+    src_offset: u64,
+    dst: &File,
+    dst_offset: u64,
+    len: usize,
+) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let mut src_offset = src_offset as libc::loff_t;
+    let mut dst_offset = dst_offset as libc::loff_t;
+    let mut remaining = len;
+    while remaining > 0 {
+        let copied = unsafe {
+            libc::copy_file_range(
+                src.as_raw_fd(),
+                &mut src_offset,
+                dst.as_raw_fd(),
+                &mut dst_offset,
+                remaining,
+                0,
+            )
+        };
+        if copied < 0 {
+            let e = std::io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP)
+                    if remaining == len =>
+                {
+                    return Ok(false)
+                }
+                _ => return Err(e),
+            }
+        }
+        if copied == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        remaining -= copied as usize;
+    }
+    Ok(true)
+}
@@ -0,0 +1,79 @@
+use crate::read_only::ReadOnlyVecFile;
+use std::time::{Duration, Instant};
+
+/// Waits for a writer in another process to change a [`ReadOnlyVecFile`], see
+/// [`ReadOnlyVecFile::watch`].
+///
+/// Changes are detected by polling the generation counter of the header, sleeping longer the
+/// longer the file stays unchanged, up to [`Watch::set_max_interval`]. File change notifications
+/// such as inotify are not used, since they do not report stores to a shared mapping.
+#[derive(Debug)]
+pub struct Watch<'f> {
+    file: &'f ReadOnlyVecFile,
+    generation: u64,
+    max_interval: Duration,
+}
+
+impl ReadOnlyVecFile {
+    /// Watch the file for changes of its length or capacity from now on.
+    pub fn watch(&self) -> Watch<'_> {
+        Watch {
+            file: self,
+            generation: self.generation(),
+            max_interval: Watch::DEFAULT_MAX_INTERVAL,
+        }
+    }
+}
+
+impl<'f> Watch<'f> {
+    const DEFAULT_MAX_INTERVAL: Duration = Duration::from_millis(10);
+    const MIN_INTERVAL: Duration = Duration::from_micros(10);
+    const SPINS: u32 = 64;
+
+    /// The longest sleep between two polls, which bounds the delay of noticing a change.
+    pub fn set_max_interval(&mut self, max_interval: Duration) {
+        self.max_interval = max_interval;
+    }
+
+    /// The generation seen by the last wait, or on creation.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Block until the generation differs from the one seen last, and return the new one.
+    pub fn wait(&mut self) -> u64 {
+        self.poll(None).expect("waits without a deadline")
+    }
+
+    /// [`Watch::wait`] for at most `timeout`, returning `None` if the file did not change.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Option<u64> {
+        self.poll(Instant::now().checked_add(timeout))
+    }
+
+    fn poll(&mut self, deadline: Option<Instant>) -> Option<u64> {
+        let mut spins = 0;
+        let mut interval = Self::MIN_INTERVAL;
+        loop {
+            let generation = self.file.generation();
+            if generation != self.generation {
+                self.generation = generation;
+                return Some(generation);
+            }
+            if spins < Self::SPINS {
+                spins += 1;
+                core::hint::spin_loop();
+                continue;
+            }
+            let mut sleep = interval;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                sleep = sleep.min(deadline - now);
+            }
+            std::thread::sleep(sleep);
+            interval = (interval * 2).min(self.max_interval);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::{header::Header, Plain};
+use core::{marker::PhantomData, ops::Range};
+use memmap2::{MmapMut, MmapOptions};
+use std::{fs::File, path::Path};
+
+/// A vector file of `T` which maps only a window of its records at a time.
+///
+/// The file format is the same as [`crate::VecFile`], but the data region is never mapped as a
+/// whole, so files larger than the address space of 32-bit targets can be used. Records are
+/// accessed through ranged accessors which map windows on demand. The most recently used windows
+/// stay mapped, up to [`WindowedVecFile::set_max_windows`] of them, so accesses alternating
+/// between a few regions do not remap every time. Since [`crate::Memory`]
+/// exposes the whole region as a slice, this is not a [`crate::Memory`] and has no
+/// [`crate::MemVec`] interface.
+pub struct WindowedVecFile<T: Plain> {
+    file: File,
+    header_mmap: MmapMut,
+    data_start: u64,
+    /// Capacity of the data region in bytes.
+    capacity: u64,
+    /// The length, which the header holds too. The header slots are verified only on open.
+    len: usize,
+    /// Minimum number of records to map at once.
+    window_len: usize,
+    /// The index of the first mapped record and the mapping of each window, most recently used
+    /// first.
+    windows: Vec<(usize, MmapMut)>,
+    max_windows: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Plain> core::fmt::Debug for WindowedVecFile<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowedVecFile")
+            .field("file", &self.file)
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .field("window_len", &self.window_len)
+            .field("windows", &self.windows.len())
+            .field("max_windows", &self.max_windows)
+            .finish()
+    }
+}
+
+impl<T: Plain> WindowedVecFile<T> {
+    const SIZE: usize = core::mem::size_of::<T>();
+
+    /// Open the vector file at `path`, mapping at least `window_len` records at a time and keeping
+    /// one window mapped.
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn open(path: impl AsRef<Path>, window_len: usize) -> std::io::Result<Self> {
+        let file = File::options().read(true).write(true).open(path)?;
+        Self::from_file(file, window_len)
+    }
+
+    /// The checksum of the file is not maintained; a stored checksum is invalidated on open.
+    /// # Safety
+    /// The data region must hold valid bytes representations of T.
+    pub unsafe fn from_file(file: File, window_len: usize) -> std::io::Result<Self> {
+        assert!(Self::SIZE > 0 && window_len > 0);
+        let file_len = file.metadata()?.len();
+        if file_len < Header::LEN as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec header",
+            ));
+        }
+        let mut header_mmap = MmapOptions::new().len(Header::LEN).map_mut(&file)?;
+        let header = &*(header_mmap.as_ptr().cast::<Header>());
+        header.validate()?;
+        let data_start = header.data_offset() as u64;
+        if file_len < data_start {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than memvec metadata region",
+            ));
+        }
+        let capacity = match header.capacity() {
+            Some(capacity) if file_len < data_start + capacity => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "file is smaller than the recorded memvec capacity",
+                ));
+            }
+            Some(capacity) => capacity,
+            None => file_len - data_start,
+        };
+        let len = header.state().len;
+        if len > capacity / Self::SIZE as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                crate::MemoryConversionError::SizeMismatch,
+            ));
+        }
+        (*(header_mmap.as_mut_ptr().cast::<Header>()))
+            .update(|state| state.set_flag(Header::FLAG_CHECKSUM_VALID, false));
+        Ok(Self {
+            file,
+            header_mmap,
+            data_start,
+            capacity,
+            // validated to fit in usize by the header
+            len: len as usize,
+            window_len,
+            windows: Vec::new(),
+            max_windows: 1,
+            _marker: PhantomData,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.header_mmap.as_ptr().cast::<Header>()) }
+    }
+
+    fn header_mut(&mut self) -> &mut Header {
+        unsafe { &mut *(self.header_mmap.as_mut_ptr().cast::<Header>()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The largest length of the file, which is limited to `u32::MAX` records for files created
+    /// with [`crate::VecFileBuilder::u32_len_cap`].
+    pub fn max_len(&self) -> usize {
+        if self.header().has_feature(Header::FEATURE_U32_LEN_CAP) {
+            u32::MAX as usize
+        } else {
+            usize::MAX
+        }
+    }
+
+    /// The number of records the data region holds.
+    pub fn capacity(&self) -> u64 {
+        self.capacity / Self::SIZE as u64
+    }
+
+    /// Keep up to `max_windows` windows mapped, unmapping the least recently used ones beyond.
+    ///
+    /// # Panics
+    /// Panics if `max_windows` is 0.
+    pub fn set_max_windows(&mut self, max_windows: usize) {
+        assert!(max_windows > 0);
+        self.max_windows = max_windows;
+        self.windows.truncate(max_windows);
+    }
+
+    pub fn max_windows(&self) -> usize {
+        self.max_windows
+    }
+
+    /// The number of windows mapped now.
+    pub fn mapped_windows(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// The index of the first record of each mapped window, the most recently used first.
+    pub fn window_starts(&self) -> impl Iterator<Item = usize> + '_ {
+        self.windows.iter().map(|(start, _)| *start)
+    }
+
+    /// Map a window covering `range` of records, unless a mapped one covers it, and make it the
+    /// most recently used.
+    fn map_window(&mut self, range: Range<usize>) -> std::io::Result<&mut [T]> {
+        if range.is_empty() {
+            return Ok(&mut []);
+        }
+        let covering = self.windows.iter().position(|(start, mmap)| {
+            *start <= range.start && range.end <= start + mmap.len() / Self::SIZE
+        });
+        match covering {
+            Some(index) => self.windows[..=index].rotate_right(1),
+            None => {
+                // windows start at multiples of the window length, so neighbouring accesses
+                // share them
+                let start = range.start - range.start % self.window_len;
+                let end = core::cmp::max(range.end, start.saturating_add(self.window_len));
+                let end = core::cmp::min(end as u64, self.capacity()) as usize;
+                // in u64, since the offset may exceed usize
+                let offset = (start as u64)
+                    .checked_mul(Self::SIZE as u64)
+                    .and_then(|offset| offset.checked_add(self.data_start))
+                    .ok_or_else(capacity_overflow)?;
+                let mmap = unsafe {
+                    MmapOptions::new()
+                        .offset(offset)
+                        .len((end - start) * Self::SIZE)
+                        .map_mut(&self.file)?
+                };
+                self.windows.truncate(self.max_windows - 1);
+                self.windows.insert(0, (start, mmap));
+            }
+        }
+        let (start, mmap) = &mut self.windows[0];
+        let (prefix, records, _suffix) = unsafe { mmap.align_to_mut::<T>() };
+        debug_assert!(prefix.is_empty());
+        Ok(&mut records[range.start - *start..range.end - *start])
+    }
+
+    /// The records in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn window(&mut self, range: Range<usize>) -> std::io::Result<&[T]> {
+        self.window_mut(range).map(|records| &*records)
+    }
+
+    /// The records in `range`, mutably.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn window_mut(&mut self, range: Range<usize>) -> std::io::Result<&mut [T]> {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "range out of bounds"
+        );
+        self.map_window(range)
+    }
+
+    pub fn get(&mut self, index: usize) -> std::io::Result<T> {
+        // a copy of its bytes, as `T` is plain
+        Ok(unsafe { core::ptr::read(&self.window(index..index + 1)?[0]) })
+    }
+
+    pub fn set(&mut self, index: usize, value: T) -> std::io::Result<()> {
+        self.window_mut(index..index + 1)?[0] = value;
+        Ok(())
+    }
+
+    /// Append `value`, growing the file if needed.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if the file already holds
+    /// [`WindowedVecFile::max_len`] records.
+    pub fn push(&mut self, value: T) -> std::io::Result<()> {
+        let len = self.len();
+        if len >= self.max_len() {
+            return Err(capacity_overflow());
+        }
+        if len as u64 == self.capacity() {
+            self.grow()?;
+        }
+        self.map_window(len..len + 1)?[0] = value;
+        self.len = len + 1;
+        self.header_mut().update_written(|state| {
+            state.len += 1;
+            state.generation = state.generation.wrapping_add(1);
+        });
+        Ok(())
+    }
+
+    fn grow(&mut self) -> std::io::Result<()> {
+        // doubling in u64, since the capacity may exceed usize
+        let capacity = core::cmp::max(self.capacity * 2, (4 * Self::SIZE) as u64);
+        // windows are limited to the old capacity
+        self.windows.clear();
+        self.file.set_len(self.data_start + capacity)?;
+        self.capacity = capacity;
+        let header = self.header_mut();
+        header.set_capacity(capacity);
+        header.update_written(|state| state.generation = state.generation.wrapping_add(1));
+        Ok(())
+    }
+
+    /// Flush the mapped windows and the header.
+    pub fn flush(&self) -> std::io::Result<()> {
+        for (_, mmap) in &self.windows {
+            mmap.flush()?;
+        }
+        self.header_mmap.flush()
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+}
+
+fn capacity_overflow() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, "capacity overflow")
+}